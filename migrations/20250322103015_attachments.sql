@@ -0,0 +1,19 @@
+-- Add migration script here
+CREATE TYPE attachment_owner_type AS ENUM ('resource', 'post', 'message', 'groupchatmessage');
+
+-- ATTACHMENTS TABLE
+CREATE TABLE attachments (
+    attachment_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    uploader_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    owner_type attachment_owner_type NOT NULL,
+    owner_id UUID NOT NULL,
+    file_name TEXT NOT NULL UNIQUE,
+    original_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL CHECK (size_bytes > 0),
+    sha1 TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_attachments_owner ON attachments (owner_type, owner_id);
+CREATE INDEX idx_attachments_uploader ON attachments (uploader_id);
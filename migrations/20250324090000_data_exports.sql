@@ -0,0 +1,19 @@
+-- Add migration script here
+CREATE TYPE data_export_status AS ENUM ('pending', 'processing', 'ready', 'failed');
+
+-- DATA EXPORTS TABLE
+CREATE TABLE data_exports (
+    export_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    status data_export_status NOT NULL DEFAULT 'pending',
+    summary TEXT,
+    archive JSONB,
+    file_name TEXT UNIQUE,
+    size_bytes BIGINT,
+    error TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMP,
+    expires_at TIMESTAMP
+);
+
+CREATE INDEX idx_data_exports_user ON data_exports (user_id, created_at DESC);
@@ -0,0 +1,8 @@
+-- Add migration script here
+ALTER TABLE users
+ADD COLUMN deletion_requested_at TIMESTAMP,
+ADD COLUMN deletion_scheduled_for TIMESTAMP,
+ADD COLUMN deleted_at TIMESTAMP;
+
+CREATE INDEX idx_users_deletion_scheduled ON users (deletion_scheduled_for)
+    WHERE deletion_scheduled_for IS NOT NULL AND deleted_at IS NULL;
@@ -0,0 +1,20 @@
+-- Add migration script here
+CREATE TYPE visibility_audience AS ENUM ('everyone', 'peers', 'sponsor', 'nobody');
+
+-- USER PRIVACY SETTINGS TABLE
+CREATE TABLE user_privacy_settings (
+    user_id UUID PRIMARY KEY REFERENCES users(user_id) ON DELETE CASCADE,
+    bio visibility_audience NOT NULL DEFAULT 'everyone',
+    interests visibility_audience NOT NULL DEFAULT 'everyone',
+    experience visibility_audience NOT NULL DEFAULT 'everyone',
+    languages visibility_audience NOT NULL DEFAULT 'everyone',
+    location_city visibility_audience NOT NULL DEFAULT 'nobody',
+    avatar visibility_audience NOT NULL DEFAULT 'everyone',
+    support_groups visibility_audience NOT NULL DEFAULT 'peers',
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Carry over the old single flag: private profiles only showed username, role and avatar
+INSERT INTO user_privacy_settings (user_id, bio, interests, experience, languages, support_groups)
+SELECT user_id, 'nobody', 'nobody', 'nobody', 'nobody', 'nobody'
+FROM users WHERE privacy = true;
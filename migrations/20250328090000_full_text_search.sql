@@ -0,0 +1,38 @@
+-- Add migration script here
+-- POSTS SEARCH
+ALTER TABLE posts
+    ADD COLUMN search_vector TSVECTOR
+    GENERATED ALWAYS AS (to_tsvector('english', content)) STORED;
+
+CREATE INDEX idx_posts_search ON posts USING GIN (search_vector);
+
+-- RESOURCES SEARCH (title ranks above content)
+ALTER TABLE resources
+    ADD COLUMN search_vector TSVECTOR
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', title), 'A') ||
+        setweight(to_tsvector('english', content), 'B')
+    ) STORED;
+
+CREATE INDEX idx_resources_search ON resources USING GIN (search_vector);
+
+-- SUPPORT GROUPS SEARCH (title ranks above description)
+ALTER TABLE support_groups
+    ADD COLUMN search_vector TSVECTOR
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('english', title), 'A') ||
+        setweight(to_tsvector('english', description), 'B')
+    ) STORED;
+
+CREATE INDEX idx_support_groups_search ON support_groups USING GIN (search_vector);
+
+-- USERS SEARCH
+-- Usernames aren't natural language, so use the 'simple' config to avoid stemming them
+ALTER TABLE users
+    ADD COLUMN search_vector TSVECTOR
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', username), 'A') ||
+        setweight(to_tsvector('simple', COALESCE(bio, '')), 'B')
+    ) STORED;
+
+CREATE INDEX idx_users_search ON users USING GIN (search_vector);
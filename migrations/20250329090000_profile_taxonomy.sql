@@ -0,0 +1,253 @@
+-- Add migration script here
+CREATE TYPE taxonomy_kind AS ENUM ('interest', 'experience', 'day', 'language');
+
+-- TAXONOMY TERMS TABLE
+-- code is what gets stored on profiles; label is what clients display
+CREATE TABLE taxonomy_terms (
+    term_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    kind taxonomy_kind NOT NULL,
+    code TEXT NOT NULL CHECK (code = lower(code) AND char_length(code) > 0),
+    label TEXT NOT NULL CHECK (char_length(label) > 0),
+    -- Display order within a kind; terms with the same position sort by label
+    position INTEGER NOT NULL DEFAULT 0,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (kind, code)
+);
+
+-- TAXONOMY ALIASES TABLE
+-- Alternative spellings that resolve to a term, stored lowercased
+CREATE TABLE taxonomy_aliases (
+    kind taxonomy_kind NOT NULL,
+    alias TEXT NOT NULL CHECK (alias = lower(alias) AND char_length(alias) > 0),
+    term_id UUID NOT NULL REFERENCES taxonomy_terms(term_id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (kind, alias)
+);
+
+CREATE INDEX idx_taxonomy_aliases_term ON taxonomy_aliases (term_id);
+
+-- TAXONOMY UNMATCHED VALUES TABLE
+-- Profile values the normalisation below couldn't place, kept so admins can add aliases for them
+CREATE TABLE taxonomy_unmatched_values (
+    user_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    kind taxonomy_kind NOT NULL,
+    value TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, kind, value)
+);
+
+-- SEED: topics shared by interests and experience areas
+INSERT INTO taxonomy_terms (kind, code, label)
+SELECT kind, code, label
+FROM (VALUES
+    ('alcohol', 'Alcohol'),
+    ('opioids', 'Opioids'),
+    ('stimulants', 'Stimulants'),
+    ('cannabis', 'Cannabis'),
+    ('prescription-drugs', 'Prescription drugs'),
+    ('nicotine', 'Nicotine'),
+    ('gambling', 'Gambling'),
+    ('gaming', 'Gaming'),
+    ('eating-disorders', 'Eating disorders'),
+    ('self-harm', 'Self-harm'),
+    ('anxiety', 'Anxiety'),
+    ('depression', 'Depression'),
+    ('trauma', 'Trauma'),
+    ('grief', 'Grief'),
+    ('mental-health', 'Mental health'),
+    ('dual-diagnosis', 'Dual diagnosis'),
+    ('relapse-prevention', 'Relapse prevention'),
+    ('family-support', 'Family support')
+) AS topics(code, label)
+CROSS JOIN (VALUES ('interest'::taxonomy_kind), ('experience'::taxonomy_kind)) AS kinds(kind);
+
+INSERT INTO taxonomy_aliases (kind, alias, term_id)
+SELECT t.kind, a.alias, t.term_id
+FROM (VALUES
+    ('alcohol', 'alcoholism'),
+    ('alcohol', 'drinking'),
+    ('alcohol', 'aa'),
+    ('opioids', 'opiates'),
+    ('opioids', 'heroin'),
+    ('opioids', 'fentanyl'),
+    ('stimulants', 'cocaine'),
+    ('stimulants', 'meth'),
+    ('stimulants', 'methamphetamine'),
+    ('stimulants', 'amphetamines'),
+    ('cannabis', 'marijuana'),
+    ('cannabis', 'weed'),
+    ('prescription-drugs', 'prescription medication'),
+    ('prescription-drugs', 'painkillers'),
+    ('prescription-drugs', 'benzodiazepines'),
+    ('nicotine', 'smoking'),
+    ('nicotine', 'tobacco'),
+    ('nicotine', 'vaping'),
+    ('gambling', 'betting'),
+    ('gaming', 'video games'),
+    ('eating-disorders', 'eating disorder'),
+    ('eating-disorders', 'anorexia'),
+    ('eating-disorders', 'bulimia'),
+    ('eating-disorders', 'binge eating'),
+    ('self-harm', 'self harm'),
+    ('self-harm', 'selfharm'),
+    ('trauma', 'ptsd'),
+    ('grief', 'bereavement'),
+    ('mental-health', 'mental illness'),
+    ('relapse-prevention', 'relapse'),
+    ('family-support', 'family'),
+    ('family-support', 'codependency')
+) AS a(code, alias)
+JOIN taxonomy_terms t ON t.code = a.code AND t.kind IN ('interest', 'experience');
+
+-- SEED: days of the week
+INSERT INTO taxonomy_terms (kind, code, label, position) VALUES
+    ('day', 'monday', 'Monday', 1),
+    ('day', 'tuesday', 'Tuesday', 2),
+    ('day', 'wednesday', 'Wednesday', 3),
+    ('day', 'thursday', 'Thursday', 4),
+    ('day', 'friday', 'Friday', 5),
+    ('day', 'saturday', 'Saturday', 6),
+    ('day', 'sunday', 'Sunday', 7);
+
+INSERT INTO taxonomy_aliases (kind, alias, term_id)
+SELECT 'day', a.alias, t.term_id
+FROM (VALUES
+    ('monday', 'mon'),
+    ('tuesday', 'tue'),
+    ('tuesday', 'tues'),
+    ('wednesday', 'wed'),
+    ('thursday', 'thu'),
+    ('thursday', 'thur'),
+    ('thursday', 'thurs'),
+    ('friday', 'fri'),
+    ('saturday', 'sat'),
+    ('sunday', 'sun')
+) AS a(code, alias)
+JOIN taxonomy_terms t ON t.code = a.code AND t.kind = 'day';
+
+-- SEED: languages as ISO 639-1 codes, with English and native names as aliases
+INSERT INTO taxonomy_terms (kind, code, label)
+SELECT 'language', code, label
+FROM (VALUES
+    ('ar', 'Arabic'),
+    ('bn', 'Bengali'),
+    ('cs', 'Czech'),
+    ('da', 'Danish'),
+    ('de', 'German'),
+    ('el', 'Greek'),
+    ('en', 'English'),
+    ('es', 'Spanish'),
+    ('fa', 'Persian'),
+    ('fi', 'Finnish'),
+    ('fr', 'French'),
+    ('he', 'Hebrew'),
+    ('hi', 'Hindi'),
+    ('hu', 'Hungarian'),
+    ('id', 'Indonesian'),
+    ('it', 'Italian'),
+    ('ja', 'Japanese'),
+    ('ko', 'Korean'),
+    ('nl', 'Dutch'),
+    ('no', 'Norwegian'),
+    ('pa', 'Punjabi'),
+    ('pl', 'Polish'),
+    ('pt', 'Portuguese'),
+    ('ro', 'Romanian'),
+    ('ru', 'Russian'),
+    ('sv', 'Swedish'),
+    ('sw', 'Swahili'),
+    ('tl', 'Tagalog'),
+    ('tr', 'Turkish'),
+    ('uk', 'Ukrainian'),
+    ('ur', 'Urdu'),
+    ('vi', 'Vietnamese'),
+    ('zh', 'Chinese')
+) AS languages(code, label);
+
+INSERT INTO taxonomy_aliases (kind, alias, term_id)
+SELECT 'language', lower(label), term_id
+FROM taxonomy_terms
+WHERE kind = 'language';
+
+INSERT INTO taxonomy_aliases (kind, alias, term_id)
+SELECT 'language', a.alias, t.term_id
+FROM (VALUES
+    ('ar', 'العربية'),
+    ('de', 'deutsch'),
+    ('el', 'ελληνικά'),
+    ('es', 'español'),
+    ('es', 'espanol'),
+    ('fa', 'farsi'),
+    ('fr', 'français'),
+    ('fr', 'francais'),
+    ('it', 'italiano'),
+    ('ja', '日本語'),
+    ('nl', 'nederlands'),
+    ('pl', 'polski'),
+    ('pt', 'português'),
+    ('pt', 'portugues'),
+    ('ru', 'русский'),
+    ('tl', 'filipino'),
+    ('uk', 'українська'),
+    ('zh', 'mandarin'),
+    ('zh', '中文')
+) AS a(code, alias)
+JOIN taxonomy_terms t ON t.code = a.code AND t.kind = 'language';
+
+-- NORMALISE EXISTING PROFILES
+-- Resolve a free-form value to a term code by code or alias, ignoring case and spacing
+CREATE FUNCTION pg_temp.resolve_taxonomy_value(k taxonomy_kind, raw TEXT) RETURNS TEXT
+LANGUAGE sql STABLE AS $$
+    SELECT t.code
+    FROM taxonomy_terms t
+    WHERE t.kind = k
+      AND (
+          t.code = regexp_replace(lower(trim(raw)), '[\s_]+', '-', 'g')
+          OR t.term_id IN (
+              SELECT a.term_id FROM taxonomy_aliases a
+              WHERE a.kind = k AND a.alias = regexp_replace(lower(trim(raw)), '\s+', ' ', 'g')
+          )
+      )
+    LIMIT 1
+$$;
+
+-- Resolve every value in an array, dropping unknowns and duplicates; NULL when nothing is left
+CREATE FUNCTION pg_temp.normalise_taxonomy_values(k taxonomy_kind, vals TEXT[]) RETURNS TEXT[]
+LANGUAGE sql STABLE AS $$
+    SELECT array_agg(code ORDER BY pos)
+    FROM (
+        SELECT code, MIN(pos) AS pos
+        FROM (
+            SELECT pg_temp.resolve_taxonomy_value(k, v.value) AS code, v.pos
+            FROM unnest(vals) WITH ORDINALITY AS v(value, pos)
+        ) resolved
+        WHERE code IS NOT NULL
+        GROUP BY code
+    ) matched
+$$;
+
+INSERT INTO taxonomy_unmatched_values (user_id, kind, value)
+SELECT DISTINCT u.user_id, f.kind, trim(f.value)
+FROM users u
+CROSS JOIN LATERAL (
+    SELECT 'interest'::taxonomy_kind AS kind, v AS value FROM unnest(u.interests) v
+    UNION ALL
+    SELECT 'experience'::taxonomy_kind, v FROM unnest(u.experience) v
+    UNION ALL
+    SELECT 'day'::taxonomy_kind, v FROM unnest(u.available_days) v
+    UNION ALL
+    SELECT 'language'::taxonomy_kind, v FROM unnest(u.languages) v
+) f
+WHERE trim(f.value) <> ''
+  AND pg_temp.resolve_taxonomy_value(f.kind, f.value) IS NULL;
+
+UPDATE users SET
+    interests = pg_temp.normalise_taxonomy_values('interest', interests),
+    experience = pg_temp.normalise_taxonomy_values('experience', experience),
+    available_days = pg_temp.normalise_taxonomy_values('day', available_days),
+    languages = pg_temp.normalise_taxonomy_values('language', languages)
+WHERE interests IS NOT NULL
+   OR experience IS NOT NULL
+   OR available_days IS NOT NULL
+   OR languages IS NOT NULL;
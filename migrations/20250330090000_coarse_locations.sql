@@ -0,0 +1,55 @@
+-- Add migration script here
+-- Locations are now { city, country, latitude, longitude } with coordinates snapped to a
+-- 0.1 degree grid. A city-only location is allowed until the server geocodes it.
+CREATE FUNCTION valid_user_location(loc JSONB) RETURNS BOOLEAN
+LANGUAGE sql IMMUTABLE AS $$
+    SELECT loc IS NULL OR (
+        jsonb_typeof(loc) = 'object'
+        AND (loc - ARRAY['city', 'country', 'latitude', 'longitude']) = '{}'::jsonb
+        AND COALESCE(jsonb_typeof(loc->'city'), 'null') IN ('string', 'null')
+        AND COALESCE(jsonb_typeof(loc->'country'), 'null') IN ('string', 'null')
+        AND COALESCE(loc->>'country' ~ '^[A-Z]{2}$', TRUE)
+        AND CASE
+            WHEN jsonb_typeof(loc->'latitude') = 'number' AND jsonb_typeof(loc->'longitude') = 'number' THEN
+                (loc->>'latitude')::numeric BETWEEN -90 AND 90
+                AND (loc->>'longitude')::numeric BETWEEN -180 AND 180
+                AND (loc->>'latitude')::numeric = round((loc->>'latitude')::numeric, 1)
+                AND (loc->>'longitude')::numeric = round((loc->>'longitude')::numeric, 1)
+            ELSE
+                loc->'latitude' IS NULL AND loc->'longitude' IS NULL AND loc->>'city' IS NOT NULL
+        END
+    )
+$$;
+
+-- Drop anything that isn't an object
+UPDATE users SET location = NULL
+WHERE location IS NOT NULL AND jsonb_typeof(location) <> 'object';
+
+-- Coordinates are only cast once both are known to be numbers
+CREATE FUNCTION pg_temp.has_valid_coordinates(loc JSONB) RETURNS BOOLEAN
+LANGUAGE sql IMMUTABLE AS $$
+    SELECT CASE
+        WHEN jsonb_typeof(loc->'latitude') = 'number' AND jsonb_typeof(loc->'longitude') = 'number' THEN
+            (loc->>'latitude')::numeric BETWEEN -90 AND 90
+            AND (loc->>'longitude')::numeric BETWEEN -180 AND 180
+        ELSE FALSE
+    END
+$$;
+
+-- Keep only the known keys, coarsen coordinates and drop country names that aren't ISO codes
+UPDATE users SET location = jsonb_strip_nulls(jsonb_build_object(
+    'city', CASE WHEN jsonb_typeof(location->'city') = 'string' THEN location->'city' END,
+    'country', CASE WHEN location->>'country' ~ '^[A-Za-z]{2}$'
+                    THEN to_jsonb(upper(location->>'country')) END,
+    'latitude', CASE WHEN pg_temp.has_valid_coordinates(location)
+                     THEN to_jsonb(round((location->>'latitude')::numeric, 1)) END,
+    'longitude', CASE WHEN pg_temp.has_valid_coordinates(location)
+                      THEN to_jsonb(round((location->>'longitude')::numeric, 1)) END
+))
+WHERE location IS NOT NULL;
+
+-- Nothing usable left (no coordinates and no city to geocode)
+UPDATE users SET location = NULL
+WHERE location IS NOT NULL AND location->'latitude' IS NULL AND location->>'city' IS NULL;
+
+ALTER TABLE users ADD CONSTRAINT users_location_valid CHECK (valid_user_location(location));
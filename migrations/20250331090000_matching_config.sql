@@ -0,0 +1,23 @@
+-- Add migration script here
+-- MATCHING CONFIG TABLE
+-- A single row of admin-tunable weights and hard filters for sponsor matching.
+-- Weights are relative: each factor's share of the 0-100 score is its weight over the total.
+CREATE TABLE matching_config (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    age_weight REAL NOT NULL DEFAULT 30 CHECK (age_weight >= 0),
+    location_weight REAL NOT NULL DEFAULT 30 CHECK (location_weight >= 0),
+    interests_weight REAL NOT NULL DEFAULT 20 CHECK (interests_weight >= 0),
+    experience_weight REAL NOT NULL DEFAULT 15 CHECK (experience_weight >= 0),
+    availability_weight REAL NOT NULL DEFAULT 10 CHECK (availability_weight >= 0),
+    language_weight REAL NOT NULL DEFAULT 5 CHECK (language_weight >= 0),
+    -- Age gap (in years) at which the age score drops to zero
+    age_range_years REAL NOT NULL DEFAULT 15 CHECK (age_range_years > 0),
+    require_shared_language BOOLEAN NOT NULL DEFAULT FALSE,
+    max_distance_km REAL CHECK (max_distance_km IS NULL OR max_distance_km > 0),
+    updated_by UUID REFERENCES users(user_id) ON DELETE SET NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK (age_weight + location_weight + interests_weight + experience_weight
+           + availability_weight + language_weight > 0)
+);
+
+INSERT INTO matching_config (id) VALUES (TRUE);
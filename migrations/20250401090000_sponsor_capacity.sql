@@ -0,0 +1,16 @@
+-- Add migration script here
+-- SPONSOR SETTINGS TABLE
+-- Sponsors without a row use the defaults below
+CREATE TABLE sponsor_settings (
+    user_id UUID PRIMARY KEY REFERENCES users(user_id) ON DELETE CASCADE,
+    max_active_mentees INTEGER NOT NULL DEFAULT 3 CHECK (max_active_mentees BETWEEN 1 AND 20),
+    accepting_new_members BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Active mentees are counted from accepted requests on every recommendation
+CREATE INDEX idx_matching_requests_sponsor_status ON matching_requests (sponsor_id, status);
+
+-- Points taken off a sponsor's score as they fill up, reaching the full amount at capacity
+ALTER TABLE matching_config
+    ADD COLUMN load_penalty REAL NOT NULL DEFAULT 20 CHECK (load_penalty BETWEEN 0 AND 100);
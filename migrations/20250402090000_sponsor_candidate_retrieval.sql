@@ -0,0 +1,48 @@
+-- Add migration script here
+-- Coordinates pulled out of the location JSON so sponsors can be pre-filtered by an indexed bounding box
+ALTER TABLE users
+    ADD COLUMN location_latitude DOUBLE PRECISION
+        GENERATED ALWAYS AS ((location->>'latitude')::double precision) STORED,
+    ADD COLUMN location_longitude DOUBLE PRECISION
+        GENERATED ALWAYS AS ((location->>'longitude')::double precision) STORED;
+
+CREATE INDEX idx_users_sponsor_coordinates ON users (location_latitude, location_longitude)
+    WHERE role = 'sponsor';
+CREATE INDEX idx_users_sponsor_languages ON users USING GIN (languages)
+    WHERE role = 'sponsor';
+
+-- Bumped whenever a field used for matching changes, so cached scores can tell they're stale
+ALTER TABLE users ADD COLUMN match_profile_updated_at TIMESTAMP NOT NULL DEFAULT NOW();
+
+CREATE FUNCTION touch_match_profile() RETURNS TRIGGER
+LANGUAGE plpgsql AS $$
+BEGIN
+    IF (NEW.dob, NEW.location, NEW.interests, NEW.experience, NEW.available_days, NEW.languages)
+       IS DISTINCT FROM
+       (OLD.dob, OLD.location, OLD.interests, OLD.experience, OLD.available_days, OLD.languages) THEN
+        NEW.match_profile_updated_at := clock_timestamp();
+    END IF;
+    RETURN NEW;
+END
+$$;
+
+CREATE TRIGGER users_touch_match_profile
+    BEFORE UPDATE ON users
+    FOR EACH ROW EXECUTE FUNCTION touch_match_profile();
+
+-- SPONSOR MATCH SCORES TABLE
+-- Cached match results, valid while both profiles and the matching config are at the stored versions.
+-- Capacity and load are applied fresh on every request, so they aren't part of the cache.
+CREATE TABLE sponsor_match_scores (
+    member_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    sponsor_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    member_version TIMESTAMP NOT NULL,
+    sponsor_version TIMESTAMP NOT NULL,
+    config_version TIMESTAMP NOT NULL,
+    score REAL NOT NULL,
+    result JSONB NOT NULL,
+    computed_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (member_id, sponsor_id)
+);
+
+CREATE INDEX idx_sponsor_match_scores_sponsor ON sponsor_match_scores (sponsor_id);
@@ -0,0 +1,68 @@
+-- Add migration script here
+CREATE TYPE sponsorship_status AS ENUM ('active', 'paused', 'ended');
+CREATE TYPE sponsorship_end_reason AS ENUM (
+    'goals_met',
+    'not_a_good_fit',
+    'unavailable',
+    'unresponsive',
+    'safety_concern',
+    'account_deleted',
+    'other'
+);
+
+-- respond_to_matching_request already sets this
+ALTER TABLE matching_requests ADD COLUMN updated_at TIMESTAMP;
+
+-- SPONSORSHIPS TABLE
+-- Created when a sponsor accepts a matching request; a member has at most one sponsorship that hasn't ended
+CREATE TABLE sponsorships (
+    sponsorship_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    member_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    sponsor_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    matching_request_id UUID REFERENCES matching_requests(matching_request_id) ON DELETE SET NULL,
+    group_chat_id UUID REFERENCES group_chats(group_chat_id) ON DELETE SET NULL,
+    status sponsorship_status NOT NULL DEFAULT 'active',
+    started_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    paused_at TIMESTAMP,
+    ended_at TIMESTAMP,
+    ended_by UUID REFERENCES users(user_id) ON DELETE SET NULL,
+    end_reason sponsorship_end_reason,
+    end_note TEXT CHECK (char_length(end_note) <= 1000),
+    CHECK (member_id <> sponsor_id),
+    CHECK ((status = 'paused') = (paused_at IS NOT NULL)),
+    CHECK ((status = 'ended') = (ended_at IS NOT NULL AND end_reason IS NOT NULL))
+);
+
+CREATE UNIQUE INDEX idx_sponsorships_one_per_member ON sponsorships (member_id)
+    WHERE status <> 'ended';
+CREATE INDEX idx_sponsorships_sponsor_status ON sponsorships (sponsor_id, status);
+
+-- Days before a member can be matched again with a sponsor they've ended a sponsorship with
+ALTER TABLE matching_config
+    ADD COLUMN rematch_cooldown_days INTEGER NOT NULL DEFAULT 30 CHECK (rematch_cooldown_days >= 0);
+
+-- Turn requests accepted before sponsorships existed into sponsorships, each with its own chat.
+-- Only the latest accepted request of each member is kept.
+DO $$
+DECLARE
+    accepted RECORD;
+    chat_id UUID;
+BEGIN
+    FOR accepted IN
+        SELECT DISTINCT ON (mr.member_id) mr.matching_request_id, mr.member_id, mr.sponsor_id, mr.created_at
+        FROM matching_requests mr
+        WHERE mr.status = 'accepted' AND mr.sponsor_id IS NOT NULL AND mr.sponsor_id <> mr.member_id
+        ORDER BY mr.member_id, mr.created_at DESC
+    LOOP
+        INSERT INTO group_chats (creator_id, created_at, flagged)
+        VALUES (accepted.sponsor_id, NOW(), FALSE)
+        RETURNING group_chat_id INTO chat_id;
+
+        INSERT INTO group_chat_members (group_chat_id, user_id)
+        VALUES (chat_id, accepted.sponsor_id), (chat_id, accepted.member_id);
+
+        INSERT INTO sponsorships (member_id, sponsor_id, matching_request_id, group_chat_id, started_at)
+        VALUES (accepted.member_id, accepted.sponsor_id, accepted.matching_request_id, chat_id, accepted.created_at);
+    END LOOP;
+END
+$$;
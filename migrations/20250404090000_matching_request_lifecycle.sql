@@ -0,0 +1,30 @@
+-- Add migration script here
+-- withdrawn: the member took the request back
+-- expired: nobody answered within request_expiry_days
+-- cancelled: the member was matched with another sponsor first
+ALTER TYPE matching_status ADD VALUE IF NOT EXISTS 'withdrawn';
+ALTER TYPE matching_status ADD VALUE IF NOT EXISTS 'expired';
+ALTER TYPE matching_status ADD VALUE IF NOT EXISTS 'cancelled';
+
+-- The expiry job and the pending-request cap only ever look at pending requests
+CREATE INDEX idx_matching_requests_pending ON matching_requests (member_id, created_at)
+    WHERE status = 'pending';
+
+ALTER TABLE matching_config
+    ADD COLUMN max_pending_requests INTEGER NOT NULL DEFAULT 3 CHECK (max_pending_requests >= 1),
+    ADD COLUMN request_expiry_days INTEGER NOT NULL DEFAULT 14 CHECK (request_expiry_days >= 1);
+
+-- NOTIFICATIONS TABLE
+-- Stored so users who weren't connected when something happened still see it
+CREATE TYPE notification_kind AS ENUM ('matching_request');
+
+CREATE TABLE notifications (
+    notification_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    kind notification_kind NOT NULL,
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    read_at TIMESTAMP
+);
+
+CREATE INDEX idx_notifications_user_created ON notifications (user_id, created_at DESC);
@@ -0,0 +1,64 @@
+-- Add migration script here
+-- info_requested: an admin asked the applicant for more detail before deciding
+ALTER TYPE application_status ADD VALUE IF NOT EXISTS 'info_requested';
+ALTER TYPE attachment_owner_type ADD VALUE IF NOT EXISTS 'sponsorapplication';
+ALTER TYPE notification_kind ADD VALUE IF NOT EXISTS 'sponsor_application';
+
+-- Structured application form. application_info stays as the applicant's own words about why
+-- they want to sponsor; applications made before the form existed only have that.
+ALTER TABLE sponsor_applications
+    ADD COLUMN recovery_since DATE,
+    ADD COLUMN experience_areas TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN availability TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN referees JSONB NOT NULL DEFAULT '[]',
+    ADD COLUMN code_of_conduct_agreed_at TIMESTAMP,
+    ADD COLUMN revision INTEGER NOT NULL DEFAULT 1,
+    ADD COLUMN submitted_at TIMESTAMP,
+    ADD COLUMN reviewed_at TIMESTAMP;
+
+UPDATE sponsor_applications SET submitted_at = created_at;
+ALTER TABLE sponsor_applications
+    ALTER COLUMN submitted_at SET NOT NULL,
+    ALTER COLUMN submitted_at SET DEFAULT NOW();
+
+-- SPONSOR APPLICATION REVISIONS TABLE
+-- A copy of the form each time it is submitted or edited
+CREATE TABLE sponsor_application_revisions (
+    revision_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    application_id UUID NOT NULL REFERENCES sponsor_applications(application_id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    application_info TEXT NOT NULL,
+    recovery_since DATE,
+    experience_areas TEXT[] NOT NULL DEFAULT '{}',
+    availability TEXT[] NOT NULL DEFAULT '{}',
+    referees JSONB NOT NULL DEFAULT '[]',
+    code_of_conduct_agreed_at TIMESTAMP,
+    submitted_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (application_id, revision)
+);
+
+-- SPONSOR APPLICATION REVIEWS TABLE
+-- Every admin decision, including requests for more information
+CREATE TABLE sponsor_application_reviews (
+    review_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    application_id UUID NOT NULL REFERENCES sponsor_applications(application_id) ON DELETE CASCADE,
+    revision INTEGER NOT NULL,
+    reviewer_id UUID REFERENCES users(user_id) ON DELETE SET NULL,
+    decision application_status NOT NULL,
+    comments TEXT,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_sponsor_application_reviews_application
+    ON sponsor_application_reviews (application_id, created_at);
+
+-- Existing applications become their own first revision, with any decision already made
+INSERT INTO sponsor_application_revisions (application_id, revision, application_info, submitted_at)
+SELECT application_id, 1, application_info, created_at FROM sponsor_applications;
+
+INSERT INTO sponsor_application_reviews (application_id, revision, reviewer_id, decision, comments, created_at)
+SELECT application_id, 1, reviewed_by, status, admin_comments, created_at
+FROM sponsor_applications
+WHERE status <> 'pending';
+
+UPDATE sponsor_applications SET reviewed_at = created_at WHERE status <> 'pending';
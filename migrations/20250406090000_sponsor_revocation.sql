@@ -0,0 +1,22 @@
+-- Add migration script here
+-- revoked: an admin took sponsor status away after the application was approved
+ALTER TYPE application_status ADD VALUE IF NOT EXISTS 'revoked';
+ALTER TYPE sponsorship_end_reason ADD VALUE IF NOT EXISTS 'sponsor_revoked';
+ALTER TYPE notification_kind ADD VALUE IF NOT EXISTS 'sponsorship';
+
+-- ADMIN AUDIT LOG TABLE
+-- Admin actions that change another user's standing, with the reason given
+CREATE TYPE admin_action AS ENUM ('sponsor_revoked');
+
+CREATE TABLE admin_audit_log (
+    audit_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    admin_id UUID REFERENCES users(user_id) ON DELETE SET NULL,
+    action admin_action NOT NULL,
+    target_user_id UUID REFERENCES users(user_id) ON DELETE SET NULL,
+    reason TEXT NOT NULL CHECK (char_length(reason) BETWEEN 1 AND 1000),
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_admin_audit_log_created ON admin_audit_log (created_at DESC);
+CREATE INDEX idx_admin_audit_log_target ON admin_audit_log (target_user_id, created_at DESC);
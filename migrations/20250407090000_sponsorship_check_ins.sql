@@ -0,0 +1,59 @@
+-- Add migration script here
+ALTER TYPE announcement_type ADD VALUE IF NOT EXISTS 'checkinreminder';
+ALTER TYPE announcement_target ADD VALUE IF NOT EXISTS 'sponsorship';
+ALTER TYPE notification_kind ADD VALUE IF NOT EXISTS 'check_in';
+
+-- Reminders are read per recipient, newest first
+CREATE INDEX idx_announcements_recipient ON announcements (recipient_id, created_at DESC);
+
+-- CHECK-IN SCHEDULES TABLE
+-- How often the member is prompted to check in; at most one schedule per sponsorship
+CREATE TABLE check_in_schedules (
+    sponsorship_id UUID PRIMARY KEY REFERENCES sponsorships(sponsorship_id) ON DELETE CASCADE,
+    frequency_days INTEGER NOT NULL CHECK (frequency_days BETWEEN 1 AND 90),
+    next_due_at TIMESTAMP NOT NULL,
+    updated_by UUID REFERENCES users(user_id) ON DELETE SET NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_check_in_schedules_due ON check_in_schedules (next_due_at);
+
+-- CHECK-INS TABLE
+-- Mood and progress entries written by the member; the sponsor only sees shared ones
+CREATE TABLE check_ins (
+    check_in_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    sponsorship_id UUID NOT NULL REFERENCES sponsorships(sponsorship_id) ON DELETE CASCADE,
+    member_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    mood INTEGER NOT NULL CHECK (mood BETWEEN 1 AND 5),
+    progress TEXT CHECK (char_length(progress) <= 2000),
+    shared_with_sponsor BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP
+);
+
+CREATE INDEX idx_check_ins_sponsorship_created ON check_ins (sponsorship_id, created_at DESC);
+
+-- SPONSOR NOTES TABLE
+-- The sponsor's own notes about a sponsorship; never shown to the member
+CREATE TABLE sponsor_notes (
+    note_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    sponsorship_id UUID NOT NULL REFERENCES sponsorships(sponsorship_id) ON DELETE CASCADE,
+    sponsor_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    content TEXT NOT NULL CHECK (char_length(content) BETWEEN 1 AND 5000),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP
+);
+
+CREATE INDEX idx_sponsor_notes_sponsorship_created ON sponsor_notes (sponsorship_id, created_at DESC);
+
+-- CHECK-IN MILESTONES TABLE
+-- Each milestone is recorded once, when it is first reached
+CREATE TYPE check_in_milestone_kind AS ENUM ('check_ins', 'streak');
+
+CREATE TABLE check_in_milestones (
+    sponsorship_id UUID NOT NULL REFERENCES sponsorships(sponsorship_id) ON DELETE CASCADE,
+    kind check_in_milestone_kind NOT NULL,
+    value INTEGER NOT NULL CHECK (value > 0),
+    reached_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (sponsorship_id, kind, value)
+);
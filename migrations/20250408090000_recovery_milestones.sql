@@ -0,0 +1,50 @@
+-- Add migration script here
+ALTER TYPE notification_kind ADD VALUE IF NOT EXISTS 'recovery_milestone';
+
+-- Who can see a user's clean time; private profiles keep it to themselves
+ALTER TABLE user_privacy_settings
+    ADD COLUMN recovery visibility_audience NOT NULL DEFAULT 'sponsor';
+UPDATE user_privacy_settings s SET recovery = 'nobody'
+FROM users u WHERE u.user_id = s.user_id AND u.privacy = true;
+
+-- Posts can belong to a support group, in which case only its members see them
+ALTER TABLE posts
+    ADD COLUMN support_group_id UUID REFERENCES support_groups(support_group_id) ON DELETE CASCADE;
+CREATE INDEX idx_posts_support_group ON posts (support_group_id, created_at DESC)
+    WHERE support_group_id IS NOT NULL;
+
+-- RECOVERY JOURNEYS TABLE
+-- Clean/sober time for one experience area (an experience taxonomy code)
+CREATE TABLE recovery_journeys (
+    journey_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    experience TEXT NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    share_with_groups BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP,
+    UNIQUE (user_id, experience)
+);
+
+-- RECOVERY RESETS TABLE
+-- Each time the count starts over; the note is only ever shown to the user
+CREATE TABLE recovery_resets (
+    reset_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    journey_id UUID NOT NULL REFERENCES recovery_journeys(journey_id) ON DELETE CASCADE,
+    previous_started_at TIMESTAMP NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    note TEXT CHECK (char_length(note) <= 2000),
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_recovery_resets_journey ON recovery_resets (journey_id, created_at DESC);
+
+-- RECOVERY MILESTONES TABLE
+-- Milestones reached since a given start; a reset starts a fresh set
+CREATE TABLE recovery_milestones (
+    journey_id UUID NOT NULL REFERENCES recovery_journeys(journey_id) ON DELETE CASCADE,
+    started_at TIMESTAMP NOT NULL,
+    days INTEGER NOT NULL CHECK (days > 0),
+    reached_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (journey_id, started_at, days)
+);
@@ -0,0 +1,30 @@
+-- Add migration script here
+ALTER TYPE meeting_status ADD VALUE IF NOT EXISTS 'cancelled';
+
+-- A recurring meeting; occurrences are materialised into group_meetings ahead of time
+CREATE TABLE meeting_series (
+    series_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    support_group_id UUID NOT NULL REFERENCES support_groups(support_group_id) ON DELETE CASCADE,
+    host_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    title TEXT NOT NULL CHECK (char_length(title) >= 5),
+    description TEXT,
+    -- RRULE subset, e.g. FREQ=WEEKLY;INTERVAL=2;BYDAY=TU or FREQ=MONTHLY;BYDAY=-1FR
+    rrule TEXT NOT NULL,
+    starts_on DATE NOT NULL,
+    start_time TIME NOT NULL,
+    -- Set when the series was split off another by a "this and following" edit
+    parent_series_id UUID REFERENCES meeting_series(series_id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    ended_at TIMESTAMP
+);
+
+CREATE INDEX idx_meeting_series_group ON meeting_series (support_group_id);
+CREATE INDEX idx_meeting_series_active ON meeting_series (series_id) WHERE ended_at IS NULL;
+
+ALTER TABLE group_meetings
+    ADD COLUMN series_id UUID REFERENCES meeting_series(series_id) ON DELETE SET NULL,
+    ADD COLUMN occurrence_date DATE,
+    -- An occurrence edited on its own keeps its changes when the series is edited
+    ADD COLUMN series_exception BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD CONSTRAINT group_meetings_series_occurrence_key UNIQUE (series_id, occurrence_date);
@@ -0,0 +1,14 @@
+-- Add migration script here
+-- Meeting times become UTC instants; the zone they were planned in is kept alongside.
+-- Existing times had no zone, so they are taken to be UTC.
+ALTER TABLE group_meetings
+    ALTER COLUMN scheduled_time TYPE TIMESTAMPTZ USING scheduled_time AT TIME ZONE 'UTC',
+    ADD COLUMN timezone TEXT NOT NULL DEFAULT 'UTC';
+
+-- A series' start date and time are wall-clock values in its zone, so it keeps its local time across DST
+ALTER TABLE meeting_series
+    ADD COLUMN timezone TEXT NOT NULL DEFAULT 'UTC';
+
+-- IANA name, e.g. Europe/London; NULL shows times in UTC
+ALTER TABLE users
+    ADD COLUMN timezone TEXT;
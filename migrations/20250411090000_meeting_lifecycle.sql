@@ -0,0 +1,24 @@
+-- Add migration script here
+-- A meeting whose host never started it
+ALTER TYPE meeting_status ADD VALUE IF NOT EXISTS 'missed';
+
+ALTER TABLE group_meetings
+    ADD COLUMN started_at TIMESTAMPTZ,
+    ADD COLUMN ended_at TIMESTAMPTZ,
+    -- Set when the scheduler ended a meeting that ran past the maximum duration
+    ADD COLUMN ended_automatically BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Meetings already running or finished get their best known times
+UPDATE group_meetings SET started_at = scheduled_time WHERE status IN ('ongoing', 'ended');
+
+CREATE INDEX idx_group_meetings_active ON group_meetings (scheduled_time)
+    WHERE status IN ('upcoming', 'ongoing');
+
+-- Reminders already sent; keyed by the scheduled time so a moved meeting is reminded again
+CREATE TABLE meeting_reminders (
+    meeting_id UUID NOT NULL REFERENCES group_meetings(meeting_id) ON DELETE CASCADE,
+    offset_minutes INT NOT NULL,
+    scheduled_time TIMESTAMPTZ NOT NULL,
+    sent_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (meeting_id, offset_minutes, scheduled_time)
+);
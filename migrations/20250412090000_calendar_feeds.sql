@@ -0,0 +1,33 @@
+-- Add migration script here
+-- Calendar clients match events by UID and take the highest SEQUENCE as the latest version
+ALTER TABLE group_meetings
+    ADD COLUMN sequence INT NOT NULL DEFAULT 0,
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE FUNCTION touch_group_meeting() RETURNS TRIGGER
+LANGUAGE plpgsql AS $$
+BEGIN
+    IF (NEW.title, NEW.description, NEW.scheduled_time, NEW.timezone, NEW.status)
+       IS DISTINCT FROM
+       (OLD.title, OLD.description, OLD.scheduled_time, OLD.timezone, OLD.status) THEN
+        NEW.sequence := OLD.sequence + 1;
+        NEW.updated_at := clock_timestamp();
+    END IF;
+    RETURN NEW;
+END
+$$;
+
+CREATE TRIGGER group_meetings_touch
+    BEFORE UPDATE ON group_meetings
+    FOR EACH ROW EXECUTE FUNCTION touch_group_meeting();
+
+-- Secret tokens for private per-user calendar feeds; a user has at most one active feed
+CREATE TABLE calendar_feeds (
+    feed_id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    token TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE UNIQUE INDEX idx_calendar_feeds_active ON calendar_feeds (user_id) WHERE revoked_at IS NULL;
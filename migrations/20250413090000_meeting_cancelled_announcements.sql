@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TYPE announcement_type ADD VALUE IF NOT EXISTS 'meetingcancelled';
@@ -0,0 +1,206 @@
+use crate::handlers::b2_storage::B2Client;
+use crate::handlers::image_processing::stored_avatar_file_names;
+use crate::handlers::meeting_series::end_series;
+use crate::handlers::sponsorships::end_sponsorships_of;
+use crate::models::all_models::SponsorshipEndReason;
+use log::{error, info, warn};
+use sqlx::PgPool;
+use std::error::Error;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Days a user has to cancel a deletion request before the account is anonymised
+pub const ACCOUNT_DELETION_GRACE_DAYS: i64 = 30;
+
+/// How often the background job looks for accounts due for anonymisation
+const DELETION_JOB_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Avatar shown for every anonymised account
+const DELETED_USER_AVATAR_URL: &str =
+    "https://ui-avatars.com/api/?name=Deleted+User&background=cccccc&size=256";
+
+// Replace every personal field on the users row with a tombstone.
+// The row itself stays so messages, posts and reports keep their author.
+const ANONYMISE_USER_QUERY: &str = "
+    UPDATE users SET
+        username = 'deleted-user-' || left(replace(user_id::text, '-', ''), 12),
+        email = 'deleted-' || user_id::text || '@deleted.invalid',
+        password_hash = '!',
+        role = 'member',
+        avatar_url = $2,
+        dob = DATE '1900-01-01',
+        user_profile = 'This account has been deleted.',
+        bio = NULL,
+        email_verified = false,
+        email_verification_token = NULL,
+        forgot_password_token = NULL,
+        forgot_password_expires_at = NULL,
+        location = NULL,
+        interests = NULL,
+        experience = NULL,
+        available_days = NULL,
+        languages = NULL,
+        privacy = true,
+        deleted_at = NOW()
+    WHERE user_id = $1 AND deleted_at IS NULL AND deletion_scheduled_for <= NOW()
+";
+
+// Anonymise one account and drop data that only matters to the user themselves.
+// Returns false if the deletion was cancelled in the meantime.
+pub async fn anonymise_user(
+    pool: &PgPool,
+    b2_client: &B2Client,
+    user_id: Uuid,
+) -> Result<bool, Box<dyn Error>> {
+    let mut tx = pool.begin().await?;
+
+    let avatar_url: Option<String> =
+        sqlx::query_scalar("SELECT avatar_url FROM users WHERE user_id = $1 FOR UPDATE")
+            .bind(user_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+    let updated = sqlx::query(ANONYMISE_USER_QUERY)
+        .bind(user_id)
+        .bind(DELETED_USER_AVATAR_URL)
+        .execute(&mut *tx)
+        .await?;
+    if updated.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    // Group memberships and open requests would otherwise show a ghost user
+    sqlx::query("DELETE FROM support_group_members WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "DELETE FROM meeting_participants WHERE user_id = $1
+         AND meeting_id IN (SELECT meeting_id FROM group_meetings WHERE status = 'upcoming')",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    // Nobody is left to host their recurring meetings
+    let hosted_series: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT series_id FROM meeting_series WHERE host_id = $1 AND ended_at IS NULL",
+    )
+    .bind(user_id)
+    .fetch_all(&mut *tx)
+    .await?;
+    for series_id in hosted_series {
+        end_series(&mut tx, series_id).await?;
+    }
+    sqlx::query(
+        "UPDATE matching_requests SET status = 'declined'
+         WHERE (member_id = $1 OR sponsor_id = $1) AND status = 'pending'",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "DELETE FROM sponsor_applications
+         WHERE user_id = $1 AND status IN ('pending', 'info_requested')",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    end_sponsorships_of(&mut tx, user_id, SponsorshipEndReason::AccountDeleted).await?;
+
+    sqlx::query("DELETE FROM notifications WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM announcements WHERE recipient_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // The member's check-ins go with them; notes about a member go if either side leaves
+    sqlx::query(
+        "DELETE FROM check_ins
+         WHERE sponsorship_id IN (SELECT sponsorship_id FROM sponsorships WHERE member_id = $1)",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "DELETE FROM sponsor_notes
+         WHERE sponsor_id = $1
+            OR sponsorship_id IN (SELECT sponsorship_id FROM sponsorships WHERE member_id = $1)",
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
+    // Recovery dates and reset notes are private; resets and milestones cascade
+    sqlx::query("DELETE FROM recovery_journeys WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // Feed URLs would otherwise keep listing meetings for anyone holding the link
+    sqlx::query("DELETE FROM calendar_feeds WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // Export archives are a full copy of the user's data; their B2 files are left for storage GC
+    sqlx::query("DELETE FROM data_exports WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    if let Some(url) = avatar_url.filter(|url| url.contains("/file/")) {
+        let file_name = url.rsplit('/').next().unwrap_or_default();
+        for name in stored_avatar_file_names(file_name) {
+            if let Err(e) = b2_client.delete_file(&name).await {
+                // Storage GC will pick it up on its next run
+                warn!("Failed to delete avatar {} of deleted user: {:?}", name, e);
+            }
+        }
+    }
+
+    info!("Anonymised deleted account {}", user_id);
+    Ok(true)
+}
+
+// Anonymise every account whose grace period has ended
+pub async fn process_due_deletions(
+    pool: &PgPool,
+    b2_client: &B2Client,
+) -> Result<usize, sqlx::Error> {
+    let due: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT user_id FROM users WHERE deleted_at IS NULL AND deletion_scheduled_for <= NOW()",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut anonymised = 0;
+    for user_id in due {
+        match anonymise_user(pool, b2_client, user_id).await {
+            Ok(true) => anonymised += 1,
+            Ok(false) => {}
+            Err(e) => error!("Failed to anonymise user {}: {}", user_id, e),
+        }
+    }
+    Ok(anonymised)
+}
+
+// Spawn the periodic account deletion job
+pub fn spawn_account_deletion_job(pool: PgPool, b2_client: B2Client) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DELETION_JOB_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            match process_due_deletions(&pool, &b2_client).await {
+                Ok(0) => {}
+                Ok(count) => info!("Account deletion job anonymised {} accounts", count),
+                Err(e) => warn!("Account deletion job failed: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,50 @@
+use crate::handlers::ws::send_to_user;
+use crate::models::all_models::{Announcement, AnnouncementTarget, AnnouncementType};
+use serde_json::{json, Value};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// An announcement addressed to a single user
+pub struct UserAnnouncement<'a> {
+    pub announcement_type: AnnouncementType,
+    pub target: Option<AnnouncementTarget>,
+    pub target_id: Option<Uuid>,
+    pub recipient_id: Uuid,
+    pub extra_data: Option<Value>,
+    pub message: &'a str,
+}
+
+// Store an announcement for one user.
+// Runs on the caller's transaction; push the result once it has committed.
+pub async fn create_user_announcement(
+    conn: &mut PgConnection,
+    announcement: UserAnnouncement<'_>,
+) -> Result<Announcement, sqlx::Error> {
+    sqlx::query_as::<_, Announcement>(
+        "INSERT INTO announcements (
+            announcement_type, announcement_target, announcement_target_id, recipient_id,
+            extra_data, message
+         )
+         VALUES ($1, $2, $3, $4, $5, $6)
+         RETURNING *",
+    )
+    .bind(announcement.announcement_type)
+    .bind(announcement.target)
+    .bind(announcement.target_id)
+    .bind(announcement.recipient_id)
+    .bind(announcement.extra_data)
+    .bind(announcement.message)
+    .fetch_one(conn)
+    .await
+}
+
+// Send user announcements to recipients who are connected; the others see them when they next list theirs
+pub async fn push_announcements(announcements: &[Announcement]) {
+    for announcement in announcements {
+        if let Some(recipient_id) = announcement.recipient_id {
+            let message = json!({ "type": "announcement", "payload": announcement });
+            // Not being connected isn't an error here
+            let _ = send_to_user(&recipient_id, message).await;
+        }
+    }
+}
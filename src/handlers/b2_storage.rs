@@ -70,33 +70,90 @@ pub struct UploadFileResponse {
     pub upload_timestamp: u64,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StartLargeFileResponse {
+    #[serde(rename = "fileId")]
+    pub file_id: String,
+    #[serde(rename = "fileName")]
+    pub file_name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GetUploadPartUrlResponse {
+    #[serde(rename = "authorizationToken")]
+    pub authorization_token: String,
+    #[serde(rename = "fileId")]
+    pub file_id: String,
+    #[serde(rename = "uploadUrl")]
+    pub upload_url: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UploadPartResponse {
+    #[serde(rename = "partNumber")]
+    pub part_number: u32,
+    #[serde(rename = "contentLength")]
+    pub content_length: u64,
+    #[serde(rename = "contentSha1")]
+    pub content_sha1: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct GetDownloadAuthorizationResponse {
+    #[serde(rename = "authorizationToken")]
+    pub authorization_token: String,
+    #[serde(rename = "bucketId")]
+    pub bucket_id: String,
+    #[serde(rename = "fileNamePrefix")]
+    pub file_name_prefix: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DeleteFileRequest {
+    #[serde(rename = "fileName")]
     pub file_name: String,
+    #[serde(rename = "fileId")]
     pub file_id: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct DeleteFileResponse {
+    #[serde(rename = "fileId")]
     pub file_id: String,
+    #[serde(rename = "fileName")]
     pub file_name: String,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ListFileNamesResponse {
     pub files: Vec<FileInfo>,
+    #[serde(rename = "nextFileName")]
     pub next_file_name: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct FileInfo {
+    #[serde(rename = "fileId")]
     pub file_id: String,
+    #[serde(rename = "fileName")]
     pub file_name: String,
-    pub content_type: String,
+    #[serde(rename = "contentType")]
+    pub content_type: Option<String>,
+    #[serde(rename = "contentLength")]
     pub content_length: u64,
+    #[serde(rename = "uploadTimestamp")]
     pub upload_timestamp: u64,
 }
 
+// Which bucket a file lives in.
+// Public files (avatars) are served from permanent URLs; private files
+// (attachments, application documents) are only reachable through short-lived signed URLs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBucket {
+    Public,
+    Private,
+}
+
 // B2 client with caching for auth tokens
 #[derive(Clone)]
 pub struct B2Client {
@@ -106,6 +163,9 @@ pub struct B2Client {
     application_key_id: String,
     application_key: String,
     bucket_id: String,
+    bucket_name: String,
+    private_bucket_id: String,
+    private_bucket_name: String,
 }
 
 impl B2Client {
@@ -113,6 +173,9 @@ impl B2Client {
         application_key_id: String,
         application_key: String,
         bucket_id: String,
+        bucket_name: String,
+        private_bucket_id: String,
+        private_bucket_name: String,
     ) -> Result<Self, Box<dyn Error>> {
         let client = Client::builder().timeout(Duration::from_secs(60)).build()?;
 
@@ -123,9 +186,26 @@ impl B2Client {
             application_key_id,
             application_key,
             bucket_id,
+            bucket_name,
+            private_bucket_id,
+            private_bucket_name,
         })
     }
 
+    fn bucket_id_for(&self, bucket: StorageBucket) -> &str {
+        match bucket {
+            StorageBucket::Public => &self.bucket_id,
+            StorageBucket::Private => &self.private_bucket_id,
+        }
+    }
+
+    fn bucket_name_for(&self, bucket: StorageBucket) -> &str {
+        match bucket {
+            StorageBucket::Public => &self.bucket_name,
+            StorageBucket::Private => &self.private_bucket_name,
+        }
+    }
+
     // Create a new B2Client from a SecretStore
     pub fn from_secrets(secrets: &shuttle_runtime::SecretStore) -> Result<Self, Box<dyn Error>> {
         let application_key_id = secrets
@@ -143,23 +223,46 @@ impl B2Client {
             .ok_or("B2_BUCKET_ID not found in secrets")?
             .to_string();
 
-        Self::new(application_key_id, application_key, bucket_id)
+        let bucket_name = secrets
+            .get("B2_BUCKET_NAME")
+            .unwrap_or_else(|| "BTH-User-Avatars".to_string());
+
+        let private_bucket_id = secrets
+            .get("B2_PRIVATE_BUCKET_ID")
+            .ok_or("B2_PRIVATE_BUCKET_ID not found in secrets")?
+            .to_string();
+
+        let private_bucket_name = secrets
+            .get("B2_PRIVATE_BUCKET_NAME")
+            .ok_or("B2_PRIVATE_BUCKET_NAME not found in secrets")?
+            .to_string();
+
+        Self::new(
+            application_key_id,
+            application_key,
+            bucket_id,
+            bucket_name,
+            private_bucket_id,
+            private_bucket_name,
+        )
     }
 
     // Authorize account and get auth token
     async fn authorize_account(&self) -> Result<AuthorizeAccountResponse, Box<dyn Error>> {
-        // Check if we have a valid auth token (less than 23 hours old)
-        let auth_time_guard = self.auth_time.lock().unwrap();
-        let auth_data_guard = self.auth_data.lock().unwrap();
-
-        if let (Some(auth_time), Some(auth_data)) = (&*auth_time_guard, &*auth_data_guard) {
-            if auth_time.elapsed() < Duration::from_secs(23 * 60 * 60) {
-                debug!("Using cached B2 authorization token");
-                return Ok(auth_data.clone());
+        // Check if we have a valid auth token (less than 23 hours old).
+        // The guards live in their own scope so they are released before any await,
+        // which keeps this future Send for use from background tasks.
+        {
+            let auth_time_guard = self.auth_time.lock().unwrap();
+            let auth_data_guard = self.auth_data.lock().unwrap();
+
+            if let (Some(auth_time), Some(auth_data)) = (&*auth_time_guard, &*auth_data_guard) {
+                if auth_time.elapsed() < Duration::from_secs(23 * 60 * 60) {
+                    debug!("Using cached B2 authorization token");
+                    return Ok(auth_data.clone());
+                }
             }
         }
-        drop(auth_time_guard);
-        drop(auth_data_guard);
 
         info!(
             "Authorizing B2 account with key ID: {}",
@@ -219,17 +322,21 @@ impl B2Client {
     }
 
     // Get upload URL
-    async fn get_upload_url(&self) -> Result<GetUploadUrlResponse, Box<dyn Error>> {
+    async fn get_upload_url(
+        &self,
+        bucket: StorageBucket,
+    ) -> Result<GetUploadUrlResponse, Box<dyn Error>> {
         let auth = self.authorize_account().await?;
+        let bucket_id = self.bucket_id_for(bucket);
 
-        info!("Getting upload URL for bucket: {}", self.bucket_id);
+        info!("Getting upload URL for bucket: {}", bucket_id);
 
         let response = self
             .client
             .post(format!("{}/b2api/v2/b2_get_upload_url", auth.api_url))
             .header(header::AUTHORIZATION, &auth.authorization_token)
             .json(&serde_json::json!({
-                "bucketId": self.bucket_id
+                "bucketId": bucket_id
             }))
             .send()
             .await?;
@@ -261,14 +368,33 @@ impl B2Client {
         Ok(upload_url)
     }
 
-    // Upload file to B2
+    // Upload file to the public bucket, returns its permanent download URL
     pub async fn upload_file(
         &self,
         file_data: &[u8],
         file_name: &str,
         content_type: &str,
     ) -> Result<String, Box<dyn Error>> {
-        let upload_url = self.get_upload_url().await?;
+        let upload_response = self
+            .upload_file_to(StorageBucket::Public, file_data, file_name, content_type)
+            .await?;
+
+        // Construct the download URL
+        let download_url = self.download_url_for(&upload_response.file_name).await?;
+
+        info!("File uploaded successfully: {}", download_url);
+        Ok(download_url)
+    }
+
+    // Upload file to the given bucket
+    pub async fn upload_file_to(
+        &self,
+        bucket: StorageBucket,
+        file_data: &[u8],
+        file_name: &str,
+        content_type: &str,
+    ) -> Result<UploadFileResponse, Box<dyn Error>> {
+        let upload_url = self.get_upload_url(bucket).await?;
 
         // Calculate SHA1 hash
         let mut hasher = Sha1::new();
@@ -288,7 +414,7 @@ impl B2Client {
             .client
             .post(&upload_url.upload_url)
             .header(header::AUTHORIZATION, &upload_url.authorization_token)
-            .header("X-Bz-File-Name", file_name)
+            .header("X-Bz-File-Name", encode_file_name(file_name))
             .header("Content-Type", content_type)
             .header("Content-Length", file_data.len().to_string())
             .header("X-Bz-Content-Sha1", sha1_hex)
@@ -319,19 +445,232 @@ impl B2Client {
             }
         };
 
-        // Construct the download URL
+        info!("File uploaded successfully: {}", upload_response.file_name);
+        Ok(upload_response)
+    }
+
+    // Public download URL for a file in the public bucket
+    async fn download_url_for(&self, file_name: &str) -> Result<String, Box<dyn Error>> {
         let auth = self.authorize_account().await?;
-        let download_url = format!(
-            "{}/file/BTH-User-Avatars/{}",
-            auth.download_url, upload_response.file_name
+        Ok(format!(
+            "{}/file/{}/{}",
+            auth.download_url,
+            self.bucket_name,
+            encode_file_name(file_name)
+        ))
+    }
+
+    // Issue a short-lived download URL for a single private file
+    pub async fn signed_download_url(
+        &self,
+        file_name: &str,
+        valid_for: Duration,
+    ) -> Result<String, Box<dyn Error>> {
+        let auth = self.authorize_account().await?;
+
+        let response = self
+            .client
+            .post(format!(
+                "{}/b2api/v2/b2_get_download_authorization",
+                auth.api_url
+            ))
+            .header(header::AUTHORIZATION, &auth.authorization_token)
+            .json(&serde_json::json!({
+                "bucketId": self.private_bucket_id,
+                "fileNamePrefix": file_name,
+                "validDurationInSeconds": valid_for.as_secs()
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            error!("Failed to get download authorization: {}", error_text);
+            return Err(format!("Failed to get download authorization: {}", error_text).into());
+        }
+
+        let authorization: GetDownloadAuthorizationResponse = response.json().await?;
+        debug!(
+            "Issued download authorization for {} ({}s)",
+            authorization.file_name_prefix,
+            valid_for.as_secs()
         );
 
-        info!("File uploaded successfully: {}", download_url);
-        Ok(download_url)
+        Ok(format!(
+            "{}/file/{}/{}?Authorization={}",
+            auth.download_url,
+            self.private_bucket_name,
+            encode_file_name(file_name),
+            authorization.authorization_token
+        ))
+    }
+
+    // Minimum part size B2 accepts for large file parts (all but the last)
+    pub async fn minimum_part_size(&self) -> Result<u64, Box<dyn Error>> {
+        let auth = self.authorize_account().await?;
+        Ok(auth.absolute_minimum_part_size)
+    }
+
+    // Start a large file upload
+    pub async fn start_large_file(
+        &self,
+        bucket: StorageBucket,
+        file_name: &str,
+        content_type: &str,
+    ) -> Result<StartLargeFileResponse, Box<dyn Error>> {
+        let auth = self.authorize_account().await?;
+
+        info!("Starting B2 large file upload: {}", file_name);
+
+        let response = self
+            .client
+            .post(format!("{}/b2api/v2/b2_start_large_file", auth.api_url))
+            .header(header::AUTHORIZATION, &auth.authorization_token)
+            .json(&serde_json::json!({
+                "bucketId": self.bucket_id_for(bucket),
+                "fileName": file_name,
+                "contentType": content_type
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            error!("Failed to start large file: {}", error_text);
+            return Err(format!("Failed to start large file: {}", error_text).into());
+        }
+
+        let started: StartLargeFileResponse = response.json().await?;
+        info!("Started large file {} with ID {}", started.file_name, started.file_id);
+        Ok(started)
+    }
+
+    // Get an upload URL for the parts of a large file
+    pub async fn get_upload_part_url(
+        &self,
+        file_id: &str,
+    ) -> Result<GetUploadPartUrlResponse, Box<dyn Error>> {
+        let auth = self.authorize_account().await?;
+
+        let response = self
+            .client
+            .post(format!("{}/b2api/v2/b2_get_upload_part_url", auth.api_url))
+            .header(header::AUTHORIZATION, &auth.authorization_token)
+            .json(&serde_json::json!({
+                "fileId": file_id
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            error!("Failed to get upload part URL: {}", error_text);
+            return Err(format!("Failed to get upload part URL: {}", error_text).into());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    // Upload one part of a large file, returns the part's SHA1 hex digest
+    pub async fn upload_part(
+        &self,
+        part_url: &GetUploadPartUrlResponse,
+        part_number: u32,
+        part_data: Vec<u8>,
+    ) -> Result<String, Box<dyn Error>> {
+        let mut hasher = Sha1::new();
+        hasher.update(&part_data);
+        let sha1_hex = format!("{:x}", hasher.finalize());
+
+        debug!(
+            "Uploading part {} ({} bytes) of large file {}",
+            part_number,
+            part_data.len(),
+            part_url.file_id
+        );
+
+        let response = self
+            .client
+            .post(&part_url.upload_url)
+            .header(header::AUTHORIZATION, &part_url.authorization_token)
+            .header("X-Bz-Part-Number", part_number.to_string())
+            .header("Content-Length", part_data.len().to_string())
+            .header("X-Bz-Content-Sha1", &sha1_hex)
+            .body(part_data)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            error!("Failed to upload part {}: {}", part_number, error_text);
+            return Err(format!("Failed to upload part {}: {}", part_number, error_text).into());
+        }
+
+        let uploaded: UploadPartResponse = response.json().await?;
+        Ok(uploaded.content_sha1)
+    }
+
+    // Finish a large file upload
+    pub async fn finish_large_file(
+        &self,
+        file_id: &str,
+        part_sha1_array: &[String],
+    ) -> Result<UploadFileResponse, Box<dyn Error>> {
+        let auth = self.authorize_account().await?;
+
+        let response = self
+            .client
+            .post(format!("{}/b2api/v2/b2_finish_large_file", auth.api_url))
+            .header(header::AUTHORIZATION, &auth.authorization_token)
+            .json(&serde_json::json!({
+                "fileId": file_id,
+                "partSha1Array": part_sha1_array
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            error!("Failed to finish large file: {}", error_text);
+            return Err(format!("Failed to finish large file: {}", error_text).into());
+        }
+
+        let finished: UploadFileResponse = response.json().await?;
+
+        info!("Large file uploaded successfully: {}", finished.file_name);
+        Ok(finished)
+    }
+
+    // Cancel an unfinished large file, discarding uploaded parts
+    pub async fn cancel_large_file(&self, file_id: &str) -> Result<(), Box<dyn Error>> {
+        let auth = self.authorize_account().await?;
+
+        let response = self
+            .client
+            .post(format!("{}/b2api/v2/b2_cancel_large_file", auth.api_url))
+            .header(header::AUTHORIZATION, &auth.authorization_token)
+            .json(&serde_json::json!({
+                "fileId": file_id
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            error!("Failed to cancel large file: {}", error_text);
+            return Err(format!("Failed to cancel large file: {}", error_text).into());
+        }
+
+        info!("Cancelled large file: {}", file_id);
+        Ok(())
     }
 
     // Find file ID by name
-    async fn find_file_id(&self, file_name: &str) -> Result<Option<String>, Box<dyn Error>> {
+    async fn find_file_id(
+        &self,
+        bucket: StorageBucket,
+        file_name: &str,
+    ) -> Result<Option<String>, Box<dyn Error>> {
         let auth = self.authorize_account().await?;
 
         let response = self
@@ -339,7 +678,7 @@ impl B2Client {
             .post(format!("{}/b2api/v2/b2_list_file_names", auth.api_url))
             .header(header::AUTHORIZATION, &auth.authorization_token)
             .json(&serde_json::json!({
-                "bucketId": self.bucket_id,
+                "bucketId": self.bucket_id_for(bucket),
                 "prefix": file_name,
                 "maxFileCount": 1
             }))
@@ -364,10 +703,103 @@ impl B2Client {
         Ok(None)
     }
 
-    // Delete file from B2
+    // List one page of file names in a bucket, starting at start_file_name
+    pub async fn list_file_names(
+        &self,
+        bucket: StorageBucket,
+        start_file_name: Option<&str>,
+        max_file_count: u32,
+    ) -> Result<ListFileNamesResponse, Box<dyn Error>> {
+        let auth = self.authorize_account().await?;
+
+        let response = self
+            .client
+            .post(format!("{}/b2api/v2/b2_list_file_names", auth.api_url))
+            .header(header::AUTHORIZATION, &auth.authorization_token)
+            .json(&serde_json::json!({
+                "bucketId": self.bucket_id_for(bucket),
+                "startFileName": start_file_name,
+                "maxFileCount": max_file_count
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            error!("Failed to list files: {}", error_text);
+            return Err(format!("Failed to list files: {}", error_text).into());
+        }
+
+        Ok(response.json().await?)
+    }
+
+    // List every file in a bucket, following B2's pagination
+    pub async fn list_all_files(&self, bucket: StorageBucket) -> Result<Vec<FileInfo>, Box<dyn Error>> {
+        let mut files = Vec::new();
+        let mut start_file_name: Option<String> = None;
+
+        loop {
+            let page = self
+                .list_file_names(bucket, start_file_name.as_deref(), 1000)
+                .await?;
+            files.extend(page.files);
+
+            match page.next_file_name {
+                Some(next) => start_file_name = Some(next),
+                None => break,
+            }
+        }
+
+        debug!(
+            "Listed {} files in bucket {}",
+            files.len(),
+            self.bucket_name_for(bucket)
+        );
+        Ok(files)
+    }
+
+    // Delete a specific file version when its ID is already known
+    pub async fn delete_file_version(
+        &self,
+        file_name: &str,
+        file_id: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        let auth = self.authorize_account().await?;
+
+        let response = self
+            .client
+            .post(format!("{}/b2api/v2/b2_delete_file_version", auth.api_url))
+            .header(header::AUTHORIZATION, &auth.authorization_token)
+            .json(&DeleteFileRequest {
+                file_name: file_name.to_string(),
+                file_id: file_id.to_string(),
+            })
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            error!("Failed to delete file: {}", error_text);
+            return Err(format!("Failed to delete file: {}", error_text).into());
+        }
+
+        info!("File version deleted successfully: {}", file_name);
+        Ok(())
+    }
+
+    // Delete file from the public bucket
     pub async fn delete_file(&self, file_name: &str) -> Result<(), Box<dyn Error>> {
+        self.delete_file_from(StorageBucket::Public, file_name).await
+    }
+
+    // Delete file from the given bucket
+    pub async fn delete_file_from(
+        &self,
+        bucket: StorageBucket,
+        file_name: &str,
+    ) -> Result<(), Box<dyn Error>> {
         // First, find the file ID
-        let file_id = match self.find_file_id(file_name).await? {
+        let file_id = match self.find_file_id(bucket, file_name).await? {
             Some(id) => id,
             None => {
                 info!("File not found for deletion: {}", file_name);
@@ -394,7 +826,26 @@ impl B2Client {
             return Err(format!("Failed to delete file: {}", error_text).into());
         }
 
-        info!("File deleted successfully: {}", file_name);
+        info!(
+            "File deleted successfully from {}: {}",
+            self.bucket_name_for(bucket),
+            file_name
+        );
         Ok(())
     }
 }
+
+// Percent-encode a B2 file name for use in a download URL or the X-Bz-File-Name header
+// (slashes are kept)
+fn encode_file_name(file_name: &str) -> String {
+    let mut encoded = String::with_capacity(file_name.len());
+    for byte in file_name.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
@@ -0,0 +1,59 @@
+use sqlx::PgPool;
+use std::collections::HashSet;
+use uuid::Uuid;
+
+// Check whether `blocker_id` has blocked `blocked_id`
+pub async fn has_blocked(
+    pool: &PgPool,
+    blocker_id: Uuid,
+    blocked_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM user_blocks WHERE blocker_id = $1 AND blocked_id = $2)",
+    )
+    .bind(blocker_id)
+    .bind(blocked_id)
+    .fetch_one(pool)
+    .await
+}
+
+// Check whether either user has blocked the other.
+// Contact is cut off in both directions so the blocker can't be drawn back into a conversation.
+pub async fn is_blocked_between(pool: &PgPool, a: Uuid, b: Uuid) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS(
+            SELECT 1 FROM user_blocks
+            WHERE (blocker_id = $1 AND blocked_id = $2) OR (blocker_id = $2 AND blocked_id = $1)
+        )",
+    )
+    .bind(a)
+    .bind(b)
+    .fetch_one(pool)
+    .await
+}
+
+// Users with a block in either direction
+pub async fn blocked_user_ids(pool: &PgPool, user_id: Uuid) -> Result<HashSet<Uuid>, sqlx::Error> {
+    let query = "
+        SELECT blocked_id FROM user_blocks WHERE blocker_id = $1
+        UNION
+        SELECT blocker_id FROM user_blocks WHERE blocked_id = $1
+    ";
+    let ids: Vec<Uuid> = sqlx::query_scalar(query)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(ids.into_iter().collect())
+}
+
+// Users whose content should be left out of this user's feeds: blocks either way plus mutes
+pub async fn hidden_user_ids(pool: &PgPool, user_id: Uuid) -> Result<HashSet<Uuid>, sqlx::Error> {
+    let mut hidden = blocked_user_ids(pool, user_id).await?;
+    let muted: Vec<Uuid> =
+        sqlx::query_scalar("SELECT muted_id FROM user_mutes WHERE muter_id = $1")
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?;
+    hidden.extend(muted);
+    Ok(hidden)
+}
@@ -0,0 +1,486 @@
+use crate::handlers::timezones::timezone_or_default;
+use crate::models::all_models::MeetingStatus;
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc,
+};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+use sqlx::{FromRow, PgConnection, PgPool};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+const CALENDAR_PRODID: &str = "-//BTH//Support Group Meetings//EN";
+
+/// Domain part of event UIDs; changing it would duplicate every event in subscribers' calendars
+const EVENT_UID_DOMAIN: &str = "meetings.bth";
+
+/// Meetings have no planned end, so calendars show them as this long
+pub const DEFAULT_EVENT_MINUTES: i64 = 60;
+
+/// How far back feeds include past meetings
+pub const CALENDAR_FEED_PAST_DAYS: i32 = 90;
+
+/// Where the server is publicly reachable when none is configured
+const DEFAULT_PUBLIC_BASE_URL: &str = "https://bth-server-ywjx.shuttle.app";
+
+/// Calendar feed settings
+#[derive(Debug, Clone)]
+pub struct CalendarConfig {
+    /// Base URL feed links are built on, without a trailing slash
+    pub public_base_url: String,
+}
+
+impl CalendarConfig {
+    // Read the PUBLIC_BASE_URL secret, defaulting to the deployed server. Feed links never
+    // come from the request's Host header, which a client controls
+    pub fn from_secrets(secrets: &shuttle_runtime::SecretStore) -> Self {
+        let public_base_url = secrets
+            .get("PUBLIC_BASE_URL")
+            .map(|url| url.trim().trim_end_matches('/').to_string())
+            .filter(|url| url.starts_with("https://") || url.starts_with("http://"))
+            .unwrap_or_else(|| DEFAULT_PUBLIC_BASE_URL.to_string());
+        CalendarConfig { public_base_url }
+    }
+
+    // The https and webcal URLs of a feed
+    pub fn feed_urls(&self, token: &str) -> (String, String) {
+        let url = format!(
+            "{}/api/public/calendar/feeds/{}.ics",
+            self.public_base_url, token
+        );
+        let without_scheme = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+        let webcal_url = format!("webcal://{}", without_scheme);
+        (url, webcal_url)
+    }
+}
+
+/// A meeting as it appears in a calendar
+#[derive(Debug, FromRow)]
+pub struct CalendarEvent {
+    pub meeting_id: Uuid,
+    pub group_title: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub scheduled_time: DateTime<Utc>,
+    pub timezone: String,
+    pub status: MeetingStatus,
+    pub sequence: i32,
+    pub updated_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+const EVENT_COLUMNS: &str = "
+    gm.meeting_id, sg.title AS group_title, gm.title, gm.description, gm.scheduled_time,
+    gm.timezone, gm.status, gm.sequence, gm.updated_at, gm.ended_at
+";
+
+// Meetings the user joined, from CALENDAR_FEED_PAST_DAYS ago on
+pub async fn user_feed_events(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<CalendarEvent>, sqlx::Error> {
+    let query = format!(
+        "SELECT {EVENT_COLUMNS}
+         FROM group_meetings gm
+         JOIN meeting_participants mp ON mp.meeting_id = gm.meeting_id
+         JOIN support_groups sg ON sg.support_group_id = gm.support_group_id
+         WHERE mp.user_id = $1
+           AND gm.scheduled_time >= NOW() - make_interval(days => $2)
+         ORDER BY gm.scheduled_time"
+    );
+    sqlx::query_as::<_, CalendarEvent>(&query)
+        .bind(user_id)
+        .bind(CALENDAR_FEED_PAST_DAYS)
+        .fetch_all(pool)
+        .await
+}
+
+// A support group's meetings, from CALENDAR_FEED_PAST_DAYS ago on
+pub async fn group_feed_events(
+    pool: &PgPool,
+    support_group_id: Uuid,
+) -> Result<Vec<CalendarEvent>, sqlx::Error> {
+    let query = format!(
+        "SELECT {EVENT_COLUMNS}
+         FROM group_meetings gm
+         JOIN support_groups sg ON sg.support_group_id = gm.support_group_id
+         WHERE gm.support_group_id = $1
+           AND gm.scheduled_time >= NOW() - make_interval(days => $2)
+         ORDER BY gm.scheduled_time"
+    );
+    sqlx::query_as::<_, CalendarEvent>(&query)
+        .bind(support_group_id)
+        .bind(CALENDAR_FEED_PAST_DAYS)
+        .fetch_all(pool)
+        .await
+}
+
+// A single meeting
+pub async fn meeting_event(
+    conn: &mut PgConnection,
+    meeting_id: Uuid,
+) -> Result<Option<CalendarEvent>, sqlx::Error> {
+    let query = format!(
+        "SELECT {EVENT_COLUMNS}
+         FROM group_meetings gm
+         JOIN support_groups sg ON sg.support_group_id = gm.support_group_id
+         WHERE gm.meeting_id = $1"
+    );
+    sqlx::query_as::<_, CalendarEvent>(&query)
+        .bind(meeting_id)
+        .fetch_optional(conn)
+        .await
+}
+
+// Escape TEXT values: backslashes, separators and line breaks
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Fold a content line at 75 octets without splitting a UTF-8 character, ending it with CRLF
+fn fold_line(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            // The leading space counts towards the continuation line's length
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+fn utc_stamp(instant: DateTime<Utc>) -> String {
+    instant.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn local_stamp(local: NaiveDateTime) -> String {
+    local.format("%Y%m%dT%H%M%S").to_string()
+}
+
+// UTC offset as +HHMM, or +HHMMSS for the odd historical offset
+fn offset_text(seconds: i32) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.abs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if seconds == 0 {
+        format!("{}{:02}{:02}", sign, hours, minutes)
+    } else {
+        format!("{}{:02}{:02}{:02}", sign, hours, minutes, seconds)
+    }
+}
+
+/// One period of a zone's offset, as a VTIMEZONE STANDARD or DAYLIGHT component
+#[derive(Debug, PartialEq)]
+struct Observance {
+    daylight: bool,
+    starts: NaiveDateTime,
+    offset_from: i32,
+    offset_to: i32,
+    name: Option<String>,
+}
+
+// The zone's offset at an instant: total seconds, whether it's DST, and its abbreviation
+fn offset_at(tz: Tz, instant: NaiveDateTime) -> (i32, bool, Option<String>) {
+    let offset = tz.offset_from_utc_datetime(&instant);
+    (
+        offset.fix().local_minus_utc(),
+        !offset.dst_offset().is_zero(),
+        offset.abbreviation().map(str::to_string),
+    )
+}
+
+// The offset in effect at the start of `from_year`, then every change up to the end of `to_year`.
+// chrono-tz doesn't expose its transitions, so each day is compared with the next and the
+// change is narrowed down to the second.
+fn observances(tz: Tz, from_year: i32, to_year: i32) -> Vec<Observance> {
+    let Some(start) = NaiveDate::from_ymd_opt(from_year, 1, 1) else {
+        return Vec::new();
+    };
+    let end = NaiveDate::from_ymd_opt(to_year + 1, 1, 1).unwrap_or(start);
+    let start = start.and_hms_opt(0, 0, 0).unwrap_or_default();
+    let end = end.and_hms_opt(0, 0, 0).unwrap_or_default();
+
+    let (offset, daylight, name) = offset_at(tz, start);
+    let mut result = vec![Observance {
+        daylight,
+        starts: start + ChronoDuration::seconds(offset.into()),
+        offset_from: offset,
+        offset_to: offset,
+        name,
+    }];
+
+    let mut current = offset_at(tz, start);
+    let mut day = start;
+    while day < end {
+        let next_day = day + ChronoDuration::days(1);
+        let next = offset_at(tz, next_day);
+        if next != current {
+            // Find the first second with the new offset
+            let (mut before, mut after) = (day, next_day);
+            while after - before > ChronoDuration::seconds(1) {
+                let middle = before + ChronoDuration::seconds((after - before).num_seconds() / 2);
+                if offset_at(tz, middle) == current {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+            let (offset, daylight, name) = offset_at(tz, after);
+            result.push(Observance {
+                daylight,
+                // DTSTART is the local time just before the change
+                starts: after + ChronoDuration::seconds(current.0.into()),
+                offset_from: current.0,
+                offset_to: offset,
+                name,
+            });
+        }
+        current = next;
+        day = next_day;
+    }
+    result
+}
+
+fn push_vtimezone(tz: Tz, from_year: i32, to_year: i32, out: &mut String) {
+    fold_line("BEGIN:VTIMEZONE", out);
+    fold_line(&format!("TZID:{}", tz.name()), out);
+    for observance in observances(tz, from_year, to_year) {
+        let kind = if observance.daylight {
+            "DAYLIGHT"
+        } else {
+            "STANDARD"
+        };
+        fold_line(&format!("BEGIN:{}", kind), out);
+        fold_line(&format!("DTSTART:{}", local_stamp(observance.starts)), out);
+        fold_line(
+            &format!("TZOFFSETFROM:{}", offset_text(observance.offset_from)),
+            out,
+        );
+        fold_line(
+            &format!("TZOFFSETTO:{}", offset_text(observance.offset_to)),
+            out,
+        );
+        if let Some(name) = observance.name {
+            fold_line(&format!("TZNAME:{}", escape_text(&name)), out);
+        }
+        fold_line(&format!("END:{}", kind), out);
+    }
+    fold_line("END:VTIMEZONE", out);
+}
+
+fn push_event(event: &CalendarEvent, tz: Tz, out: &mut String) {
+    let end = match event.ended_at {
+        Some(ended_at) if ended_at > event.scheduled_time => ended_at,
+        _ => event.scheduled_time + ChronoDuration::minutes(DEFAULT_EVENT_MINUTES),
+    };
+    // UTC meetings are written as UTC times so they don't need a VTIMEZONE
+    let time = |property: &str, instant: DateTime<Utc>| {
+        if tz == chrono_tz::UTC {
+            format!("{}:{}", property, utc_stamp(instant))
+        } else {
+            format!(
+                "{};TZID={}:{}",
+                property,
+                tz.name(),
+                local_stamp(instant.with_timezone(&tz).naive_local())
+            )
+        }
+    };
+
+    fold_line("BEGIN:VEVENT", out);
+    fold_line(
+        &format!("UID:{}@{}", event.meeting_id, EVENT_UID_DOMAIN),
+        out,
+    );
+    fold_line(&format!("DTSTAMP:{}", utc_stamp(event.updated_at)), out);
+    fold_line(
+        &format!("LAST-MODIFIED:{}", utc_stamp(event.updated_at)),
+        out,
+    );
+    fold_line(&format!("SEQUENCE:{}", event.sequence), out);
+    fold_line(&time("DTSTART", event.scheduled_time), out);
+    fold_line(&time("DTEND", end), out);
+    fold_line(&format!("SUMMARY:{}", escape_text(&event.title)), out);
+    if let Some(description) = event.description.as_deref().filter(|d| !d.is_empty()) {
+        fold_line(&format!("DESCRIPTION:{}", escape_text(description)), out);
+    }
+    fold_line(
+        &format!("CATEGORIES:{}", escape_text(&event.group_title)),
+        out,
+    );
+    // A missed meeting was never started by its host, so it shows as not having happened
+    let status = match event.status {
+        MeetingStatus::Cancelled | MeetingStatus::Missed => "CANCELLED",
+        _ => "CONFIRMED",
+    };
+    fold_line(&format!("STATUS:{}", status), out);
+    fold_line("END:VEVENT", out);
+}
+
+// An iCalendar document with the events and a VTIMEZONE for each zone they use
+pub fn render_calendar(name: &str, events: &[CalendarEvent]) -> String {
+    let mut out = String::new();
+    fold_line("BEGIN:VCALENDAR", &mut out);
+    fold_line("VERSION:2.0", &mut out);
+    fold_line(&format!("PRODID:{}", CALENDAR_PRODID), &mut out);
+    fold_line("CALSCALE:GREGORIAN", &mut out);
+    fold_line("METHOD:PUBLISH", &mut out);
+    fold_line(&format!("X-WR-CALNAME:{}", escape_text(name)), &mut out);
+    fold_line("REFRESH-INTERVAL;VALUE=DURATION:PT1H", &mut out);
+    fold_line("X-PUBLISHED-TTL:PT1H", &mut out);
+
+    // Each zone only needs to cover the years its events fall in
+    let mut zones: BTreeMap<String, (Tz, i32, i32)> = BTreeMap::new();
+    for event in events {
+        let tz = timezone_or_default(Some(&event.timezone));
+        if tz == chrono_tz::UTC {
+            continue;
+        }
+        let year = event.scheduled_time.with_timezone(&tz).year();
+        let entry = zones
+            .entry(tz.name().to_string())
+            .or_insert((tz, year, year));
+        entry.1 = entry.1.min(year);
+        entry.2 = entry.2.max(year);
+    }
+    for (tz, from_year, to_year) in zones.values() {
+        push_vtimezone(*tz, *from_year, *to_year, &mut out);
+    }
+
+    for event in events {
+        push_event(event, timezone_or_default(Some(&event.timezone)), &mut out);
+    }
+    fold_line("END:VCALENDAR", &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(timezone: &str, status: MeetingStatus) -> CalendarEvent {
+        let scheduled_time = Utc.with_ymd_and_hms(2025, 4, 8, 18, 0, 0).unwrap();
+        CalendarEvent {
+            meeting_id: Uuid::nil(),
+            group_title: "Anxiety, Together".to_string(),
+            title: "Weekly check-in; all welcome".to_string(),
+            description: Some("Bring a friend\nor two".to_string()),
+            scheduled_time,
+            timezone: timezone.to_string(),
+            status,
+            sequence: 2,
+            updated_at: scheduled_time,
+            ended_at: None,
+        }
+    }
+
+    #[test]
+    fn escapes_and_folds_long_lines() {
+        assert_eq!(escape_text("a,b;c\\d\ne"), "a\\,b\\;c\\\\d\\ne");
+
+        let mut out = String::new();
+        fold_line(&format!("SUMMARY:{}", "é".repeat(60)), &mut out);
+        for line in out.trim_end_matches("\r\n").split("\r\n") {
+            assert!(line.len() <= 75);
+        }
+        assert_eq!(
+            out.replace("\r\n ", ""),
+            format!("SUMMARY:{}\r\n", "é".repeat(60))
+        );
+    }
+
+    #[test]
+    fn formats_offsets() {
+        assert_eq!(offset_text(3600), "+0100");
+        assert_eq!(offset_text(-5 * 3600), "-0500");
+        assert_eq!(offset_text(5 * 3600 + 45 * 60), "+0545");
+        assert_eq!(offset_text(-75), "-000115");
+    }
+
+    #[test]
+    fn finds_dst_transitions() {
+        let london: Tz = "Europe/London".parse().unwrap();
+        let found = observances(london, 2025, 2025);
+        assert_eq!(found.len(), 3);
+        assert!(!found[0].daylight);
+        // Clocks go forward at 01:00 GMT on 30 March and back at 02:00 BST on 26 October
+        assert_eq!(
+            found[1],
+            Observance {
+                daylight: true,
+                starts: NaiveDate::from_ymd_opt(2025, 3, 30)
+                    .unwrap()
+                    .and_hms_opt(1, 0, 0)
+                    .unwrap(),
+                offset_from: 0,
+                offset_to: 3600,
+                name: Some("BST".to_string()),
+            }
+        );
+        assert_eq!(
+            found[2].starts,
+            NaiveDate::from_ymd_opt(2025, 10, 26)
+                .unwrap()
+                .and_hms_opt(2, 0, 0)
+                .unwrap()
+        );
+        assert!(!found[2].daylight);
+
+        let tokyo: Tz = "Asia/Tokyo".parse().unwrap();
+        assert_eq!(observances(tokyo, 2025, 2026).len(), 1);
+    }
+
+    #[test]
+    fn renders_events_in_their_zone() {
+        let calendar = render_calendar(
+            "My meetings",
+            &[
+                event("Europe/London", MeetingStatus::Upcoming),
+                event("UTC", MeetingStatus::Cancelled),
+                event("UTC", MeetingStatus::Missed),
+            ],
+        );
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(calendar.matches("BEGIN:VTIMEZONE").count(), 1);
+        assert!(calendar.contains("TZID:Europe/London\r\n"));
+        assert!(calendar.contains("DTSTART;TZID=Europe/London:20250408T190000\r\n"));
+        assert!(calendar.contains("DTEND;TZID=Europe/London:20250408T200000\r\n"));
+        assert!(calendar.contains("DTSTART:20250408T180000Z\r\n"));
+        assert!(calendar.contains("UID:00000000-0000-0000-0000-000000000000@meetings.bth\r\n"));
+        assert!(calendar.contains("SUMMARY:Weekly check-in\\; all welcome\r\n"));
+        assert!(calendar.contains("DESCRIPTION:Bring a friend\\nor two\r\n"));
+        assert!(calendar.contains("SEQUENCE:2\r\n"));
+        assert_eq!(calendar.matches("STATUS:CONFIRMED\r\n").count(), 1);
+        assert_eq!(calendar.matches("STATUS:CANCELLED\r\n").count(), 2);
+    }
+
+    #[test]
+    fn feed_urls_use_the_configured_base() {
+        let config = CalendarConfig {
+            public_base_url: "https://example.org".to_string(),
+        };
+        let (url, webcal_url) = config.feed_urls("abc123");
+        assert_eq!(
+            url,
+            "https://example.org/api/public/calendar/feeds/abc123.ics"
+        );
+        assert_eq!(
+            webcal_url,
+            "webcal://example.org/api/public/calendar/feeds/abc123.ics"
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use crate::handlers::announcements::{
+    create_user_announcement, push_announcements, UserAnnouncement,
+};
+use crate::models::all_models::{
+    AnnouncementTarget, AnnouncementType, CheckInMilestone, Sponsorship,
+};
+use chrono::{NaiveDateTime, Utc};
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::collections::BTreeSet;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Streak period for sponsorships without a check-in schedule
+pub const DEFAULT_STREAK_PERIOD_DAYS: i32 = 7;
+
+/// Total check-ins worth celebrating
+pub const CHECK_IN_COUNT_MILESTONES: &[i32] = &[1, 10, 25, 50, 100, 250];
+
+/// Streak lengths (in periods) worth celebrating
+pub const CHECK_IN_STREAK_MILESTONES: &[i32] = &[3, 5, 10, 26, 52];
+
+/// How often the background job looks for check-ins that are due
+const CHECK_IN_REMINDER_JOB_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// Consecutive periods with at least one check-in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CheckInStreak {
+    pub current: i32,
+    pub longest: i32,
+}
+
+// Count streaks over fixed periods starting at the beginning of the sponsorship.
+// The current period still counts as part of the streak until it is over.
+pub fn check_in_streak(
+    check_in_times: &[NaiveDateTime],
+    started_at: NaiveDateTime,
+    now: NaiveDateTime,
+    period_days: i32,
+) -> CheckInStreak {
+    let period_seconds = i64::from(period_days.max(1)) * 24 * 60 * 60;
+    let period_of = |time: NaiveDateTime| (time - started_at).num_seconds().max(0) / period_seconds;
+    let periods: BTreeSet<i64> = check_in_times.iter().map(|t| period_of(*t)).collect();
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<i64> = None;
+    for &period in &periods {
+        run = if previous == Some(period - 1) {
+            run + 1
+        } else {
+            1
+        };
+        longest = longest.max(run);
+        previous = Some(period);
+    }
+
+    let current_period = period_of(now);
+    let mut period = if periods.contains(&current_period) {
+        current_period
+    } else {
+        current_period - 1
+    };
+    let mut current = 0;
+    while periods.contains(&period) {
+        current += 1;
+        period -= 1;
+    }
+
+    CheckInStreak { current, longest }
+}
+
+/// Where a sponsorship's check-ins stand
+#[derive(Debug, Serialize)]
+pub struct CheckInProgress {
+    pub sponsorship_id: Uuid,
+    pub total_check_ins: i64,
+    pub last_check_in_at: Option<NaiveDateTime>,
+    pub period_days: i32,
+    pub streak: CheckInStreak,
+    pub next_due_at: Option<NaiveDateTime>,
+    pub milestones: Vec<CheckInMilestone>,
+}
+
+// Helper function: The streak period and next due time from the schedule, if there is one
+async fn schedule_of(
+    conn: &mut PgConnection,
+    sponsorship_id: Uuid,
+) -> Result<(i32, Option<NaiveDateTime>), sqlx::Error> {
+    let schedule: Option<(i32, NaiveDateTime)> = sqlx::query_as(
+        "SELECT frequency_days, next_due_at FROM check_in_schedules WHERE sponsorship_id = $1",
+    )
+    .bind(sponsorship_id)
+    .fetch_optional(conn)
+    .await?;
+    Ok(match schedule {
+        Some((frequency_days, next_due_at)) => (frequency_days, Some(next_due_at)),
+        None => (DEFAULT_STREAK_PERIOD_DAYS, None),
+    })
+}
+
+// Helper function: When each check-in of the sponsorship was made, oldest first
+async fn check_in_times(
+    conn: &mut PgConnection,
+    sponsorship_id: Uuid,
+) -> Result<Vec<NaiveDateTime>, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT created_at FROM check_ins WHERE sponsorship_id = $1 ORDER BY created_at",
+    )
+    .bind(sponsorship_id)
+    .fetch_all(conn)
+    .await
+}
+
+// Streak, totals and milestones for a sponsorship.
+// Private check-ins count too; only their content is hidden from the sponsor.
+pub async fn load_progress(
+    pool: &PgPool,
+    sponsorship: &Sponsorship,
+) -> Result<CheckInProgress, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let (period_days, next_due_at) = schedule_of(&mut conn, sponsorship.sponsorship_id).await?;
+    let times = check_in_times(&mut conn, sponsorship.sponsorship_id).await?;
+    let milestones = sqlx::query_as::<_, CheckInMilestone>(
+        "SELECT * FROM check_in_milestones WHERE sponsorship_id = $1 ORDER BY reached_at, value",
+    )
+    .bind(sponsorship.sponsorship_id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    // An ended sponsorship's streak stops where it ended
+    let now = sponsorship
+        .ended_at
+        .unwrap_or_else(|| Utc::now().naive_utc());
+    Ok(CheckInProgress {
+        sponsorship_id: sponsorship.sponsorship_id,
+        total_check_ins: times.len() as i64,
+        last_check_in_at: times.last().copied(),
+        period_days,
+        streak: check_in_streak(&times, sponsorship.started_at, now, period_days),
+        next_due_at,
+        milestones,
+    })
+}
+
+// Record any milestones the sponsorship has just reached and return only the new ones.
+// Runs on the caller's transaction, after the check-in has been inserted.
+pub async fn record_new_milestones(
+    conn: &mut PgConnection,
+    sponsorship: &Sponsorship,
+) -> Result<Vec<CheckInMilestone>, sqlx::Error> {
+    let (period_days, _) = schedule_of(conn, sponsorship.sponsorship_id).await?;
+    let times = check_in_times(conn, sponsorship.sponsorship_id).await?;
+    let streak = check_in_streak(
+        &times,
+        sponsorship.started_at,
+        Utc::now().naive_utc(),
+        period_days,
+    );
+
+    let mut kinds = Vec::new();
+    let mut values = Vec::new();
+    for &value in CHECK_IN_COUNT_MILESTONES {
+        if times.len() as i32 >= value {
+            kinds.push("check_ins");
+            values.push(value);
+        }
+    }
+    for &value in CHECK_IN_STREAK_MILESTONES {
+        if streak.current >= value {
+            kinds.push("streak");
+            values.push(value);
+        }
+    }
+    if values.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    sqlx::query_as::<_, CheckInMilestone>(
+        "INSERT INTO check_in_milestones (sponsorship_id, kind, value)
+         SELECT $1, kind::check_in_milestone_kind, value
+         FROM UNNEST($2::text[], $3::int[]) AS m(kind, value)
+         ON CONFLICT DO NOTHING
+         RETURNING *",
+    )
+    .bind(sponsorship.sponsorship_id)
+    .bind(kinds)
+    .bind(values)
+    .fetch_all(conn)
+    .await
+}
+
+// Remind members whose check-in is due, and move each schedule on to its next due time.
+// Members who already checked in during the last period aren't reminded.
+pub async fn send_due_check_in_reminders(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let due_query = "
+        WITH due AS (
+            UPDATE check_in_schedules c
+            SET next_due_at = c.next_due_at + make_interval(
+                days => c.frequency_days * (
+                    FLOOR(EXTRACT(EPOCH FROM NOW() - c.next_due_at) / (c.frequency_days * 86400))::int + 1
+                )
+            )
+            FROM sponsorships s
+            WHERE s.sponsorship_id = c.sponsorship_id AND s.status = 'active'
+              AND c.next_due_at <= NOW()
+            RETURNING c.sponsorship_id, c.frequency_days, s.member_id
+        )
+        SELECT due.sponsorship_id, due.member_id FROM due
+        WHERE NOT EXISTS (
+            SELECT 1 FROM check_ins ci
+            WHERE ci.sponsorship_id = due.sponsorship_id
+              AND ci.created_at > NOW() - make_interval(days => due.frequency_days)
+        )
+    ";
+    let due: Vec<(Uuid, Uuid)> = sqlx::query_as(due_query).fetch_all(&mut *tx).await?;
+
+    let mut announcements = Vec::with_capacity(due.len());
+    for (sponsorship_id, member_id) in due {
+        let announcement = UserAnnouncement {
+            announcement_type: AnnouncementType::CheckInReminder,
+            target: Some(AnnouncementTarget::Sponsorship),
+            target_id: Some(sponsorship_id),
+            recipient_id: member_id,
+            extra_data: Some(json!({ "sponsorship_id": sponsorship_id })),
+            message: "It's time to check in with your sponsor.",
+        };
+        announcements.push(create_user_announcement(&mut tx, announcement).await?);
+    }
+    tx.commit().await?;
+
+    push_announcements(&announcements).await;
+    Ok(announcements.len())
+}
+
+// Spawn the periodic check-in reminder job
+pub fn spawn_check_in_reminder_job(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_IN_REMINDER_JOB_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            match send_due_check_in_reminders(&pool).await {
+                Ok(0) => {}
+                Ok(sent) => info!("Sent {} check-in reminders", sent),
+                Err(e) => warn!("Failed to send check-in reminders: {:?}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn day(n: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2025, 1, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            + chrono::Duration::days(i64::from(n))
+    }
+
+    #[test]
+    fn no_check_ins_means_no_streak() {
+        let streak = check_in_streak(&[], day(0), day(20), 7);
+        assert_eq!(
+            streak,
+            CheckInStreak {
+                current: 0,
+                longest: 0
+            }
+        );
+    }
+
+    #[test]
+    fn consecutive_periods_build_a_streak() {
+        let times = [day(1), day(8), day(9), day(15)];
+        let streak = check_in_streak(&times, day(0), day(16), 7);
+        assert_eq!(
+            streak,
+            CheckInStreak {
+                current: 3,
+                longest: 3
+            }
+        );
+    }
+
+    #[test]
+    fn current_period_without_check_in_keeps_the_streak() {
+        let times = [day(1), day(8)];
+        let streak = check_in_streak(&times, day(0), day(15), 7);
+        assert_eq!(streak.current, 2);
+    }
+
+    #[test]
+    fn missed_period_breaks_the_streak() {
+        let times = [day(1), day(8), day(15), day(29)];
+        let streak = check_in_streak(&times, day(0), day(30), 7);
+        assert_eq!(
+            streak,
+            CheckInStreak {
+                current: 1,
+                longest: 3
+            }
+        );
+
+        let streak = check_in_streak(&times[..3], day(0), day(30), 7);
+        assert_eq!(streak.current, 0);
+    }
+}
@@ -0,0 +1,528 @@
+use crate::handlers::b2_storage::{B2Client, StorageBucket};
+use crate::handlers::privacy::privacy_settings_for;
+use crate::models::all_models::{
+    Attachment, CheckIn, Comment, GroupChatMessage, GroupMeeting, MatchingRequest, MeetingSeries,
+    Message, Notification, Post, PostLike, PrivacySettings, RecoveryJourney, RecoveryReset, Report,
+    SponsorApplication, SponsorApplicationRevision, SponsorNote, Sponsorship, SupportGroupMember,
+    User,
+};
+use chrono::{Duration as ChronoDuration, NaiveDateTime, Utc};
+use log::{error, info, warn};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::error::Error;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Archives larger than this are uploaded to the private bucket instead of kept in the database
+pub const INLINE_EXPORT_MAX_BYTES: usize = 1024 * 1024;
+
+/// How long a finished export can be downloaded
+pub const EXPORT_RETENTION_DAYS: i64 = 7;
+
+/// How often exports past their download window are purged
+const EXPORT_EXPIRY_JOB_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Columns of the users row that are never included in an export
+const REDACTED_USER_FIELDS: &[&str] = &[
+    "password_hash",
+    "email_verification_token",
+    "forgot_password_token",
+    "forgot_password_expires_at",
+];
+
+/// Everything the platform holds about one user
+#[derive(Debug, Serialize)]
+pub struct UserDataArchive {
+    pub export_id: Uuid,
+    pub generated_at: NaiveDateTime,
+    pub summary: String,
+    pub user: Value,
+    pub privacy_settings: PrivacySettings,
+    pub messages: Vec<Message>,
+    pub group_chat_messages: Vec<GroupChatMessage>,
+    pub posts: Vec<Post>,
+    pub comments: Vec<Comment>,
+    pub post_likes: Vec<PostLike>,
+    pub reports_filed: Vec<Report>,
+    pub matching_requests: Vec<MatchingRequest>,
+    pub sponsor_applications: Vec<SponsorApplication>,
+    pub sponsor_application_revisions: Vec<SponsorApplicationRevision>,
+    pub sponsorships: Vec<Sponsorship>,
+    pub check_ins: Vec<CheckIn>,
+    pub sponsor_notes: Vec<SponsorNote>,
+    pub recovery_journeys: Vec<RecoveryJourney>,
+    pub recovery_resets: Vec<RecoveryReset>,
+    pub meetings: Vec<GroupMeeting>,
+    pub meeting_series: Vec<MeetingSeries>,
+    pub support_group_memberships: Vec<SupportGroupMember>,
+    pub attachments: Vec<Attachment>,
+    pub notifications: Vec<Notification>,
+}
+
+// Collect every row tied to the user
+pub async fn build_user_archive(
+    pool: &PgPool,
+    export_id: Uuid,
+    user_id: Uuid,
+) -> Result<UserDataArchive, sqlx::Error> {
+    let user = sqlx::query_as::<_, User>("SELECT * FROM users WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_one(pool)
+        .await?;
+
+    let mut user_json = serde_json::to_value(&user).unwrap_or(Value::Null);
+    if let Some(fields) = user_json.as_object_mut() {
+        for field in REDACTED_USER_FIELDS {
+            fields.remove(*field);
+        }
+    }
+    let privacy_settings = privacy_settings_for(pool, user_id).await?;
+
+    let messages = sqlx::query_as::<_, Message>(
+        "SELECT * FROM messages WHERE sender_id = $1 OR receiver_id = $1 ORDER BY timestamp ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let group_chat_messages = sqlx::query_as::<_, GroupChatMessage>(
+        "SELECT * FROM group_chat_messages WHERE sender_id = $1 ORDER BY timestamp ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let posts = sqlx::query_as::<_, Post>(
+        "SELECT * FROM posts WHERE author_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let comments = sqlx::query_as::<_, Comment>(
+        "SELECT * FROM comments WHERE author_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let post_likes = sqlx::query_as::<_, PostLike>("SELECT * FROM post_likes WHERE user_id = $1")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    let reports_filed = sqlx::query_as::<_, Report>(
+        "SELECT * FROM reports WHERE reporter_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let matching_requests = sqlx::query_as::<_, MatchingRequest>(
+        "SELECT * FROM matching_requests WHERE member_id = $1 OR sponsor_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let sponsor_applications = sqlx::query_as::<_, SponsorApplication>(
+        "SELECT * FROM sponsor_applications WHERE user_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let revisions_query = "
+        SELECT r.* FROM sponsor_application_revisions r
+        JOIN sponsor_applications a ON r.application_id = a.application_id
+        WHERE a.user_id = $1
+        ORDER BY r.submitted_at ASC
+    ";
+    let sponsor_application_revisions =
+        sqlx::query_as::<_, SponsorApplicationRevision>(revisions_query)
+            .bind(user_id)
+            .fetch_all(pool)
+            .await?;
+
+    let sponsorships = sqlx::query_as::<_, Sponsorship>(
+        "SELECT * FROM sponsorships WHERE member_id = $1 OR sponsor_id = $1 ORDER BY started_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let check_ins = sqlx::query_as::<_, CheckIn>(
+        "SELECT * FROM check_ins WHERE member_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let sponsor_notes = sqlx::query_as::<_, SponsorNote>(
+        "SELECT * FROM sponsor_notes WHERE sponsor_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let recovery_journeys = sqlx::query_as::<_, RecoveryJourney>(
+        "SELECT * FROM recovery_journeys WHERE user_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let recovery_resets = sqlx::query_as::<_, RecoveryReset>(
+        "SELECT r.* FROM recovery_resets r
+         JOIN recovery_journeys j ON j.journey_id = r.journey_id
+         WHERE j.user_id = $1
+         ORDER BY r.created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let meetings_query = "
+        SELECT * FROM group_meetings
+        WHERE host_id = $1
+           OR meeting_id IN (SELECT meeting_id FROM meeting_participants WHERE user_id = $1)
+        ORDER BY scheduled_time ASC
+    ";
+    let meetings = sqlx::query_as::<_, GroupMeeting>(meetings_query)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+
+    let meeting_series = sqlx::query_as::<_, MeetingSeries>(
+        "SELECT * FROM meeting_series WHERE host_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let support_group_memberships = sqlx::query_as::<_, SupportGroupMember>(
+        "SELECT * FROM support_group_members WHERE user_id = $1 ORDER BY joined_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let attachments = sqlx::query_as::<_, Attachment>(
+        "SELECT * FROM attachments WHERE uploader_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let notifications = sqlx::query_as::<_, Notification>(
+        "SELECT * FROM notifications WHERE user_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut archive = UserDataArchive {
+        export_id,
+        generated_at: Utc::now().naive_utc(),
+        summary: String::new(),
+        user: user_json,
+        privacy_settings,
+        messages,
+        group_chat_messages,
+        posts,
+        comments,
+        post_likes,
+        reports_filed,
+        matching_requests,
+        sponsor_applications,
+        sponsor_application_revisions,
+        sponsorships,
+        check_ins,
+        sponsor_notes,
+        recovery_journeys,
+        recovery_resets,
+        meetings,
+        meeting_series,
+        support_group_memberships,
+        attachments,
+        notifications,
+    };
+    archive.summary = render_summary(&user, &archive);
+
+    Ok(archive)
+}
+
+// Plain-text overview of an archive, readable without opening the JSON
+pub fn render_summary(user: &User, archive: &UserDataArchive) -> String {
+    let privacy = &archive.privacy_settings;
+    let sent = archive
+        .messages
+        .iter()
+        .filter(|m| m.sender_id == user.user_id)
+        .count();
+    let received = archive.messages.len() - sent;
+    let hosted = archive
+        .meetings
+        .iter()
+        .filter(|m| m.host_id == user.user_id)
+        .count();
+    let as_member = archive
+        .matching_requests
+        .iter()
+        .filter(|r| r.member_id == user.user_id)
+        .count();
+    let sponsored = archive
+        .sponsorships
+        .iter()
+        .filter(|s| s.member_id == user.user_id)
+        .count();
+
+    let lines = [
+        "Beyond The Horizon - Personal Data Export".to_string(),
+        format!("Generated: {} UTC", archive.generated_at.format("%Y-%m-%d %H:%M")),
+        String::new(),
+        "Account".to_string(),
+        format!("  Username: {}", user.username),
+        format!("  Email: {}", user.email),
+        format!("  Role: {}", user.role),
+        format!("  Joined: {}", user.created_at.format("%Y-%m-%d")),
+        format!("  Date of birth: {}", user.dob),
+        String::new(),
+        "Who can see your profile".to_string(),
+        format!("  Bio: {}", privacy.bio.as_str()),
+        format!("  Interests: {}", privacy.interests.as_str()),
+        format!("  Experience: {}", privacy.experience.as_str()),
+        format!("  Languages: {}", privacy.languages.as_str()),
+        format!("  City: {}", privacy.location_city.as_str()),
+        format!("  Avatar: {}", privacy.avatar.as_str()),
+        format!("  Support groups: {}", privacy.support_groups.as_str()),
+        format!("  Recovery progress: {}", privacy.recovery.as_str()),
+        String::new(),
+        "What we hold".to_string(),
+        format!("  Private messages: {} sent, {} received", sent, received),
+        format!("  Group chat messages: {}", archive.group_chat_messages.len()),
+        format!("  Posts: {}", archive.posts.len()),
+        format!("  Comments: {}", archive.comments.len()),
+        format!("  Post likes: {}", archive.post_likes.len()),
+        format!("  Reports filed: {}", archive.reports_filed.len()),
+        format!(
+            "  Matching requests: {} as member, {} as sponsor",
+            as_member,
+            archive.matching_requests.len() - as_member
+        ),
+        format!(
+            "  Sponsor applications: {} ({} revisions)",
+            archive.sponsor_applications.len(),
+            archive.sponsor_application_revisions.len()
+        ),
+        format!(
+            "  Sponsorships: {} as member, {} as sponsor",
+            sponsored,
+            archive.sponsorships.len() - sponsored
+        ),
+        format!(
+            "  Check-ins: {}, sponsor notes written: {}",
+            archive.check_ins.len(),
+            archive.sponsor_notes.len()
+        ),
+        format!(
+            "  Recovery journeys: {} ({} resets)",
+            archive.recovery_journeys.len(),
+            archive.recovery_resets.len()
+        ),
+        format!(
+            "  Meetings: {} hosted, {} attended",
+            hosted,
+            archive.meetings.len() - hosted
+        ),
+        format!(
+            "  Recurring meeting series hosted: {}",
+            archive.meeting_series.len()
+        ),
+        format!(
+            "  Support group memberships: {}",
+            archive.support_group_memberships.len()
+        ),
+        format!("  Attachments uploaded: {}", archive.attachments.len()),
+        format!("  Notifications: {}", archive.notifications.len()),
+        String::new(),
+        "The accompanying JSON contains the full records. Passwords and account tokens are never exported."
+            .to_string(),
+    ];
+    lines.join("\n")
+}
+
+// Build an export and store it inline or in B2 depending on its size
+async fn generate_export(
+    pool: &PgPool,
+    b2_client: &B2Client,
+    export_id: Uuid,
+    user_id: Uuid,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    sqlx::query("UPDATE data_exports SET status = 'processing' WHERE export_id = $1")
+        .bind(export_id)
+        .execute(pool)
+        .await?;
+
+    let archive = build_user_archive(pool, export_id, user_id).await?;
+    let bytes = serde_json::to_vec_pretty(&archive)?;
+    let expires_at = Utc::now().naive_utc() + ChronoDuration::days(EXPORT_RETENTION_DAYS);
+
+    if bytes.len() <= INLINE_EXPORT_MAX_BYTES {
+        let query = "
+            UPDATE data_exports
+            SET status = 'ready', summary = $2, archive = $3, size_bytes = $4,
+                completed_at = NOW(), expires_at = $5
+            WHERE export_id = $1
+        ";
+        sqlx::query(query)
+            .bind(export_id)
+            .bind(&archive.summary)
+            .bind(serde_json::to_value(&archive)?)
+            .bind(bytes.len() as i64)
+            .bind(expires_at)
+            .execute(pool)
+            .await?;
+    } else {
+        let file_name = format!("exports/{}/{}.json", user_id, export_id.simple());
+        b2_client
+            .upload_file_to(
+                StorageBucket::Private,
+                &bytes,
+                &file_name,
+                "application/json",
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let query = "
+            UPDATE data_exports
+            SET status = 'ready', summary = $2, file_name = $3, size_bytes = $4,
+                completed_at = NOW(), expires_at = $5
+            WHERE export_id = $1
+        ";
+        if let Err(e) = sqlx::query(query)
+            .bind(export_id)
+            .bind(&archive.summary)
+            .bind(&file_name)
+            .bind(bytes.len() as i64)
+            .bind(expires_at)
+            .execute(pool)
+            .await
+        {
+            // Don't leave an unreferenced file behind
+            if let Err(e) = b2_client
+                .delete_file_from(StorageBucket::Private, &file_name)
+                .await
+            {
+                error!("Failed to clean up export {}: {:?}", file_name, e);
+            }
+            return Err(e.into());
+        }
+    }
+
+    info!(
+        "Data export {} ready for user {} ({} bytes)",
+        export_id,
+        user_id,
+        bytes.len()
+    );
+    Ok(())
+}
+
+// Generate an export in the background, recording failures on the export row
+pub fn spawn_data_export(pool: PgPool, b2_client: B2Client, export_id: Uuid, user_id: Uuid) {
+    tokio::spawn(async move {
+        if let Err(e) = generate_export(&pool, &b2_client, export_id, user_id).await {
+            error!("Data export {} failed: {}", export_id, e);
+            let query = "
+                UPDATE data_exports
+                SET status = 'failed', error = $2, completed_at = NOW()
+                WHERE export_id = $1
+            ";
+            if let Err(e) = sqlx::query(query)
+                .bind(export_id)
+                .bind("Export generation failed, please request a new export")
+                .execute(&pool)
+                .await
+            {
+                error!("Failed to mark data export {} as failed: {}", export_id, e);
+            }
+        }
+    });
+}
+
+// Exports left pending or processing by a restart will never finish; fail them so users can retry
+pub async fn fail_interrupted_exports(pool: &PgPool) {
+    let query = "
+        UPDATE data_exports
+        SET status = 'failed', error = 'Export was interrupted, please request a new export', completed_at = NOW()
+        WHERE status IN ('pending', 'processing')
+    ";
+    match sqlx::query(query).execute(pool).await {
+        Ok(result) if result.rows_affected() > 0 => {
+            warn!(
+                "Marked {} interrupted data exports as failed",
+                result.rows_affected()
+            );
+        }
+        Ok(_) => {}
+        Err(e) => error!("Failed to clean up interrupted data exports: {}", e),
+    }
+}
+
+// Remove the contents of exports past their download window: the archive or B2 file, and the
+// summary. The row itself is kept so the user can still see that the export existed and expired.
+pub async fn purge_expired_exports(
+    pool: &PgPool,
+    b2_client: &B2Client,
+) -> Result<u64, sqlx::Error> {
+    let expired: Vec<(Uuid, Option<String>)> = sqlx::query_as(
+        "SELECT export_id, file_name FROM data_exports
+         WHERE expires_at <= NOW()
+           AND (archive IS NOT NULL OR summary IS NOT NULL OR file_name IS NOT NULL)",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut purged = 0;
+    for (export_id, file_name) in expired {
+        if let Some(file_name) = &file_name {
+            // Keep the reference so the next run tries again
+            if let Err(e) = b2_client
+                .delete_file_from(StorageBucket::Private, file_name)
+                .await
+            {
+                warn!("Failed to delete expired export {}: {:?}", file_name, e);
+                continue;
+            }
+        }
+        sqlx::query(
+            "UPDATE data_exports SET archive = NULL, summary = NULL, file_name = NULL
+             WHERE export_id = $1",
+        )
+        .bind(export_id)
+        .execute(pool)
+        .await?;
+        purged += 1;
+    }
+    Ok(purged)
+}
+
+// Spawn the periodic job that purges expired exports
+pub fn spawn_export_expiry_job(pool: PgPool, b2_client: B2Client) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(EXPORT_EXPIRY_JOB_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            match purge_expired_exports(&pool, &b2_client).await {
+                Ok(0) => {}
+                Ok(count) => info!("Purged {} expired data exports", count),
+                Err(e) => warn!("Data export expiry job failed: {}", e),
+            }
+        }
+    });
+}
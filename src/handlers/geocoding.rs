@@ -0,0 +1,468 @@
+use crate::models::all_models::Location;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Stored coordinates are snapped to a grid of this many degrees (about 11km north-south),
+/// so an exact home address never reaches the database
+pub const LOCATION_GRID_DEGREES: f64 = 0.1;
+
+// Major cities in GeoNames format, used when no full dataset is configured
+const BUNDLED_CITIES: &str = include_str!("../../data/geonames_cities_extract.txt");
+
+/// Location as entered by a user: a city (optionally narrowed by country) or a postcode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LocationInput {
+    pub city: Option<String>,
+    // ISO 3166-1 alpha-2 code, e.g. "GB"
+    pub country: Option<String>,
+    pub postcode: Option<String>,
+}
+
+/// A place resolved by a geocoder, at whatever precision the source has
+#[derive(Debug, Clone)]
+pub struct GeocodedPlace {
+    pub name: String,
+    pub country_code: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum GeocodeError {
+    InvalidInput(String),
+    Unsupported(String),
+}
+
+impl fmt::Display for GeocodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeocodeError::InvalidInput(msg) => write!(f, "{}", msg),
+            GeocodeError::Unsupported(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for GeocodeError {}
+
+/// Resolves user-entered locations to coordinates; Ok(None) means nothing matched
+pub trait Geocoder: Send + Sync {
+    fn geocode(&self, input: &LocationInput) -> Result<Option<GeocodedPlace>, GeocodeError>;
+}
+
+// Snap one coordinate to the storage grid
+pub fn snap_to_grid(value: f64) -> f64 {
+    let cells = (1.0 / LOCATION_GRID_DEGREES).round();
+    (value * cells).round() / cells
+}
+
+// The only form of a place that gets stored on a profile
+pub fn coarse_location(place: &GeocodedPlace) -> Location {
+    Location {
+        latitude: snap_to_grid(place.latitude),
+        longitude: snap_to_grid(place.longitude),
+        city: Some(place.name.clone()),
+        country: Some(place.country_code.clone()),
+    }
+}
+
+// Check the shape of the input and normalise the country code
+fn validate_input(input: &LocationInput) -> Result<LocationInput, GeocodeError> {
+    let clean = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    };
+    let city = clean(&input.city);
+    let postcode = clean(&input.postcode);
+    let country = clean(&input.country).map(|c| c.to_uppercase());
+
+    if let Some(country) = &country {
+        if country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err(GeocodeError::InvalidInput(
+                "Country must be a two-letter ISO code, e.g. \"GB\"".to_string(),
+            ));
+        }
+    }
+    if city.as_ref().is_some_and(|c| c.chars().count() > 100) {
+        return Err(GeocodeError::InvalidInput(
+            "City cannot be longer than 100 characters".to_string(),
+        ));
+    }
+    if let Some(postcode) = &postcode {
+        if postcode.len() > 12
+            || !postcode
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-')
+        {
+            return Err(GeocodeError::InvalidInput("Invalid postcode".to_string()));
+        }
+        if country.is_none() {
+            return Err(GeocodeError::InvalidInput(
+                "A country is needed to look up a postcode".to_string(),
+            ));
+        }
+    }
+    if city.is_none() && postcode.is_none() {
+        return Err(GeocodeError::InvalidInput(
+            "Enter a city or a postcode".to_string(),
+        ));
+    }
+
+    Ok(LocationInput {
+        city,
+        country,
+        postcode,
+    })
+}
+
+fn name_key(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn postcode_key(postcode: &str) -> String {
+    postcode
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_uppercase()
+}
+
+struct GeoCity {
+    place: GeocodedPlace,
+    population: u64,
+}
+
+/// Geocoder backed by GeoNames dumps held in memory, so lookups never leave the server
+pub struct OfflineGeocoder {
+    cities: Vec<GeoCity>,
+    names: HashMap<String, Vec<usize>>,
+    postcodes: HashMap<(String, String), GeocodedPlace>,
+}
+
+impl OfflineGeocoder {
+    /// Build from a GeoNames cities file (cities15000.txt layout) and an optional
+    /// GeoNames postal codes file (allCountries.txt from the zip export)
+    pub fn from_geonames(cities_data: &str, postcodes_data: Option<&str>) -> Self {
+        let mut geocoder = OfflineGeocoder {
+            cities: Vec::new(),
+            names: HashMap::new(),
+            postcodes: HashMap::new(),
+        };
+
+        for line in cities_data.lines() {
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            let cols: Vec<&str> = line.split('\t').collect();
+            if cols.len() < 18 {
+                continue;
+            }
+            let (Ok(latitude), Ok(longitude)) = (cols[4].parse::<f64>(), cols[5].parse::<f64>())
+            else {
+                continue;
+            };
+            let index = geocoder.cities.len();
+            geocoder.cities.push(GeoCity {
+                place: GeocodedPlace {
+                    name: cols[1].to_string(),
+                    country_code: cols[8].to_uppercase(),
+                    latitude,
+                    longitude,
+                    timezone: Some(cols[17].to_string()).filter(|tz| !tz.is_empty()),
+                },
+                population: cols[14].parse().unwrap_or(0),
+            });
+
+            let mut keys: Vec<String> = vec![name_key(cols[1]), name_key(cols[2])];
+            keys.extend(cols[3].split(',').map(name_key));
+            keys.retain(|k| !k.is_empty());
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                geocoder.names.entry(key).or_default().push(index);
+            }
+        }
+
+        for line in postcodes_data.unwrap_or("").lines() {
+            let cols: Vec<&str> = line.split('\t').collect();
+            if cols.len() < 11 {
+                continue;
+            }
+            let (Ok(latitude), Ok(longitude)) = (cols[9].parse::<f64>(), cols[10].parse::<f64>())
+            else {
+                continue;
+            };
+            let country_code = cols[0].to_uppercase();
+            geocoder
+                .postcodes
+                .entry((country_code.clone(), postcode_key(cols[1])))
+                .or_insert(GeocodedPlace {
+                    name: cols[2].to_string(),
+                    country_code,
+                    latitude,
+                    longitude,
+                    timezone: None,
+                });
+        }
+
+        geocoder
+    }
+
+    /// Load the datasets named by the GEONAMES_CITIES_PATH and GEONAMES_POSTCODES_PATH secrets,
+    /// falling back to the bundled cities extract
+    pub fn from_secrets(secrets: &shuttle_runtime::SecretStore) -> Self {
+        let read = |key: &str| {
+            let path = secrets.get(key)?;
+            match std::fs::read_to_string(&path) {
+                Ok(data) => Some(data),
+                Err(e) => {
+                    error!("Failed to read {} ({}): {}", key, path, e);
+                    None
+                }
+            }
+        };
+
+        let cities = read("GEONAMES_CITIES_PATH");
+        let postcodes = read("GEONAMES_POSTCODES_PATH");
+        let geocoder = OfflineGeocoder::from_geonames(
+            cities.as_deref().unwrap_or(BUNDLED_CITIES),
+            postcodes.as_deref(),
+        );
+        info!(
+            "Offline geocoder loaded {} cities ({}) and {} postcodes",
+            geocoder.cities.len(),
+            if cities.is_some() {
+                "configured dataset"
+            } else {
+                "bundled extract"
+            },
+            geocoder.postcodes.len()
+        );
+        geocoder
+    }
+}
+
+impl Geocoder for OfflineGeocoder {
+    fn geocode(&self, input: &LocationInput) -> Result<Option<GeocodedPlace>, GeocodeError> {
+        let input = validate_input(input)?;
+
+        if let (Some(postcode), Some(country)) = (&input.postcode, &input.country) {
+            if self.postcodes.is_empty() {
+                return Err(GeocodeError::Unsupported(
+                    "Postcode lookup isn't available, enter a city instead".to_string(),
+                ));
+            }
+            // Fall back to the outward part (e.g. "SW1A" of "SW1A 1AA") for datasets without full codes
+            let outward = postcode.split_whitespace().next().unwrap_or(postcode);
+            let found = [postcode_key(postcode), postcode_key(outward)]
+                .iter()
+                .find_map(|key| self.postcodes.get(&(country.clone(), key.clone())))
+                .cloned();
+            if found.is_some() || input.city.is_none() {
+                return Ok(found);
+            }
+        }
+
+        let city = match &input.city {
+            Some(city) => city,
+            None => return Ok(None),
+        };
+        let best = self
+            .names
+            .get(&name_key(city))
+            .into_iter()
+            .flatten()
+            .map(|&i| &self.cities[i])
+            .filter(|c| {
+                input.country.is_none() || input.country.as_ref() == Some(&c.place.country_code)
+            })
+            .max_by_key(|c| c.population);
+
+        Ok(best.map(|c| c.place.clone()))
+    }
+}
+
+// Geocode locations saved before geocoding existed, which only have a city
+pub async fn backfill_legacy_locations(
+    pool: &PgPool,
+    geocoder: &dyn Geocoder,
+) -> Result<(u64, u64), sqlx::Error> {
+    let rows: Vec<(Uuid, Option<String>, Option<String>)> = sqlx::query_as(
+        "SELECT user_id, location->>'city', location->>'country'
+         FROM users
+         WHERE location IS NOT NULL AND location->'latitude' IS NULL",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let (mut resolved, mut unresolved) = (0, 0);
+    for (user_id, city, country) in rows {
+        let input = LocationInput {
+            city,
+            country,
+            postcode: None,
+        };
+        let place = match geocoder.geocode(&input) {
+            Ok(Some(place)) => place,
+            Ok(None) | Err(_) => {
+                unresolved += 1;
+                continue;
+            }
+        };
+        let location = match serde_json::to_value(coarse_location(&place)) {
+            Ok(location) => location,
+            Err(e) => {
+                warn!("Failed to serialise location for {}: {}", user_id, e);
+                unresolved += 1;
+                continue;
+            }
+        };
+        sqlx::query("UPDATE users SET location = $1 WHERE user_id = $2")
+            .bind(location)
+            .bind(user_id)
+            .execute(pool)
+            .await?;
+        resolved += 1;
+    }
+
+    Ok((resolved, unresolved))
+}
+
+// Run the legacy location backfill once in the background at startup
+pub fn spawn_location_backfill(pool: PgPool, geocoder: Arc<dyn Geocoder>) {
+    tokio::spawn(async move {
+        match backfill_legacy_locations(&pool, geocoder.as_ref()).await {
+            Ok((0, 0)) => {}
+            Ok((resolved, unresolved)) => info!(
+                "Location backfill geocoded {} profiles, {} left for their owners to update",
+                resolved, unresolved
+            ),
+            Err(e) => warn!("Location backfill failed: {}", e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two cities named London and one known by an alternate name, in GeoNames column layout
+    const CITIES: &str = "\
+# comment lines are skipped
+\tLondon\tLondon\t\t51.5085\t-0.1257\tP\tPPLC\tGB\t\t\t\t\t\t8961989\t\t\tEurope/London\t
+\tLondon\tLondon\t\t42.9834\t-81.2330\tP\tPPL\tCA\t\t\t\t\t\t346765\t\t\tAmerica/Toronto\t
+\tMunich\tMunich\tMuenchen,München\t48.1374\t11.5755\tP\tPPLA\tDE\t\t\t\t\t\t1260391\t\t\tEurope/Berlin\t
+";
+    const POSTCODES: &str = "\
+GB\tSW1A\tWestminster\tEngland\tENG\t\t\t\t\t51.5010\t-0.1416\t4
+";
+
+    fn city(city: &str, country: Option<&str>) -> LocationInput {
+        LocationInput {
+            city: Some(city.to_string()),
+            country: country.map(str::to_string),
+            postcode: None,
+        }
+    }
+
+    fn postcode(postcode: &str, country: &str) -> LocationInput {
+        LocationInput {
+            city: None,
+            country: Some(country.to_string()),
+            postcode: Some(postcode.to_string()),
+        }
+    }
+
+    #[test]
+    fn snaps_coordinates_to_the_grid() {
+        assert_eq!(snap_to_grid(51.5085), 51.5);
+        assert_eq!(snap_to_grid(-0.1257), -0.1);
+        assert_eq!(snap_to_grid(-81.2330), -81.2);
+        assert_eq!(snap_to_grid(42.9834), 43.0);
+        assert_eq!(snap_to_grid(0.04), 0.0);
+    }
+
+    #[test]
+    fn coarse_location_keeps_only_city_country_and_snapped_coordinates() {
+        let geocoder = OfflineGeocoder::from_geonames(CITIES, None);
+        let place = geocoder.geocode(&city("London", None)).unwrap().unwrap();
+        let location = coarse_location(&place);
+        assert_eq!(location.latitude, 51.5);
+        assert_eq!(location.longitude, -0.1);
+        assert_eq!(location.city.as_deref(), Some("London"));
+        assert_eq!(location.country.as_deref(), Some("GB"));
+    }
+
+    #[test]
+    fn looks_up_cities_by_population_country_and_alternate_name() {
+        let geocoder = OfflineGeocoder::from_geonames(CITIES, None);
+
+        let london = geocoder.geocode(&city("  london ", None)).unwrap().unwrap();
+        assert_eq!(london.country_code, "GB");
+        assert_eq!(london.timezone.as_deref(), Some("Europe/London"));
+
+        let ontario = geocoder
+            .geocode(&city("London", Some("ca")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(ontario.country_code, "CA");
+        assert_eq!(ontario.timezone.as_deref(), Some("America/Toronto"));
+
+        let munich = geocoder.geocode(&city("München", None)).unwrap().unwrap();
+        assert_eq!(munich.name, "Munich");
+
+        assert!(geocoder
+            .geocode(&city("London", Some("DE")))
+            .unwrap()
+            .is_none());
+        assert!(geocoder.geocode(&city("Atlantis", None)).unwrap().is_none());
+    }
+
+    #[test]
+    fn looks_up_postcodes_falling_back_to_the_outward_code() {
+        let geocoder = OfflineGeocoder::from_geonames(CITIES, Some(POSTCODES));
+
+        let full = geocoder
+            .geocode(&postcode("sw1a 1aa", "GB"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(full.name, "Westminster");
+        let outward = geocoder.geocode(&postcode("SW1A", "gb")).unwrap().unwrap();
+        assert_eq!(outward.name, "Westminster");
+
+        assert!(geocoder
+            .geocode(&postcode("EC1A 1BB", "GB"))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn rejects_bad_input_and_postcodes_without_a_dataset() {
+        let geocoder = OfflineGeocoder::from_geonames(CITIES, None);
+
+        assert!(matches!(
+            geocoder.geocode(&postcode("SW1A 1AA", "GB")),
+            Err(GeocodeError::Unsupported(_))
+        ));
+        assert!(matches!(
+            geocoder.geocode(&city("London", Some("GBR"))),
+            Err(GeocodeError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            geocoder.geocode(&city("   ", None)),
+            Err(GeocodeError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,239 @@
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, Limits};
+use log::{debug, error};
+use std::error::Error;
+use std::io::Cursor;
+use uuid::Uuid;
+
+/// Square avatar sizes (in pixels) produced for every upload
+pub const AVATAR_SIZES: [u32; 3] = [64, 128, 256];
+
+/// Size stored in `users.avatar_url`
+pub const PRIMARY_AVATAR_SIZE: u32 = 256;
+
+/// Largest input dimension we are willing to decode
+const MAX_INPUT_DIMENSION: u32 = 8192;
+
+/// Upper bound on decoder allocations, guards against decompression bombs
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// Image formats accepted for uploads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SniffedFormat {
+    Jpeg,
+    Png,
+    WebP,
+}
+
+impl SniffedFormat {
+    fn image_format(self) -> ImageFormat {
+        match self {
+            SniffedFormat::Jpeg => ImageFormat::Jpeg,
+            SniffedFormat::Png => ImageFormat::Png,
+            SniffedFormat::WebP => ImageFormat::WebP,
+        }
+    }
+}
+
+/// A single re-encoded avatar variant
+pub struct AvatarVariant {
+    pub size: u32,
+    pub data: Vec<u8>,
+}
+
+// Detect the real image type from magic bytes, ignoring filename and client headers
+pub fn sniff_image_format(data: &[u8]) -> Option<SniffedFormat> {
+    if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some(SniffedFormat::Jpeg)
+    } else if data.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        Some(SniffedFormat::Png)
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some(SniffedFormat::WebP)
+    } else {
+        None
+    }
+}
+
+// Decode the upload with bounded limits, turning it upright first.
+// Only pixel data survives decoding, so EXIF/GPS and other metadata are dropped here;
+// the EXIF orientation is applied beforehand so phone photos don't end up sideways.
+fn decode_image(data: &[u8], format: SniffedFormat) -> Result<DynamicImage, Box<dyn Error>> {
+    let mut reader = ImageReader::with_format(Cursor::new(data), format.image_format());
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_INPUT_DIMENSION);
+    limits.max_image_height = Some(MAX_INPUT_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    reader.limits(limits.clone());
+
+    let mut decoder = reader.into_decoder()?;
+    limits.reserve(decoder.total_bytes())?;
+    let orientation = decoder.orientation()?;
+
+    let mut img = DynamicImage::from_decoder(decoder)?;
+    img.apply_orientation(orientation);
+    Ok(img)
+}
+
+// Crop the largest centred square out of the image
+fn centre_crop_square(img: &DynamicImage) -> DynamicImage {
+    let (width, height) = (img.width(), img.height());
+    let side = width.min(height);
+    let x = (width - side) / 2;
+    let y = (height - side) / 2;
+    img.crop_imm(x, y, side, side)
+}
+
+// Encode an image as (lossless) WebP
+fn encode_webp(img: &DynamicImage) -> Result<Vec<u8>, Box<dyn Error>> {
+    let rgba = img.to_rgba8();
+    let mut out = Vec::new();
+    WebPEncoder::new_lossless(&mut out).encode(
+        rgba.as_raw(),
+        rgba.width(),
+        rgba.height(),
+        image::ExtendedColorType::Rgba8,
+    )?;
+    Ok(out)
+}
+
+// Validate, sanitise and resize an uploaded avatar.
+// Returns one WebP variant per entry in AVATAR_SIZES.
+pub fn process_avatar(data: &[u8]) -> Result<Vec<AvatarVariant>, Box<dyn Error>> {
+    let format = match sniff_image_format(data) {
+        Some(format) => format,
+        None => return Err("Unsupported image type (expected JPEG, PNG or WebP)".into()),
+    };
+    debug!("Sniffed avatar format: {:?}", format);
+
+    let decoded = match decode_image(data, format) {
+        Ok(img) => img,
+        Err(e) => {
+            error!("Failed to decode avatar image: {}", e);
+            return Err(format!("Invalid image data: {}", e).into());
+        }
+    };
+
+    let square = centre_crop_square(&decoded);
+
+    let mut variants = Vec::with_capacity(AVATAR_SIZES.len());
+    for size in AVATAR_SIZES {
+        let resized = square.resize_exact(size, size, FilterType::Lanczos3);
+        let encoded = encode_webp(&resized)?;
+        debug!("Encoded {}px avatar variant: {} bytes", size, encoded.len());
+        variants.push(AvatarVariant {
+            size,
+            data: encoded,
+        });
+    }
+
+    Ok(variants)
+}
+
+// B2 object name for one variant of an avatar upload.
+// A fresh upload_id per upload keeps URLs cache-safe and old variants addressable for deletion.
+pub fn avatar_file_name(user_id: &Uuid, upload_id: &Uuid, size: u32) -> String {
+    format!("avatar_{}_{}_{}.webp", user_id, upload_id.simple(), size)
+}
+
+// B2 object names to remove when an avatar is replaced or reset.
+// Processed avatars expand to all their size variants; legacy single-file avatars are returned as is.
+pub fn stored_avatar_file_names(current_file_name: &str) -> Vec<String> {
+    let primary_suffix = format!("_{}.webp", PRIMARY_AVATAR_SIZE);
+    match current_file_name.strip_suffix(&primary_suffix) {
+        Some(prefix) => AVATAR_SIZES
+            .iter()
+            .map(|size| format!("{}_{}.webp", prefix, size))
+            .collect(),
+        None => vec![current_file_name.to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    // A PNG of the given size: left half red, right half blue
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = RgbaImage::from_fn(width, height, |x, _| {
+            if x < width / 2 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 255])
+            }
+        });
+        let mut out = Vec::new();
+        DynamicImage::ImageRgba8(img)
+            .write_to(&mut Cursor::new(&mut out), ImageFormat::Png)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn sniffs_formats_from_magic_bytes() {
+        assert_eq!(
+            sniff_image_format(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Some(SniffedFormat::Jpeg)
+        );
+        assert_eq!(sniff_image_format(&png(2, 2)), Some(SniffedFormat::Png));
+        assert_eq!(
+            sniff_image_format(b"RIFF\x10\x00\x00\x00WEBPVP8L"),
+            Some(SniffedFormat::WebP)
+        );
+        // RIFF containers that aren't WebP, truncated headers and text are all rejected
+        assert_eq!(sniff_image_format(b"RIFF\x10\x00\x00\x00WAVEfmt "), None);
+        assert_eq!(sniff_image_format(b"RIFF"), None);
+        assert_eq!(sniff_image_format(&[0xFF, 0xD8]), None);
+        assert_eq!(sniff_image_format(b"<svg xmlns=\"\"/>"), None);
+        assert_eq!(sniff_image_format(&[]), None);
+    }
+
+    #[test]
+    fn crops_and_resizes_into_square_webp_variants() {
+        let variants = process_avatar(&png(300, 100)).unwrap();
+        assert_eq!(
+            variants.iter().map(|v| v.size).collect::<Vec<_>>(),
+            AVATAR_SIZES
+        );
+        for variant in &variants {
+            assert_eq!(sniff_image_format(&variant.data), Some(SniffedFormat::WebP));
+            let img = image::load_from_memory(&variant.data).unwrap();
+            assert_eq!((img.width(), img.height()), (variant.size, variant.size));
+        }
+
+        // The centred 100x100 square straddles the red/blue boundary
+        let img = image::load_from_memory(&variants[0].data)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(img.get_pixel(0, 32), &Rgba([255, 0, 0, 255]));
+        assert_eq!(img.get_pixel(63, 32), &Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn rejects_unsupported_and_corrupt_uploads() {
+        assert!(process_avatar(b"GIF89a\x01\x00\x01\x00").is_err());
+        let mut truncated = png(10, 10);
+        truncated.truncate(20);
+        assert!(process_avatar(&truncated).is_err());
+    }
+
+    #[test]
+    fn expands_stored_avatars_to_every_variant() {
+        let user_id = Uuid::nil();
+        let upload_id = Uuid::nil();
+        let primary = avatar_file_name(&user_id, &upload_id, PRIMARY_AVATAR_SIZE);
+        assert_eq!(
+            stored_avatar_file_names(&primary),
+            AVATAR_SIZES
+                .iter()
+                .map(|size| avatar_file_name(&user_id, &upload_id, *size))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            stored_avatar_file_names("avatar_legacy.png"),
+            vec!["avatar_legacy.png".to_string()]
+        );
+    }
+}
@@ -1,77 +1,544 @@
-use crate::models::all_models::MatchUser;
-use chrono::{Datelike, Utc};
+use crate::handlers::sponsor_capacity::SponsorCapacity;
+use crate::models::all_models::{MatchUser, MatchingConfig};
 use geoutils::Location;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
 
-/// Calculate match score between two users
-pub fn calculate_match_score(member: &MatchUser, sponsor: &MatchUser) -> f32 {
-    let mut score: f32 = 0.0;
-
-    let member_age = Utc::now().year() - member.dob.year();
-    let sponsor_age = Utc::now().year() - sponsor.dob.year();
-    let age_diff = (member_age as i32 - sponsor_age as i32).abs();
-    let age_score = 30.0 * (1.0 - (age_diff as f32 / 15.0)).max(0.0);
-    score += age_score;
-
-    if let (Some(member_loc), Some(sponsor_loc)) =
-        (member.parse_location(), sponsor.parse_location())
-    {
-        let member_point = Location::new(member_loc.longitude, member_loc.latitude);
-        let sponsor_point = Location::new(sponsor_loc.longitude, sponsor_loc.latitude);
-
-        let distance = member_point.haversine_distance_to(&sponsor_point).meters();
-
-        let location_score = if distance <= 10000.0 {
-            30.0 // Same city or close distance
-        } else if distance <= 50000.0 {
-            20.0 // Nearby cities
-        } else if distance <= 200000.0 {
-            10.0 // Regional match
-        } else {
-            5.0 // Very distant
-        };
-        score += location_score;
+const DAYS_PER_YEAR: f32 = 365.2425;
+
+/// One of the things a sponsor match is scored on
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchFactor {
+    Age,
+    Location,
+    Interests,
+    Experience,
+    Availability,
+    Language,
+}
+
+/// How one factor contributed to the overall score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FactorScore {
+    pub factor: MatchFactor,
+    // Points earned, out of `max` (the factor's share of 100)
+    pub points: f32,
+    pub max: f32,
+    pub detail: Option<String>,
+}
+
+/// A hard filter that rules a sponsor out of recommendations
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchExclusion {
+    NoSharedLanguage,
+    TooFar,
+    LocationUnknown,
+    NotAccepting,
+    AtCapacity,
+}
+
+/// Score out of 100 with a per-factor breakdown
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchResult {
+    pub score: f32,
+    pub factors: Vec<FactorScore>,
+    // Points taken off for how many mentees the sponsor already has
+    pub load_penalty: f32,
+    pub excluded_by: Option<MatchExclusion>,
+}
+
+// Current matching config, falling back to the defaults if the row is missing
+pub async fn load_matching_config(pool: &PgPool) -> Result<MatchingConfig, sqlx::Error> {
+    let config = sqlx::query_as::<_, MatchingConfig>(
+        "SELECT age_weight, location_weight, interests_weight, experience_weight,
+                availability_weight, language_weight, age_range_years,
+                require_shared_language, max_distance_km, load_penalty, rematch_cooldown_days,
+                max_pending_requests, request_expiry_days, updated_by, updated_at
+         FROM matching_config WHERE id = TRUE",
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(config.unwrap_or_default())
+}
+
+// Exact age gap in years, from the two dates of birth
+fn age_difference_years(member: &MatchUser, sponsor: &MatchUser) -> f32 {
+    (member.dob - sponsor.dob).num_days().abs() as f32 / DAYS_PER_YEAR
+}
+
+// Distance in km between the two users, when both have coordinates
+fn distance_km(member: &MatchUser, sponsor: &MatchUser) -> Option<f32> {
+    let (member_loc, sponsor_loc) = (member.parse_location()?, sponsor.parse_location()?);
+    let member_point = Location::new(member_loc.latitude, member_loc.longitude);
+    let sponsor_point = Location::new(sponsor_loc.latitude, sponsor_loc.longitude);
+    Some((member_point.haversine_distance_to(&sponsor_point).meters() / 1000.0) as f32)
+}
+
+// Share of the distance points for a given distance
+fn location_fraction(distance_km: f32) -> f32 {
+    if distance_km <= 10.0 {
+        1.0 // Same city or close distance
+    } else if distance_km <= 50.0 {
+        2.0 / 3.0 // Nearby cities
+    } else if distance_km <= 200.0 {
+        1.0 / 3.0 // Regional match
+    } else {
+        1.0 / 6.0 // Very distant
     }
+}
 
-    if let (Some(member_interests), Some(sponsor_interests)) =
-        (&member.interests, &sponsor.interests)
-    {
-        let common = member_interests
+// Values the member listed that the sponsor also has
+fn shared_values(member: &Option<Vec<String>>, sponsor: &Option<Vec<String>>) -> Vec<String> {
+    match (member, sponsor) {
+        (Some(member), Some(sponsor)) => member
             .iter()
-            .filter(|i| sponsor_interests.contains(i))
-            .count();
-        let interest_score = 20.0 * (common as f32 / member_interests.len().max(1) as f32);
-        score += interest_score;
+            .filter(|v| sponsor.contains(v))
+            .cloned()
+            .collect(),
+        _ => Vec::new(),
     }
+}
 
-    if let (Some(member_exp), Some(sponsor_exp)) = (&member.experience, &sponsor.experience) {
-        let common = member_exp
-            .iter()
-            .filter(|e| sponsor_exp.contains(e))
-            .count();
-        let exp_score = 15.0 * (common as f32 / member_exp.len().max(1) as f32);
-        score += exp_score;
+// Score a list factor as the fraction of the member's values the sponsor shares
+fn overlap_factor(
+    factor: MatchFactor,
+    max: f32,
+    member: &Option<Vec<String>>,
+    sponsor: &Option<Vec<String>>,
+) -> (FactorScore, usize) {
+    let shared = shared_values(member, sponsor);
+    let member_count = member.as_ref().map_or(0, |m| m.len());
+    let fraction = shared.len() as f32 / member_count.max(1) as f32;
+    let detail = if shared.is_empty() {
+        "Nothing in common".to_string()
+    } else {
+        format!("Shared: {}", shared.join(", "))
+    };
+    (
+        FactorScore {
+            factor,
+            points: max * fraction,
+            max,
+            detail: Some(detail),
+        },
+        shared.len(),
+    )
+}
+
+/// Score a sponsor for a member with the given config, and check the hard filters
+pub fn evaluate_match(
+    member: &MatchUser,
+    sponsor: &MatchUser,
+    config: &MatchingConfig,
+) -> MatchResult {
+    let total_weight = config.age_weight
+        + config.location_weight
+        + config.interests_weight
+        + config.experience_weight
+        + config.availability_weight
+        + config.language_weight;
+    // Each factor's share of 100 points
+    let share = |weight: f32| {
+        if total_weight > 0.0 {
+            100.0 * weight / total_weight
+        } else {
+            0.0
+        }
+    };
+
+    let mut factors = Vec::with_capacity(6);
+    let mut excluded_by = None;
+
+    let age_gap = age_difference_years(member, sponsor);
+    let age_max = share(config.age_weight);
+    factors.push(FactorScore {
+        factor: MatchFactor::Age,
+        points: age_max * (1.0 - age_gap / config.age_range_years).max(0.0),
+        max: age_max,
+        detail: Some(format!("{:.0} years apart", age_gap)),
+    });
+
+    let location_max = share(config.location_weight);
+    let distance = distance_km(member, sponsor);
+    factors.push(FactorScore {
+        factor: MatchFactor::Location,
+        points: distance.map_or(0.0, |d| location_max * location_fraction(d)),
+        max: location_max,
+        detail: Some(match distance {
+            Some(d) if d < 10.0 => "Under 10 km away".to_string(),
+            Some(d) => format!("About {:.0} km away", d),
+            None => "Location unknown".to_string(),
+        }),
+    });
+    if let Some(max_distance) = config.max_distance_km {
+        match distance {
+            Some(d) if d > max_distance => excluded_by = Some(MatchExclusion::TooFar),
+            None => excluded_by = Some(MatchExclusion::LocationUnknown),
+            _ => {}
+        }
+    }
+
+    let (interests, _) = overlap_factor(
+        MatchFactor::Interests,
+        share(config.interests_weight),
+        &member.interests,
+        &sponsor.interests,
+    );
+    factors.push(interests);
+
+    let (experience, _) = overlap_factor(
+        MatchFactor::Experience,
+        share(config.experience_weight),
+        &member.experience,
+        &sponsor.experience,
+    );
+    factors.push(experience);
+
+    let (availability, _) = overlap_factor(
+        MatchFactor::Availability,
+        share(config.availability_weight),
+        &member.available_days,
+        &sponsor.available_days,
+    );
+    factors.push(availability);
+
+    let (language, shared_languages) = overlap_factor(
+        MatchFactor::Language,
+        share(config.language_weight),
+        &member.languages,
+        &sponsor.languages,
+    );
+    factors.push(language);
+    if config.require_shared_language && shared_languages == 0 {
+        excluded_by = excluded_by.or(Some(MatchExclusion::NoSharedLanguage));
+    }
+
+    let score: f32 = factors.iter().map(|f| f.points).sum();
+    MatchResult {
+        score: score.clamp(0.0, 100.0),
+        factors,
+        load_penalty: 0.0,
+        excluded_by,
+    }
+}
+
+/// Rule out sponsors who can't take anyone on and lower the score of busy ones,
+/// so new members are spread across sponsors rather than piling onto the best match
+pub fn apply_sponsor_load(
+    result: &mut MatchResult,
+    capacity: &SponsorCapacity,
+    config: &MatchingConfig,
+) {
+    if !capacity.accepting_new_members {
+        result.excluded_by = result.excluded_by.or(Some(MatchExclusion::NotAccepting));
+    } else if !capacity.has_room() {
+        result.excluded_by = result.excluded_by.or(Some(MatchExclusion::AtCapacity));
+    }
+
+    let penalty = config.load_penalty * capacity.load();
+    result.load_penalty = penalty.min(result.score);
+    result.score -= result.load_penalty;
+}
+
+/// Calculate match score between two users
+pub fn calculate_match_score(
+    member: &MatchUser,
+    sponsor: &MatchUser,
+    config: &MatchingConfig,
+) -> f32 {
+    evaluate_match(member, sponsor, config).score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use uuid::Uuid;
+
+    fn user(dob: (i32, u32, u32)) -> MatchUser {
+        MatchUser {
+            id: Uuid::new_v4(),
+            dob: NaiveDate::from_ymd_opt(dob.0, dob.1, dob.2).unwrap(),
+            location: None,
+            interests: None,
+            experience: None,
+            available_days: None,
+            languages: None,
+        }
     }
 
-    if let (Some(member_avail), Some(sponsor_avail)) =
-        (&member.available_days, &sponsor.available_days)
-    {
-        let common = member_avail
+    fn at(mut user: MatchUser, latitude: f64, longitude: f64) -> MatchUser {
+        user.location = Some(format!(
+            r#"{{"latitude":{},"longitude":{},"city":null,"country":null}}"#,
+            latitude, longitude
+        ));
+        user
+    }
+
+    fn list(values: &[&str]) -> Option<Vec<String>> {
+        Some(values.iter().map(|v| v.to_string()).collect())
+    }
+
+    fn points(result: &MatchResult, factor: MatchFactor) -> f32 {
+        result
+            .factors
             .iter()
-            .filter(|d| sponsor_avail.contains(d))
-            .count();
-        let avail_score = 10.0 * (common as f32 / member_avail.len().max(1) as f32);
-        score += avail_score;
+            .find(|f| f.factor == factor)
+            .map(|f| f.points)
+            .unwrap()
+    }
+
+    // The default weights add up to 110, so a factor's points are scaled down to a share of 100
+    fn share(points: f32) -> f32 {
+        points * 100.0 / 110.0
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn age_uses_exact_dates_not_calendar_years() {
+        let config = MatchingConfig::default();
+        // Born a day apart across New Year: calendar years differ by one, real gap is a day
+        let member = user((1990, 1, 1));
+        let sponsor = user((1989, 12, 31));
+        assert_close(
+            points(
+                &evaluate_match(&member, &sponsor, &config),
+                MatchFactor::Age,
+            ),
+            share(30.0) * (1.0 - (1.0 / DAYS_PER_YEAR) / 15.0),
+        );
+
+        let sponsor = user((1982, 7, 2));
+        let gap = age_difference_years(&member, &sponsor);
+        assert_close(gap, 7.5);
+        assert_close(
+            points(
+                &evaluate_match(&member, &sponsor, &config),
+                MatchFactor::Age,
+            ),
+            share(15.0),
+        );
+
+        let sponsor = user((1960, 1, 1));
+        assert_close(
+            points(
+                &evaluate_match(&member, &sponsor, &config),
+                MatchFactor::Age,
+            ),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn location_scores_by_distance_band() {
+        let config = MatchingConfig::default();
+        let member = at(user((1990, 1, 1)), 51.5, -0.1);
+
+        let near = at(user((1990, 1, 1)), 51.5, -0.2);
+        assert_close(
+            points(
+                &evaluate_match(&member, &near, &config),
+                MatchFactor::Location,
+            ),
+            share(30.0),
+        );
+
+        // Roughly 160 km away
+        let regional = at(user((1990, 1, 1)), 52.5, -1.9);
+        assert_close(
+            points(
+                &evaluate_match(&member, &regional, &config),
+                MatchFactor::Location,
+            ),
+            share(10.0),
+        );
+
+        let unknown = user((1990, 1, 1));
+        assert_close(
+            points(
+                &evaluate_match(&member, &unknown, &config),
+                MatchFactor::Location,
+            ),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn max_distance_excludes_far_and_unknown_locations() {
+        let config = MatchingConfig {
+            max_distance_km: Some(50.0),
+            ..MatchingConfig::default()
+        };
+        let member = at(user((1990, 1, 1)), 51.5, -0.1);
+
+        let near = at(user((1990, 1, 1)), 51.6, -0.1);
+        assert_eq!(evaluate_match(&member, &near, &config).excluded_by, None);
+
+        let far = at(user((1990, 1, 1)), 53.5, -2.2);
+        assert_eq!(
+            evaluate_match(&member, &far, &config).excluded_by,
+            Some(MatchExclusion::TooFar)
+        );
+
+        let unknown = user((1990, 1, 1));
+        assert_eq!(
+            evaluate_match(&member, &unknown, &config).excluded_by,
+            Some(MatchExclusion::LocationUnknown)
+        );
     }
 
-    if let (Some(member_lang), Some(sponsor_lang)) = (&member.languages, &sponsor.languages) {
-        let common = member_lang
+    #[test]
+    fn interests_score_is_share_of_member_interests() {
+        let config = MatchingConfig::default();
+        let mut member = user((1990, 1, 1));
+        member.interests = list(&["alcohol", "anxiety", "grief", "gambling"]);
+        let mut sponsor = user((1990, 1, 1));
+        sponsor.interests = list(&["anxiety", "alcohol", "trauma"]);
+
+        let result = evaluate_match(&member, &sponsor, &config);
+        assert_close(points(&result, MatchFactor::Interests), share(10.0));
+        let detail = result
+            .factors
             .iter()
-            .filter(|l| sponsor_lang.contains(l))
-            .count();
-        let lang_score = 5.0 * (common as f32 / member_lang.len().max(1) as f32);
-        score += lang_score;
+            .find(|f| f.factor == MatchFactor::Interests)
+            .and_then(|f| f.detail.clone())
+            .unwrap();
+        assert_eq!(detail, "Shared: alcohol, anxiety");
+    }
+
+    #[test]
+    fn experience_score_is_share_of_member_experience() {
+        let config = MatchingConfig::default();
+        let mut member = user((1990, 1, 1));
+        member.experience = list(&["alcohol"]);
+        let mut sponsor = user((1990, 1, 1));
+        sponsor.experience = list(&["alcohol", "opioids"]);
+        assert_close(
+            points(
+                &evaluate_match(&member, &sponsor, &config),
+                MatchFactor::Experience,
+            ),
+            share(15.0),
+        );
+
+        sponsor.experience = None;
+        assert_close(
+            points(
+                &evaluate_match(&member, &sponsor, &config),
+                MatchFactor::Experience,
+            ),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn availability_score_is_share_of_member_days() {
+        let config = MatchingConfig::default();
+        let mut member = user((1990, 1, 1));
+        member.available_days = list(&["monday", "wednesday", "friday", "sunday"]);
+        let mut sponsor = user((1990, 1, 1));
+        sponsor.available_days = list(&["friday"]);
+        assert_close(
+            points(
+                &evaluate_match(&member, &sponsor, &config),
+                MatchFactor::Availability,
+            ),
+            share(2.5),
+        );
+    }
+
+    #[test]
+    fn language_scores_and_optional_hard_filter() {
+        let mut config = MatchingConfig::default();
+        let mut member = user((1990, 1, 1));
+        member.languages = list(&["en", "ms"]);
+        let mut sponsor = user((1990, 1, 1));
+        sponsor.languages = list(&["ms"]);
+        assert_close(
+            points(
+                &evaluate_match(&member, &sponsor, &config),
+                MatchFactor::Language,
+            ),
+            share(2.5),
+        );
+
+        sponsor.languages = list(&["fr"]);
+        assert_eq!(evaluate_match(&member, &sponsor, &config).excluded_by, None);
+
+        config.require_shared_language = true;
+        assert_eq!(
+            evaluate_match(&member, &sponsor, &config).excluded_by,
+            Some(MatchExclusion::NoSharedLanguage)
+        );
     }
 
-    let normalized_score = (score / 100.0) * 100.0;
-    normalized_score.min(100.0)
+    fn capacity(max_active_mentees: i32, active_mentees: i64, accepting: bool) -> SponsorCapacity {
+        SponsorCapacity {
+            sponsor_id: Uuid::new_v4(),
+            max_active_mentees,
+            accepting_new_members: accepting,
+            active_mentees,
+        }
+    }
+
+    #[test]
+    fn sponsor_load_penalises_busy_sponsors_and_excludes_full_ones() {
+        let config = MatchingConfig::default();
+        let member = user((1990, 1, 1));
+        let sponsor = user((1990, 1, 1));
+        let base = evaluate_match(&member, &sponsor, &config);
+
+        let mut idle = base.clone();
+        apply_sponsor_load(&mut idle, &capacity(4, 0, true), &config);
+        assert_close(idle.score, base.score);
+        assert_eq!(idle.excluded_by, None);
+
+        let mut busy = base.clone();
+        apply_sponsor_load(&mut busy, &capacity(4, 3, true), &config);
+        assert_close(busy.load_penalty, 15.0);
+        assert_close(busy.score, base.score - 15.0);
+        assert_eq!(busy.excluded_by, None);
+
+        let mut full = base.clone();
+        apply_sponsor_load(&mut full, &capacity(4, 4, true), &config);
+        assert_eq!(full.excluded_by, Some(MatchExclusion::AtCapacity));
+
+        let mut paused = base.clone();
+        apply_sponsor_load(&mut paused, &capacity(4, 0, false), &config);
+        assert_eq!(paused.excluded_by, Some(MatchExclusion::NotAccepting));
+    }
+
+    #[test]
+    fn weights_are_relative_and_scores_sum_to_total() {
+        let config = MatchingConfig {
+            age_weight: 1.0,
+            location_weight: 0.0,
+            interests_weight: 1.0,
+            experience_weight: 0.0,
+            availability_weight: 0.0,
+            language_weight: 0.0,
+            ..MatchingConfig::default()
+        };
+        let mut member = user((1990, 1, 1));
+        member.interests = list(&["alcohol"]);
+        let mut sponsor = user((1990, 1, 1));
+        sponsor.interests = list(&["alcohol"]);
+
+        let result = evaluate_match(&member, &sponsor, &config);
+        assert_close(points(&result, MatchFactor::Age), 50.0);
+        assert_close(points(&result, MatchFactor::Interests), 50.0);
+        assert_close(points(&result, MatchFactor::Location), 0.0);
+        assert_close(result.score, 100.0);
+        assert_close(result.factors.iter().map(|f| f.points).sum(), result.score);
+    }
 }
@@ -0,0 +1,105 @@
+use crate::handlers::matching_algo::load_matching_config;
+use crate::handlers::notifications::{create_notifications, push_notifications};
+use crate::models::all_models::{MatchingRequest, MatchingStatus, Notification, NotificationKind};
+use log::{info, warn};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How often the background job looks for requests nobody answered in time
+const REQUEST_EXPIRY_JOB_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Tell both the member and the sponsor that a request was created or changed status
+pub async fn notify_request_change(
+    conn: &mut PgConnection,
+    request: &MatchingRequest,
+) -> Result<Vec<Notification>, sqlx::Error> {
+    let mut user_ids = vec![request.member_id];
+    user_ids.extend(request.sponsor_id);
+    let payload = json!({
+        "matching_request_id": request.matching_request_id,
+        "member_id": request.member_id,
+        "sponsor_id": request.sponsor_id,
+        "status": request.status,
+    });
+    create_notifications(conn, &user_ids, NotificationKind::MatchingRequest, &payload).await
+}
+
+// Cancel the member's other pending requests once one of them has been accepted
+pub async fn cancel_sibling_requests(
+    conn: &mut PgConnection,
+    member_id: Uuid,
+    accepted_request_id: Uuid,
+) -> Result<Vec<MatchingRequest>, sqlx::Error> {
+    sqlx::query_as::<_, MatchingRequest>(
+        "UPDATE matching_requests SET status = $1, updated_at = NOW()
+         WHERE member_id = $2 AND matching_request_id <> $3 AND status = $4
+         RETURNING *",
+    )
+    .bind(MatchingStatus::Cancelled)
+    .bind(member_id)
+    .bind(accepted_request_id)
+    .bind(MatchingStatus::Pending)
+    .fetch_all(conn)
+    .await
+}
+
+// Cancel every pending request sent to a sponsor, e.g. when their sponsor status is revoked
+pub async fn cancel_requests_to_sponsor(
+    conn: &mut PgConnection,
+    sponsor_id: Uuid,
+) -> Result<Vec<MatchingRequest>, sqlx::Error> {
+    sqlx::query_as::<_, MatchingRequest>(
+        "UPDATE matching_requests SET status = $1, updated_at = NOW()
+         WHERE sponsor_id = $2 AND status = $3
+         RETURNING *",
+    )
+    .bind(MatchingStatus::Cancelled)
+    .bind(sponsor_id)
+    .bind(MatchingStatus::Pending)
+    .fetch_all(conn)
+    .await
+}
+
+// Expire every request left pending for longer than the configured window
+pub async fn expire_stale_requests(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let config = load_matching_config(pool).await?;
+    let mut tx = pool.begin().await?;
+
+    let expired = sqlx::query_as::<_, MatchingRequest>(
+        "UPDATE matching_requests SET status = $1, updated_at = NOW()
+         WHERE status = $2 AND created_at <= NOW() - make_interval(days => $3)
+         RETURNING *",
+    )
+    .bind(MatchingStatus::Expired)
+    .bind(MatchingStatus::Pending)
+    .bind(config.request_expiry_days)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut notifications = Vec::new();
+    for request in &expired {
+        notifications.extend(notify_request_change(&mut tx, request).await?);
+    }
+    tx.commit().await?;
+
+    push_notifications(&notifications).await;
+    Ok(expired.len())
+}
+
+// Spawn the periodic request expiry job
+pub fn spawn_request_expiry_job(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(REQUEST_EXPIRY_JOB_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            match expire_stale_requests(&pool).await {
+                Ok(0) => {}
+                Ok(count) => info!("Request expiry job expired {} matching requests", count),
+                Err(e) => warn!("Request expiry job failed: {}", e),
+            }
+        }
+    });
+}
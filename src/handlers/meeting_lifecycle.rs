@@ -0,0 +1,375 @@
+use crate::handlers::announcements::{
+    create_user_announcement, push_announcements, UserAnnouncement,
+};
+use crate::handlers::ws::send_to_users;
+use crate::models::all_models::{AnnouncementTarget, AnnouncementType, MeetingStatus};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How often the background job checks meetings
+const MEETING_LIFECYCLE_JOB_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Settings for the meeting scheduler
+#[derive(Debug, Clone)]
+pub struct MeetingLifecycleConfig {
+    /// How long before a meeting participants are reminded, in minutes, largest first
+    pub reminder_offsets: Vec<i32>,
+    /// Ongoing meetings are ended this long after they started
+    pub max_duration_minutes: i32,
+    /// Upcoming meetings the host hasn't started this long after the scheduled time are missed
+    pub no_show_grace_minutes: i32,
+}
+
+impl MeetingLifecycleConfig {
+    // Read settings from secrets, defaulting to reminders 24h, 1h and 10m ahead,
+    // a 3 hour maximum duration and 30 minutes' grace for late starts
+    pub fn from_secrets(secrets: &shuttle_runtime::SecretStore) -> Self {
+        let minutes = |key: &str, default: i32| {
+            secrets
+                .get(key)
+                .and_then(|v| v.parse::<i32>().ok())
+                .filter(|&v| v > 0)
+                .unwrap_or(default)
+        };
+        let reminder_offsets = secrets
+            .get("MEETING_REMINDER_OFFSETS_MINUTES")
+            .map(|v| parse_reminder_offsets(&v))
+            .unwrap_or_else(|| vec![24 * 60, 60, 10]);
+
+        MeetingLifecycleConfig {
+            reminder_offsets,
+            max_duration_minutes: minutes("MEETING_MAX_DURATION_MINUTES", 180),
+            no_show_grace_minutes: minutes("MEETING_NO_SHOW_GRACE_MINUTES", 30),
+        }
+    }
+}
+
+// Comma separated minutes such as "1440,60,10"; invalid entries are ignored
+pub fn parse_reminder_offsets(raw: &str) -> Vec<i32> {
+    let mut offsets: Vec<i32> = raw
+        .split(',')
+        .filter_map(|v| v.trim().parse::<i32>().ok())
+        .filter(|&v| v > 0)
+        .collect();
+    offsets.sort_unstable_by(|a, b| b.cmp(a));
+    offsets.dedup();
+    offsets
+}
+
+// Offsets that have come due and haven't been sent for the meeting's current time.
+// All of them are recorded, but only the last (closest to the start) is sent, so a meeting
+// scheduled at short notice doesn't get several reminders at once.
+pub fn due_reminder_offsets(
+    offsets: &[i32],
+    already_sent: &[i32],
+    minutes_until_start: i64,
+) -> Vec<i32> {
+    offsets
+        .iter()
+        .copied()
+        .filter(|&offset| i64::from(offset) >= minutes_until_start)
+        .filter(|offset| !already_sent.contains(offset))
+        .collect()
+}
+
+// "24 hours", "1 hour", "90 minutes"
+pub fn lead_time_text(offset_minutes: i32) -> String {
+    let (value, unit) = if offset_minutes % 60 == 0 {
+        (offset_minutes / 60, "hour")
+    } else {
+        (offset_minutes, "minute")
+    };
+    if value == 1 {
+        format!("1 {}", unit)
+    } else {
+        format!("{} {}s", value, unit)
+    }
+}
+
+// Helper function: Upcoming meetings starting within `furthest` minutes, locked so
+// concurrent runs skip them, with the offsets already sent for their current time
+async fn due_meetings(
+    conn: &mut PgConnection,
+    furthest: i32,
+) -> Result<Vec<(Uuid, String, DateTime<Utc>, Vec<i32>)>, sqlx::Error> {
+    let query = "
+        WITH due AS (
+            SELECT meeting_id, title, scheduled_time
+            FROM group_meetings
+            WHERE status = $1
+              AND scheduled_time > NOW()
+              AND scheduled_time <= NOW() + make_interval(mins => $2)
+            FOR UPDATE SKIP LOCKED
+        )
+        SELECT due.meeting_id, due.title, due.scheduled_time,
+               ARRAY(
+                   SELECT mr.offset_minutes FROM meeting_reminders mr
+                   WHERE mr.meeting_id = due.meeting_id
+                     AND mr.scheduled_time = due.scheduled_time
+               ) AS sent
+        FROM due
+    ";
+    sqlx::query_as(query)
+        .bind(MeetingStatus::Upcoming)
+        .bind(furthest)
+        .fetch_all(conn)
+        .await
+}
+
+// Remind participants of meetings starting within one of the configured offsets
+pub async fn send_meeting_reminders(
+    pool: &PgPool,
+    config: &MeetingLifecycleConfig,
+) -> Result<usize, sqlx::Error> {
+    let Some(&furthest) = config.reminder_offsets.first() else {
+        return Ok(0);
+    };
+
+    let mut tx = pool.begin().await?;
+    let candidates = due_meetings(&mut tx, furthest).await?;
+
+    let now = Utc::now();
+    let mut announcements = Vec::new();
+    for (meeting_id, title, scheduled_time, sent) in candidates {
+        // Rounded up, so a reminder picked up at 59m40s still reads "1 hour"
+        let minutes_until_start = ((scheduled_time - now).num_seconds() + 59) / 60;
+        let due = due_reminder_offsets(&config.reminder_offsets, &sent, minutes_until_start);
+        let Some(&offset) = due.last() else {
+            continue;
+        };
+
+        sqlx::query(
+            "INSERT INTO meeting_reminders (meeting_id, offset_minutes, scheduled_time)
+             SELECT $1, o, $3 FROM UNNEST($2::int[]) AS o
+             ON CONFLICT DO NOTHING",
+        )
+        .bind(meeting_id)
+        .bind(&due)
+        .bind(scheduled_time)
+        .execute(&mut *tx)
+        .await?;
+
+        let participants: Vec<Uuid> =
+            sqlx::query_scalar("SELECT user_id FROM meeting_participants WHERE meeting_id = $1")
+                .bind(meeting_id)
+                .fetch_all(&mut *tx)
+                .await?;
+        let message = format!(
+            "\"{}\" starts in {}.",
+            title,
+            lead_time_text(offset.min(minutes_until_start.max(1) as i32))
+        );
+        for participant_id in participants {
+            let announcement = UserAnnouncement {
+                announcement_type: AnnouncementType::MeetingReminder,
+                target: Some(AnnouncementTarget::GroupMeeting),
+                target_id: Some(meeting_id),
+                recipient_id: participant_id,
+                extra_data: Some(json!({
+                    "meeting_id": meeting_id,
+                    "scheduled_time": scheduled_time,
+                    "offset_minutes": offset,
+                })),
+                message: &message,
+            };
+            announcements.push(create_user_announcement(&mut tx, announcement).await?);
+        }
+    }
+    tx.commit().await?;
+
+    push_announcements(&announcements).await;
+    Ok(announcements.len())
+}
+
+// End ongoing meetings that have run past the maximum duration and tell their participants
+pub async fn end_overrunning_meetings(
+    pool: &PgPool,
+    config: &MeetingLifecycleConfig,
+) -> Result<usize, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let ended: Vec<(Uuid, Uuid)> = sqlx::query_as(
+        "UPDATE group_meetings
+         SET status = $1, ended_at = NOW(), ended_automatically = TRUE
+         WHERE status = $2
+           AND COALESCE(started_at, scheduled_time) + make_interval(mins => $3) <= NOW()
+         RETURNING meeting_id, host_id",
+    )
+    .bind(MeetingStatus::Ended)
+    .bind(MeetingStatus::Ongoing)
+    .bind(config.max_duration_minutes)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let mut announcements = Vec::with_capacity(ended.len());
+    let mut notices = Vec::with_capacity(ended.len());
+    for (meeting_id, host_id) in &ended {
+        let announcement = UserAnnouncement {
+            announcement_type: AnnouncementType::MeetingEnded,
+            target: Some(AnnouncementTarget::GroupMeeting),
+            target_id: Some(*meeting_id),
+            recipient_id: *host_id,
+            extra_data: Some(json!({ "meeting_id": meeting_id, "automatic": true })),
+            message: "Your meeting was ended automatically after reaching the maximum duration.",
+        };
+        announcements.push(create_user_announcement(&mut tx, announcement).await?);
+
+        let participants: Vec<Uuid> =
+            sqlx::query_scalar("SELECT user_id FROM meeting_participants WHERE meeting_id = $1")
+                .bind(meeting_id)
+                .fetch_all(&mut *tx)
+                .await?;
+        notices.push((*meeting_id, participants));
+    }
+    tx.commit().await?;
+
+    push_announcements(&announcements).await;
+    for (meeting_id, participants) in notices {
+        let message = json!({
+            "type": "meeting_ended",
+            "payload": { "meeting_id": meeting_id, "automatic": true }
+        });
+        // Participants who aren't connected see the status next time they load the meeting
+        let _ = send_to_users(&participants, message).await;
+    }
+    Ok(ended.len())
+}
+
+// Flag upcoming meetings the host never started as missed
+pub async fn flag_missed_meetings(
+    pool: &PgPool,
+    config: &MeetingLifecycleConfig,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE group_meetings
+         SET status = $1
+         WHERE status = $2
+           AND scheduled_time + make_interval(mins => $3) <= NOW()",
+    )
+    .bind(MeetingStatus::Missed)
+    .bind(MeetingStatus::Upcoming)
+    .bind(config.no_show_grace_minutes)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+// Spawn the periodic meeting scheduler: reminders, auto-ending and no-shows
+pub fn spawn_meeting_lifecycle_job(pool: PgPool, config: MeetingLifecycleConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(MEETING_LIFECYCLE_JOB_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            match send_meeting_reminders(&pool, &config).await {
+                Ok(0) => {}
+                Ok(sent) => info!("Sent {} meeting reminders", sent),
+                Err(e) => warn!("Failed to send meeting reminders: {:?}", e),
+            }
+            match end_overrunning_meetings(&pool, &config).await {
+                Ok(0) => {}
+                Ok(ended) => info!("Automatically ended {} meetings", ended),
+                Err(e) => warn!("Failed to end overrunning meetings: {:?}", e),
+            }
+            match flag_missed_meetings(&pool, &config).await {
+                Ok(0) => {}
+                Ok(missed) => info!("Flagged {} meetings as missed", missed),
+                Err(e) => warn!("Failed to flag missed meetings: {:?}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_offsets_largest_first() {
+        assert_eq!(
+            parse_reminder_offsets("10, 1440,60,x,-5,60"),
+            vec![1440, 60, 10]
+        );
+        assert!(parse_reminder_offsets("").is_empty());
+    }
+
+    #[test]
+    fn only_unsent_offsets_that_have_come_due() {
+        let offsets = [1440, 60, 10];
+        assert_eq!(due_reminder_offsets(&offsets, &[], 2000), Vec::<i32>::new());
+        assert_eq!(due_reminder_offsets(&offsets, &[], 1439), vec![1440]);
+        assert_eq!(due_reminder_offsets(&offsets, &[1440], 59), vec![60]);
+        // Scheduled at short notice: everything is due, the closest one is sent
+        assert_eq!(due_reminder_offsets(&offsets, &[], 5), vec![1440, 60, 10]);
+        assert!(due_reminder_offsets(&offsets, &[1440, 60, 10], 5).is_empty());
+    }
+
+    // Runs the reminder query against a migrated database, inside a transaction that is rolled
+    // back. Run with: DATABASE_URL=postgres://... cargo test due_meetings_query -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn due_meetings_query_runs_on_postgres() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&url).await.unwrap();
+        let mut tx = pool.begin().await.unwrap();
+
+        let host_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO users (username, email, password_hash, avatar_url, dob, user_profile)
+             VALUES ($1, $1 || '@example.com', '', 'https://example.com/a.png', '1990-01-01',
+                     'Reminder test user')
+             RETURNING user_id",
+        )
+        .bind(format!("reminder-test-{}", Uuid::new_v4()))
+        .fetch_one(&mut *tx)
+        .await
+        .unwrap();
+        let support_group_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO support_groups (title, description, status)
+             VALUES ('Reminder test', '', 'approved')
+             RETURNING support_group_id",
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .unwrap();
+
+        let meeting_in = |minutes: i32| {
+            sqlx::query_as::<_, (Uuid, DateTime<Utc>)>(
+                "INSERT INTO group_meetings (support_group_id, host_id, title, scheduled_time, status)
+                 VALUES ($1, $2, 'Reminder test meeting', NOW() + make_interval(mins => $3), $4)
+                 RETURNING meeting_id, scheduled_time",
+            )
+            .bind(support_group_id)
+            .bind(host_id)
+            .bind(minutes)
+            .bind(MeetingStatus::Upcoming)
+        };
+        let (soon, soon_time) = meeting_in(30).fetch_one(&mut *tx).await.unwrap();
+        let (later, _) = meeting_in(3000).fetch_one(&mut *tx).await.unwrap();
+        sqlx::query(
+            "INSERT INTO meeting_reminders (meeting_id, offset_minutes, scheduled_time)
+             VALUES ($1, 1440, $2), ($1, 60, $2 - INTERVAL '1 day')",
+        )
+        .bind(soon)
+        .bind(soon_time)
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+
+        let due = due_meetings(&mut tx, 1440).await.unwrap();
+        // Only reminders sent for the meeting's current time count
+        let (_, _, _, sent) = due.iter().find(|m| m.0 == soon).unwrap();
+        assert_eq!(sent, &vec![1440]);
+        assert!(due.iter().all(|m| m.0 != later));
+    }
+
+    #[test]
+    fn lead_times_read_naturally() {
+        assert_eq!(lead_time_text(1440), "24 hours");
+        assert_eq!(lead_time_text(60), "1 hour");
+        assert_eq!(lead_time_text(10), "10 minutes");
+        assert_eq!(lead_time_text(1), "1 minute");
+    }
+}
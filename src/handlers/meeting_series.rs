@@ -0,0 +1,760 @@
+use crate::handlers::announcements::{create_user_announcement, UserAnnouncement};
+use crate::handlers::timezones::{local_to_utc, timezone_or_default};
+use crate::models::all_models::{
+    Announcement, AnnouncementTarget, AnnouncementType, MeetingSeries,
+};
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc, Weekday};
+use log::{info, warn};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How far ahead occurrences are created, so members can join them
+pub const MEETING_SERIES_HORIZON_DAYS: u64 = 56;
+
+const MAX_RECURRENCE_INTERVAL: u32 = 12;
+const MAX_RECURRENCE_COUNT: u32 = 520;
+
+/// How often the background job creates occurrences that have come within the horizon
+const MEETING_SERIES_JOB_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecurrenceFrequency {
+    Weekly,
+    Monthly,
+}
+
+/// The part of RFC 5545 RRULE that meeting series use:
+/// `FREQ=WEEKLY;INTERVAL=2;BYDAY=TU`, `FREQ=WEEKLY;BYDAY=MO,TH`, `FREQ=MONTHLY;BYDAY=2TU`
+/// or `FREQ=MONTHLY;BYDAY=-1FR`, optionally ended by `UNTIL=YYYYMMDD` or `COUNT=n`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurrenceRule {
+    pub frequency: RecurrenceFrequency,
+    pub interval: u32,
+    /// Weekly: the days it falls on, Monday first; monthly: a single day
+    pub weekdays: Vec<Weekday>,
+    /// Monthly: which of the month's weekdays, 1-4 or -1 for the last
+    pub week_of_month: Option<i32>,
+    pub until: Option<NaiveDate>,
+    pub count: Option<u32>,
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn parse_weekday(code: &str) -> Result<Weekday, String> {
+    match code {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        _ => Err(format!("Unknown day in BYDAY: {}", code)),
+    }
+}
+
+impl FromStr for RecurrenceRule {
+    type Err = String;
+
+    fn from_str(raw: &str) -> Result<Self, Self::Err> {
+        let upper = raw.trim().to_ascii_uppercase();
+        let rule = upper.strip_prefix("RRULE:").unwrap_or(&upper);
+
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut by_day = None;
+        let mut until = None;
+        let mut count = None;
+        for part in rule.split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid rule part: {}", part))?;
+            match key {
+                "FREQ" => {
+                    frequency = Some(match value {
+                        "WEEKLY" => RecurrenceFrequency::Weekly,
+                        "MONTHLY" => RecurrenceFrequency::Monthly,
+                        _ => return Err(format!("Unsupported frequency: {}", value)),
+                    })
+                }
+                "INTERVAL" => {
+                    interval = value
+                        .parse()
+                        .ok()
+                        .filter(|i| (1..=MAX_RECURRENCE_INTERVAL).contains(i))
+                        .ok_or_else(|| {
+                            format!("INTERVAL must be between 1 and {}", MAX_RECURRENCE_INTERVAL)
+                        })?
+                }
+                "BYDAY" => by_day = Some(value),
+                // Date-times are accepted, only the date is used
+                "UNTIL" => {
+                    until = Some(
+                        NaiveDate::parse_from_str(value.get(..8).unwrap_or(value), "%Y%m%d")
+                            .map_err(|_| "UNTIL must be a date like 20251231".to_string())?,
+                    )
+                }
+                "COUNT" => {
+                    count = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|c| (1..=MAX_RECURRENCE_COUNT).contains(c))
+                            .ok_or_else(|| {
+                                format!("COUNT must be between 1 and {}", MAX_RECURRENCE_COUNT)
+                            })?,
+                    )
+                }
+                _ => return Err(format!("Unsupported rule part: {}", key)),
+            }
+        }
+        if until.is_some() && count.is_some() {
+            return Err("A rule can't have both UNTIL and COUNT".to_string());
+        }
+        let frequency = frequency.ok_or_else(|| "FREQ is required".to_string())?;
+        let by_day = by_day.ok_or_else(|| "BYDAY is required".to_string())?;
+
+        let (weekdays, week_of_month) = match frequency {
+            RecurrenceFrequency::Weekly => {
+                let mut days = by_day
+                    .split(',')
+                    .map(parse_weekday)
+                    .collect::<Result<Vec<_>, _>>()?;
+                days.sort_by_key(|day| day.num_days_from_monday());
+                days.dedup();
+                (days, None)
+            }
+            RecurrenceFrequency::Monthly => {
+                if by_day.contains(',') || by_day.len() < 3 {
+                    return Err(
+                        "Monthly rules need a single day with its week, like 2TU or -1FR"
+                            .to_string(),
+                    );
+                }
+                let (ordinal, day) = by_day.split_at(by_day.len() - 2);
+                let week = match ordinal.trim_start_matches('+').parse::<i32>() {
+                    Ok(week) if (1..=4).contains(&week) || week == -1 => week,
+                    _ => {
+                        return Err(
+                            "The week of the month must be 1-4 or -1 for the last".to_string()
+                        )
+                    }
+                };
+                (vec![parse_weekday(day)?], Some(week))
+            }
+        };
+
+        Ok(RecurrenceRule {
+            frequency,
+            interval,
+            weekdays,
+            week_of_month,
+            until,
+            count,
+        })
+    }
+}
+
+impl fmt::Display for RecurrenceRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.frequency {
+            RecurrenceFrequency::Weekly => write!(f, "FREQ=WEEKLY")?,
+            RecurrenceFrequency::Monthly => write!(f, "FREQ=MONTHLY")?,
+        }
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        let days: Vec<String> = self
+            .weekdays
+            .iter()
+            .map(|&day| match self.week_of_month {
+                Some(week) => format!("{}{}", week, weekday_code(day)),
+                None => weekday_code(day).to_string(),
+            })
+            .collect();
+        write!(f, ";BYDAY={}", days.join(","))?;
+        if let Some(until) = self.until {
+            write!(f, ";UNTIL={}", until.format("%Y%m%d"))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        Ok(())
+    }
+}
+
+impl RecurrenceRule {
+    // Every date the rule falls on from `starts_on` up to and including `to`
+    pub fn occurrences(&self, starts_on: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let last = self.until.map_or(to, |until| until.min(to));
+        let limit = self.count.map_or(usize::MAX, |count| count as usize);
+        let mut dates = Vec::new();
+
+        for period in 0u64.. {
+            let step = period * u64::from(self.interval);
+            let period_start = match self.frequency {
+                RecurrenceFrequency::Weekly => starts_on
+                    .checked_sub_days(Days::new(starts_on.weekday().num_days_from_monday().into()))
+                    .and_then(|monday| monday.checked_add_days(Days::new(7 * step))),
+                RecurrenceFrequency::Monthly => u32::try_from(step).ok().and_then(|months| {
+                    starts_on
+                        .with_day(1)
+                        .and_then(|first| first.checked_add_months(Months::new(months)))
+                }),
+            };
+            let period_start = match period_start {
+                Some(start) if start <= last => start,
+                _ => break,
+            };
+
+            for date in self.dates_in_period(period_start) {
+                if date < starts_on {
+                    continue;
+                }
+                if date > last || dates.len() >= limit {
+                    return dates;
+                }
+                dates.push(date);
+            }
+        }
+        dates
+    }
+
+    // Helper function: The dates the rule falls on in the week or month starting at `start`
+    fn dates_in_period(&self, start: NaiveDate) -> Vec<NaiveDate> {
+        match (self.frequency, self.week_of_month) {
+            (RecurrenceFrequency::Monthly, Some(week)) => {
+                let day = self.weekdays[0];
+                let date = if week > 0 {
+                    NaiveDate::from_weekday_of_month_opt(
+                        start.year(),
+                        start.month(),
+                        day,
+                        week as u8,
+                    )
+                } else {
+                    start
+                        .checked_add_months(Months::new(1))
+                        .and_then(|next| next.pred_opt())
+                        .map(|last_day| {
+                            let back = (last_day.weekday().num_days_from_monday() + 7
+                                - day.num_days_from_monday())
+                                % 7;
+                            last_day - Days::new(back.into())
+                        })
+                };
+                date.into_iter().collect()
+            }
+            _ => self
+                .weekdays
+                .iter()
+                .filter_map(|day| {
+                    start.checked_add_days(Days::new(day.num_days_from_monday().into()))
+                })
+                .collect(),
+        }
+    }
+
+    /// The first date on or after `from` the rule falls on, for a series starting on `starts_on`
+    pub fn next_occurrence(&self, starts_on: NaiveDate, from: NaiveDate) -> Option<NaiveDate> {
+        // The next occurrence is at most one interval (plus the rest of a month) away
+        let to = from.checked_add_months(Months::new(self.interval + 1))?;
+        self.occurrences(starts_on, to)
+            .into_iter()
+            .find(|date| *date >= from)
+    }
+
+    // The same rule, stopped after `until`
+    pub fn ending_on(&self, until: NaiveDate) -> Self {
+        RecurrenceRule {
+            until: Some(until),
+            count: None,
+            ..self.clone()
+        }
+    }
+}
+
+// Helper function: The last day occurrences are created for
+fn horizon_end() -> NaiveDate {
+    Utc::now().date_naive() + Days::new(MEETING_SERIES_HORIZON_DAYS)
+}
+
+// The series' occurrence dates from `from` up to the horizon
+pub fn upcoming_occurrences(series: &MeetingSeries, from: NaiveDate) -> Vec<NaiveDate> {
+    match series.rrule.parse::<RecurrenceRule>() {
+        Ok(rule) => rule
+            .occurrences(series.starts_on, horizon_end())
+            .into_iter()
+            .filter(|date| *date >= from)
+            .collect(),
+        Err(e) => {
+            warn!(
+                "Meeting series {} has an invalid rule: {}",
+                series.series_id, e
+            );
+            Vec::new()
+        }
+    }
+}
+
+// When the occurrence on `date` starts, keeping the series' local time across DST changes
+pub fn occurrence_start(series: &MeetingSeries, date: NaiveDate) -> DateTime<Utc> {
+    local_to_utc(
+        timezone_or_default(Some(&series.timezone)),
+        date.and_time(series.start_time),
+    )
+}
+
+// Create the occurrences from `from` up to the horizon that don't exist yet, with the host joined.
+// Runs on the caller's transaction.
+pub async fn materialise_occurrences(
+    conn: &mut PgConnection,
+    series: &MeetingSeries,
+    from: NaiveDate,
+) -> Result<usize, sqlx::Error> {
+    let dates = upcoming_occurrences(series, from);
+    if dates.is_empty() {
+        return Ok(0);
+    }
+    let starts: Vec<DateTime<Utc>> = dates
+        .iter()
+        .map(|date| occurrence_start(series, *date))
+        .collect();
+
+    let insert_query = "
+        INSERT INTO group_meetings (group_chat_id, support_group_id, host_id, title, description,
+                                    scheduled_time, timezone, status, series_id, occurrence_date)
+        SELECT sg.group_chat_id, sg.support_group_id, $2, $3, $4, o.starts_at, $5, 'upcoming', $1, o.day
+        FROM UNNEST($6::date[], $7::timestamptz[]) AS o(day, starts_at)
+        JOIN support_groups sg ON sg.support_group_id = $8 AND sg.status = 'approved'
+        ON CONFLICT (series_id, occurrence_date) DO NOTHING
+        RETURNING meeting_id
+    ";
+    let meeting_ids: Vec<Uuid> = sqlx::query_scalar(insert_query)
+        .bind(series.series_id)
+        .bind(series.host_id)
+        .bind(&series.title)
+        .bind(&series.description)
+        .bind(&series.timezone)
+        .bind(&dates)
+        .bind(&starts)
+        .bind(series.support_group_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    sqlx::query(
+        "INSERT INTO meeting_participants (meeting_id, user_id)
+         SELECT UNNEST($1::uuid[]), $2
+         ON CONFLICT (meeting_id, user_id) DO NOTHING",
+    )
+    .bind(&meeting_ids)
+    .bind(series.host_id)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(meeting_ids.len())
+}
+
+// Bring occurrences on or after `from` that belonged to `previous_series_id` in line with `series`.
+// Those still on the rule move to `series` with their participants, taking its details unless they
+// were edited on their own or cancelled. The others are removed, or cancelled if anyone besides the
+// host joined them; those participants get an announcement, returned for pushing after commit.
+// Missing ones are then created.
+pub async fn reconcile_occurrences(
+    conn: &mut PgConnection,
+    previous_series_id: Uuid,
+    series: &MeetingSeries,
+    from: NaiveDate,
+) -> Result<Vec<Announcement>, sqlx::Error> {
+    let dates: BTreeSet<NaiveDate> = upcoming_occurrences(series, from).into_iter().collect();
+
+    let existing: Vec<(Uuid, NaiveDate, bool)> = sqlx::query_as(
+        "SELECT gm.meeting_id, gm.occurrence_date,
+                EXISTS (SELECT 1 FROM meeting_participants mp
+                        WHERE mp.meeting_id = gm.meeting_id AND mp.user_id <> gm.host_id)
+         FROM group_meetings gm
+         WHERE gm.series_id = $1 AND gm.occurrence_date >= $2
+           AND gm.status IN ('upcoming', 'cancelled')",
+    )
+    .bind(previous_series_id)
+    .bind(from)
+    .fetch_all(&mut *conn)
+    .await?;
+    let (kept, removed): (Vec<_>, Vec<_>) = existing
+        .into_iter()
+        .partition(|(_, date, _)| dates.contains(date));
+    let kept_starts: Vec<DateTime<Utc>> = kept
+        .iter()
+        .map(|(_, date, _)| occurrence_start(series, *date))
+        .collect();
+    let kept: Vec<Uuid> = kept
+        .into_iter()
+        .map(|(meeting_id, _, _)| meeting_id)
+        .collect();
+    let (joined, unjoined): (Vec<_>, Vec<_>) =
+        removed.into_iter().partition(|(_, _, joined)| *joined);
+    let joined: Vec<Uuid> = joined
+        .into_iter()
+        .map(|(meeting_id, _, _)| meeting_id)
+        .collect();
+    let unjoined: Vec<Uuid> = unjoined
+        .into_iter()
+        .map(|(meeting_id, _, _)| meeting_id)
+        .collect();
+
+    sqlx::query("DELETE FROM group_meetings WHERE meeting_id = ANY($1)")
+        .bind(&unjoined)
+        .execute(&mut *conn)
+        .await?;
+    let announcements = cancel_joined_occurrences(conn, &joined).await?;
+    let update_query = "
+        UPDATE group_meetings gm
+        SET series_id = $3,
+            title = CASE WHEN gm.series_exception OR gm.status <> 'upcoming' THEN gm.title ELSE $4 END,
+            description = CASE WHEN gm.series_exception OR gm.status <> 'upcoming' THEN gm.description ELSE $5 END,
+            timezone = CASE WHEN gm.series_exception OR gm.status <> 'upcoming' THEN gm.timezone ELSE $6 END,
+            scheduled_time = CASE WHEN gm.series_exception OR gm.status <> 'upcoming' THEN gm.scheduled_time
+                                  ELSE k.starts_at END
+        FROM UNNEST($1::uuid[], $2::timestamptz[]) AS k(meeting_id, starts_at)
+        WHERE gm.meeting_id = k.meeting_id
+    ";
+    sqlx::query(update_query)
+        .bind(&kept)
+        .bind(&kept_starts)
+        .bind(series.series_id)
+        .bind(&series.title)
+        .bind(&series.description)
+        .bind(&series.timezone)
+        .execute(&mut *conn)
+        .await?;
+
+    materialise_occurrences(conn, series, from).await?;
+    Ok(announcements)
+}
+
+// Helper function: Cancel occurrences people have joined and tell everyone but the host.
+// Occurrences that were already cancelled are left as they are.
+async fn cancel_joined_occurrences(
+    conn: &mut PgConnection,
+    meeting_ids: &[Uuid],
+) -> Result<Vec<Announcement>, sqlx::Error> {
+    if meeting_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let cancelled: Vec<(Uuid, String, DateTime<Utc>)> = sqlx::query_as(
+        "UPDATE group_meetings SET status = 'cancelled'
+         WHERE meeting_id = ANY($1) AND status = 'upcoming'
+         RETURNING meeting_id, title, scheduled_time",
+    )
+    .bind(meeting_ids)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut announcements = Vec::new();
+    for (meeting_id, title, scheduled_time) in cancelled {
+        let participants: Vec<Uuid> = sqlx::query_scalar(
+            "SELECT mp.user_id FROM meeting_participants mp
+             JOIN group_meetings gm ON gm.meeting_id = mp.meeting_id
+             WHERE mp.meeting_id = $1 AND mp.user_id <> gm.host_id",
+        )
+        .bind(meeting_id)
+        .fetch_all(&mut *conn)
+        .await?;
+        let message = format!(
+            "\"{}\" was cancelled because its series no longer meets that day.",
+            title
+        );
+        for participant_id in participants {
+            let announcement = UserAnnouncement {
+                announcement_type: AnnouncementType::MeetingCancelled,
+                target: Some(AnnouncementTarget::GroupMeeting),
+                target_id: Some(meeting_id),
+                recipient_id: participant_id,
+                extra_data: Some(json!({
+                    "meeting_id": meeting_id,
+                    "scheduled_time": scheduled_time,
+                })),
+                message: &message,
+            };
+            announcements.push(create_user_announcement(&mut *conn, announcement).await?);
+        }
+    }
+    Ok(announcements)
+}
+
+// Stop a series and cancel its upcoming occurrences
+pub async fn end_series(conn: &mut PgConnection, series_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE meeting_series SET ended_at = NOW(), updated_at = NOW()
+         WHERE series_id = $1 AND ended_at IS NULL",
+    )
+    .bind(series_id)
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(
+        "UPDATE group_meetings SET status = 'cancelled'
+         WHERE series_id = $1 AND status = 'upcoming'",
+    )
+    .bind(series_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+// Create occurrences that have come within the horizon for every active series
+pub async fn materialise_meeting_series(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let series = sqlx::query_as::<_, MeetingSeries>(
+        "SELECT ms.* FROM meeting_series ms
+         JOIN support_groups sg ON sg.support_group_id = ms.support_group_id
+         WHERE ms.ended_at IS NULL AND sg.status = 'approved'",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let today = Utc::now().date_naive();
+    let mut created = 0;
+    for s in &series {
+        let mut tx = pool.begin().await?;
+        match materialise_occurrences(&mut tx, s, today).await {
+            Ok(count) => {
+                tx.commit().await?;
+                created += count;
+            }
+            Err(e) => warn!(
+                "Failed to create occurrences of meeting series {}: {:?}",
+                s.series_id, e
+            ),
+        }
+    }
+    Ok(created)
+}
+
+// Spawn the periodic meeting series job
+pub fn spawn_meeting_series_job(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(MEETING_SERIES_JOB_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            match materialise_meeting_series(&pool).await {
+                Ok(0) => {}
+                Ok(count) => info!("Created {} recurring meeting occurrences", count),
+                Err(e) => warn!("Failed to create recurring meeting occurrences: {:?}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::all_models::MeetingStatus;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn parses_and_prints_rules() {
+        let rule: RecurrenceRule = "rrule:freq=weekly;interval=2;byday=tu".parse().unwrap();
+        assert_eq!(rule.weekdays, vec![Weekday::Tue]);
+        assert_eq!(rule.interval, 2);
+        assert_eq!(rule.to_string(), "FREQ=WEEKLY;INTERVAL=2;BYDAY=TU");
+
+        let rule: RecurrenceRule = "FREQ=MONTHLY;BYDAY=-1FR;COUNT=6".parse().unwrap();
+        assert_eq!(rule.week_of_month, Some(-1));
+        assert_eq!(rule.to_string(), "FREQ=MONTHLY;BYDAY=-1FR;COUNT=6");
+    }
+
+    #[test]
+    fn rejects_unsupported_rules() {
+        assert!("FREQ=DAILY;BYDAY=MO".parse::<RecurrenceRule>().is_err());
+        assert!("FREQ=WEEKLY".parse::<RecurrenceRule>().is_err());
+        assert!("FREQ=MONTHLY;BYDAY=5MO".parse::<RecurrenceRule>().is_err());
+        assert!("FREQ=WEEKLY;BYDAY=TU;COUNT=3;UNTIL=20250101"
+            .parse::<RecurrenceRule>()
+            .is_err());
+    }
+
+    #[test]
+    fn weekly_and_biweekly() {
+        // 2025-04-01 is a Tuesday
+        let weekly: RecurrenceRule = "FREQ=WEEKLY;BYDAY=TU".parse().unwrap();
+        assert_eq!(
+            weekly.occurrences(date(2025, 4, 2), date(2025, 4, 22)),
+            vec![date(2025, 4, 8), date(2025, 4, 15), date(2025, 4, 22)]
+        );
+
+        let biweekly: RecurrenceRule = "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TH".parse().unwrap();
+        assert_eq!(
+            biweekly.occurrences(date(2025, 4, 1), date(2025, 4, 30)),
+            vec![
+                date(2025, 4, 3),
+                date(2025, 4, 14),
+                date(2025, 4, 17),
+                date(2025, 4, 28)
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_nth_and_last_weekday() {
+        let second_tuesday: RecurrenceRule = "FREQ=MONTHLY;BYDAY=2TU".parse().unwrap();
+        assert_eq!(
+            second_tuesday.occurrences(date(2025, 4, 9), date(2025, 7, 1)),
+            vec![date(2025, 5, 13), date(2025, 6, 10)]
+        );
+
+        let last_friday: RecurrenceRule = "FREQ=MONTHLY;BYDAY=-1FR".parse().unwrap();
+        assert_eq!(
+            last_friday.occurrences(date(2025, 1, 1), date(2025, 3, 31)),
+            vec![date(2025, 1, 31), date(2025, 2, 28), date(2025, 3, 28)]
+        );
+    }
+
+    #[test]
+    fn count_and_until_end_the_series() {
+        let counted: RecurrenceRule = "FREQ=WEEKLY;BYDAY=TU;COUNT=2".parse().unwrap();
+        assert_eq!(
+            counted.occurrences(date(2025, 4, 1), date(2025, 12, 31)),
+            vec![date(2025, 4, 1), date(2025, 4, 8)]
+        );
+
+        let ended = counted.ending_on(date(2025, 4, 14));
+        assert_eq!(ended.count, None);
+        assert_eq!(
+            ended.occurrences(date(2025, 4, 1), date(2025, 12, 31)),
+            vec![date(2025, 4, 1), date(2025, 4, 8)]
+        );
+    }
+
+    #[test]
+    fn next_occurrence_keeps_the_series_phase() {
+        // 2025-04-01 is a Tuesday; a split on 04-09 should carry on from 04-15, not 04-22
+        let biweekly: RecurrenceRule = "FREQ=WEEKLY;INTERVAL=2;BYDAY=TU".parse().unwrap();
+        let next = biweekly.next_occurrence(date(2025, 4, 1), date(2025, 4, 9));
+        assert_eq!(next, Some(date(2025, 4, 15)));
+        assert_eq!(
+            biweekly.occurrences(date(2025, 4, 15), date(2025, 5, 13)),
+            vec![date(2025, 4, 15), date(2025, 4, 29), date(2025, 5, 13)]
+        );
+        assert_eq!(
+            biweekly.next_occurrence(date(2025, 4, 1), date(2025, 4, 15)),
+            Some(date(2025, 4, 15))
+        );
+
+        let second_tuesday: RecurrenceRule = "FREQ=MONTHLY;INTERVAL=3;BYDAY=2TU".parse().unwrap();
+        assert_eq!(
+            second_tuesday.next_occurrence(date(2025, 1, 14), date(2025, 1, 15)),
+            Some(date(2025, 4, 8))
+        );
+
+        let counted: RecurrenceRule = "FREQ=WEEKLY;BYDAY=TU;COUNT=2".parse().unwrap();
+        assert_eq!(
+            counted.next_occurrence(date(2025, 4, 1), date(2025, 4, 9)),
+            None
+        );
+    }
+
+    // Runs against a migrated database, inside a transaction that is rolled back.
+    // Run with: DATABASE_URL=postgres://... cargo test reconcile_cancels -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn reconcile_cancels_joined_occurrences_off_the_rule() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&url).await.unwrap();
+        let mut tx = pool.begin().await.unwrap();
+
+        let mut user_ids = Vec::new();
+        for _ in 0..2 {
+            let user_id: Uuid = sqlx::query_scalar(
+                "INSERT INTO users (username, email, password_hash, avatar_url, dob, user_profile)
+                 VALUES ($1, $1 || '@example.com', '', 'https://example.com/a.png', '1990-01-01',
+                         'Meeting series test user')
+                 RETURNING user_id",
+            )
+            .bind(format!("series-test-{}", Uuid::new_v4()))
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+            user_ids.push(user_id);
+        }
+        let (host_id, member_id) = (user_ids[0], user_ids[1]);
+        let support_group_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO support_groups (title, description, status)
+             VALUES ('Series test', '', 'approved')
+             RETURNING support_group_id",
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .unwrap();
+
+        let today = Utc::now().date_naive();
+        let series = sqlx::query_as::<_, MeetingSeries>(
+            "INSERT INTO meeting_series (support_group_id, host_id, title, rrule, starts_on, start_time)
+             VALUES ($1, $2, 'Series test meeting', 'FREQ=WEEKLY;BYDAY=TU', $3, '18:00')
+             RETURNING *",
+        )
+        .bind(support_group_id)
+        .bind(host_id)
+        .bind(today + Days::new(1))
+        .fetch_one(&mut *tx)
+        .await
+        .unwrap();
+        let created = materialise_occurrences(&mut tx, &series, today)
+            .await
+            .unwrap();
+        assert!(created > 1);
+
+        let joined: Uuid = sqlx::query_scalar(
+            "SELECT meeting_id FROM group_meetings WHERE series_id = $1
+             ORDER BY occurrence_date LIMIT 1",
+        )
+        .bind(series.series_id)
+        .fetch_one(&mut *tx)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO meeting_participants (meeting_id, user_id) VALUES ($1, $2)")
+            .bind(joined)
+            .bind(member_id)
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+
+        // Move the series to Wednesdays: the joined Tuesday is cancelled, the others go
+        let moved = MeetingSeries {
+            rrule: "FREQ=WEEKLY;BYDAY=WE".to_string(),
+            ..series
+        };
+        let announcements = reconcile_occurrences(&mut tx, moved.series_id, &moved, today)
+            .await
+            .unwrap();
+        assert_eq!(announcements.len(), 1);
+        assert_eq!(announcements[0].recipient_id, Some(member_id));
+
+        let tuesdays: Vec<(Uuid, MeetingStatus)> = sqlx::query_as(
+            "SELECT meeting_id, status FROM group_meetings
+             WHERE series_id = $1 AND EXTRACT(ISODOW FROM occurrence_date) = 2",
+        )
+        .bind(moved.series_id)
+        .fetch_all(&mut *tx)
+        .await
+        .unwrap();
+        assert_eq!(tuesdays, vec![(joined, MeetingStatus::Cancelled)]);
+    }
+}
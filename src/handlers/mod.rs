@@ -1,7 +1,29 @@
+pub mod account_deletion;
+pub mod announcements;
 pub mod auth;
+pub mod blocking;
+pub mod calendar;
+pub mod check_ins;
 pub mod db;
 pub mod matching_algo;
+pub mod matching_requests;
+pub mod meeting_lifecycle;
+pub mod meeting_series;
+pub mod notifications;
 pub mod password;
+pub mod privacy;
+pub mod recovery;
+pub mod sponsor_applications;
+pub mod sponsor_candidates;
+pub mod sponsor_capacity;
+pub mod sponsorships;
+pub mod taxonomy;
+pub mod timezones;
 pub mod ws;
 
-pub mod b2_storage;
\ No newline at end of file
+pub mod b2_storage;
+pub mod data_export;
+pub mod geocoding;
+pub mod image_processing;
+pub mod storage_gc;
+pub mod upload_stream;
@@ -0,0 +1,34 @@
+use crate::handlers::ws::send_to_user;
+use crate::models::all_models::{Notification, NotificationKind};
+use serde_json::{json, Value};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+// Store the same notification for each user.
+// Runs on the caller's transaction; push the result once it has committed.
+pub async fn create_notifications(
+    conn: &mut PgConnection,
+    user_ids: &[Uuid],
+    kind: NotificationKind,
+    payload: &Value,
+) -> Result<Vec<Notification>, sqlx::Error> {
+    sqlx::query_as::<_, Notification>(
+        "INSERT INTO notifications (user_id, kind, payload)
+         SELECT user_id, $2, $3 FROM UNNEST($1::uuid[]) AS user_id
+         RETURNING *",
+    )
+    .bind(user_ids)
+    .bind(kind)
+    .bind(payload)
+    .fetch_all(conn)
+    .await
+}
+
+// Send stored notifications to users who are connected; the others see them when they next list theirs
+pub async fn push_notifications(notifications: &[Notification]) {
+    for notification in notifications {
+        let message = json!({ "type": "notification", "payload": notification });
+        // Not being connected isn't an error here
+        let _ = send_to_user(&notification.user_id, message).await;
+    }
+}
@@ -0,0 +1,216 @@
+use crate::models::all_models::{PrivacySettings, UserRole, VisibilityAudience};
+use serde_json::Value;
+use sqlx::{PgConnection, PgPool};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// How the viewer is related to the user whose data is being shown
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ViewerRelation {
+    /// Viewer is the user themselves or an admin
+    pub is_owner: bool,
+    /// Viewer shares at least one support group with the user
+    pub is_peer: bool,
+    /// Viewer is the user's accepted sponsor
+    pub is_sponsor: bool,
+}
+
+impl ViewerRelation {
+    // Check whether a field with the given audience is visible to this viewer
+    pub fn can_see(&self, audience: VisibilityAudience) -> bool {
+        if self.is_owner {
+            return true;
+        }
+        match audience {
+            VisibilityAudience::Everyone => true,
+            VisibilityAudience::Peers => self.is_peer || self.is_sponsor,
+            VisibilityAudience::Sponsor => self.is_sponsor,
+            VisibilityAudience::Nobody => false,
+        }
+    }
+}
+
+// Privacy settings for many users at once, falling back to defaults for users without a row
+pub async fn privacy_settings_for_many(
+    pool: &PgPool,
+    user_ids: &[Uuid],
+) -> Result<HashMap<Uuid, PrivacySettings>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, PrivacySettings>(
+        "SELECT * FROM user_privacy_settings WHERE user_id = ANY($1)",
+    )
+    .bind(user_ids)
+    .fetch_all(pool)
+    .await?;
+
+    let mut settings: HashMap<Uuid, PrivacySettings> =
+        rows.into_iter().map(|s| (s.user_id, s)).collect();
+    for user_id in user_ids {
+        settings
+            .entry(*user_id)
+            .or_insert_with(|| PrivacySettings::default_for(*user_id));
+    }
+    Ok(settings)
+}
+
+// Privacy settings for a single user
+pub async fn privacy_settings_for(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<PrivacySettings, sqlx::Error> {
+    let mut settings = privacy_settings_for_many(pool, &[user_id]).await?;
+    Ok(settings
+        .remove(&user_id)
+        .unwrap_or_else(|| PrivacySettings::default_for(user_id)))
+}
+
+// Work out how the viewer relates to each of the given users
+pub async fn viewer_relations(
+    pool: &PgPool,
+    viewer_id: Uuid,
+    viewer_role: UserRole,
+    user_ids: &[Uuid],
+) -> Result<HashMap<Uuid, ViewerRelation>, sqlx::Error> {
+    let peers_query = "
+        SELECT DISTINCT theirs.user_id
+        FROM support_group_members mine
+        JOIN support_group_members theirs ON mine.support_group_id = theirs.support_group_id
+        WHERE mine.user_id = $1 AND theirs.user_id = ANY($2)
+    ";
+    let peers: HashSet<Uuid> = sqlx::query_scalar::<_, Uuid>(peers_query)
+        .bind(viewer_id)
+        .bind(user_ids)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    let sponsored_query = "
+        SELECT DISTINCT member_id FROM sponsorships
+        WHERE sponsor_id = $1 AND member_id = ANY($2) AND status <> 'ended'
+    ";
+    let sponsored: HashSet<Uuid> = sqlx::query_scalar::<_, Uuid>(sponsored_query)
+        .bind(viewer_id)
+        .bind(user_ids)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    Ok(user_ids
+        .iter()
+        .map(|user_id| {
+            let relation = ViewerRelation {
+                is_owner: *user_id == viewer_id || viewer_role == UserRole::Admin,
+                is_peer: peers.contains(user_id),
+                is_sponsor: sponsored.contains(user_id),
+            };
+            (*user_id, relation)
+        })
+        .collect())
+}
+
+// Relation between the viewer and a single user
+pub async fn viewer_relation(
+    pool: &PgPool,
+    viewer_id: Uuid,
+    viewer_role: UserRole,
+    user_id: Uuid,
+) -> Result<ViewerRelation, sqlx::Error> {
+    let mut relations = viewer_relations(pool, viewer_id, viewer_role, &[user_id]).await?;
+    Ok(relations.remove(&user_id).unwrap_or_default())
+}
+
+/// Privacy settings and viewer relations for a batch of users, for filtering listings
+pub struct AudienceCheck {
+    settings: HashMap<Uuid, PrivacySettings>,
+    relations: HashMap<Uuid, ViewerRelation>,
+}
+
+impl AudienceCheck {
+    pub async fn load(
+        pool: &PgPool,
+        viewer_id: Uuid,
+        viewer_role: UserRole,
+        user_ids: &[Uuid],
+    ) -> Result<Self, sqlx::Error> {
+        Ok(AudienceCheck {
+            settings: privacy_settings_for_many(pool, user_ids).await?,
+            relations: viewer_relations(pool, viewer_id, viewer_role, user_ids).await?,
+        })
+    }
+
+    // Check one field of one user; users that weren't loaded are treated as hidden
+    pub fn can_see(
+        &self,
+        user_id: Uuid,
+        field: fn(&PrivacySettings) -> VisibilityAudience,
+    ) -> bool {
+        match (self.settings.get(&user_id), self.relations.get(&user_id)) {
+            (Some(settings), Some(relation)) => relation.can_see(field(settings)),
+            _ => false,
+        }
+    }
+}
+
+// Placeholder avatar shown when the real one is hidden
+pub fn placeholder_avatar_url(username: &str) -> String {
+    format!(
+        "https://ui-avatars.com/api/?name={}&background=random&size=256",
+        username
+    )
+}
+
+// Reduce a stored location to just its city, never exposing coordinates
+pub fn location_city(location: Option<&Value>) -> Option<String> {
+    location
+        .and_then(|loc| loc.get("city"))
+        .and_then(|city| city.as_str())
+        .map(|city| city.to_string())
+}
+
+// Replace all settings with the preset matching the old single privacy flag
+pub async fn apply_privacy_preset(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    private: bool,
+) -> Result<(), sqlx::Error> {
+    let defaults = PrivacySettings::default_for(user_id);
+    let field = |audience: VisibilityAudience| {
+        if private {
+            VisibilityAudience::Nobody
+        } else {
+            audience
+        }
+    };
+
+    let query = "
+        INSERT INTO user_privacy_settings
+            (user_id, bio, interests, experience, languages, location_city, avatar, support_groups,
+             recovery, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW())
+        ON CONFLICT (user_id) DO UPDATE SET
+            bio = EXCLUDED.bio,
+            interests = EXCLUDED.interests,
+            experience = EXCLUDED.experience,
+            languages = EXCLUDED.languages,
+            location_city = EXCLUDED.location_city,
+            avatar = EXCLUDED.avatar,
+            support_groups = EXCLUDED.support_groups,
+            recovery = EXCLUDED.recovery,
+            updated_at = NOW()
+    ";
+    sqlx::query(query)
+        .bind(user_id)
+        .bind(field(defaults.bio))
+        .bind(field(defaults.interests))
+        .bind(field(defaults.experience))
+        .bind(field(defaults.languages))
+        .bind(field(defaults.location_city))
+        // Private profiles always kept their avatar visible
+        .bind(defaults.avatar)
+        .bind(field(defaults.support_groups))
+        .bind(field(defaults.recovery))
+        .execute(conn)
+        .await?;
+    Ok(())
+}
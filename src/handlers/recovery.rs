@@ -0,0 +1,257 @@
+use crate::handlers::notifications::{create_notifications, push_notifications};
+use crate::handlers::privacy::{privacy_settings_for, ViewerRelation};
+use crate::models::all_models::{NotificationKind, RecoveryJourney, RecoveryMilestone};
+use crate::routes::posts::insert_post;
+use chrono::{Duration as ChronoDuration, NaiveDateTime, Utc};
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Day counts celebrated in the first year; after that every full year is a milestone
+pub const RECOVERY_MILESTONE_DAYS: &[i32] = &[1, 7, 30, 60, 90, 180, 270, 365];
+
+const DAYS_PER_YEAR: i32 = 365;
+
+/// How often the background job looks for newly reached milestones
+const RECOVERY_MILESTONE_JOB_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+// Whole days of clean time; a start in the future counts as zero
+pub fn days_since(started_at: NaiveDateTime, now: NaiveDateTime) -> i32 {
+    (now - started_at).num_days().clamp(0, i64::from(i32::MAX)) as i32
+}
+
+// Every milestone reached after the given number of days, smallest first
+pub fn reached_milestones(days: i32) -> Vec<i32> {
+    let mut reached: Vec<i32> = RECOVERY_MILESTONE_DAYS
+        .iter()
+        .copied()
+        .filter(|&milestone| milestone <= days)
+        .collect();
+    reached.extend((2..=days / DAYS_PER_YEAR).map(|years| years * DAYS_PER_YEAR));
+    reached
+}
+
+// The first milestone still ahead
+pub fn next_milestone(days: i32) -> i32 {
+    RECOVERY_MILESTONE_DAYS
+        .iter()
+        .copied()
+        .find(|&milestone| milestone > days)
+        .unwrap_or((days / DAYS_PER_YEAR + 1) * DAYS_PER_YEAR)
+}
+
+// How a milestone is shown: "24 hours", "30 days", "1 year", "2 years"
+pub fn milestone_label(days: i32) -> String {
+    match days {
+        1 => "24 hours".to_string(),
+        DAYS_PER_YEAR => "1 year".to_string(),
+        d if d % DAYS_PER_YEAR == 0 => format!("{} years", d / DAYS_PER_YEAR),
+        d => format!("{} days", d),
+    }
+}
+
+/// A journey with its day count worked out
+#[derive(Debug, Serialize)]
+pub struct RecoveryProgress {
+    #[serde(flatten)]
+    pub journey: RecoveryJourney,
+    pub days: i32,
+    pub current_milestone: Option<String>,
+    pub next_milestone: String,
+    pub next_milestone_at: NaiveDateTime,
+}
+
+impl RecoveryProgress {
+    pub fn new(journey: RecoveryJourney, now: NaiveDateTime) -> Self {
+        let days = days_since(journey.started_at, now);
+        let next = next_milestone(days);
+        RecoveryProgress {
+            days,
+            current_milestone: reached_milestones(days).last().map(|&d| milestone_label(d)),
+            next_milestone: milestone_label(next),
+            next_milestone_at: journey.started_at + ChronoDuration::days(i64::from(next)),
+            journey,
+        }
+    }
+}
+
+// Record every milestone the journey has reached since its current start and return the new ones.
+// Runs on the caller's transaction.
+pub async fn record_reached_milestones(
+    conn: &mut PgConnection,
+    journey: &RecoveryJourney,
+) -> Result<Vec<RecoveryMilestone>, sqlx::Error> {
+    let days = reached_milestones(days_since(journey.started_at, Utc::now().naive_utc()));
+    if days.is_empty() {
+        return Ok(Vec::new());
+    }
+    sqlx::query_as::<_, RecoveryMilestone>(
+        "INSERT INTO recovery_milestones (journey_id, started_at, days)
+         SELECT $1, $2, days FROM UNNEST($3::int[]) AS days
+         ON CONFLICT DO NOTHING
+         RETURNING *",
+    )
+    .bind(journey.journey_id)
+    .bind(journey.started_at)
+    .bind(days)
+    .fetch_all(conn)
+    .await
+}
+
+// Helper function: Display name of an experience code, falling back to the code itself
+async fn experience_label(pool: &PgPool, code: &str) -> Result<String, sqlx::Error> {
+    let label: Option<String> = sqlx::query_scalar(
+        "SELECT label FROM taxonomy_terms WHERE kind = 'experience' AND code = $1",
+    )
+    .bind(code)
+    .fetch_optional(pool)
+    .await?;
+    Ok(label.unwrap_or_else(|| code.to_string()))
+}
+
+// Celebrate newly reached milestones of one journey: tell the user and, if they chose to and
+// their privacy settings let support group peers see it, post in each of their support groups
+async fn celebrate_journey(pool: &PgPool, journey: &RecoveryJourney) -> Result<usize, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let new_milestones = record_reached_milestones(&mut tx, journey).await?;
+    // Only the furthest one is worth celebrating if several were reached at once
+    let milestone = match new_milestones.iter().max_by_key(|m| m.days) {
+        Some(milestone) => milestone,
+        None => return Ok(0),
+    };
+    let label = milestone_label(milestone.days);
+
+    let payload = json!({
+        "journey_id": journey.journey_id,
+        "experience": journey.experience,
+        "days": milestone.days,
+        "label": label,
+    });
+    let notifications = create_notifications(
+        &mut tx,
+        &[journey.user_id],
+        NotificationKind::RecoveryMilestone,
+        &payload,
+    )
+    .await?;
+
+    if journey.share_with_groups {
+        let settings = privacy_settings_for(pool, journey.user_id).await?;
+        let peer = ViewerRelation {
+            is_peer: true,
+            ..Default::default()
+        };
+        if peer.can_see(settings.recovery) {
+            let username: String =
+                sqlx::query_scalar("SELECT username FROM users WHERE user_id = $1")
+                    .bind(journey.user_id)
+                    .fetch_one(&mut *tx)
+                    .await?;
+            // The area is only named if the user shows their experience to peers
+            let content = if peer.can_see(settings.experience) {
+                let area = experience_label(pool, &journey.experience).await?;
+                format!(
+                    "{} has reached {} in recovery from {}!",
+                    username, label, area
+                )
+            } else {
+                format!("{} has reached {} in recovery!", username, label)
+            };
+            let group_ids: Vec<Uuid> = sqlx::query_scalar(
+                "SELECT sgm.support_group_id FROM support_group_members sgm
+                 JOIN support_groups sg ON sg.support_group_id = sgm.support_group_id
+                 WHERE sgm.user_id = $1 AND sg.status = 'approved'",
+            )
+            .bind(journey.user_id)
+            .fetch_all(&mut *tx)
+            .await?;
+            for group_id in group_ids {
+                insert_post(
+                    &mut tx,
+                    journey.user_id,
+                    &content,
+                    Some(vec!["milestone".to_string()]),
+                    Some(group_id),
+                )
+                .await?;
+            }
+        }
+    }
+
+    tx.commit().await?;
+    push_notifications(&notifications).await;
+    Ok(1)
+}
+
+// Celebrate milestones reached since the last run
+pub async fn celebrate_recovery_milestones(pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let journeys = sqlx::query_as::<_, RecoveryJourney>(
+        "SELECT j.* FROM recovery_journeys j
+         JOIN users u ON u.user_id = j.user_id
+         WHERE u.deleted_at IS NULL AND j.started_at <= NOW() - INTERVAL '1 day'",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut celebrated = 0;
+    for journey in &journeys {
+        match celebrate_journey(pool, journey).await {
+            Ok(count) => celebrated += count,
+            Err(e) => warn!(
+                "Failed to celebrate milestones of journey {}: {:?}",
+                journey.journey_id, e
+            ),
+        }
+    }
+    Ok(celebrated)
+}
+
+// Spawn the periodic recovery milestone job
+pub fn spawn_recovery_milestone_job(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RECOVERY_MILESTONE_JOB_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            match celebrate_recovery_milestones(&pool).await {
+                Ok(0) => {}
+                Ok(count) => info!("Celebrated {} recovery milestones", count),
+                Err(e) => warn!("Failed to check recovery milestones: {:?}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn milestones_in_the_first_year() {
+        assert!(reached_milestones(0).is_empty());
+        assert_eq!(reached_milestones(1), vec![1]);
+        assert_eq!(reached_milestones(45), vec![1, 7, 30]);
+        assert_eq!(next_milestone(45), 60);
+    }
+
+    #[test]
+    fn every_year_is_a_milestone_after_the_first() {
+        assert_eq!(
+            reached_milestones(800),
+            vec![1, 7, 30, 60, 90, 180, 270, 365, 730]
+        );
+        assert_eq!(next_milestone(365), 730);
+        assert_eq!(next_milestone(800), 1095);
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(milestone_label(1), "24 hours");
+        assert_eq!(milestone_label(90), "90 days");
+        assert_eq!(milestone_label(365), "1 year");
+        assert_eq!(milestone_label(1095), "3 years");
+    }
+}
@@ -0,0 +1,115 @@
+use crate::handlers::notifications::create_notifications;
+use crate::models::all_models::{
+    ApplicationStatus, Attachment, AttachmentOwnerType, Notification, NotificationKind,
+    SponsorApplication, SponsorApplicationReview, SponsorApplicationRevision,
+};
+use chrono::{Duration, NaiveDateTime};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+/// Days after a rejection or revocation before the applicant can apply again
+pub const SPONSOR_REAPPLY_COOLDOWN_DAYS: i64 = 90;
+
+// When a rejected or revoked applicant may apply again; None if the application is still open or approved
+pub fn reapply_available_at(application: &SponsorApplication) -> Option<NaiveDateTime> {
+    if !matches!(
+        application.status,
+        ApplicationStatus::Rejected | ApplicationStatus::Revoked
+    ) {
+        return None;
+    }
+    let rejected_at = application.reviewed_at.unwrap_or(application.submitted_at);
+    Some(rejected_at + Duration::days(SPONSOR_REAPPLY_COOLDOWN_DAYS))
+}
+
+// Keep a copy of the form as it was just submitted
+pub async fn record_revision(
+    conn: &mut PgConnection,
+    application: &SponsorApplication,
+) -> Result<SponsorApplicationRevision, sqlx::Error> {
+    sqlx::query_as::<_, SponsorApplicationRevision>(
+        "INSERT INTO sponsor_application_revisions (
+            application_id, revision, application_info, recovery_since, experience_areas,
+            availability, referees, code_of_conduct_agreed_at, submitted_at
+         )
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+         RETURNING *",
+    )
+    .bind(application.application_id)
+    .bind(application.revision)
+    .bind(&application.application_info)
+    .bind(application.recovery_since)
+    .bind(&application.experience_areas)
+    .bind(&application.availability)
+    .bind(&application.referees)
+    .bind(application.code_of_conduct_agreed_at)
+    .bind(application.submitted_at)
+    .fetch_one(conn)
+    .await
+}
+
+/// An application together with its revisions, reviews and supporting documents
+#[derive(Debug, Serialize)]
+pub struct SponsorApplicationDetails {
+    pub application: SponsorApplication,
+    pub revisions: Vec<SponsorApplicationRevision>,
+    pub reviews: Vec<SponsorApplicationReview>,
+    pub attachments: Vec<Attachment>,
+    pub can_reapply_at: Option<NaiveDateTime>,
+}
+
+// Load the full history of an application, oldest first
+pub async fn application_details(
+    pool: &PgPool,
+    application: SponsorApplication,
+) -> Result<SponsorApplicationDetails, sqlx::Error> {
+    let revisions = sqlx::query_as::<_, SponsorApplicationRevision>(
+        "SELECT * FROM sponsor_application_revisions WHERE application_id = $1 ORDER BY revision",
+    )
+    .bind(application.application_id)
+    .fetch_all(pool)
+    .await?;
+    let reviews = sqlx::query_as::<_, SponsorApplicationReview>(
+        "SELECT * FROM sponsor_application_reviews WHERE application_id = $1 ORDER BY created_at",
+    )
+    .bind(application.application_id)
+    .fetch_all(pool)
+    .await?;
+    let attachments = sqlx::query_as::<_, Attachment>(
+        "SELECT * FROM attachments WHERE owner_type = $1 AND owner_id = $2 ORDER BY created_at",
+    )
+    .bind(AttachmentOwnerType::SponsorApplication)
+    .bind(application.application_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(SponsorApplicationDetails {
+        can_reapply_at: reapply_available_at(&application),
+        application,
+        revisions,
+        reviews,
+        attachments,
+    })
+}
+
+// Let the applicant know an admin has acted on their application
+pub async fn notify_applicant(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    review: &SponsorApplicationReview,
+) -> Result<Vec<Notification>, sqlx::Error> {
+    let payload = json!({
+        "application_id": review.application_id,
+        "decision": review.decision,
+        "comments": review.comments,
+    });
+    create_notifications(
+        conn,
+        &[user_id],
+        NotificationKind::SponsorApplication,
+        &payload,
+    )
+    .await
+}
@@ -0,0 +1,592 @@
+use crate::handlers::matching_algo::{apply_sponsor_load, evaluate_match, MatchResult};
+use crate::handlers::sponsor_capacity::SponsorCapacity;
+use crate::models::all_models::{MatchUser, MatchingConfig};
+use chrono::NaiveDateTime;
+use log::warn;
+use serde_json::Value;
+use sqlx::types::Json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+// A little under the ~111.2km per degree the haversine distance uses,
+// so the bounding box always contains the whole search radius
+const KM_PER_DEGREE: f64 = 111.0;
+
+/// Latitude/longitude box around a point. Longitude bounds are None when the box spans
+/// every longitude (near the poles or for huge radii); min > max means it crosses the antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_latitude: f64,
+    pub max_latitude: f64,
+    pub longitude: Option<(f64, f64)>,
+}
+
+// Smallest box containing every point within radius_km of the given point
+pub fn bounding_box(latitude: f64, longitude: f64, radius_km: f64) -> BoundingBox {
+    let latitude_delta = radius_km / KM_PER_DEGREE;
+    let min_latitude = (latitude - latitude_delta).max(-90.0);
+    let max_latitude = (latitude + latitude_delta).min(90.0);
+
+    // Degrees of longitude get shorter towards the poles, so size the box at its poleward edge
+    let poleward = min_latitude.abs().max(max_latitude.abs());
+    let km_per_degree_longitude = KM_PER_DEGREE * poleward.to_radians().cos();
+    let longitude_delta = radius_km / km_per_degree_longitude;
+    if poleward >= 89.9 || !longitude_delta.is_finite() || longitude_delta >= 180.0 {
+        return BoundingBox {
+            min_latitude,
+            max_latitude,
+            longitude: None,
+        };
+    }
+
+    let wrap = |value: f64| {
+        if value < -180.0 {
+            value + 360.0
+        } else if value > 180.0 {
+            value - 360.0
+        } else {
+            value
+        }
+    };
+    BoundingBox {
+        min_latitude,
+        max_latitude,
+        longitude: Some((
+            wrap(longitude - longitude_delta),
+            wrap(longitude + longitude_delta),
+        )),
+    }
+}
+
+/// Match profile along with the version it was read at, for caching scores against
+#[derive(Debug, sqlx::FromRow)]
+struct VersionedMatchUser {
+    #[sqlx(flatten)]
+    user: MatchUser,
+    match_profile_updated_at: NaiveDateTime,
+}
+
+// Match profiles for the given users, in no particular order
+async fn load_versioned_match_users(
+    pool: &PgPool,
+    user_ids: &[Uuid],
+) -> Result<Vec<VersionedMatchUser>, sqlx::Error> {
+    let query = "
+        SELECT
+            user_id as id,
+            dob,
+            location::text as location,
+            interests,
+            experience,
+            available_days,
+            languages,
+            match_profile_updated_at
+        FROM users WHERE user_id = ANY($1)";
+    sqlx::query_as::<_, VersionedMatchUser>(query)
+        .bind(user_ids)
+        .fetch_all(pool)
+        .await
+}
+
+// Match profiles for the given users, keyed by user
+pub async fn load_match_users(
+    pool: &PgPool,
+    user_ids: &[Uuid],
+) -> Result<HashMap<Uuid, MatchUser>, sqlx::Error> {
+    Ok(load_versioned_match_users(pool, user_ids)
+        .await?
+        .into_iter()
+        .map(|versioned| (versioned.user.id, versioned.user))
+        .collect())
+}
+
+/// A sponsor that passed the SQL pre-filter, with any still-valid cached result
+#[derive(Debug, sqlx::FromRow)]
+struct CandidateRow {
+    sponsor_id: Uuid,
+    max_active_mentees: i32,
+    accepting_new_members: bool,
+    active_mentees: i64,
+    cached_result: Option<Json<MatchResult>>,
+}
+
+/// A recommendable sponsor with their final score, load penalty included
+#[derive(Debug, Clone)]
+pub struct RankedSponsor {
+    pub sponsor_id: Uuid,
+    pub result: MatchResult,
+}
+
+// Apply capacity and load to each result, drop excluded sponsors and sort best first.
+// Ties are broken by id so pages stay stable between requests.
+pub fn rank_results(
+    results: Vec<(SponsorCapacity, MatchResult)>,
+    config: &MatchingConfig,
+) -> Vec<RankedSponsor> {
+    let mut ranked: Vec<RankedSponsor> = results
+        .into_iter()
+        .filter_map(|(capacity, mut result)| {
+            apply_sponsor_load(&mut result, &capacity, config);
+            if result.excluded_by.is_some() {
+                return None;
+            }
+            Some(RankedSponsor {
+                sponsor_id: capacity.sponsor_id,
+                result,
+            })
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.result
+            .score
+            .total_cmp(&a.result.score)
+            .then(a.sponsor_id.cmp(&b.sponsor_id))
+    });
+    ranked
+}
+
+/// Every sponsor the member could be recommended, best match first.
+/// Sponsors are narrowed down in SQL (not banned or deleted, not blocked, accepting and below
+/// capacity, not in a rematch cooldown, sharing a language and inside the distance limit when
+/// those filters are on), and
+/// only those without a valid cached score are loaded and scored.
+pub async fn ranked_sponsors_for(
+    pool: &PgPool,
+    member_id: Uuid,
+    config: &MatchingConfig,
+    blocked: &[Uuid],
+) -> Result<Vec<RankedSponsor>, sqlx::Error> {
+    let member = load_versioned_match_users(pool, &[member_id])
+        .await?
+        .pop()
+        .ok_or(sqlx::Error::RowNotFound)?;
+
+    let mut conditions = vec![
+        "u.role = 'sponsor'".to_string(),
+        "u.user_id <> $1".to_string(),
+        "NOT (u.user_id = ANY($4))".to_string(),
+        "u.deleted_at IS NULL".to_string(),
+        "(u.banned_until IS NULL OR u.banned_until <= NOW())".to_string(),
+        "COALESCE(ss.accepting_new_members, TRUE)".to_string(),
+        "COALESCE(active.mentees, 0) < COALESCE(ss.max_active_mentees, 3)".to_string(),
+        // Not the member's current sponsor, nor one they parted with during the cooldown
+        "NOT EXISTS (
+            SELECT 1 FROM sponsorships sp
+            WHERE sp.member_id = $1 AND sp.sponsor_id = u.user_id
+              AND (sp.status <> 'ended' OR sp.ended_at > NOW() - make_interval(days => $5))
+        )"
+        .to_string(),
+    ];
+    let mut next_param = 6;
+
+    let languages = config
+        .require_shared_language
+        .then(|| member.user.languages.clone().unwrap_or_default());
+    if languages.is_some() {
+        conditions.push(format!("u.languages && ${}", next_param));
+        next_param += 1;
+    }
+
+    let bounds = match (config.max_distance_km, member.user.parse_location()) {
+        (Some(max_distance), Some(location)) => Some(bounding_box(
+            location.latitude,
+            location.longitude,
+            max_distance as f64,
+        )),
+        // Without a location the member can't be within any distance limit
+        (Some(_), None) => return Ok(Vec::new()),
+        (None, _) => None,
+    };
+    if let Some(bounds) = &bounds {
+        conditions.push(format!(
+            "u.location_latitude BETWEEN ${} AND ${}",
+            next_param,
+            next_param + 1
+        ));
+        next_param += 2;
+        if let Some((min_longitude, max_longitude)) = bounds.longitude {
+            let join = if min_longitude <= max_longitude {
+                "AND"
+            } else {
+                "OR"
+            };
+            conditions.push(format!(
+                "(u.location_longitude >= ${} {} u.location_longitude <= ${})",
+                next_param,
+                join,
+                next_param + 1
+            ));
+        }
+    }
+
+    let candidates_query = format!(
+        "
+        SELECT
+            u.user_id AS sponsor_id,
+            COALESCE(ss.max_active_mentees, 3) AS max_active_mentees,
+            COALESCE(ss.accepting_new_members, TRUE) AS accepting_new_members,
+            COALESCE(active.mentees, 0) AS active_mentees,
+            c.result AS cached_result
+        FROM users u
+        LEFT JOIN sponsor_settings ss ON ss.user_id = u.user_id
+        LEFT JOIN (
+            SELECT sponsor_id, COUNT(*) AS mentees
+            FROM sponsorships
+            WHERE status <> 'ended'
+            GROUP BY sponsor_id
+        ) active ON active.sponsor_id = u.user_id
+        LEFT JOIN sponsor_match_scores c
+            ON c.member_id = $1
+           AND c.sponsor_id = u.user_id
+           AND c.member_version = $2
+           AND c.sponsor_version = u.match_profile_updated_at
+           AND c.config_version = $3
+        WHERE {}",
+        conditions.join("\n          AND ")
+    );
+
+    let mut query = sqlx::query_as::<_, CandidateRow>(&candidates_query)
+        .bind(member_id)
+        .bind(member.match_profile_updated_at)
+        .bind(config.updated_at)
+        .bind(blocked)
+        .bind(config.rematch_cooldown_days);
+    if let Some(languages) = &languages {
+        query = query.bind(languages);
+    }
+    if let Some(bounds) = &bounds {
+        query = query.bind(bounds.min_latitude).bind(bounds.max_latitude);
+        if let Some((min_longitude, max_longitude)) = bounds.longitude {
+            query = query.bind(min_longitude).bind(max_longitude);
+        }
+    }
+    let candidates = query.fetch_all(pool).await?;
+
+    // Score the sponsors without a valid cached result, and cache them for next time
+    let uncached_ids: Vec<Uuid> = candidates
+        .iter()
+        .filter(|c| c.cached_result.is_none())
+        .map(|c| c.sponsor_id)
+        .collect();
+    let mut fresh: HashMap<Uuid, MatchResult> = HashMap::new();
+    if !uncached_ids.is_empty() {
+        let sponsors = load_versioned_match_users(pool, &uncached_ids).await?;
+        let mut entries = Vec::with_capacity(sponsors.len());
+        for sponsor in sponsors {
+            let result = evaluate_match(&member.user, &sponsor.user, config);
+            match serde_json::to_value(&result) {
+                Ok(value) => entries.push(CacheEntry {
+                    sponsor_id: sponsor.user.id,
+                    sponsor_version: sponsor.match_profile_updated_at,
+                    score: result.score,
+                    result: value,
+                }),
+                Err(e) => warn!("Failed to serialise match result: {}", e),
+            }
+            fresh.insert(sponsor.user.id, result);
+        }
+
+        // A failed write only costs a recompute next time
+        if let Err(e) = cache_results(pool, &member, config.updated_at, entries).await {
+            warn!("Failed to cache match scores for {}: {}", member_id, e);
+        }
+    }
+
+    let results: Vec<(SponsorCapacity, MatchResult)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let result = match candidate.cached_result {
+                Some(Json(result)) => result,
+                None => fresh.remove(&candidate.sponsor_id)?,
+            };
+            let capacity = SponsorCapacity {
+                sponsor_id: candidate.sponsor_id,
+                max_active_mentees: candidate.max_active_mentees,
+                accepting_new_members: candidate.accepting_new_members,
+                active_mentees: candidate.active_mentees,
+            };
+            Some((capacity, result))
+        })
+        .collect();
+
+    Ok(rank_results(results, config))
+}
+
+struct CacheEntry {
+    sponsor_id: Uuid,
+    sponsor_version: NaiveDateTime,
+    score: f32,
+    result: Value,
+}
+
+// Store freshly computed results against the profile and config versions they were computed from
+async fn cache_results(
+    pool: &PgPool,
+    member: &VersionedMatchUser,
+    config_version: NaiveDateTime,
+    entries: Vec<CacheEntry>,
+) -> Result<(), sqlx::Error> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let mut sponsor_ids = Vec::with_capacity(entries.len());
+    let mut sponsor_versions = Vec::with_capacity(entries.len());
+    let mut scores = Vec::with_capacity(entries.len());
+    let mut results = Vec::with_capacity(entries.len());
+    for entry in entries {
+        sponsor_ids.push(entry.sponsor_id);
+        sponsor_versions.push(entry.sponsor_version);
+        scores.push(entry.score);
+        results.push(entry.result);
+    }
+
+    let upsert_query = "
+        INSERT INTO sponsor_match_scores
+            (member_id, sponsor_id, member_version, sponsor_version, config_version, score, result, computed_at)
+        SELECT $1, s.sponsor_id, $2, s.sponsor_version, $3, s.score, s.result, NOW()
+        FROM UNNEST($4::uuid[], $5::timestamp[], $6::real[], $7::jsonb[])
+            AS s(sponsor_id, sponsor_version, score, result)
+        ON CONFLICT (member_id, sponsor_id) DO UPDATE SET
+            member_version = EXCLUDED.member_version,
+            sponsor_version = EXCLUDED.sponsor_version,
+            config_version = EXCLUDED.config_version,
+            score = EXCLUDED.score,
+            result = EXCLUDED.result,
+            computed_at = EXCLUDED.computed_at";
+    sqlx::query(upsert_query)
+        .bind(member.user.id)
+        .bind(member.match_profile_updated_at)
+        .bind(config_version)
+        .bind(sponsor_ids)
+        .bind(sponsor_versions)
+        .bind(scores)
+        .bind(results)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::matching_algo::MatchExclusion;
+    use chrono::NaiveDate;
+    use geoutils::Location;
+    use std::time::Instant;
+
+    fn distance_km(from: (f64, f64), to: (f64, f64)) -> f64 {
+        Location::new(from.0, from.1)
+            .haversine_distance_to(&Location::new(to.0, to.1))
+            .meters()
+            / 1000.0
+    }
+
+    fn contains(bounds: &BoundingBox, point: (f64, f64)) -> bool {
+        let latitude_ok = (bounds.min_latitude..=bounds.max_latitude).contains(&point.0);
+        let longitude_ok = match bounds.longitude {
+            None => true,
+            Some((min, max)) if min <= max => (min..=max).contains(&point.1),
+            Some((min, max)) => point.1 >= min || point.1 <= max,
+        };
+        latitude_ok && longitude_ok
+    }
+
+    #[test]
+    fn bounding_box_contains_every_point_within_the_radius() {
+        for &(centre, radius) in &[
+            ((51.5, -0.1), 50.0),
+            ((-33.9, 151.2), 200.0),
+            ((64.1, -21.9), 500.0),
+            ((0.0, 179.9), 100.0),
+        ] {
+            let bounds = bounding_box(centre.0, centre.1, radius);
+            // Sweep a grid well past the radius in every direction
+            for i in -100..=100 {
+                for j in -100..=100 {
+                    let latitude = centre.0 + i as f64 * 0.1;
+                    let mut longitude = centre.1 + j as f64 * 0.25;
+                    if longitude > 180.0 {
+                        longitude -= 360.0;
+                    }
+                    let point = (latitude, longitude);
+                    if distance_km(centre, point) <= radius {
+                        assert!(
+                            contains(&bounds, point),
+                            "{:?} is within {} km of {:?} but outside {:?}",
+                            point,
+                            radius,
+                            centre,
+                            bounds
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bounding_box_crossing_antimeridian_wraps() {
+        let bounds = bounding_box(0.0, 179.9, 100.0);
+        let (min, max) = bounds.longitude.unwrap();
+        assert!(min > max);
+        assert!(contains(&bounds, (0.0, -179.8)));
+        assert!(!contains(&bounds, (0.0, 0.0)));
+
+        let polar = bounding_box(89.5, 10.0, 100.0);
+        assert_eq!(polar.longitude, None);
+        assert_eq!(polar.max_latitude, 90.0);
+    }
+
+    // Deterministic pseudo-random numbers, so runs are comparable
+    struct Lcg(u64);
+
+    impl Lcg {
+        fn next(&mut self) -> f64 {
+            self.0 = self
+                .0
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (self.0 >> 11) as f64 / (1u64 << 53) as f64
+        }
+
+        fn pick(&mut self, values: &[&str], count: usize) -> Option<Vec<String>> {
+            let mut picked: Vec<String> = (0..count)
+                .map(|_| values[(self.next() * values.len() as f64) as usize].to_string())
+                .collect();
+            picked.sort();
+            picked.dedup();
+            Some(picked)
+        }
+    }
+
+    fn synthetic_user(rng: &mut Lcg) -> MatchUser {
+        const TOPICS: &[&str] = &[
+            "alcohol",
+            "opioids",
+            "stimulants",
+            "cannabis",
+            "gambling",
+            "gaming",
+            "anxiety",
+            "depression",
+            "trauma",
+            "grief",
+        ];
+        const DAYS: &[&str] = &[
+            "monday",
+            "tuesday",
+            "wednesday",
+            "thursday",
+            "friday",
+            "saturday",
+            "sunday",
+        ];
+        const LANGUAGES: &[&str] = &["en", "es", "fr", "de", "pl", "ur"];
+        let latitude = 50.0 + rng.next() * 8.0;
+        let longitude = -6.0 + rng.next() * 8.0;
+        MatchUser {
+            id: Uuid::new_v4(),
+            dob: NaiveDate::from_ymd_opt(1950, 1, 1).unwrap()
+                + chrono::Duration::days((rng.next() * 20000.0) as i64),
+            location: Some(format!(
+                r#"{{"latitude":{:.1},"longitude":{:.1},"city":null,"country":"GB"}}"#,
+                latitude, longitude
+            )),
+            interests: rng.pick(TOPICS, 4),
+            experience: rng.pick(TOPICS, 3),
+            available_days: rng.pick(DAYS, 3),
+            languages: rng.pick(LANGUAGES, 2),
+        }
+    }
+
+    // Ranking 10k sponsors in memory, cold (every score computed) and warm (every score decoded
+    // from the cache). Run with: cargo test --release bench_rank_10k_sponsors -- --ignored --nocapture
+    #[test]
+    #[ignore]
+    fn bench_rank_10k_sponsors() {
+        const SPONSORS: usize = 10_000;
+        let config = MatchingConfig {
+            require_shared_language: true,
+            max_distance_km: Some(100.0),
+            ..MatchingConfig::default()
+        };
+        let mut rng = Lcg(42);
+        let member = synthetic_user(&mut rng);
+        let sponsors: Vec<MatchUser> = (0..SPONSORS).map(|_| synthetic_user(&mut rng)).collect();
+        let capacity = |sponsor: &MatchUser, active_mentees: i64| SponsorCapacity {
+            sponsor_id: sponsor.id,
+            max_active_mentees: 3,
+            accepting_new_members: true,
+            active_mentees,
+        };
+
+        // What the SQL pre-filter would let through
+        let member_location = member.parse_location().unwrap();
+        let bounds = bounding_box(member_location.latitude, member_location.longitude, 100.0);
+        let prefiltered = sponsors
+            .iter()
+            .filter(|s| {
+                let location = s.parse_location().unwrap();
+                let shares_language = s
+                    .languages
+                    .iter()
+                    .flatten()
+                    .any(|l| member.languages.iter().flatten().any(|m| m == l));
+                shares_language && contains(&bounds, (location.latitude, location.longitude))
+            })
+            .count();
+
+        let start = Instant::now();
+        let results: Vec<(SponsorCapacity, MatchResult)> = sponsors
+            .iter()
+            .enumerate()
+            .map(|(i, sponsor)| {
+                (
+                    capacity(sponsor, (i % 3) as i64),
+                    evaluate_match(&member, sponsor, &config),
+                )
+            })
+            .collect();
+        let cached: Vec<String> = results
+            .iter()
+            .map(|(_, result)| serde_json::to_string(result).unwrap())
+            .collect();
+        let ranked = rank_results(results, &config);
+        let cold = start.elapsed();
+
+        let start = Instant::now();
+        let results: Vec<(SponsorCapacity, MatchResult)> = sponsors
+            .iter()
+            .zip(&cached)
+            .enumerate()
+            .map(|(i, (sponsor, json))| {
+                (
+                    capacity(sponsor, (i % 3) as i64),
+                    serde_json::from_str(json).unwrap(),
+                )
+            })
+            .collect();
+        let warm_ranked = rank_results(results, &config);
+        let warm = start.elapsed();
+
+        println!(
+            "{} sponsors, {} past the SQL pre-filter, {} recommendable; cold {:?}, warm {:?}",
+            SPONSORS,
+            prefiltered,
+            ranked.len(),
+            cold,
+            warm
+        );
+        assert_eq!(ranked.len(), warm_ranked.len());
+        assert!(ranked.len() <= prefiltered);
+        assert!(ranked
+            .iter()
+            .all(|r| r.result.excluded_by != Some(MatchExclusion::TooFar)));
+        assert!(ranked
+            .windows(2)
+            .all(|pair| pair[0].result.score >= pair[1].result.score));
+        assert!(cold.as_secs_f64() < 1.0, "cold ranking took {:?}", cold);
+        assert!(warm.as_secs_f64() < 1.0, "warm ranking took {:?}", warm);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::models::all_models::SponsorSettings;
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// How many members a sponsor is taking on, and how many they already have
+#[derive(Debug, Clone, Serialize)]
+pub struct SponsorCapacity {
+    pub sponsor_id: Uuid,
+    pub max_active_mentees: i32,
+    pub accepting_new_members: bool,
+    pub active_mentees: i64,
+}
+
+impl SponsorCapacity {
+    fn from_settings(settings: &SponsorSettings, active_mentees: i64) -> Self {
+        SponsorCapacity {
+            sponsor_id: settings.user_id,
+            max_active_mentees: settings.max_active_mentees,
+            accepting_new_members: settings.accepting_new_members,
+            active_mentees,
+        }
+    }
+
+    pub fn has_room(&self) -> bool {
+        self.active_mentees < self.max_active_mentees as i64
+    }
+
+    // Share of the sponsor's capacity already in use, from 0 to 1
+    pub fn load(&self) -> f32 {
+        (self.active_mentees as f32 / self.max_active_mentees.max(1) as f32).min(1.0)
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.accepting_new_members && self.has_room()
+    }
+}
+
+// Capacity for many sponsors at once, using the default settings for sponsors without a row
+pub async fn sponsor_capacities(
+    pool: &PgPool,
+    sponsor_ids: &[Uuid],
+) -> Result<HashMap<Uuid, SponsorCapacity>, sqlx::Error> {
+    let settings: HashMap<Uuid, SponsorSettings> = sqlx::query_as::<_, SponsorSettings>(
+        "SELECT * FROM sponsor_settings WHERE user_id = ANY($1)",
+    )
+    .bind(sponsor_ids)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|s| (s.user_id, s))
+    .collect();
+
+    let active_query = "
+        SELECT sponsor_id, COUNT(*) FROM sponsorships
+        WHERE sponsor_id = ANY($1) AND status <> 'ended'
+        GROUP BY sponsor_id
+    ";
+    let active: HashMap<Uuid, i64> = sqlx::query_as::<_, (Uuid, i64)>(active_query)
+        .bind(sponsor_ids)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    Ok(sponsor_ids
+        .iter()
+        .map(|sponsor_id| {
+            let settings = settings
+                .get(sponsor_id)
+                .cloned()
+                .unwrap_or_else(|| SponsorSettings::default_for(*sponsor_id));
+            let active_mentees = active.get(sponsor_id).copied().unwrap_or(0);
+            (
+                *sponsor_id,
+                SponsorCapacity::from_settings(&settings, active_mentees),
+            )
+        })
+        .collect())
+}
+
+// Capacity for a single sponsor
+pub async fn sponsor_capacity(
+    pool: &PgPool,
+    sponsor_id: Uuid,
+) -> Result<SponsorCapacity, sqlx::Error> {
+    let mut capacities = sponsor_capacities(pool, &[sponsor_id]).await?;
+    Ok(capacities.remove(&sponsor_id).unwrap_or_else(|| {
+        SponsorCapacity::from_settings(&SponsorSettings::default_for(sponsor_id), 0)
+    }))
+}
+
+// Capacity for a sponsor inside a transaction, locking the sponsor's user row so concurrent
+// accepts are counted one after another
+pub async fn lock_sponsor_capacity(
+    conn: &mut PgConnection,
+    sponsor_id: Uuid,
+) -> Result<SponsorCapacity, sqlx::Error> {
+    sqlx::query("SELECT 1 FROM users WHERE user_id = $1 FOR UPDATE")
+        .bind(sponsor_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let settings =
+        sqlx::query_as::<_, SponsorSettings>("SELECT * FROM sponsor_settings WHERE user_id = $1")
+            .bind(sponsor_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .unwrap_or_else(|| SponsorSettings::default_for(sponsor_id));
+
+    let active_mentees: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM sponsorships WHERE sponsor_id = $1 AND status <> 'ended'",
+    )
+    .bind(sponsor_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(SponsorCapacity::from_settings(&settings, active_mentees))
+}
@@ -0,0 +1,238 @@
+use crate::handlers::notifications::create_notifications;
+use crate::models::all_models::{
+    Notification, NotificationKind, Sponsorship, SponsorshipEndReason,
+};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+// Start a sponsorship for an accepted request, with a private group chat for the two of them.
+// Runs on the caller's transaction so the request, chat and sponsorship are created together.
+pub async fn start_sponsorship(
+    conn: &mut PgConnection,
+    matching_request_id: Uuid,
+    member_id: Uuid,
+    sponsor_id: Uuid,
+) -> Result<Sponsorship, sqlx::Error> {
+    let group_chat_id: Uuid = sqlx::query_scalar(
+        "INSERT INTO group_chats (creator_id, created_at, flagged)
+         VALUES ($1, NOW(), false)
+         RETURNING group_chat_id",
+    )
+    .bind(sponsor_id)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO group_chat_members (group_chat_id, user_id) VALUES ($1, $2), ($1, $3)",
+    )
+    .bind(group_chat_id)
+    .bind(sponsor_id)
+    .bind(member_id)
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query_as::<_, Sponsorship>(
+        "INSERT INTO sponsorships (member_id, sponsor_id, matching_request_id, group_chat_id)
+         VALUES ($1, $2, $3, $4)
+         RETURNING *",
+    )
+    .bind(member_id)
+    .bind(sponsor_id)
+    .bind(matching_request_id)
+    .bind(group_chat_id)
+    .fetch_one(&mut *conn)
+    .await
+}
+
+// End every sponsorship the user is part of, e.g. when their account goes away
+pub async fn end_sponsorships_of(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    reason: SponsorshipEndReason,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE sponsorships
+         SET status = 'ended', paused_at = NULL, ended_at = NOW(), end_reason = $2
+         WHERE (member_id = $1 OR sponsor_id = $1) AND status <> 'ended'",
+    )
+    .bind(user_id)
+    .bind(reason)
+    .execute(conn)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+// End every open sponsorship where the user is the sponsor, recording who ended them
+pub async fn end_sponsorships_as_sponsor(
+    conn: &mut PgConnection,
+    sponsor_id: Uuid,
+    ended_by: Uuid,
+    reason: SponsorshipEndReason,
+) -> Result<Vec<Sponsorship>, sqlx::Error> {
+    sqlx::query_as::<_, Sponsorship>(
+        "UPDATE sponsorships
+         SET status = 'ended', paused_at = NULL, ended_at = NOW(), ended_by = $2, end_reason = $3
+         WHERE sponsor_id = $1 AND status <> 'ended'
+         RETURNING *",
+    )
+    .bind(sponsor_id)
+    .bind(ended_by)
+    .bind(reason)
+    .fetch_all(conn)
+    .await
+}
+
+// Close the private chats of ended sponsorships by removing both parties from them.
+// The messages stay for moderation; nobody can read or post there any more.
+pub async fn close_sponsorship_chats(
+    conn: &mut PgConnection,
+    sponsorships: &[Sponsorship],
+) -> Result<u64, sqlx::Error> {
+    let group_chat_ids: Vec<Uuid> = sponsorships
+        .iter()
+        .filter_map(|sponsorship| sponsorship.group_chat_id)
+        .collect();
+    if group_chat_ids.is_empty() {
+        return Ok(0);
+    }
+    let result = sqlx::query("DELETE FROM group_chat_members WHERE group_chat_id = ANY($1)")
+        .bind(&group_chat_ids)
+        .execute(conn)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+// Tell one side of a sponsorship that it has ended
+pub async fn notify_sponsorship_ended(
+    conn: &mut PgConnection,
+    sponsorship: &Sponsorship,
+    user_id: Uuid,
+) -> Result<Vec<Notification>, sqlx::Error> {
+    let payload = json!({
+        "sponsorship_id": sponsorship.sponsorship_id,
+        "status": sponsorship.status,
+        "end_reason": sponsorship.end_reason,
+    });
+    create_notifications(conn, &[user_id], NotificationKind::Sponsorship, &payload).await
+}
+
+// Fetch a sponsorship the user is part of, as member or sponsor
+pub async fn fetch_own_sponsorship(
+    pool: &PgPool,
+    user_id: Uuid,
+    sponsorship_id: Uuid,
+) -> Result<Option<Sponsorship>, sqlx::Error> {
+    sqlx::query_as::<_, Sponsorship>(
+        "SELECT * FROM sponsorships
+         WHERE sponsorship_id = $1 AND (member_id = $2 OR sponsor_id = $2)",
+    )
+    .bind(sponsorship_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+// The member's current (active or paused) sponsorship, if any
+pub async fn open_sponsorship_of(
+    pool: &PgPool,
+    member_id: Uuid,
+) -> Result<Option<Sponsorship>, sqlx::Error> {
+    sqlx::query_as::<_, Sponsorship>(
+        "SELECT * FROM sponsorships WHERE member_id = $1 AND status <> 'ended'",
+    )
+    .bind(member_id)
+    .fetch_optional(pool)
+    .await
+}
+
+// Whether the pair ended a sponsorship too recently to be matched again
+pub async fn in_rematch_cooldown(
+    pool: &PgPool,
+    member_id: Uuid,
+    sponsor_id: Uuid,
+    cooldown_days: i32,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        "SELECT EXISTS (
+            SELECT 1 FROM sponsorships
+            WHERE member_id = $1 AND sponsor_id = $2 AND status = 'ended'
+              AND ended_at > NOW() - make_interval(days => $3)
+        )",
+    )
+    .bind(member_id)
+    .bind(sponsor_id)
+    .bind(cooldown_days)
+    .fetch_one(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs against a migrated database, inside a transaction that is rolled back.
+    // Run with: DATABASE_URL=postgres://... cargo test revoked_sponsor -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn revoked_sponsor_leaves_the_sponsorship_chats() {
+        let url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = PgPool::connect(&url).await.unwrap();
+        let mut tx = pool.begin().await.unwrap();
+
+        let mut user_ids = Vec::new();
+        for _ in 0..3 {
+            let user_id: Uuid = sqlx::query_scalar(
+                "INSERT INTO users (username, email, password_hash, avatar_url, dob, user_profile)
+                 VALUES ($1, $1 || '@example.com', '', 'https://example.com/a.png', '1990-01-01',
+                         'Sponsorship test user')
+                 RETURNING user_id",
+            )
+            .bind(format!("sponsorship-test-{}", Uuid::new_v4()))
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+            user_ids.push(user_id);
+        }
+        let (sponsor_id, members) = (user_ids[0], &user_ids[1..]);
+
+        let mut group_chat_ids = Vec::new();
+        for member_id in members {
+            let matching_request_id: Uuid = sqlx::query_scalar(
+                "INSERT INTO matching_requests (member_id, sponsor_id, status)
+                 VALUES ($1, $2, 'accepted')
+                 RETURNING matching_request_id",
+            )
+            .bind(member_id)
+            .bind(sponsor_id)
+            .fetch_one(&mut *tx)
+            .await
+            .unwrap();
+            let sponsorship =
+                start_sponsorship(&mut tx, matching_request_id, *member_id, sponsor_id)
+                    .await
+                    .unwrap();
+            group_chat_ids.push(sponsorship.group_chat_id.unwrap());
+        }
+
+        let ended = end_sponsorships_as_sponsor(
+            &mut tx,
+            sponsor_id,
+            sponsor_id,
+            SponsorshipEndReason::SponsorRevoked,
+        )
+        .await
+        .unwrap();
+        assert_eq!(ended.len(), 2);
+        assert_eq!(close_sponsorship_chats(&mut tx, &ended).await.unwrap(), 4);
+
+        let remaining: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM group_chat_members WHERE group_chat_id = ANY($1)",
+        )
+        .bind(&group_chat_ids)
+        .fetch_one(&mut *tx)
+        .await
+        .unwrap();
+        assert_eq!(remaining, 0);
+    }
+}
@@ -0,0 +1,294 @@
+use crate::handlers::b2_storage::{B2Client, FileInfo, StorageBucket};
+use crate::handlers::image_processing::stored_avatar_file_names;
+use chrono::{NaiveDateTime, Utc};
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::error::Error;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Storage GC job settings
+#[derive(Debug, Clone)]
+pub struct StorageGcConfig {
+    /// How often the background job runs
+    pub interval: Duration,
+    /// Files younger than this are never touched (uploads may still be in flight)
+    pub grace_period: Duration,
+    /// Report orphans without deleting anything
+    pub dry_run: bool,
+}
+
+impl StorageGcConfig {
+    // Read settings from secrets, defaulting to a daily dry run with a 24h grace period
+    pub fn from_secrets(secrets: &shuttle_runtime::SecretStore) -> Self {
+        let hours = |key: &str, default: u64| {
+            secrets
+                .get(key)
+                .and_then(|v| v.parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+
+        StorageGcConfig {
+            interval: Duration::from_secs(hours("STORAGE_GC_INTERVAL_HOURS", 24) * 60 * 60),
+            grace_period: Duration::from_secs(hours("STORAGE_GC_GRACE_HOURS", 24) * 60 * 60),
+            dry_run: secrets
+                .get("STORAGE_GC_DRY_RUN")
+                .map(|v| v != "false")
+                .unwrap_or(true),
+        }
+    }
+}
+
+/// A stored file with no database reference
+#[derive(Debug, Clone, Serialize)]
+pub struct OrphanFile {
+    pub bucket: String,
+    pub file_name: String,
+    pub file_id: String,
+    pub size_bytes: u64,
+    pub uploaded_at: Option<NaiveDateTime>,
+    pub deleted: bool,
+}
+
+/// Outcome of one GC run
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageGcReport {
+    pub started_at: NaiveDateTime,
+    pub finished_at: NaiveDateTime,
+    pub dry_run: bool,
+    pub grace_period_hours: u64,
+    pub files_scanned: usize,
+    pub files_referenced: usize,
+    pub files_in_grace_period: usize,
+    pub dangling_attachments_removed: u64,
+    pub orphans: Vec<OrphanFile>,
+    pub orphan_bytes: u64,
+    pub errors: Vec<String>,
+}
+
+lazy_static! {
+    // Most recent report, served to admins
+    static ref LAST_GC_REPORT: Arc<Mutex<Option<StorageGcReport>>> = Arc::new(Mutex::new(None));
+}
+
+// Latest GC report, if the job has run since startup
+pub fn last_gc_report() -> Option<StorageGcReport> {
+    LAST_GC_REPORT.lock().ok().and_then(|guard| guard.clone())
+}
+
+// Attachment rows whose owning resource/post/message/application no longer exists
+const DANGLING_ATTACHMENTS_QUERY: &str = "
+    SELECT a.attachment_id FROM attachments a
+    WHERE (a.owner_type = 'resource'
+            AND NOT EXISTS (SELECT 1 FROM resources r WHERE r.resource_id = a.owner_id))
+       OR (a.owner_type = 'post'
+            AND NOT EXISTS (SELECT 1 FROM posts p WHERE p.post_id = a.owner_id))
+       OR (a.owner_type = 'message'
+            AND NOT EXISTS (SELECT 1 FROM messages m WHERE m.message_id = a.owner_id))
+       OR (a.owner_type = 'groupchatmessage'
+            AND NOT EXISTS (SELECT 1 FROM group_chat_messages g WHERE g.group_chat_message_id = a.owner_id))
+       OR (a.owner_type = 'sponsorapplication'
+            AND NOT EXISTS (SELECT 1 FROM sponsor_applications s WHERE s.application_id = a.owner_id))
+";
+
+// Collect every B2 file name the database still points at, per bucket
+async fn referenced_file_names(
+    pool: &PgPool,
+) -> Result<(HashSet<String>, HashSet<String>), sqlx::Error> {
+    let mut public = HashSet::new();
+    let avatar_urls: Vec<String> =
+        sqlx::query_scalar("SELECT avatar_url FROM users WHERE avatar_url LIKE '%/file/%'")
+            .fetch_all(pool)
+            .await?;
+    for url in avatar_urls {
+        let file_name = url.rsplit('/').next().unwrap_or_default();
+        public.extend(stored_avatar_file_names(file_name));
+    }
+
+    let attachment_names: Vec<String> = sqlx::query_scalar("SELECT file_name FROM attachments")
+        .fetch_all(pool)
+        .await?;
+    let mut private: HashSet<String> = attachment_names.into_iter().collect();
+
+    // Large data exports stay downloadable until they expire
+    let export_names: Vec<String> = sqlx::query_scalar(
+        "SELECT file_name FROM data_exports WHERE file_name IS NOT NULL AND expires_at > NOW()",
+    )
+    .fetch_all(pool)
+    .await?;
+    private.extend(export_names);
+
+    Ok((public, private))
+}
+
+// Delete attachment rows whose owner is gone, so their files become collectable
+async fn remove_dangling_attachments(pool: &PgPool, dry_run: bool) -> Result<u64, sqlx::Error> {
+    let ids: Vec<Uuid> = sqlx::query_scalar(DANGLING_ATTACHMENTS_QUERY)
+        .fetch_all(pool)
+        .await?;
+
+    if dry_run || ids.is_empty() {
+        return Ok(ids.len() as u64);
+    }
+
+    let result = sqlx::query("DELETE FROM attachments WHERE attachment_id = ANY($1)")
+        .bind(&ids)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+// Compare one bucket against its referenced names and delete (or just report) orphans
+async fn sweep_bucket(
+    b2_client: &B2Client,
+    bucket: StorageBucket,
+    bucket_label: &str,
+    referenced: &HashSet<String>,
+    grace_cutoff_ms: u64,
+    dry_run: bool,
+    report: &mut StorageGcReport,
+) {
+    let files: Vec<FileInfo> = match b2_client.list_all_files(bucket).await {
+        Ok(files) => files,
+        Err(e) => {
+            error!("Storage GC failed to list {} bucket: {}", bucket_label, e);
+            report
+                .errors
+                .push(format!("Failed to list {} bucket: {}", bucket_label, e));
+            return;
+        }
+    };
+
+    for file in files {
+        report.files_scanned += 1;
+
+        if referenced.contains(&file.file_name) {
+            report.files_referenced += 1;
+            continue;
+        }
+
+        if file.upload_timestamp > grace_cutoff_ms {
+            report.files_in_grace_period += 1;
+            continue;
+        }
+
+        let mut deleted = false;
+        if !dry_run {
+            match b2_client
+                .delete_file_version(&file.file_name, &file.file_id)
+                .await
+            {
+                Ok(_) => deleted = true,
+                Err(e) => {
+                    error!("Storage GC failed to delete {}: {}", file.file_name, e);
+                    report
+                        .errors
+                        .push(format!("Failed to delete {}: {}", file.file_name, e));
+                }
+            }
+        }
+
+        report.orphan_bytes += file.content_length;
+        report.orphans.push(OrphanFile {
+            bucket: bucket_label.to_string(),
+            file_name: file.file_name,
+            file_id: file.file_id,
+            size_bytes: file.content_length,
+            uploaded_at: chrono::DateTime::from_timestamp_millis(file.upload_timestamp as i64)
+                .map(|dt| dt.naive_utc()),
+            deleted,
+        });
+    }
+}
+
+// Run one GC pass over both buckets
+pub async fn run_storage_gc(
+    pool: &PgPool,
+    b2_client: &B2Client,
+    grace_period: Duration,
+    dry_run: bool,
+) -> Result<StorageGcReport, Box<dyn Error>> {
+    let started_at = Utc::now();
+    info!("Storage GC starting (dry run: {})", dry_run);
+
+    let mut report = StorageGcReport {
+        started_at: started_at.naive_utc(),
+        finished_at: started_at.naive_utc(),
+        dry_run,
+        grace_period_hours: grace_period.as_secs() / 3600,
+        files_scanned: 0,
+        files_referenced: 0,
+        files_in_grace_period: 0,
+        dangling_attachments_removed: 0,
+        orphans: Vec::new(),
+        orphan_bytes: 0,
+        errors: Vec::new(),
+    };
+
+    report.dangling_attachments_removed = remove_dangling_attachments(pool, dry_run).await?;
+
+    // Read references after cleaning dangling rows so their files show up as orphans.
+    // In a dry run the dangling rows still exist, so their files are reported on the next real run.
+    let (public_refs, private_refs) = referenced_file_names(pool).await?;
+
+    let grace_cutoff_ms = (started_at.timestamp_millis() as u64)
+        .saturating_sub(grace_period.as_millis() as u64);
+
+    sweep_bucket(
+        b2_client,
+        StorageBucket::Public,
+        "public",
+        &public_refs,
+        grace_cutoff_ms,
+        dry_run,
+        &mut report,
+    )
+    .await;
+    sweep_bucket(
+        b2_client,
+        StorageBucket::Private,
+        "private",
+        &private_refs,
+        grace_cutoff_ms,
+        dry_run,
+        &mut report,
+    )
+    .await;
+
+    report.finished_at = Utc::now().naive_utc();
+    info!(
+        "Storage GC finished: {} scanned, {} orphans ({} bytes), {} errors",
+        report.files_scanned,
+        report.orphans.len(),
+        report.orphan_bytes,
+        report.errors.len()
+    );
+
+    if let Ok(mut guard) = LAST_GC_REPORT.lock() {
+        *guard = Some(report.clone());
+    }
+
+    Ok(report)
+}
+
+// Spawn the periodic GC job
+pub fn spawn_storage_gc_job(pool: PgPool, b2_client: B2Client, config: StorageGcConfig) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(config.interval);
+        // The first tick fires immediately; skip it so startup isn't slowed by a full bucket scan
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            if let Err(e) =
+                run_storage_gc(&pool, &b2_client, config.grace_period, config.dry_run).await
+            {
+                warn!("Storage GC run failed: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,327 @@
+use crate::models::all_models::{TaxonomyAlias, TaxonomyKind, TaxonomyTerm};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{FromRow, PgPool};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Active controlled vocabularies, indexed for resolving free-form input to term codes
+pub struct Taxonomy {
+    codes: HashMap<(TaxonomyKind, String), String>,
+    aliases: HashMap<(TaxonomyKind, String), String>,
+}
+
+// Codes are hyphenated ("eating-disorders"), so spaces and underscores map onto hyphens
+pub fn code_key(raw: &str) -> String {
+    raw.trim()
+        .to_lowercase()
+        .split(|c: char| c.is_whitespace() || c == '_')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+// Aliases are free text, so only case and repeated whitespace are ignored
+pub fn alias_key(raw: &str) -> String {
+    raw.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+// Profile column holding values of the given kind
+pub fn profile_column(kind: TaxonomyKind) -> &'static str {
+    match kind {
+        TaxonomyKind::Interest => "interests",
+        TaxonomyKind::Experience => "experience",
+        TaxonomyKind::Day => "available_days",
+        TaxonomyKind::Language => "languages",
+    }
+}
+
+impl Taxonomy {
+    pub async fn load(pool: &PgPool) -> Result<Self, sqlx::Error> {
+        let terms =
+            sqlx::query_as::<_, TaxonomyTerm>("SELECT * FROM taxonomy_terms WHERE active = TRUE")
+                .fetch_all(pool)
+                .await?;
+        let aliases = sqlx::query_as::<_, TaxonomyAlias>(
+            "SELECT a.* FROM taxonomy_aliases a
+             JOIN taxonomy_terms t ON a.term_id = t.term_id
+             WHERE t.active = TRUE",
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let code_by_term: HashMap<Uuid, String> =
+            terms.iter().map(|t| (t.term_id, t.code.clone())).collect();
+
+        Ok(Taxonomy {
+            aliases: aliases
+                .into_iter()
+                .filter_map(|a| {
+                    code_by_term
+                        .get(&a.term_id)
+                        .map(|code| ((a.kind, alias_key(&a.alias)), code.clone()))
+                })
+                .collect(),
+            codes: terms
+                .into_iter()
+                .map(|t| ((t.kind, t.code.clone()), t.code))
+                .collect(),
+        })
+    }
+
+    // Resolve one free-form value to its term code
+    pub fn resolve(&self, kind: TaxonomyKind, raw: &str) -> Option<&str> {
+        self.codes
+            .get(&(kind, code_key(raw)))
+            .or_else(|| self.aliases.get(&(kind, alias_key(raw))))
+            .map(|code| code.as_str())
+    }
+
+    // Resolve every value, dropping duplicates; Err holds the values that didn't resolve
+    pub fn normalise(
+        &self,
+        kind: TaxonomyKind,
+        values: &[String],
+    ) -> Result<Vec<String>, Vec<String>> {
+        let mut codes: Vec<String> = Vec::new();
+        let mut unknown: Vec<String> = Vec::new();
+        for value in values {
+            if value.trim().is_empty() {
+                continue;
+            }
+            match self.resolve(kind, value) {
+                Some(code) => {
+                    if !codes.iter().any(|c| c == code) {
+                        codes.push(code.to_string());
+                    }
+                }
+                None => unknown.push(value.trim().to_string()),
+            }
+        }
+        if unknown.is_empty() {
+            Ok(codes)
+        } else {
+            Err(unknown)
+        }
+    }
+
+    // Values that don't (or no longer) resolve to an active term
+    pub fn unrecognised(&self, kind: TaxonomyKind, values: &[String]) -> Vec<String> {
+        values
+            .iter()
+            .filter(|v| self.resolve(kind, v).is_none())
+            .cloned()
+            .collect()
+    }
+}
+
+/// A profile field that still needs filling in
+#[derive(Debug, Serialize)]
+pub struct MissingField {
+    pub field: &'static str,
+    pub required_for_matching: bool,
+    pub reason: String,
+}
+
+/// Stored values that no longer match the vocabulary, e.g. after a term was retired
+#[derive(Debug, Serialize)]
+pub struct UnrecognisedValues {
+    pub field: &'static str,
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileCompleteness {
+    pub complete: bool,
+    pub ready_for_matching: bool,
+    pub percent: u8,
+    pub missing: Vec<MissingField>,
+    pub unrecognised: Vec<UnrecognisedValues>,
+}
+
+impl ProfileCompleteness {
+    // Names of the fields that block matching, for error messages
+    pub fn missing_for_matching(&self) -> Vec<&'static str> {
+        self.missing
+            .iter()
+            .filter(|m| m.required_for_matching)
+            .map(|m| m.field)
+            .collect()
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct CompletenessRow {
+    user_profile: Option<String>,
+    bio: Option<String>,
+    location: Option<Value>,
+    interests: Option<Vec<String>>,
+    experience: Option<Vec<String>>,
+    available_days: Option<Vec<String>>,
+    languages: Option<Vec<String>>,
+}
+
+// Work out which profile fields are missing, and whether the user can be matched yet
+pub async fn profile_completeness(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<ProfileCompleteness, sqlx::Error> {
+    let row = sqlx::query_as::<_, CompletenessRow>(
+        "SELECT user_profile, bio, location, interests, experience, available_days, languages
+         FROM users WHERE user_id = $1",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    let taxonomy = Taxonomy::load(pool).await?;
+
+    let mut missing: Vec<MissingField> = Vec::new();
+    let mut unrecognised: Vec<UnrecognisedValues> = Vec::new();
+    let mut filled = 0;
+
+    let is_blank = |text: &Option<String>| text.as_deref().unwrap_or("").trim().is_empty();
+    for (field, value, reason) in [
+        (
+            "user_profile",
+            &row.user_profile,
+            "Write a short introduction",
+        ),
+        ("bio", &row.bio, "Add a bio"),
+    ] {
+        if is_blank(value) {
+            missing.push(MissingField {
+                field,
+                required_for_matching: false,
+                reason: reason.to_string(),
+            });
+        } else {
+            filled += 1;
+        }
+    }
+
+    // Locations saved before geocoding may only have a city until they're re-entered
+    let has_coordinates = row
+        .location
+        .as_ref()
+        .and_then(|l| l.get("latitude"))
+        .is_some_and(|lat| lat.is_number());
+    if !has_coordinates {
+        missing.push(MissingField {
+            field: "location",
+            required_for_matching: true,
+            reason: "Set your city or postcode".to_string(),
+        });
+    } else {
+        filled += 1;
+    }
+
+    for (field, kind, values, reason) in [
+        (
+            "interests",
+            TaxonomyKind::Interest,
+            &row.interests,
+            "Pick at least one interest",
+        ),
+        (
+            "experience",
+            TaxonomyKind::Experience,
+            &row.experience,
+            "Pick at least one experience area",
+        ),
+        (
+            "available_days",
+            TaxonomyKind::Day,
+            &row.available_days,
+            "Pick at least one day you're available",
+        ),
+        (
+            "languages",
+            TaxonomyKind::Language,
+            &row.languages,
+            "Pick at least one language",
+        ),
+    ] {
+        let values = values.as_deref().unwrap_or(&[]);
+        let unknown = taxonomy.unrecognised(kind, values);
+        if values.len() > unknown.len() {
+            filled += 1;
+        } else {
+            missing.push(MissingField {
+                field,
+                required_for_matching: true,
+                reason: reason.to_string(),
+            });
+        }
+        if !unknown.is_empty() {
+            unrecognised.push(UnrecognisedValues {
+                field,
+                values: unknown,
+            });
+        }
+    }
+
+    let total = filled + missing.len();
+    Ok(ProfileCompleteness {
+        complete: missing.is_empty(),
+        ready_for_matching: !missing.iter().any(|m| m.required_for_matching),
+        percent: ((filled * 100) / total.max(1)) as u8,
+        missing,
+        unrecognised,
+    })
+}
+
+// Drop stored unmatched values that an alias now covers and add the resolved code to those profiles
+pub async fn apply_alias_to_unmatched(
+    pool: &PgPool,
+    kind: TaxonomyKind,
+    alias: &str,
+    code: &str,
+) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let values: Vec<(Uuid, String)> =
+        sqlx::query_as("SELECT user_id, value FROM taxonomy_unmatched_values WHERE kind = $1")
+            .bind(kind)
+            .fetch_all(&mut *tx)
+            .await?;
+    let matched: Vec<(Uuid, String)> = values
+        .into_iter()
+        .filter(|(_, value)| alias_key(value) == alias || code_key(value) == code)
+        .collect();
+    let user_ids: Vec<Uuid> = matched
+        .iter()
+        .map(|(user_id, _)| *user_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    // Column names can't be bound, but profile_column only returns fixed names
+    let update_query = format!(
+        "UPDATE users SET {col} = array_append(COALESCE({col}, '{{}}'), $1)
+         WHERE user_id = ANY($2) AND NOT ($1 = ANY(COALESCE({col}, '{{}}')))",
+        col = profile_column(kind)
+    );
+    let updated = sqlx::query(&update_query)
+        .bind(code)
+        .bind(&user_ids)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+    for (user_id, value) in &matched {
+        sqlx::query(
+            "DELETE FROM taxonomy_unmatched_values WHERE user_id = $1 AND kind = $2 AND value = $3",
+        )
+        .bind(user_id)
+        .bind(kind)
+        .bind(value)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(updated)
+}
@@ -0,0 +1,133 @@
+use crate::models::all_models::GroupMeeting;
+use chrono::{
+    DateTime, Duration as ChronoDuration, FixedOffset, LocalResult, NaiveDateTime, Offset,
+    TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Zone for users who haven't chosen one, and for meetings from before zones were stored
+pub const DEFAULT_TIMEZONE: Tz = chrono_tz::UTC;
+
+// Validate an IANA zone name such as Europe/London
+pub fn parse_timezone(raw: &str) -> Result<Tz, String> {
+    raw.trim().parse::<Tz>().map_err(|_| {
+        format!(
+            "Unknown timezone: {} (use an IANA name like Europe/London)",
+            raw.trim()
+        )
+    })
+}
+
+// Stored names were validated when saved; anything unreadable falls back to UTC
+pub fn timezone_or_default(name: Option<&str>) -> Tz {
+    name.and_then(|name| name.parse().ok())
+        .unwrap_or(DEFAULT_TIMEZONE)
+}
+
+// The instant a wall-clock time in `tz` refers to. A time skipped by a DST change is moved forward
+// by the size of the gap (01:30 becomes 02:30), and a repeated time uses its first occurrence.
+pub fn local_to_utc(tz: Tz, local: NaiveDateTime) -> DateTime<Utc> {
+    match tz.from_local_datetime(&local) {
+        LocalResult::Single(time) => time.with_timezone(&Utc),
+        LocalResult::Ambiguous(earliest, _) => earliest.with_timezone(&Utc),
+        LocalResult::None => {
+            // Zones never change twice in a day, so the offset a day earlier is the one before the gap
+            let before = tz
+                .offset_from_utc_datetime(&(local - ChronoDuration::days(1)))
+                .fix();
+            let utc = local - ChronoDuration::seconds(before.local_minus_utc().into());
+            Utc.from_utc_datetime(&utc)
+        }
+    }
+}
+
+// An instant as wall-clock time with its UTC offset in `tz`, e.g. 2025-04-08T19:00:00+01:00
+pub fn localize(instant: DateTime<Utc>, tz: Tz) -> DateTime<FixedOffset> {
+    instant.with_timezone(&tz).fixed_offset()
+}
+
+// The user's preferred zone, or UTC if they haven't set one
+pub async fn user_timezone(pool: &PgPool, user_id: Uuid) -> Result<Tz, sqlx::Error> {
+    let name: Option<Option<String>> =
+        sqlx::query_scalar("SELECT timezone FROM users WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+    Ok(timezone_or_default(name.flatten().as_deref()))
+}
+
+/// A meeting with its start shown in the host's zone and in the viewer's
+#[derive(Debug, Serialize)]
+pub struct LocalizedMeeting {
+    #[serde(flatten)]
+    pub meeting: GroupMeeting,
+    pub host_local_time: DateTime<FixedOffset>,
+    pub local_time: DateTime<FixedOffset>,
+    pub local_timezone: String,
+}
+
+impl LocalizedMeeting {
+    pub fn new(meeting: GroupMeeting, viewer_timezone: Tz) -> Self {
+        let host_timezone = timezone_or_default(Some(&meeting.timezone));
+        LocalizedMeeting {
+            host_local_time: localize(meeting.scheduled_time, host_timezone),
+            local_time: localize(meeting.scheduled_time, viewer_timezone),
+            local_timezone: viewer_timezone.name().to_string(),
+            meeting,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn wall_clock_time_follows_dst() {
+        let london: Tz = parse_timezone("Europe/London").unwrap();
+        // 19:00 is 19:00 UTC in winter and 18:00 UTC in summer
+        assert_eq!(
+            local_to_utc(london, at(2025, 3, 25, 19, 0)).naive_utc(),
+            at(2025, 3, 25, 19, 0)
+        );
+        assert_eq!(
+            local_to_utc(london, at(2025, 4, 1, 19, 0)).naive_utc(),
+            at(2025, 4, 1, 18, 0)
+        );
+    }
+
+    #[test]
+    fn skipped_and_repeated_times() {
+        let new_york: Tz = parse_timezone("America/New_York").unwrap();
+        // 02:30 doesn't exist on 2025-03-09; it's moved to 03:30 EDT
+        let skipped = local_to_utc(new_york, at(2025, 3, 9, 2, 30));
+        assert_eq!(skipped.naive_utc(), at(2025, 3, 9, 7, 30));
+        assert_eq!(
+            localize(skipped, new_york).naive_local(),
+            at(2025, 3, 9, 3, 30)
+        );
+        // 01:30 happens twice on 2025-11-02; the first (EDT) one is used
+        assert_eq!(
+            local_to_utc(new_york, at(2025, 11, 2, 1, 30)).naive_utc(),
+            at(2025, 11, 2, 5, 30)
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_zones() {
+        assert!(parse_timezone("Mars/Olympus_Mons").is_err());
+        assert_eq!(timezone_or_default(Some("nonsense")), DEFAULT_TIMEZONE);
+        assert_eq!(timezone_or_default(None), DEFAULT_TIMEZONE);
+    }
+}
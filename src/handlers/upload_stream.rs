@@ -0,0 +1,349 @@
+use crate::handlers::b2_storage::{B2Client, GetUploadPartUrlResponse, StorageBucket};
+use crate::handlers::image_processing::{sniff_image_format, SniffedFormat};
+use actix_multipart::Field;
+use futures::StreamExt;
+use log::{error, info};
+use sha1::{Digest, Sha1};
+use std::fmt;
+
+/// Part size used for B2 large file uploads (B2 requires at least 5MB per part)
+const LARGE_FILE_PART_SIZE: usize = 10 * 1024 * 1024;
+
+/// Errors that can occur while streaming an upload
+#[derive(Debug)]
+pub enum UploadError {
+    TooLarge(usize),
+    Multipart(String),
+    Storage(String),
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadError::TooLarge(limit) => {
+                write!(f, "File too large (max {}MB)", limit / (1024 * 1024))
+            }
+            UploadError::Multipart(e) => write!(f, "Error reading upload: {}", e),
+            UploadError::Storage(e) => write!(f, "Error storing upload: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+/// A field read fully into memory
+pub struct BufferedUpload {
+    pub data: Vec<u8>,
+}
+
+/// A field streamed straight to B2
+pub struct StoredUpload {
+    pub file_name: String,
+    pub size: u64,
+    pub sha1: String,
+}
+
+// Helper function: Reject an upload once it has grown past max_bytes (exactly max_bytes is fine)
+fn check_size(total: usize, max_bytes: usize) -> Result<(), UploadError> {
+    if total > max_bytes {
+        error!("Upload exceeded limit of {} bytes, aborting", max_bytes);
+        return Err(UploadError::TooLarge(max_bytes));
+    }
+    Ok(())
+}
+
+// Helper function: Take the full parts off the front of the buffer, leaving the rest.
+// A part is only taken once more data follows it, so a file that is exactly one part
+// stays in the buffer for the regular upload (B2 won't finish a single-part large file).
+fn take_full_parts(buffer: &mut Vec<u8>, part_size: usize) -> Vec<Vec<u8>> {
+    let mut parts = Vec::new();
+    while buffer.len() > part_size {
+        let rest = buffer.split_off(part_size);
+        parts.push(std::mem::replace(buffer, rest));
+    }
+    parts
+}
+
+// Read a small multipart field into memory, rejecting it as soon as it passes max_bytes
+pub async fn read_field_limited(
+    mut field: Field,
+    max_bytes: usize,
+) -> Result<BufferedUpload, UploadError> {
+    let mut data = Vec::new();
+
+    while let Some(chunk) = field.next().await {
+        let bytes = chunk.map_err(|e| {
+            error!("Error reading chunk: {:?}", e);
+            UploadError::Multipart(e.to_string())
+        })?;
+
+        check_size(data.len() + bytes.len(), max_bytes)?;
+        data.extend_from_slice(&bytes);
+    }
+
+    Ok(BufferedUpload { data })
+}
+
+// Read at least min_len bytes from the start of a field (less only if the field is shorter).
+// The returned bytes should be passed on as `first_chunk` when streaming the rest.
+pub async fn read_field_head(field: &mut Field, min_len: usize) -> Result<Vec<u8>, UploadError> {
+    let mut head = Vec::new();
+    while head.len() < min_len {
+        match field.next().await {
+            Some(Ok(bytes)) => head.extend_from_slice(&bytes),
+            Some(Err(e)) => {
+                error!("Error reading chunk: {:?}", e);
+                return Err(UploadError::Multipart(e.to_string()));
+            }
+            None => break,
+        }
+    }
+    Ok(head)
+}
+
+// Detect a file's content type from its leading bytes.
+// Office documents are zip containers, so for those the extension decides which one it is.
+pub fn sniff_content_type(head: &[u8], original_name: &str) -> Option<&'static str> {
+    if let Some(format) = sniff_image_format(head) {
+        return Some(match format {
+            SniffedFormat::Jpeg => "image/jpeg",
+            SniffedFormat::Png => "image/png",
+            SniffedFormat::WebP => "image/webp",
+        });
+    }
+
+    if head.starts_with(b"%PDF-") {
+        return Some("application/pdf");
+    }
+
+    if head.starts_with(&[b'P', b'K', 0x03, 0x04]) {
+        let extension = original_name
+            .rsplit('.')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        return match extension.as_str() {
+            "docx" => {
+                Some("application/vnd.openxmlformats-officedocument.wordprocessingml.document")
+            }
+            "xlsx" => Some("application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+            "pptx" => {
+                Some("application/vnd.openxmlformats-officedocument.presentationml.presentation")
+            }
+            _ => None,
+        };
+    }
+
+    None
+}
+
+// Stream a multipart field to B2 without buffering the whole file.
+// Files that fit in a single part use the regular upload; anything bigger switches to
+// the large file API so memory use stays bounded to one part. `first_chunk` lets callers
+// pass bytes they already consumed (e.g. for sniffing) ahead of the rest of the field.
+pub async fn stream_field_to_b2(
+    mut field: Field,
+    first_chunk: Vec<u8>,
+    b2_client: &B2Client,
+    bucket: StorageBucket,
+    file_name: &str,
+    content_type: &str,
+    max_bytes: usize,
+) -> Result<StoredUpload, UploadError> {
+    let part_size = match b2_client.minimum_part_size().await {
+        Ok(min) => LARGE_FILE_PART_SIZE.max(min as usize),
+        Err(e) => return Err(UploadError::Storage(e.to_string())),
+    };
+
+    let mut total: usize = first_chunk.len();
+    check_size(total, max_bytes)?;
+
+    let mut file_hasher = Sha1::new();
+    file_hasher.update(&first_chunk);
+    let mut buffer = first_chunk;
+
+    let mut large_file: Option<LargeFileUpload> = None;
+
+    while let Some(chunk) = field.next().await {
+        let bytes = match chunk {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                error!("Error reading chunk: {:?}", e);
+                abort_large_file(b2_client, large_file).await;
+                return Err(UploadError::Multipart(e.to_string()));
+            }
+        };
+
+        total += bytes.len();
+        if let Err(e) = check_size(total, max_bytes) {
+            abort_large_file(b2_client, large_file).await;
+            return Err(e);
+        }
+
+        file_hasher.update(&bytes);
+        buffer.extend_from_slice(&bytes);
+
+        for part in take_full_parts(&mut buffer, part_size) {
+            if large_file.is_none() {
+                match LargeFileUpload::start(b2_client, bucket, file_name, content_type).await {
+                    Ok(upload) => large_file = Some(upload),
+                    Err(e) => return Err(e),
+                }
+            }
+
+            if let Some(upload) = large_file.as_mut() {
+                if let Err(e) = upload.push_part(b2_client, part).await {
+                    abort_large_file(b2_client, large_file).await;
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    let sha1 = format!("{:x}", file_hasher.finalize());
+
+    match large_file {
+        Some(mut upload) => {
+            if !buffer.is_empty() {
+                if let Err(e) = upload.push_part(b2_client, buffer).await {
+                    abort_large_file(b2_client, Some(upload)).await;
+                    return Err(e);
+                }
+            }
+            if let Err(e) = b2_client
+                .finish_large_file(&upload.file_id, &upload.part_sha1s)
+                .await
+            {
+                let message = e.to_string();
+                abort_large_file(b2_client, Some(upload)).await;
+                return Err(UploadError::Storage(message));
+            }
+        }
+        None => {
+            b2_client
+                .upload_file_to(bucket, &buffer, file_name, content_type)
+                .await
+                .map_err(|e| UploadError::Storage(e.to_string()))?;
+        }
+    }
+
+    info!("Streamed upload {} complete: {} bytes", file_name, total);
+
+    Ok(StoredUpload {
+        file_name: file_name.to_string(),
+        size: total as u64,
+        sha1,
+    })
+}
+
+// State of an in-progress B2 large file upload
+struct LargeFileUpload {
+    file_id: String,
+    part_url: GetUploadPartUrlResponse,
+    part_sha1s: Vec<String>,
+}
+
+impl LargeFileUpload {
+    async fn start(
+        b2_client: &B2Client,
+        bucket: StorageBucket,
+        file_name: &str,
+        content_type: &str,
+    ) -> Result<Self, UploadError> {
+        let started = b2_client
+            .start_large_file(bucket, file_name, content_type)
+            .await
+            .map_err(|e| UploadError::Storage(e.to_string()))?;
+
+        let part_url = match b2_client.get_upload_part_url(&started.file_id).await {
+            Ok(url) => url,
+            Err(e) => {
+                let message = e.to_string();
+                if let Err(e) = b2_client.cancel_large_file(&started.file_id).await {
+                    error!("Failed to cancel large file {}: {:?}", started.file_id, e);
+                }
+                return Err(UploadError::Storage(message));
+            }
+        };
+
+        Ok(LargeFileUpload {
+            file_id: started.file_id,
+            part_url,
+            part_sha1s: Vec::new(),
+        })
+    }
+
+    async fn push_part(&mut self, b2_client: &B2Client, part: Vec<u8>) -> Result<(), UploadError> {
+        let part_number = self.part_sha1s.len() as u32 + 1;
+        let sha1 = b2_client
+            .upload_part(&self.part_url, part_number, part)
+            .await
+            .map_err(|e| UploadError::Storage(e.to_string()))?;
+        self.part_sha1s.push(sha1);
+        Ok(())
+    }
+}
+
+// Cancel a large file upload, if one was started
+async fn abort_large_file(b2_client: &B2Client, upload: Option<LargeFileUpload>) {
+    if let Some(upload) = upload {
+        if let Err(e) = b2_client.cancel_large_file(&upload.file_id).await {
+            error!("Failed to cancel large file {}: {:?}", upload.file_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_limit_allows_exactly_the_maximum() {
+        assert!(check_size(0, 10).is_ok());
+        assert!(check_size(10, 10).is_ok());
+        assert!(matches!(check_size(11, 10), Err(UploadError::TooLarge(10))));
+    }
+
+    #[test]
+    fn one_part_stays_on_the_regular_upload() {
+        // Exactly one part: nothing is flushed, so the file goes up as a regular upload
+        let mut buffer = vec![1u8; 8];
+        assert!(take_full_parts(&mut buffer, 8).is_empty());
+        assert_eq!(buffer.len(), 8);
+
+        // One byte more switches to a large file, keeping the tail for the last part
+        buffer.push(2);
+        let parts = take_full_parts(&mut buffer, 8);
+        assert_eq!(parts, vec![vec![1u8; 8]]);
+        assert_eq!(buffer, vec![2]);
+    }
+
+    #[test]
+    fn full_parts_wait_for_more_data() {
+        let mut buffer: Vec<u8> = (0..24).collect();
+        let parts = take_full_parts(&mut buffer, 8);
+        // The third full part is held back until more data shows it isn't the last
+        assert_eq!(parts, vec![(0..8).collect::<Vec<u8>>(), (8..16).collect()]);
+        assert_eq!(buffer, (16..24).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn sniffs_content_types() {
+        assert_eq!(
+            sniff_content_type(&[0xFF, 0xD8, 0xFF, 0xE0], "photo.png"),
+            Some("image/jpeg")
+        );
+        assert_eq!(
+            sniff_content_type(b"%PDF-1.7\n", "notes.txt"),
+            Some("application/pdf")
+        );
+        let zip = [b'P', b'K', 0x03, 0x04, 0x14, 0x00];
+        assert_eq!(
+            sniff_content_type(&zip, "Plan.DOCX"),
+            Some("application/vnd.openxmlformats-officedocument.wordprocessingml.document")
+        );
+        // Zip containers other than Office documents, and unknown bytes, are refused
+        assert_eq!(sniff_content_type(&zip, "archive.zip"), None);
+        assert_eq!(sniff_content_type(b"#!/bin/sh\n", "script.pdf"), None);
+    }
+}
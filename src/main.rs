@@ -12,7 +12,17 @@ use actix_web::{
     web, HttpResponse,
 };
 use anyhow;
+use handlers::account_deletion::spawn_account_deletion_job;
 use handlers::b2_storage::B2Client;
+use handlers::calendar::CalendarConfig;
+use handlers::check_ins::spawn_check_in_reminder_job;
+use handlers::data_export::{fail_interrupted_exports, spawn_export_expiry_job};
+use handlers::geocoding::{spawn_location_backfill, Geocoder, OfflineGeocoder};
+use handlers::matching_requests::spawn_request_expiry_job;
+use handlers::meeting_lifecycle::{spawn_meeting_lifecycle_job, MeetingLifecycleConfig};
+use handlers::meeting_series::spawn_meeting_series_job;
+use handlers::recovery::spawn_recovery_milestone_job;
+use handlers::storage_gc::{spawn_storage_gc_job, StorageGcConfig};
 use handlers::ws::init_ws_routes;
 use log::{error, info};
 use middleware::{
@@ -21,21 +31,33 @@ use middleware::{
 };
 use routes::{
     admin::config_admin_routes,
+    announcements::config_announcement_routes,
+    attachments::config_attachment_routes,
+    blocking::config_blocking_routes,
+    calendar::{config_calendar_routes, config_public_calendar_routes},
+    check_ins::config_check_in_routes,
+    data_export::config_data_export_routes,
     group_chats::config_group_chat_routes,
+    notifications::config_notification_routes,
     posts::config_feed_routes,
     private_messaging::config_message_routes,
+    recovery::config_recovery_routes,
     report::config_report_routes,
     resources::config_resource_routes,
+    search::config_search_routes,
     sponsor_matching::config_matching_routes,
     sponsor_role::config_sponsor_routes,
+    sponsorships::config_sponsorship_routes,
     support_group_meetings::config_meeting_routes,
     support_groups::config_support_group_routes,
+    taxonomy::config_taxonomy_routes,
     user_auth::{config_protected_auth_routes, config_user_auth_routes},
     user_data::config_user_data_routes,
 };
 use shuttle_actix_web::ShuttleActixWeb;
 use shuttle_runtime::SecretStore;
 use sqlx::postgres::PgPoolOptions;
+use std::sync::Arc;
 use std::time::Duration;
 
 #[shuttle_runtime::main]
@@ -114,6 +136,46 @@ async fn main(
         }
     };
 
+    // Start the orphaned storage cleanup job
+    let storage_gc_config = StorageGcConfig::from_secrets(&secrets);
+    info!(
+        "Storage GC scheduled every {}h (dry run: {})",
+        storage_gc_config.interval.as_secs() / 3600,
+        storage_gc_config.dry_run
+    );
+    spawn_storage_gc_job(pool.clone(), b2_client.clone(), storage_gc_config.clone());
+
+    // Anonymise accounts whose deletion grace period has ended
+    spawn_account_deletion_job(pool.clone(), b2_client.clone());
+
+    // Expire sponsor requests nobody answered in time
+    spawn_request_expiry_job(pool.clone());
+
+    // Prompt members whose sponsorship check-in is due
+    spawn_check_in_reminder_job(pool.clone());
+
+    // Celebrate recovery milestones as members reach them
+    spawn_recovery_milestone_job(pool.clone());
+
+    // Create recurring meetings as they come within the scheduling horizon
+    spawn_meeting_series_job(pool.clone());
+
+    // Remind participants before meetings, end overrunning ones and flag no-shows
+    spawn_meeting_lifecycle_job(pool.clone(), MeetingLifecycleConfig::from_secrets(&secrets));
+
+    // Exports are generated in-process, so any left running by the last shutdown are lost
+    fail_interrupted_exports(&pool).await;
+
+    // Delete export archives and files once they can no longer be downloaded
+    spawn_export_expiry_job(pool.clone(), b2_client.clone());
+
+    // Load the offline geocoder and resolve any locations saved before geocoding existed
+    let geocoder: Arc<dyn Geocoder> = Arc::new(OfflineGeocoder::from_secrets(&secrets));
+    spawn_location_backfill(pool.clone(), geocoder.clone());
+
+    // Public base URL used to build calendar feed links
+    let calendar_config = CalendarConfig::from_secrets(&secrets);
+
     info!("Starting BTH API Server with Shuttle...");
 
     // Create a configuration closure for Shuttle
@@ -130,6 +192,9 @@ async fn main(
         cfg.app_data(web::Data::new(pool.clone()));
         cfg.app_data(web::Data::new(session_secret.clone()));
         cfg.app_data(web::Data::new(b2_client)); // Make B2 client available to handlers
+        cfg.app_data(web::Data::new(storage_gc_config));
+        cfg.app_data(web::Data::new(calendar_config));
+        cfg.app_data(web::Data::from(geocoder));
         cfg.service(
             web::scope("")
                 .wrap(Logger::new(
@@ -150,7 +215,11 @@ async fn main(
                 .wrap(SessionRefreshMiddleware::new(30 * 60))
                 .service(
                     web::scope("/api")
-                        .service(web::scope("/public").configure(config_user_auth_routes))
+                        .service(
+                            web::scope("/public")
+                                .configure(config_user_auth_routes)
+                                .configure(config_public_calendar_routes),
+                        )
                         .service(
                             web::scope("/protected")
                                 .wrap(AuthMiddleware)
@@ -158,12 +227,23 @@ async fn main(
                                 .configure(config_user_data_routes)
                                 .configure(config_feed_routes)
                                 .configure(config_message_routes)
+                                .configure(config_notification_routes)
+                                .configure(config_announcement_routes)
                                 .configure(config_matching_routes)
                                 .configure(config_sponsor_routes)
+                                .configure(config_sponsorship_routes)
+                                .configure(config_check_in_routes)
+                                .configure(config_recovery_routes)
                                 .configure(config_support_group_routes)
                                 .configure(config_meeting_routes)
+                                .configure(config_calendar_routes)
                                 .configure(config_group_chat_routes)
                                 .configure(config_resource_routes)
+                                .configure(config_attachment_routes)
+                                .configure(config_data_export_routes)
+                                .configure(config_blocking_routes)
+                                .configure(config_search_routes)
+                                .configure(config_taxonomy_routes)
                                 .configure(config_report_routes)
                                 .configure(init_ws_routes)
                                 .configure(config_admin_routes),
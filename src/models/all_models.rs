@@ -1,4 +1,4 @@
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sqlx::{Decode, FromRow};
@@ -38,6 +38,10 @@ pub struct User {
     pub available_days: Option<Vec<String>>,
     pub languages: Option<Vec<String>>,
     pub privacy: bool,
+    pub deletion_requested_at: Option<NaiveDateTime>,
+    pub deletion_scheduled_for: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
+    pub timezone: Option<String>,
 }
 
 //  SPONSOR APPLICATION
@@ -48,6 +52,9 @@ pub enum ApplicationStatus {
     Pending,
     Approved,
     Rejected,
+    #[sqlx(rename = "info_requested")]
+    InfoRequested,
+    Revoked,
 }
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct SponsorApplication {
@@ -58,6 +65,47 @@ pub struct SponsorApplication {
     pub reviewed_by: Option<Uuid>,
     pub admin_comments: Option<String>,
     pub created_at: NaiveDateTime,
+    pub recovery_since: Option<NaiveDate>,
+    pub experience_areas: Vec<String>,
+    pub availability: Vec<String>,
+    pub referees: Value,
+    pub code_of_conduct_agreed_at: Option<NaiveDateTime>,
+    pub revision: i32,
+    pub submitted_at: NaiveDateTime,
+    pub reviewed_at: Option<NaiveDateTime>,
+}
+
+// Someone who can vouch for an applicant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsorReferee {
+    pub name: String,
+    pub relationship: String,
+    pub contact: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SponsorApplicationRevision {
+    pub revision_id: Uuid,
+    pub application_id: Uuid,
+    pub revision: i32,
+    pub application_info: String,
+    pub recovery_since: Option<NaiveDate>,
+    pub experience_areas: Vec<String>,
+    pub availability: Vec<String>,
+    pub referees: Value,
+    pub code_of_conduct_agreed_at: Option<NaiveDateTime>,
+    pub submitted_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SponsorApplicationReview {
+    pub review_id: Uuid,
+    pub application_id: Uuid,
+    pub revision: i32,
+    pub reviewer_id: Option<Uuid>,
+    pub decision: ApplicationStatus,
+    pub comments: Option<String>,
+    pub created_at: NaiveDateTime,
 }
 //  LOCATION STRUCT (For Matching & Users)
 
@@ -72,12 +120,15 @@ pub struct Location {
 
 //  MATCHING REQUESTS
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Clone, Copy)]
 #[sqlx(type_name = "matching_status", rename_all = "lowercase")]
 pub enum MatchingStatus {
     Pending,
     Accepted,
     Declined,
+    Withdrawn,
+    Expired,
+    Cancelled,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -111,6 +162,111 @@ impl MatchUser {
     }
 }
 
+// Admin-tunable sponsor matching weights and hard filters (single row)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MatchingConfig {
+    pub age_weight: f32,
+    pub location_weight: f32,
+    pub interests_weight: f32,
+    pub experience_weight: f32,
+    pub availability_weight: f32,
+    pub language_weight: f32,
+    pub age_range_years: f32,
+    pub require_shared_language: bool,
+    pub max_distance_km: Option<f32>,
+    pub load_penalty: f32,
+    pub rematch_cooldown_days: i32,
+    pub max_pending_requests: i32,
+    pub request_expiry_days: i32,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Default for MatchingConfig {
+    // Matches the table defaults
+    fn default() -> Self {
+        MatchingConfig {
+            age_weight: 30.0,
+            location_weight: 30.0,
+            interests_weight: 20.0,
+            experience_weight: 15.0,
+            availability_weight: 10.0,
+            language_weight: 5.0,
+            age_range_years: 15.0,
+            require_shared_language: false,
+            max_distance_km: None,
+            load_penalty: 20.0,
+            rematch_cooldown_days: 30,
+            max_pending_requests: 3,
+            request_expiry_days: 14,
+            updated_by: None,
+            updated_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
+
+// A sponsor's capacity settings
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SponsorSettings {
+    pub user_id: Uuid,
+    pub max_active_mentees: i32,
+    pub accepting_new_members: bool,
+    pub updated_at: NaiveDateTime,
+}
+
+impl SponsorSettings {
+    // Settings used for sponsors who have never saved any (matches the table defaults)
+    pub fn default_for(user_id: Uuid) -> Self {
+        SponsorSettings {
+            user_id,
+            max_active_mentees: 3,
+            accepting_new_members: true,
+            updated_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
+
+//  SPONSORSHIPS
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "sponsorship_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum SponsorshipStatus {
+    Active,
+    Paused,
+    Ended,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "sponsorship_end_reason", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SponsorshipEndReason {
+    GoalsMet,
+    NotAGoodFit,
+    Unavailable,
+    Unresponsive,
+    SafetyConcern,
+    AccountDeleted,
+    SponsorRevoked,
+    Other,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Sponsorship {
+    pub sponsorship_id: Uuid,
+    pub member_id: Uuid,
+    pub sponsor_id: Uuid,
+    pub matching_request_id: Option<Uuid>,
+    pub group_chat_id: Option<Uuid>,
+    pub status: SponsorshipStatus,
+    pub started_at: NaiveDateTime,
+    pub paused_at: Option<NaiveDateTime>,
+    pub ended_at: Option<NaiveDateTime>,
+    pub ended_by: Option<Uuid>,
+    pub end_reason: Option<SponsorshipEndReason>,
+    pub end_note: Option<String>,
+}
+
 //  1-1 MESSAGES & GROUP CHATS
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -159,6 +315,9 @@ pub enum MeetingStatus {
     Upcoming,
     Ongoing,
     Ended,
+    Cancelled,
+    // Never started by the host
+    Missed,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -169,9 +328,19 @@ pub struct GroupMeeting {
     pub host_id: Uuid,
     pub title: String,
     pub description: Option<String>,
-    pub scheduled_time: NaiveDateTime,
+    pub scheduled_time: DateTime<Utc>,
     pub status: MeetingStatus,
     pub meeting_chat_id: Option<Uuid>,
+    pub series_id: Option<Uuid>,
+    // The date the series rule put this occurrence on, even if it was moved since
+    pub occurrence_date: Option<NaiveDate>,
+    pub series_exception: bool,
+    // IANA zone the host planned the meeting in
+    pub timezone: String,
+    pub started_at: Option<DateTime<Utc>>,
+    pub ended_at: Option<DateTime<Utc>>,
+    // Ended by the scheduler after running past the maximum duration
+    pub ended_automatically: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -179,6 +348,25 @@ pub struct MeetingParticipant {
     pub meeting_id: Uuid,
     pub user_id: Uuid,
 }
+
+// A recurring meeting; its occurrences are GroupMeeting rows
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct MeetingSeries {
+    pub series_id: Uuid,
+    pub support_group_id: Uuid,
+    pub host_id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub rrule: String,
+    pub starts_on: NaiveDate,
+    // Wall-clock time in `timezone`, so the meeting keeps its local time across DST changes
+    pub start_time: NaiveTime,
+    pub timezone: String,
+    pub parent_series_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub ended_at: Option<NaiveDateTime>,
+}
 // SUPPORT GROUPS
 #[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
 #[sqlx(type_name = "support_group_status", rename_all = "lowercase")]
@@ -260,6 +448,7 @@ pub struct Post {
     pub content: String,
     pub created_at: NaiveDateTime,
     pub tags: Option<Vec<String>>,
+    pub support_group_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -305,6 +494,8 @@ pub enum AnnouncementType {
     MatchingRequestAccepted,
     MatchingRequestDeclined,
     AdminAction,
+    CheckInReminder,
+    MeetingCancelled,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::Type, Display, EnumString, PartialEq)]
@@ -320,6 +511,7 @@ pub enum AnnouncementTarget {
     Comment,
     Resource,
     MatchingRequest,
+    Sponsorship,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -334,3 +526,309 @@ pub struct Announcement {
     pub message: String,
     pub created_at: NaiveDateTime,
 }
+
+//  ATTACHMENTS
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "attachment_owner_type", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentOwnerType {
+    Resource,
+    Post,
+    Message,
+    GroupChatMessage,
+    SponsorApplication,
+}
+
+impl AttachmentOwnerType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttachmentOwnerType::Resource => "resource",
+            AttachmentOwnerType::Post => "post",
+            AttachmentOwnerType::Message => "message",
+            AttachmentOwnerType::GroupChatMessage => "groupchatmessage",
+            AttachmentOwnerType::SponsorApplication => "sponsorapplication",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Attachment {
+    pub attachment_id: Uuid,
+    pub uploader_id: Uuid,
+    pub owner_type: AttachmentOwnerType,
+    pub owner_id: Uuid,
+    #[serde(skip_serializing)]
+    pub file_name: String,
+    pub original_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    // Hex SHA-1 of the content, so clients can verify a download
+    pub sha1: String,
+    pub created_at: NaiveDateTime,
+}
+
+//  DATA EXPORTS
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "data_export_status", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum DataExportStatus {
+    Pending,
+    Processing,
+    Ready,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct DataExport {
+    pub export_id: Uuid,
+    pub user_id: Uuid,
+    pub status: DataExportStatus,
+    pub summary: Option<String>,
+    #[serde(skip_serializing)]
+    pub file_name: Option<String>,
+    pub size_bytes: Option<i64>,
+    pub error: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+//  PRIVACY SETTINGS
+
+// Who may see a profile field. Audiences are nested: "peers" also covers the user's sponsor.
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "visibility_audience", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum VisibilityAudience {
+    Everyone,
+    Peers,
+    Sponsor,
+    Nobody,
+}
+
+impl VisibilityAudience {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VisibilityAudience::Everyone => "everyone",
+            VisibilityAudience::Peers => "peers",
+            VisibilityAudience::Sponsor => "sponsor",
+            VisibilityAudience::Nobody => "nobody",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct PrivacySettings {
+    pub user_id: Uuid,
+    pub bio: VisibilityAudience,
+    pub interests: VisibilityAudience,
+    pub experience: VisibilityAudience,
+    pub languages: VisibilityAudience,
+    pub location_city: VisibilityAudience,
+    pub avatar: VisibilityAudience,
+    pub support_groups: VisibilityAudience,
+    pub recovery: VisibilityAudience,
+    pub updated_at: NaiveDateTime,
+}
+
+impl PrivacySettings {
+    // Settings used for users who have never saved any (matches the table defaults)
+    pub fn default_for(user_id: Uuid) -> Self {
+        PrivacySettings {
+            user_id,
+            bio: VisibilityAudience::Everyone,
+            interests: VisibilityAudience::Everyone,
+            experience: VisibilityAudience::Everyone,
+            languages: VisibilityAudience::Everyone,
+            location_city: VisibilityAudience::Nobody,
+            avatar: VisibilityAudience::Everyone,
+            support_groups: VisibilityAudience::Peers,
+            recovery: VisibilityAudience::Sponsor,
+            updated_at: chrono::Utc::now().naive_utc(),
+        }
+    }
+}
+
+//  BLOCKS & MUTES
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct UserBlock {
+    pub blocker_id: Uuid,
+    pub blocked_id: Uuid,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct UserMute {
+    pub muter_id: Uuid,
+    pub muted_id: Uuid,
+    pub created_at: NaiveDateTime,
+}
+
+//  PROFILE TAXONOMY
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Hash, Clone, Copy)]
+#[sqlx(type_name = "taxonomy_kind", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum TaxonomyKind {
+    Interest,
+    Experience,
+    Day,
+    Language,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct TaxonomyTerm {
+    pub term_id: Uuid,
+    pub kind: TaxonomyKind,
+    pub code: String,
+    pub label: String,
+    pub position: i32,
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct TaxonomyAlias {
+    pub kind: TaxonomyKind,
+    pub alias: String,
+    pub term_id: Uuid,
+    pub created_at: NaiveDateTime,
+}
+
+//  NOTIFICATIONS
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "notification_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    MatchingRequest,
+    SponsorApplication,
+    Sponsorship,
+    CheckIn,
+    RecoveryMilestone,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Notification {
+    pub notification_id: Uuid,
+    pub user_id: Uuid,
+    pub kind: NotificationKind,
+    pub payload: Value,
+    pub created_at: NaiveDateTime,
+    pub read_at: Option<NaiveDateTime>,
+}
+
+//  ADMIN AUDIT LOG
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "admin_action", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AdminAction {
+    SponsorRevoked,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct AdminAuditEntry {
+    pub audit_id: Uuid,
+    pub admin_id: Option<Uuid>,
+    pub action: AdminAction,
+    pub target_user_id: Option<Uuid>,
+    pub reason: String,
+    pub details: Value,
+    pub created_at: NaiveDateTime,
+}
+
+//  CHECK-INS
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CheckInSchedule {
+    pub sponsorship_id: Uuid,
+    pub frequency_days: i32,
+    pub next_due_at: NaiveDateTime,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CheckIn {
+    pub check_in_id: Uuid,
+    pub sponsorship_id: Uuid,
+    pub member_id: Uuid,
+    pub mood: i32,
+    pub progress: Option<String>,
+    pub shared_with_sponsor: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SponsorNote {
+    pub note_id: Uuid,
+    pub sponsorship_id: Uuid,
+    pub sponsor_id: Uuid,
+    pub content: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Clone, Copy)]
+#[sqlx(type_name = "check_in_milestone_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum CheckInMilestoneKind {
+    CheckIns,
+    Streak,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CheckInMilestone {
+    pub sponsorship_id: Uuid,
+    pub kind: CheckInMilestoneKind,
+    pub value: i32,
+    pub reached_at: NaiveDateTime,
+}
+
+//  RECOVERY
+
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct RecoveryJourney {
+    pub journey_id: Uuid,
+    pub user_id: Uuid,
+    pub experience: String,
+    pub started_at: NaiveDateTime,
+    pub share_with_groups: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct RecoveryReset {
+    pub reset_id: Uuid,
+    pub journey_id: Uuid,
+    pub previous_started_at: NaiveDateTime,
+    pub started_at: NaiveDateTime,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct RecoveryMilestone {
+    pub journey_id: Uuid,
+    pub started_at: NaiveDateTime,
+    pub days: i32,
+    pub reached_at: NaiveDateTime,
+}
+
+//  CALENDAR FEEDS
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CalendarFeed {
+    pub feed_id: Uuid,
+    pub user_id: Uuid,
+    // Secret part of the feed URL; anyone holding it can read the feed
+    pub token: String,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
@@ -1,6 +1,18 @@
 use crate::handlers::auth::Claims;
+use crate::handlers::b2_storage::B2Client;
+use crate::handlers::matching_algo::load_matching_config;
+use crate::handlers::matching_requests::{cancel_requests_to_sponsor, notify_request_change};
+use crate::handlers::notifications::push_notifications;
+use crate::handlers::sponsor_applications::{application_details, notify_applicant};
+use crate::handlers::sponsorships::{
+    close_sponsorship_chats, end_sponsorships_as_sponsor, notify_sponsorship_ended,
+};
+use crate::handlers::storage_gc::{last_gc_report, run_storage_gc, StorageGcConfig};
+use crate::handlers::taxonomy::{alias_key, apply_alias_to_unmatched, code_key};
 use crate::models::all_models::{
-    ApplicationStatus, ReportStatus, ReportedType, SupportGroupStatus, UserRole,
+    AdminAction, AdminAuditEntry, ApplicationStatus, MatchingConfig, ReportStatus, ReportedType,
+    SponsorApplication, SponsorApplicationReview, SponsorshipEndReason, SupportGroupStatus,
+    TaxonomyAlias, TaxonomyKind, TaxonomyTerm, UserRole,
 };
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use chrono::{NaiveDate, NaiveDateTime, Utc};
@@ -63,6 +75,23 @@ pub struct UnbanUserRequest {
     pub user_id: Uuid,
 }
 
+//Revoke Sponsor Request
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RevokeSponsorRequest {
+    pub user_id: Uuid,
+    pub reason: String,
+}
+
+//Get Audit Log Query Params
+#[derive(Debug, Deserialize)]
+pub struct GetAuditLogParams {
+    pub target_user_id: Option<Uuid>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+const MAX_AUDIT_REASON_LENGTH: usize = 1000;
+
 //Get Admin Stats Response
 #[derive(Debug, Serialize)]
 pub struct GetAdminStatsResponse {
@@ -83,6 +112,12 @@ pub struct GetAllUsersParams {
     pub offset: Option<i64>,
 }
 
+//Run Storage GC Request
+#[derive(Debug, Deserialize)]
+pub struct RunStorageGcRequest {
+    pub dry_run: Option<bool>,
+}
+
 //Ensure Admin Helper Function
 fn ensure_admin(req: &HttpRequest) -> Result<(), HttpResponse> {
     if let Some(claims) = req.extensions().get::<Claims>() {
@@ -122,6 +157,13 @@ pub async fn get_pending_sponsor_applications(
             sa.reviewed_by, 
             sa.admin_comments, 
             sa.created_at,
+            sa.recovery_since,
+            sa.experience_areas,
+            sa.availability,
+            sa.referees,
+            sa.code_of_conduct_agreed_at,
+            sa.revision,
+            sa.submitted_at,
             u.username,
             u.email
         FROM 
@@ -131,7 +173,7 @@ pub async fn get_pending_sponsor_applications(
         WHERE 
             sa.status = $1
         ORDER BY 
-            sa.created_at DESC
+            sa.submitted_at DESC
     "#;
 
     match sqlx::query(query)
@@ -143,16 +185,10 @@ pub async fn get_pending_sponsor_applications(
             let applications = rows
                 .iter()
                 .map(|row| {
-                    // Parse the application_info from TEXT to JSON
+                    // Older applications stored a JSON document here; newer ones plain text
                     let application_info_str: String = row.get("application_info");
-                    let application_info = match serde_json::from_str(&application_info_str) {
-                        Ok(json) => json,
-                        Err(e) => {
-                            error!("Failed to parse application_info as JSON: {}", e);
-                            error!("Raw application_info: {}", application_info_str);
-                            serde_json::json!({})
-                        }
-                    };
+                    let application_info = serde_json::from_str(&application_info_str)
+                        .unwrap_or(serde_json::Value::String(application_info_str));
 
                     json!({
                         "application_id": row.get::<Uuid, _>("application_id"),
@@ -164,6 +200,13 @@ pub async fn get_pending_sponsor_applications(
                         "reviewed_by": row.get::<Option<Uuid>, _>("reviewed_by"),
                         "admin_comments": row.get::<Option<String>, _>("admin_comments"),
                         "created_at": row.get::<NaiveDateTime, _>("created_at"),
+                        "recovery_since": row.get::<Option<NaiveDate>, _>("recovery_since"),
+                        "experience_areas": row.get::<Vec<String>, _>("experience_areas"),
+                        "availability": row.get::<Vec<String>, _>("availability"),
+                        "referees": row.get::<serde_json::Value, _>("referees"),
+                        "code_of_conduct_agreed_at": row.get::<Option<NaiveDateTime>, _>("code_of_conduct_agreed_at"),
+                        "revision": row.get::<i32, _>("revision"),
+                        "submitted_at": row.get::<NaiveDateTime, _>("submitted_at"),
                     })
                 })
                 .collect::<Vec<_>>();
@@ -177,9 +220,46 @@ pub async fn get_pending_sponsor_applications(
     }
 }
 
+//Get Sponsor Application
+//Get Sponsor Application Input: HttpRequest(JWT Token), Path (/admin/sponsor-applications/{application_id})
+//Get Sponsor Application Output: SponsorApplicationDetails
+pub async fn get_sponsor_application(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    if let Err(response) = ensure_admin(&req) {
+        return response;
+    }
+
+    let application = match sqlx::query_as::<_, SponsorApplication>(
+        "SELECT * FROM sponsor_applications WHERE application_id = $1",
+    )
+    .bind(path.into_inner())
+    .fetch_optional(pool.get_ref())
+    .await
+    {
+        Ok(Some(application)) => application,
+        Ok(None) => return HttpResponse::NotFound().body("Application not found"),
+        Err(e) => {
+            error!("Failed to fetch sponsor application: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch application");
+        }
+    };
+
+    match application_details(pool.get_ref(), application).await {
+        Ok(details) => HttpResponse::Ok().json(details),
+        Err(e) => {
+            error!("Failed to fetch sponsor application history: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch application")
+        }
+    }
+}
+
 //Review Sponsor Application
 //Review Sponsor Application Input: HttpRequest(JWT Token), ReviewSponsorApplicationRequest
 //Review Sponsor Application Output: AdminActionResponse
+//InfoRequested sends the application back to the applicant with the comments as the question
 pub async fn review_sponsor_application(
     pool: web::Data<PgPool>,
     req: HttpRequest,
@@ -198,10 +278,20 @@ pub async fn review_sponsor_application(
     };
 
     // Validate input
-    if payload.status != ApplicationStatus::Approved
-        && payload.status != ApplicationStatus::Rejected
-    {
-        return HttpResponse::BadRequest().body("Invalid status. Must be 'approved' or 'rejected'");
+    if matches!(
+        payload.status,
+        ApplicationStatus::Pending | ApplicationStatus::Revoked
+    ) {
+        return HttpResponse::BadRequest()
+            .body("Invalid status. Must be 'Approved', 'Rejected' or 'InfoRequested'");
+    }
+    let admin_comments = payload
+        .admin_comments
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty());
+    if payload.status == ApplicationStatus::InfoRequested && admin_comments.is_none() {
+        return HttpResponse::BadRequest().body("Say what information is needed in the comments");
     }
 
     // Start a transaction
@@ -213,27 +303,32 @@ pub async fn review_sponsor_application(
         }
     };
 
-    // Update the application status
+    // Update the application status; only applications waiting for review can be decided
     let update_query = r#"
         UPDATE sponsor_applications
         SET 
             status = $1, 
             reviewed_by = $2, 
-            admin_comments = $3
+            admin_comments = $3,
+            reviewed_at = NOW()
         WHERE 
-            application_id = $4
-        RETURNING user_id
+            application_id = $4 AND status = $5
+        RETURNING *
     "#;
 
-    let user_id = match sqlx::query_scalar::<_, Uuid>(update_query)
+    let application = match sqlx::query_as::<_, SponsorApplication>(update_query)
         .bind(&payload.status)
         .bind(admin_id)
-        .bind(&payload.admin_comments)
+        .bind(admin_comments)
         .bind(payload.application_id)
-        .fetch_one(&mut *tx)
+        .bind(ApplicationStatus::Pending)
+        .fetch_optional(&mut *tx)
         .await
     {
-        Ok(user_id) => user_id,
+        Ok(Some(application)) => application,
+        Ok(None) => {
+            return HttpResponse::Conflict().body("Application not found or not awaiting review")
+        }
         Err(e) => {
             eprintln!("Failed to update application: {:?}", e);
             let _ = tx.rollback().await;
@@ -241,6 +336,30 @@ pub async fn review_sponsor_application(
         }
     };
 
+    // Keep the decision in the application's history
+    let review_query = "
+        INSERT INTO sponsor_application_reviews
+            (application_id, revision, reviewer_id, decision, comments)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *
+    ";
+    let review = match sqlx::query_as::<_, SponsorApplicationReview>(review_query)
+        .bind(application.application_id)
+        .bind(application.revision)
+        .bind(admin_id)
+        .bind(&payload.status)
+        .bind(admin_comments)
+        .fetch_one(&mut *tx)
+        .await
+    {
+        Ok(review) => review,
+        Err(e) => {
+            eprintln!("Failed to record review: {:?}", e);
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to update application");
+        }
+    };
+
     // If approved, update the user's role to Sponsor
     if payload.status == ApplicationStatus::Approved {
         let update_user_query = r#"
@@ -251,7 +370,7 @@ pub async fn review_sponsor_application(
 
         if let Err(e) = sqlx::query(update_user_query)
             .bind(UserRole::Sponsor)
-            .bind(user_id)
+            .bind(application.user_id)
             .execute(&mut *tx)
             .await
         {
@@ -261,16 +380,272 @@ pub async fn review_sponsor_application(
         }
     }
 
+    let notifications = match notify_applicant(&mut tx, application.user_id, &review).await {
+        Ok(notifications) => notifications,
+        Err(e) => {
+            eprintln!("Failed to notify applicant: {:?}", e);
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to update application");
+        }
+    };
+
     // Commit the transaction
     if let Err(e) = tx.commit().await {
         eprintln!("Failed to commit transaction: {:?}", e);
         return HttpResponse::InternalServerError().body("Database error");
     }
+    push_notifications(&notifications).await;
 
     // Return success response
+    let message = if payload.status == ApplicationStatus::InfoRequested {
+        "More information requested from the applicant".to_string()
+    } else {
+        format!("Sponsor application {} successfully", payload.status)
+    };
+    HttpResponse::Ok().json(AdminActionResponse {
+        success: true,
+        message,
+    })
+}
+
+//Revoke Sponsor
+//Revoke Sponsor Input: HttpRequest(JWT Token), RevokeSponsorRequest
+//Revoke Sponsor Output: AdminActionResponse
+//Demotes the sponsor to member, ends their sponsorships, cancels requests waiting on them
+//and records the reason in the admin audit log
+pub async fn revoke_sponsor(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    payload: web::Json<RevokeSponsorRequest>,
+) -> impl Responder {
+    // Check if user is admin
+    if let Err(response) = ensure_admin(&req) {
+        return response;
+    }
+
+    // Get admin ID from claims
+    let admin_id = if let Some(claims) = req.extensions().get::<Claims>() {
+        claims.id
+    } else {
+        return HttpResponse::Unauthorized().body("Authentication required");
+    };
+
+    // Validate input
+    let reason = payload.reason.trim();
+    if reason.is_empty() {
+        return HttpResponse::BadRequest().body("Reason cannot be empty");
+    }
+    if reason.chars().count() > MAX_AUDIT_REASON_LENGTH {
+        return HttpResponse::BadRequest().body(format!(
+            "Reason must be at most {} characters",
+            MAX_AUDIT_REASON_LENGTH
+        ));
+    }
+
+    // Start a transaction
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            error!("Failed to start transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Database error");
+        }
+    };
+
+    // Lock the user so a concurrent review or revoke can't interleave
+    let user = sqlx::query("SELECT username, role FROM users WHERE user_id = $1 FOR UPDATE")
+        .bind(payload.user_id)
+        .fetch_optional(&mut *tx)
+        .await;
+    let username = match user {
+        Ok(Some(row)) if row.get::<UserRole, _>("role") == UserRole::Sponsor => {
+            row.get::<String, _>("username")
+        }
+        Ok(Some(_)) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::BadRequest().body("User is not a sponsor");
+        }
+        Ok(None) => {
+            let _ = tx.rollback().await;
+            return HttpResponse::NotFound().body("User not found");
+        }
+        Err(e) => {
+            error!("Failed to check user: {:?}", e);
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Database error");
+        }
+    };
+
+    if let Err(e) = sqlx::query("UPDATE users SET role = $1 WHERE user_id = $2")
+        .bind(UserRole::Member)
+        .bind(payload.user_id)
+        .execute(&mut *tx)
+        .await
+    {
+        error!("Failed to update user role: {:?}", e);
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to update user role");
+    }
+
+    let mut notifications = Vec::new();
+
+    // End their sponsorships and let each member know
+    let ended = match end_sponsorships_as_sponsor(
+        &mut tx,
+        payload.user_id,
+        admin_id,
+        SponsorshipEndReason::SponsorRevoked,
+    )
+    .await
+    {
+        Ok(ended) => ended,
+        Err(e) => {
+            error!("Failed to end sponsorships: {:?}", e);
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to revoke sponsor");
+        }
+    };
+    if let Err(e) = close_sponsorship_chats(&mut tx, &ended).await {
+        error!("Failed to close sponsorship chats: {:?}", e);
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to revoke sponsor");
+    }
+    for sponsorship in &ended {
+        match notify_sponsorship_ended(&mut tx, sponsorship, sponsorship.member_id).await {
+            Ok(sent) => notifications.extend(sent),
+            Err(e) => {
+                error!("Failed to notify member: {:?}", e);
+                let _ = tx.rollback().await;
+                return HttpResponse::InternalServerError().body("Failed to revoke sponsor");
+            }
+        }
+    }
+
+    // Requests still waiting on them can no longer be accepted
+    let cancelled = match cancel_requests_to_sponsor(&mut tx, payload.user_id).await {
+        Ok(cancelled) => cancelled,
+        Err(e) => {
+            error!("Failed to cancel matching requests: {:?}", e);
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to revoke sponsor");
+        }
+    };
+    for request in &cancelled {
+        match notify_request_change(&mut tx, request).await {
+            Ok(sent) => notifications.extend(sent),
+            Err(e) => {
+                error!("Failed to notify request change: {:?}", e);
+                let _ = tx.rollback().await;
+                return HttpResponse::InternalServerError().body("Failed to revoke sponsor");
+            }
+        }
+    }
+
+    // Recommendations already filter on role; drop cached scores so nothing stale lingers
+    if let Err(e) = sqlx::query("DELETE FROM sponsor_match_scores WHERE sponsor_id = $1")
+        .bind(payload.user_id)
+        .execute(&mut *tx)
+        .await
+    {
+        error!("Failed to clear match scores: {:?}", e);
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to revoke sponsor");
+    }
+
+    // Close the approved application so the reapply cooldown applies
+    let revoke_application_query = "
+        UPDATE sponsor_applications
+        SET status = $1, reviewed_by = $2, admin_comments = $3, reviewed_at = NOW()
+        WHERE user_id = $4 AND status = $5
+        RETURNING *
+    ";
+    let application = match sqlx::query_as::<_, SponsorApplication>(revoke_application_query)
+        .bind(ApplicationStatus::Revoked)
+        .bind(admin_id)
+        .bind(reason)
+        .bind(payload.user_id)
+        .bind(ApplicationStatus::Approved)
+        .fetch_optional(&mut *tx)
+        .await
+    {
+        Ok(application) => application,
+        Err(e) => {
+            error!("Failed to update application: {:?}", e);
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to revoke sponsor");
+        }
+    };
+    if let Some(application) = application {
+        let review_query = "
+            INSERT INTO sponsor_application_reviews
+                (application_id, revision, reviewer_id, decision, comments)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+        ";
+        let review = match sqlx::query_as::<_, SponsorApplicationReview>(review_query)
+            .bind(application.application_id)
+            .bind(application.revision)
+            .bind(admin_id)
+            .bind(ApplicationStatus::Revoked)
+            .bind(reason)
+            .fetch_one(&mut *tx)
+            .await
+        {
+            Ok(review) => review,
+            Err(e) => {
+                error!("Failed to record review: {:?}", e);
+                let _ = tx.rollback().await;
+                return HttpResponse::InternalServerError().body("Failed to revoke sponsor");
+            }
+        };
+        match notify_applicant(&mut tx, application.user_id, &review).await {
+            Ok(sent) => notifications.extend(sent),
+            Err(e) => {
+                error!("Failed to notify applicant: {:?}", e);
+                let _ = tx.rollback().await;
+                return HttpResponse::InternalServerError().body("Failed to revoke sponsor");
+            }
+        }
+    }
+
+    let audit_query = "
+        INSERT INTO admin_audit_log (admin_id, action, target_user_id, reason, details)
+        VALUES ($1, $2, $3, $4, $5)
+    ";
+    if let Err(e) = sqlx::query(audit_query)
+        .bind(admin_id)
+        .bind(AdminAction::SponsorRevoked)
+        .bind(payload.user_id)
+        .bind(reason)
+        .bind(json!({
+            "ended_sponsorships": ended.iter().map(|s| s.sponsorship_id).collect::<Vec<_>>(),
+            "cancelled_requests": cancelled
+                .iter()
+                .map(|r| r.matching_request_id)
+                .collect::<Vec<_>>(),
+        }))
+        .execute(&mut *tx)
+        .await
+    {
+        error!("Failed to write audit log: {:?}", e);
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to revoke sponsor");
+    }
+
+    // Commit the transaction
+    if let Err(e) = tx.commit().await {
+        error!("Failed to commit transaction: {:?}", e);
+        return HttpResponse::InternalServerError().body("Database error");
+    }
+    push_notifications(&notifications).await;
+
     HttpResponse::Ok().json(AdminActionResponse {
         success: true,
-        message: format!("Sponsor application {} successfully", payload.status),
+        message: format!(
+            "Sponsor status revoked for {}; {} sponsorships ended, {} requests cancelled",
+            username,
+            ended.len(),
+            cancelled.len()
+        ),
     })
 }
 
@@ -1578,9 +1953,610 @@ pub async fn get_admin_stats(pool: web::Data<PgPool>, req: HttpRequest) -> impl
     }))
 }
 
+//Get Storage GC Report
+//Get Storage GC Report Input: HttpRequest(JWT Token)
+//Get Storage GC Report Output: StorageGcReport
+pub async fn get_storage_gc_report(req: HttpRequest) -> impl Responder {
+    if let Err(resp) = ensure_admin(&req) {
+        return resp;
+    }
+
+    match last_gc_report() {
+        Some(report) => HttpResponse::Ok().json(report),
+        None => HttpResponse::NotFound().body("Storage GC has not run since startup"),
+    }
+}
+
+//Run Storage GC
+//Run Storage GC Input: HttpRequest(JWT Token), RunStorageGcRequest
+//Run Storage GC Output: StorageGcReport
+pub async fn run_storage_gc_now(
+    pool: web::Data<PgPool>,
+    b2_client: web::Data<B2Client>,
+    gc_config: web::Data<StorageGcConfig>,
+    req: HttpRequest,
+    payload: Option<web::Json<RunStorageGcRequest>>,
+) -> impl Responder {
+    if let Err(resp) = ensure_admin(&req) {
+        return resp;
+    }
+
+    // Default to the job's configured mode so a missing or empty body never deletes unexpectedly
+    let dry_run = payload
+        .and_then(|payload| payload.dry_run)
+        .unwrap_or(gc_config.dry_run);
+
+    match run_storage_gc(&pool, &b2_client, gc_config.grace_period, dry_run).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => {
+            error!("Storage GC run failed: {}", e);
+            HttpResponse::InternalServerError().body("Storage GC run failed")
+        }
+    }
+}
+
+//Taxonomy Term With Aliases
+#[derive(Debug, Serialize)]
+pub struct TaxonomyTermWithAliases {
+    #[serde(flatten)]
+    pub term: TaxonomyTerm,
+    pub aliases: Vec<String>,
+}
+
+//List Taxonomy Terms Params
+#[derive(Debug, Deserialize)]
+pub struct ListTaxonomyTermsParams {
+    pub kind: Option<TaxonomyKind>,
+}
+
+//Create Taxonomy Term Request
+#[derive(Debug, Deserialize)]
+pub struct CreateTaxonomyTermRequest {
+    pub kind: TaxonomyKind,
+    pub code: String,
+    pub label: String,
+    pub position: Option<i32>,
+    pub aliases: Option<Vec<String>>,
+}
+
+//Update Taxonomy Term Request
+//Codes can't change since they're stored on profiles; retire the term and add a new one instead
+#[derive(Debug, Deserialize)]
+pub struct UpdateTaxonomyTermRequest {
+    pub label: Option<String>,
+    pub position: Option<i32>,
+    pub active: Option<bool>,
+}
+
+//Add Taxonomy Alias Request
+#[derive(Debug, Deserialize)]
+pub struct AddTaxonomyAliasRequest {
+    pub alias: String,
+}
+
+//Add Taxonomy Alias Response
+#[derive(Debug, Serialize)]
+pub struct AddTaxonomyAliasResponse {
+    pub alias: TaxonomyAlias,
+    // Profiles that had this value dropped during normalisation and now have the term back
+    pub profiles_updated: u64,
+}
+
+//Unmatched Taxonomy Value
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct UnmatchedTaxonomyValue {
+    pub kind: TaxonomyKind,
+    pub value: String,
+    pub user_count: i64,
+}
+
+//List Taxonomy Terms
+//List Taxonomy Terms Input: HttpRequest(JWT Token), ListTaxonomyTermsParams (?kind=)
+//List Taxonomy Terms Output: Vec<TaxonomyTermWithAliases>, including retired terms
+pub async fn list_taxonomy_terms(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    params: web::Query<ListTaxonomyTermsParams>,
+) -> impl Responder {
+    if let Err(resp) = ensure_admin(&req) {
+        return resp;
+    }
+
+    let terms_query = "
+        SELECT * FROM taxonomy_terms
+        WHERE $1::taxonomy_kind IS NULL OR kind = $1
+        ORDER BY kind, position, label
+    ";
+    let terms = match sqlx::query_as::<_, TaxonomyTerm>(terms_query)
+        .bind(params.kind)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(terms) => terms,
+        Err(e) => {
+            error!("Failed to list taxonomy terms: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to list taxonomy terms");
+        }
+    };
+
+    let aliases_query = "
+        SELECT * FROM taxonomy_aliases
+        WHERE $1::taxonomy_kind IS NULL OR kind = $1
+        ORDER BY alias
+    ";
+    let aliases = match sqlx::query_as::<_, TaxonomyAlias>(aliases_query)
+        .bind(params.kind)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(aliases) => aliases,
+        Err(e) => {
+            error!("Failed to list taxonomy aliases: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to list taxonomy terms");
+        }
+    };
+
+    let terms: Vec<TaxonomyTermWithAliases> = terms
+        .into_iter()
+        .map(|term| TaxonomyTermWithAliases {
+            aliases: aliases
+                .iter()
+                .filter(|a| a.term_id == term.term_id)
+                .map(|a| a.alias.clone())
+                .collect(),
+            term,
+        })
+        .collect();
+
+    HttpResponse::Ok().json(terms)
+}
+
+//Create Taxonomy Term
+//Create Taxonomy Term Input: HttpRequest(JWT Token), CreateTaxonomyTermRequest
+//Create Taxonomy Term Output: TaxonomyTermWithAliases
+pub async fn create_taxonomy_term(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    payload: web::Json<CreateTaxonomyTermRequest>,
+) -> impl Responder {
+    if let Err(resp) = ensure_admin(&req) {
+        return resp;
+    }
+
+    let code = code_key(&payload.code);
+    let label = payload.label.trim();
+    if code.is_empty() || label.is_empty() {
+        return HttpResponse::BadRequest().body("Code and label cannot be empty");
+    }
+    let aliases: Vec<String> = payload
+        .aliases
+        .iter()
+        .flatten()
+        .map(|a| alias_key(a))
+        .filter(|a| !a.is_empty() && *a != code)
+        .collect();
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            error!("Failed to start transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to create taxonomy term");
+        }
+    };
+
+    let insert_query = "
+        INSERT INTO taxonomy_terms (kind, code, label, position)
+        VALUES ($1, $2, $3, $4)
+        RETURNING *
+    ";
+    let term = match sqlx::query_as::<_, TaxonomyTerm>(insert_query)
+        .bind(payload.kind)
+        .bind(&code)
+        .bind(label)
+        .bind(payload.position.unwrap_or(0))
+        .fetch_one(&mut *tx)
+        .await
+    {
+        Ok(term) => term,
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return HttpResponse::Conflict().body("A term with this code already exists");
+        }
+        Err(e) => {
+            error!("Failed to create taxonomy term: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to create taxonomy term");
+        }
+    };
+
+    for alias in &aliases {
+        let alias_query = "INSERT INTO taxonomy_aliases (kind, alias, term_id) VALUES ($1, $2, $3)";
+        match sqlx::query(alias_query)
+            .bind(payload.kind)
+            .bind(alias)
+            .bind(term.term_id)
+            .execute(&mut *tx)
+            .await
+        {
+            Ok(_) => {}
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                return HttpResponse::Conflict()
+                    .body(format!("Alias '{}' already belongs to another term", alias));
+            }
+            Err(e) => {
+                error!("Failed to add taxonomy alias: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to create taxonomy term");
+            }
+        }
+    }
+
+    if let Err(e) = tx.commit().await {
+        error!("Failed to commit taxonomy term: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to create taxonomy term");
+    }
+
+    // Values dropped during normalisation may match the new term or one of its aliases
+    for alias in aliases.iter().chain(std::iter::once(&code)) {
+        if let Err(e) = apply_alias_to_unmatched(pool.get_ref(), term.kind, alias, &code).await {
+            error!("Failed to apply taxonomy term to unmatched values: {:?}", e);
+        }
+    }
+
+    HttpResponse::Ok().json(TaxonomyTermWithAliases { term, aliases })
+}
+
+//Update Taxonomy Term
+//Update Taxonomy Term Input: HttpRequest(JWT Token), Path (/taxonomy/terms/{term_id}), UpdateTaxonomyTermRequest
+//Update Taxonomy Term Output: TaxonomyTerm
+//Retired terms stay on existing profiles but can't be picked again
+pub async fn update_taxonomy_term(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    payload: web::Json<UpdateTaxonomyTermRequest>,
+) -> impl Responder {
+    if let Err(resp) = ensure_admin(&req) {
+        return resp;
+    }
+
+    let label = payload.label.as_deref().map(str::trim);
+    if label == Some("") {
+        return HttpResponse::BadRequest().body("Label cannot be empty");
+    }
+
+    let update_query = "
+        UPDATE taxonomy_terms SET
+            label = COALESCE($1, label),
+            position = COALESCE($2, position),
+            active = COALESCE($3, active)
+        WHERE term_id = $4
+        RETURNING *
+    ";
+    match sqlx::query_as::<_, TaxonomyTerm>(update_query)
+        .bind(label)
+        .bind(payload.position)
+        .bind(payload.active)
+        .bind(path.into_inner())
+        .fetch_optional(pool.get_ref())
+        .await
+    {
+        Ok(Some(term)) => HttpResponse::Ok().json(term),
+        Ok(None) => HttpResponse::NotFound().body("Taxonomy term not found"),
+        Err(e) => {
+            error!("Failed to update taxonomy term: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update taxonomy term")
+        }
+    }
+}
+
+//Add Taxonomy Alias
+//Add Taxonomy Alias Input: HttpRequest(JWT Token), Path (/taxonomy/terms/{term_id}/aliases), AddTaxonomyAliasRequest
+//Add Taxonomy Alias Output: AddTaxonomyAliasResponse
+pub async fn add_taxonomy_alias(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    payload: web::Json<AddTaxonomyAliasRequest>,
+) -> impl Responder {
+    if let Err(resp) = ensure_admin(&req) {
+        return resp;
+    }
+
+    let alias = alias_key(&payload.alias);
+    if alias.is_empty() {
+        return HttpResponse::BadRequest().body("Alias cannot be empty");
+    }
+
+    let term =
+        match sqlx::query_as::<_, TaxonomyTerm>("SELECT * FROM taxonomy_terms WHERE term_id = $1")
+            .bind(path.into_inner())
+            .fetch_optional(pool.get_ref())
+            .await
+        {
+            Ok(Some(term)) => term,
+            Ok(None) => return HttpResponse::NotFound().body("Taxonomy term not found"),
+            Err(e) => {
+                error!("Failed to fetch taxonomy term: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to add alias");
+            }
+        };
+
+    let insert_query = "
+        INSERT INTO taxonomy_aliases (kind, alias, term_id)
+        VALUES ($1, $2, $3)
+        RETURNING *
+    ";
+    let created = match sqlx::query_as::<_, TaxonomyAlias>(insert_query)
+        .bind(term.kind)
+        .bind(&alias)
+        .bind(term.term_id)
+        .fetch_one(pool.get_ref())
+        .await
+    {
+        Ok(created) => created,
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+            return HttpResponse::Conflict().body("This alias already belongs to a term");
+        }
+        Err(e) => {
+            error!("Failed to add taxonomy alias: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to add alias");
+        }
+    };
+
+    let profiles_updated =
+        match apply_alias_to_unmatched(pool.get_ref(), term.kind, &alias, &term.code).await {
+            Ok(count) => count,
+            Err(e) => {
+                error!("Failed to apply alias to unmatched values: {:?}", e);
+                0
+            }
+        };
+
+    HttpResponse::Ok().json(AddTaxonomyAliasResponse {
+        alias: created,
+        profiles_updated,
+    })
+}
+
+//Delete Taxonomy Alias
+//Delete Taxonomy Alias Input: HttpRequest(JWT Token), Path (/taxonomy/aliases/{kind}/{alias})
+//Delete Taxonomy Alias Output: Success message
+pub async fn delete_taxonomy_alias(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<(TaxonomyKind, String)>,
+) -> impl Responder {
+    if let Err(resp) = ensure_admin(&req) {
+        return resp;
+    }
+    let (kind, alias) = path.into_inner();
+
+    match sqlx::query("DELETE FROM taxonomy_aliases WHERE kind = $1 AND alias = $2")
+        .bind(kind)
+        .bind(alias_key(&alias))
+        .execute(pool.get_ref())
+        .await
+    {
+        Ok(result) if result.rows_affected() > 0 => {
+            HttpResponse::Ok().body("Alias deleted successfully")
+        }
+        Ok(_) => HttpResponse::NotFound().body("Alias not found"),
+        Err(e) => {
+            error!("Failed to delete taxonomy alias: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to delete alias")
+        }
+    }
+}
+
+//List Unmatched Taxonomy Values
+//List Unmatched Taxonomy Values Input: HttpRequest(JWT Token)
+//List Unmatched Taxonomy Values Output: Vec<UnmatchedTaxonomyValue>
+//Values dropped from profiles when they were normalised, most common first
+pub async fn list_unmatched_taxonomy_values(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+) -> impl Responder {
+    if let Err(resp) = ensure_admin(&req) {
+        return resp;
+    }
+
+    let query = "
+        SELECT kind, value, COUNT(*) AS user_count
+        FROM taxonomy_unmatched_values
+        GROUP BY kind, value
+        ORDER BY user_count DESC, kind, value
+    ";
+    match sqlx::query_as::<_, UnmatchedTaxonomyValue>(query)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(values) => HttpResponse::Ok().json(values),
+        Err(e) => {
+            error!("Failed to list unmatched taxonomy values: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to list unmatched values")
+        }
+    }
+}
+
+//Update Matching Config Request
+//Weights are relative to each other; each factor's share of the score is its weight over the total
+#[derive(Debug, Deserialize)]
+pub struct UpdateMatchingConfigRequest {
+    pub age_weight: f32,
+    pub location_weight: f32,
+    pub interests_weight: f32,
+    pub experience_weight: f32,
+    pub availability_weight: f32,
+    pub language_weight: f32,
+    pub age_range_years: f32,
+    pub require_shared_language: bool,
+    pub max_distance_km: Option<f32>,
+    pub load_penalty: f32,
+    pub rematch_cooldown_days: i32,
+    pub max_pending_requests: i32,
+    pub request_expiry_days: i32,
+}
+
+//Get Matching Config
+//Get Matching Config Input: HttpRequest(JWT Token)
+//Get Matching Config Output: MatchingConfig
+pub async fn get_matching_config(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    if let Err(resp) = ensure_admin(&req) {
+        return resp;
+    }
+
+    match load_matching_config(pool.get_ref()).await {
+        Ok(config) => HttpResponse::Ok().json(config),
+        Err(e) => {
+            error!("Failed to load matching config: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to load matching config")
+        }
+    }
+}
+
+//Update Matching Config
+//Update Matching Config Input: HttpRequest(JWT Token), UpdateMatchingConfigRequest
+//Update Matching Config Output: MatchingConfig
+pub async fn update_matching_config(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    payload: web::Json<UpdateMatchingConfigRequest>,
+) -> impl Responder {
+    if let Err(resp) = ensure_admin(&req) {
+        return resp;
+    }
+    let admin_id = get_user_id_from_request(&req);
+
+    // Validate input
+    let weights = [
+        payload.age_weight,
+        payload.location_weight,
+        payload.interests_weight,
+        payload.experience_weight,
+        payload.availability_weight,
+        payload.language_weight,
+    ];
+    if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+        return HttpResponse::BadRequest().body("Weights must be zero or more");
+    }
+    if weights.iter().sum::<f32>() <= 0.0 {
+        return HttpResponse::BadRequest().body("At least one weight must be above zero");
+    }
+    if !payload.age_range_years.is_finite() || payload.age_range_years <= 0.0 {
+        return HttpResponse::BadRequest().body("Age range must be above zero");
+    }
+    if payload
+        .max_distance_km
+        .is_some_and(|d| !d.is_finite() || d <= 0.0)
+    {
+        return HttpResponse::BadRequest().body("Max distance must be above zero");
+    }
+    if !(0.0..=100.0).contains(&payload.load_penalty) {
+        return HttpResponse::BadRequest().body("Load penalty must be between 0 and 100");
+    }
+    if payload.rematch_cooldown_days < 0 {
+        return HttpResponse::BadRequest().body("Rematch cooldown cannot be negative");
+    }
+    if payload.max_pending_requests < 1 {
+        return HttpResponse::BadRequest()
+            .body("Members must be allowed at least one pending request");
+    }
+    if payload.request_expiry_days < 1 {
+        return HttpResponse::BadRequest().body("Requests must stay open for at least one day");
+    }
+
+    let update_query = "
+        INSERT INTO matching_config (
+            id, age_weight, location_weight, interests_weight, experience_weight,
+            availability_weight, language_weight, age_range_years,
+            require_shared_language, max_distance_km, load_penalty, rematch_cooldown_days,
+            max_pending_requests, request_expiry_days, updated_by, updated_at
+        )
+        VALUES (TRUE, $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, NOW())
+        ON CONFLICT (id) DO UPDATE SET
+            age_weight = EXCLUDED.age_weight,
+            location_weight = EXCLUDED.location_weight,
+            interests_weight = EXCLUDED.interests_weight,
+            experience_weight = EXCLUDED.experience_weight,
+            availability_weight = EXCLUDED.availability_weight,
+            language_weight = EXCLUDED.language_weight,
+            age_range_years = EXCLUDED.age_range_years,
+            require_shared_language = EXCLUDED.require_shared_language,
+            max_distance_km = EXCLUDED.max_distance_km,
+            load_penalty = EXCLUDED.load_penalty,
+            rematch_cooldown_days = EXCLUDED.rematch_cooldown_days,
+            max_pending_requests = EXCLUDED.max_pending_requests,
+            request_expiry_days = EXCLUDED.request_expiry_days,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = EXCLUDED.updated_at
+        RETURNING age_weight, location_weight, interests_weight, experience_weight,
+                  availability_weight, language_weight, age_range_years,
+                  require_shared_language, max_distance_km, load_penalty, rematch_cooldown_days,
+                  max_pending_requests, request_expiry_days, updated_by, updated_at
+    ";
+    match sqlx::query_as::<_, MatchingConfig>(update_query)
+        .bind(payload.age_weight)
+        .bind(payload.location_weight)
+        .bind(payload.interests_weight)
+        .bind(payload.experience_weight)
+        .bind(payload.availability_weight)
+        .bind(payload.language_weight)
+        .bind(payload.age_range_years)
+        .bind(payload.require_shared_language)
+        .bind(payload.max_distance_km)
+        .bind(payload.load_penalty)
+        .bind(payload.rematch_cooldown_days)
+        .bind(payload.max_pending_requests)
+        .bind(payload.request_expiry_days)
+        .bind(admin_id)
+        .fetch_one(pool.get_ref())
+        .await
+    {
+        Ok(config) => HttpResponse::Ok().json(config),
+        Err(e) => {
+            error!("Failed to update matching config: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update matching config")
+        }
+    }
+}
+
+//Get Audit Log
+//Get Audit Log Input: HttpRequest(JWT Token), Query (?target_user_id=&limit=&offset=)
+//Get Audit Log Output: Vec<AdminAuditEntry>
+pub async fn get_audit_log(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    query: web::Query<GetAuditLogParams>,
+) -> impl Responder {
+    // Check if user is admin
+    if let Err(response) = ensure_admin(&req) {
+        return response;
+    }
+
+    let audit_query = "
+        SELECT * FROM admin_audit_log
+        WHERE ($1::uuid IS NULL OR target_user_id = $1)
+        ORDER BY created_at DESC
+        LIMIT $2 OFFSET $3
+    ";
+    match sqlx::query_as::<_, AdminAuditEntry>(audit_query)
+        .bind(query.target_user_id)
+        .bind(query.limit.unwrap_or(50).clamp(1, 200))
+        .bind(query.offset.unwrap_or(0).max(0))
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => {
+            error!("Failed to fetch audit log: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch audit log")
+        }
+    }
+}
+
 //Config Admin Routes
 // GET /admin/sponsor-applications
 // POST /admin/sponsor-applications/review
+// GET /admin/sponsor-applications/{application_id}
+// POST /admin/sponsors/revoke
 // GET /admin/support-groups
 // POST /admin/support-groups/review
 // GET /admin/resources
@@ -1592,6 +2568,17 @@ pub async fn get_admin_stats(pool: web::Data<PgPool>, req: HttpRequest) -> impl
 // GET /admin/users/banned
 // GET /admin/users
 // GET /admin/stats
+// GET /admin/storage/gc-report
+// POST /admin/storage/gc-run
+// GET /admin/taxonomy/terms
+// POST /admin/taxonomy/terms
+// PATCH /admin/taxonomy/terms/{term_id}
+// POST /admin/taxonomy/terms/{term_id}/aliases
+// DELETE /admin/taxonomy/aliases/{kind}/{alias}
+// GET /admin/taxonomy/unmatched
+// GET /admin/matching/config
+// PUT /admin/matching/config
+// GET /admin/audit-log
 pub fn config_admin_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/admin")
@@ -1604,6 +2591,11 @@ pub fn config_admin_routes(cfg: &mut web::ServiceConfig) {
                 "/sponsor-applications/review",
                 web::post().to(review_sponsor_application),
             )
+            .route(
+                "/sponsor-applications/{application_id}",
+                web::get().to(get_sponsor_application),
+            )
+            .route("/sponsors/revoke", web::post().to(revoke_sponsor))
             // Support group routes
             .route(
                 "/support-groups/pending",
@@ -1625,6 +2617,33 @@ pub fn config_admin_routes(cfg: &mut web::ServiceConfig) {
             .route("/users/banned", web::get().to(get_banned_users))
             .route("/users", web::get().to(get_all_users))
             // Admin dashboard routes
-            .route("/stats", web::get().to(get_admin_stats)),
+            .route("/stats", web::get().to(get_admin_stats))
+            // Storage maintenance routes
+            .route("/storage/gc-report", web::get().to(get_storage_gc_report))
+            .route("/storage/gc-run", web::post().to(run_storage_gc_now))
+            // Profile taxonomy routes
+            .route("/taxonomy/terms", web::get().to(list_taxonomy_terms))
+            .route("/taxonomy/terms", web::post().to(create_taxonomy_term))
+            .route(
+                "/taxonomy/terms/{term_id}",
+                web::patch().to(update_taxonomy_term),
+            )
+            .route(
+                "/taxonomy/terms/{term_id}/aliases",
+                web::post().to(add_taxonomy_alias),
+            )
+            .route(
+                "/taxonomy/aliases/{kind}/{alias}",
+                web::delete().to(delete_taxonomy_alias),
+            )
+            .route(
+                "/taxonomy/unmatched",
+                web::get().to(list_unmatched_taxonomy_values),
+            )
+            // Sponsor matching routes
+            .route("/matching/config", web::get().to(get_matching_config))
+            .route("/matching/config", web::put().to(update_matching_config))
+            // Audit routes
+            .route("/audit-log", web::get().to(get_audit_log)),
     );
 }
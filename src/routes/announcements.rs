@@ -0,0 +1,60 @@
+use crate::handlers::auth::Claims;
+use crate::models::all_models::Announcement;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+const DEFAULT_ANNOUNCEMENT_LIMIT: i64 = 50;
+const MAX_ANNOUNCEMENT_LIMIT: i64 = 200;
+
+//List Announcements Params
+#[derive(Debug, Deserialize)]
+pub struct ListAnnouncementsParams {
+    pub limit: Option<i64>,
+}
+
+//List Announcements
+//List Announcements Input: HttpRequest(JWT Token), Query (?limit=)
+//List Announcements Output: Vec<Announcement>
+//Announcements addressed to the caller, to their role, or to everyone
+pub async fn list_announcements(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    query: web::Query<ListAnnouncementsParams>,
+) -> impl Responder {
+    let (user_id, role) = match req.extensions().get::<Claims>() {
+        Some(claims) => (claims.id, claims.role),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_ANNOUNCEMENT_LIMIT)
+        .clamp(1, MAX_ANNOUNCEMENT_LIMIT);
+
+    let list_query = "
+        SELECT * FROM announcements
+        WHERE recipient_id = $1
+           OR (recipient_id IS NULL AND (recipient_role IS NULL OR recipient_role = $2))
+        ORDER BY created_at DESC
+        LIMIT $3
+    ";
+    match sqlx::query_as::<_, Announcement>(list_query)
+        .bind(user_id)
+        .bind(role)
+        .bind(limit)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(announcements) => HttpResponse::Ok().json(announcements),
+        Err(e) => {
+            eprintln!("Error fetching announcements: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch announcements")
+        }
+    }
+}
+
+//Config Announcement Routes
+// GET /announcements
+pub fn config_announcement_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/announcements").route("", web::get().to(list_announcements)));
+}
@@ -0,0 +1,499 @@
+use crate::handlers::auth::Claims;
+use crate::handlers::b2_storage::{B2Client, StorageBucket};
+use crate::handlers::upload_stream::{
+    read_field_head, sniff_content_type, stream_field_to_b2, UploadError,
+};
+use crate::models::all_models::{Attachment, AttachmentOwnerType, UserRole};
+use crate::routes::posts::can_view_post;
+use actix_multipart::Multipart;
+use actix_web::{http::header, web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use futures::TryStreamExt;
+use log::{error, info};
+use sanitize_filename::sanitize;
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Total attachment storage allowed per user
+const USER_STORAGE_QUOTA_BYTES: i64 = 250 * 1024 * 1024;
+
+/// How long a signed attachment download link stays valid
+const DOWNLOAD_LINK_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Bytes needed to sniff a file's type
+const SNIFF_LEN: usize = 16;
+
+const IMAGE_TYPES: &[&str] = &["image/jpeg", "image/png", "image/webp"];
+
+const DOCUMENT_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/png",
+    "image/webp",
+    "application/pdf",
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+];
+
+const CHAT_TYPES: &[&str] = &["image/jpeg", "image/png", "image/webp", "application/pdf"];
+
+//Attachment Policy
+struct AttachmentPolicy {
+    allowed_types: &'static [&'static str],
+    max_bytes: usize,
+}
+
+// MIME allowlist and size limit for each kind of owner
+fn policy_for(owner_type: AttachmentOwnerType) -> AttachmentPolicy {
+    match owner_type {
+        AttachmentOwnerType::Resource => AttachmentPolicy {
+            allowed_types: DOCUMENT_TYPES,
+            max_bytes: 50 * 1024 * 1024,
+        },
+        AttachmentOwnerType::Post => AttachmentPolicy {
+            allowed_types: IMAGE_TYPES,
+            max_bytes: 10 * 1024 * 1024,
+        },
+        AttachmentOwnerType::Message => AttachmentPolicy {
+            allowed_types: CHAT_TYPES,
+            max_bytes: 20 * 1024 * 1024,
+        },
+        AttachmentOwnerType::GroupChatMessage => AttachmentPolicy {
+            allowed_types: DOCUMENT_TYPES,
+            max_bytes: 20 * 1024 * 1024,
+        },
+        AttachmentOwnerType::SponsorApplication => AttachmentPolicy {
+            allowed_types: DOCUMENT_TYPES,
+            max_bytes: 10 * 1024 * 1024,
+        },
+    }
+}
+
+// Helper function: Check if a user may attach files to an item (they must have authored it)
+async fn can_attach(
+    pool: &PgPool,
+    owner_type: AttachmentOwnerType,
+    owner_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let query = match owner_type {
+        AttachmentOwnerType::Resource => {
+            "SELECT EXISTS(SELECT 1 FROM resources WHERE resource_id = $1 AND contributor_id = $2)"
+        }
+        AttachmentOwnerType::Post => {
+            "SELECT EXISTS(SELECT 1 FROM posts WHERE post_id = $1 AND author_id = $2)"
+        }
+        AttachmentOwnerType::Message => {
+            "SELECT EXISTS(SELECT 1 FROM messages WHERE message_id = $1 AND sender_id = $2 AND deleted = false)"
+        }
+        AttachmentOwnerType::GroupChatMessage => {
+            "SELECT EXISTS(SELECT 1 FROM group_chat_messages WHERE group_chat_message_id = $1 AND sender_id = $2 AND deleted = false)"
+        }
+        // Documents can only be added while the application is still open
+        AttachmentOwnerType::SponsorApplication => {
+            "SELECT EXISTS(SELECT 1 FROM sponsor_applications WHERE application_id = $1 AND user_id = $2 AND status IN ('pending', 'info_requested'))"
+        }
+    };
+
+    sqlx::query_scalar(query)
+        .bind(owner_id)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+}
+
+// Helper function: Check if a user may see an item's attachments.
+// Private message attachments are limited to the two participants, group chat
+// attachments to chat members, unapproved resources to their contributor and admins,
+// post attachments to users who can see the post (no block with its author, and
+// membership for support group posts), and sponsor application documents to the
+// applicant and admins.
+pub async fn can_view_attachments(
+    pool: &PgPool,
+    owner_type: AttachmentOwnerType,
+    owner_id: Uuid,
+    claims: &Claims,
+) -> Result<bool, sqlx::Error> {
+    let (user_id, role) = (claims.id, claims.role);
+    match owner_type {
+        AttachmentOwnerType::Resource => {
+            let query = "
+                SELECT EXISTS(
+                    SELECT 1 FROM resources
+                    WHERE resource_id = $1 AND (approved = true OR contributor_id = $2 OR $3)
+                )";
+            sqlx::query_scalar(query)
+                .bind(owner_id)
+                .bind(user_id)
+                .bind(role == UserRole::Admin)
+                .fetch_one(pool)
+                .await
+        }
+        AttachmentOwnerType::Post => can_view_post(pool, owner_id, claims).await,
+        AttachmentOwnerType::Message => {
+            let query = "
+                SELECT EXISTS(
+                    SELECT 1 FROM messages
+                    WHERE message_id = $1 AND (sender_id = $2 OR receiver_id = $2)
+                )";
+            sqlx::query_scalar(query)
+                .bind(owner_id)
+                .bind(user_id)
+                .fetch_one(pool)
+                .await
+        }
+        AttachmentOwnerType::GroupChatMessage => {
+            let query = "
+                SELECT EXISTS(
+                    SELECT 1 FROM group_chat_messages gcm
+                    JOIN group_chat_members gcmb ON gcm.group_chat_id = gcmb.group_chat_id
+                    WHERE gcm.group_chat_message_id = $1 AND gcmb.user_id = $2
+                )";
+            sqlx::query_scalar(query)
+                .bind(owner_id)
+                .bind(user_id)
+                .fetch_one(pool)
+                .await
+        }
+        AttachmentOwnerType::SponsorApplication => {
+            let query = "
+                SELECT EXISTS(
+                    SELECT 1 FROM sponsor_applications
+                    WHERE application_id = $1 AND (user_id = $2 OR $3)
+                )";
+            sqlx::query_scalar(query)
+                .bind(owner_id)
+                .bind(user_id)
+                .bind(role == UserRole::Admin)
+                .fetch_one(pool)
+                .await
+        }
+    }
+}
+
+//Upload Attachment
+//Upload Attachment Input: HttpRequest(JWT Token), Path (/attachments/{owner_type}/{owner_id}), Multipart (file)
+//Upload Attachment Output: Attachment
+pub async fn upload_attachment(
+    pool: web::Data<PgPool>,
+    b2_client: web::Data<B2Client>,
+    req: HttpRequest,
+    path: web::Path<(AttachmentOwnerType, Uuid)>,
+    mut payload: Multipart,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let (owner_type, owner_id) = path.into_inner();
+    let policy = policy_for(owner_type);
+
+    match can_attach(pool.get_ref(), owner_type, owner_id, claims.id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return HttpResponse::Forbidden().body("You can only attach files to your own content");
+        }
+        Err(e) => {
+            eprintln!("Error checking attachment owner: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to verify ownership");
+        }
+    }
+
+    // Check how much of the user's storage quota is left
+    let used_query = "SELECT COALESCE(SUM(size_bytes), 0)::BIGINT FROM attachments WHERE uploader_id = $1";
+    let used: i64 = match sqlx::query_scalar(used_query)
+        .bind(claims.id)
+        .fetch_one(pool.get_ref())
+        .await
+    {
+        Ok(used) => used,
+        Err(e) => {
+            eprintln!("Error checking storage quota: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to check storage quota");
+        }
+    };
+    let remaining = USER_STORAGE_QUOTA_BYTES - used;
+    if remaining <= 0 {
+        return HttpResponse::PayloadTooLarge().body("Storage quota exceeded");
+    }
+    let max_bytes = policy.max_bytes.min(remaining as usize);
+
+    while let Ok(Some(mut field)) = payload.try_next().await {
+        let (field_name, original_name) = match field.content_disposition() {
+            Some(cd) => (
+                cd.get_name().unwrap_or_default().to_string(),
+                cd.get_filename().map(sanitize).unwrap_or_default(),
+            ),
+            None => continue,
+        };
+
+        if field_name != "file" {
+            continue;
+        }
+
+        if original_name.is_empty() {
+            return HttpResponse::BadRequest().body("Attachment filename is required");
+        }
+
+        // Sniff the real type from the first bytes rather than trusting the client
+        let head = match read_field_head(&mut field, SNIFF_LEN).await {
+            Ok(head) => head,
+            Err(e) => {
+                error!("Failed to read attachment: {}", e);
+                return HttpResponse::BadRequest().body(e.to_string());
+            }
+        };
+
+        let content_type = match sniff_content_type(&head, &original_name) {
+            Some(ct) if policy.allowed_types.contains(&ct) => ct,
+            _ => {
+                return HttpResponse::UnsupportedMediaType()
+                    .body("This file type is not allowed here");
+            }
+        };
+
+        let attachment_id = Uuid::new_v4();
+        let file_name = format!(
+            "attachments/{}/{}/{}_{}",
+            owner_type.as_str(),
+            owner_id,
+            attachment_id.simple(),
+            original_name.replace(' ', "_")
+        );
+
+        let stored = match stream_field_to_b2(
+            field,
+            head,
+            b2_client.get_ref(),
+            StorageBucket::Private,
+            &file_name,
+            content_type,
+            max_bytes,
+        )
+        .await
+        {
+            Ok(stored) => stored,
+            Err(UploadError::TooLarge(limit)) => {
+                let message = if limit < policy.max_bytes {
+                    "File exceeds your remaining storage quota".to_string()
+                } else {
+                    format!("File too large (max {}MB)", limit / (1024 * 1024))
+                };
+                return HttpResponse::PayloadTooLarge().body(message);
+            }
+            Err(e) => {
+                error!("Failed to store attachment: {}", e);
+                return HttpResponse::InternalServerError().body("Failed to upload attachment");
+            }
+        };
+
+        let insert_query = "
+            INSERT INTO attachments
+                (attachment_id, uploader_id, owner_type, owner_id, file_name, original_name, content_type, size_bytes, sha1)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING attachment_id, uploader_id, owner_type, owner_id, file_name, original_name, content_type, size_bytes, sha1, created_at
+        ";
+        return match sqlx::query_as::<_, Attachment>(insert_query)
+            .bind(attachment_id)
+            .bind(claims.id)
+            .bind(owner_type)
+            .bind(owner_id)
+            .bind(&stored.file_name)
+            .bind(&original_name)
+            .bind(content_type)
+            .bind(stored.size as i64)
+            .bind(&stored.sha1)
+            .fetch_one(pool.get_ref())
+            .await
+        {
+            Ok(attachment) => {
+                info!("Stored attachment {} ({} bytes)", attachment_id, stored.size);
+                HttpResponse::Ok().json(attachment)
+            }
+            Err(e) => {
+                eprintln!("Error saving attachment: {:?}", e);
+                // Don't leave an unreferenced file behind
+                if let Err(e) = b2_client
+                    .delete_file_from(StorageBucket::Private, &stored.file_name)
+                    .await
+                {
+                    error!("Failed to clean up attachment {}: {:?}", stored.file_name, e);
+                }
+                HttpResponse::InternalServerError().body("Failed to save attachment")
+            }
+        };
+    }
+
+    HttpResponse::BadRequest().body("No file provided")
+}
+
+//List Attachments
+//List Attachments Input: HttpRequest(JWT Token), Path (/attachments/{owner_type}/{owner_id})
+//List Attachments Output: Vec<Attachment>
+pub async fn list_attachments(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<(AttachmentOwnerType, Uuid)>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let (owner_type, owner_id) = path.into_inner();
+
+    match can_view_attachments(pool.get_ref(), owner_type, owner_id, &claims).await {
+        Ok(true) => {}
+        Ok(false) => return HttpResponse::NotFound().body("Attachments not found"),
+        Err(e) => {
+            eprintln!("Error checking attachment access: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to verify access");
+        }
+    }
+
+    let query = "
+        SELECT * FROM attachments
+        WHERE owner_type = $1 AND owner_id = $2
+        ORDER BY created_at ASC
+    ";
+    match sqlx::query_as::<_, Attachment>(query)
+        .bind(owner_type)
+        .bind(owner_id)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(attachments) => HttpResponse::Ok().json(attachments),
+        Err(e) => {
+            eprintln!("Error listing attachments: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to list attachments")
+        }
+    }
+}
+
+//Download Attachment
+//Download Attachment Input: HttpRequest(JWT Token), Path (/attachments/{attachment_id}/download)
+//Download Attachment Output: Redirect to a short-lived signed URL
+pub async fn download_attachment(
+    pool: web::Data<PgPool>,
+    b2_client: web::Data<B2Client>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let attachment_id = path.into_inner();
+
+    let attachment = match sqlx::query_as::<_, Attachment>(
+        "SELECT * FROM attachments WHERE attachment_id = $1",
+    )
+    .bind(attachment_id)
+    .fetch_optional(pool.get_ref())
+    .await
+    {
+        Ok(Some(attachment)) => attachment,
+        Ok(None) => return HttpResponse::NotFound().body("Attachment not found"),
+        Err(e) => {
+            eprintln!("Error fetching attachment: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch attachment");
+        }
+    };
+
+    match can_view_attachments(
+        pool.get_ref(),
+        attachment.owner_type,
+        attachment.owner_id,
+        &claims,
+    )
+    .await
+    {
+        Ok(true) => {}
+        // Same response as a missing attachment so IDs can't be probed
+        Ok(false) => return HttpResponse::NotFound().body("Attachment not found"),
+        Err(e) => {
+            eprintln!("Error checking attachment access: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to verify access");
+        }
+    }
+
+    // Attachments live in the private bucket, so hand out a link that expires
+    match b2_client
+        .signed_download_url(&attachment.file_name, DOWNLOAD_LINK_TTL)
+        .await
+    {
+        Ok(url) => HttpResponse::Found()
+            .insert_header((header::LOCATION, url))
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .finish(),
+        Err(e) => {
+            error!("Failed to build download URL: {:?}", e);
+            HttpResponse::InternalServerError().body("Storage service unavailable")
+        }
+    }
+}
+
+//Delete Attachment
+//Delete Attachment Input: HttpRequest(JWT Token), Path (/attachments/{attachment_id})
+//Delete Attachment Output: Success message
+pub async fn delete_attachment(
+    pool: web::Data<PgPool>,
+    b2_client: web::Data<B2Client>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let attachment_id = path.into_inner();
+
+    let query = "
+        DELETE FROM attachments
+        WHERE attachment_id = $1 AND (uploader_id = $2 OR $3)
+        RETURNING file_name
+    ";
+    let file_name: Option<String> = match sqlx::query_scalar(query)
+        .bind(attachment_id)
+        .bind(claims.id)
+        .bind(claims.role == UserRole::Admin)
+        .fetch_optional(pool.get_ref())
+        .await
+    {
+        Ok(name) => name,
+        Err(e) => {
+            eprintln!("Error deleting attachment: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to delete attachment");
+        }
+    };
+
+    match file_name {
+        Some(name) => {
+            if let Err(e) = b2_client
+                .delete_file_from(StorageBucket::Private, &name)
+                .await
+            {
+                error!("Failed to delete attachment file {}: {:?}", name, e);
+            }
+            HttpResponse::Ok().body("Attachment deleted successfully")
+        }
+        None => HttpResponse::NotFound().body("Attachment not found or not owned by you"),
+    }
+}
+
+//Config Attachment Routes
+// POST /attachments/{owner_type}/{owner_id}
+// GET /attachments/{owner_type}/{owner_id}
+// GET /attachments/{attachment_id}/download
+// DELETE /attachments/{attachment_id}
+pub fn config_attachment_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/attachments")
+            .route(
+                "/{attachment_id}/download",
+                web::get().to(download_attachment),
+            )
+            .route("/{attachment_id}", web::delete().to(delete_attachment))
+            .route("/{owner_type}/{owner_id}", web::post().to(upload_attachment))
+            .route("/{owner_type}/{owner_id}", web::get().to(list_attachments)),
+    );
+}
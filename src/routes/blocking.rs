@@ -0,0 +1,269 @@
+use crate::handlers::auth::Claims;
+use crate::models::all_models::{UserBlock, UserMute};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+//Listed User
+#[derive(Debug, Serialize, FromRow)]
+pub struct ListedUser {
+    pub user_id: Uuid,
+    pub username: String,
+    pub avatar_url: String,
+    pub created_at: NaiveDateTime,
+}
+
+// Helper function: Make sure the target exists and isn't the caller
+async fn validate_target(pool: &PgPool, user_id: Uuid, target_id: Uuid) -> Option<HttpResponse> {
+    if user_id == target_id {
+        return Some(HttpResponse::BadRequest().body("You can't do this to yourself"));
+    }
+    match sqlx::query_scalar::<_, bool>("SELECT EXISTS(SELECT 1 FROM users WHERE user_id = $1)")
+        .bind(target_id)
+        .fetch_one(pool)
+        .await
+    {
+        Ok(true) => None,
+        Ok(false) => Some(HttpResponse::NotFound().body("User not found")),
+        Err(e) => {
+            eprintln!("Error checking user: {:?}", e);
+            Some(HttpResponse::InternalServerError().body("Failed to find user"))
+        }
+    }
+}
+
+//Block User
+//Block User Input: HttpRequest(JWT Token), Path (/blocks/{user_id})
+//Block User Output: UserBlock
+pub async fn block_user(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let blocked_id = path.into_inner();
+
+    if let Some(resp) = validate_target(pool.get_ref(), claims.id, blocked_id).await {
+        return resp;
+    }
+
+    let insert_query = "
+        INSERT INTO user_blocks (blocker_id, blocked_id)
+        VALUES ($1, $2)
+        ON CONFLICT (blocker_id, blocked_id) DO UPDATE SET blocker_id = EXCLUDED.blocker_id
+        RETURNING blocker_id, blocked_id, created_at
+    ";
+    let block = match sqlx::query_as::<_, UserBlock>(insert_query)
+        .bind(claims.id)
+        .bind(blocked_id)
+        .fetch_one(pool.get_ref())
+        .await
+    {
+        Ok(block) => block,
+        Err(e) => {
+            eprintln!("Error blocking user: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to block user");
+        }
+    };
+
+    // Open sponsor requests between the two can no longer go anywhere
+    let decline_query = "
+        UPDATE matching_requests SET status = 'declined'
+        WHERE status = 'pending'
+          AND ((member_id = $1 AND sponsor_id = $2) OR (member_id = $2 AND sponsor_id = $1))
+    ";
+    if let Err(e) = sqlx::query(decline_query)
+        .bind(claims.id)
+        .bind(blocked_id)
+        .execute(pool.get_ref())
+        .await
+    {
+        eprintln!("Error declining matching requests after block: {:?}", e);
+    }
+
+    HttpResponse::Ok().json(block)
+}
+
+//Unblock User
+//Unblock User Input: HttpRequest(JWT Token), Path (/blocks/{user_id})
+//Unblock User Output: Success message
+pub async fn unblock_user(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    match sqlx::query("DELETE FROM user_blocks WHERE blocker_id = $1 AND blocked_id = $2")
+        .bind(claims.id)
+        .bind(path.into_inner())
+        .execute(pool.get_ref())
+        .await
+    {
+        Ok(result) if result.rows_affected() > 0 => {
+            HttpResponse::Ok().body("User unblocked successfully")
+        }
+        Ok(_) => HttpResponse::NotFound().body("User is not blocked"),
+        Err(e) => {
+            eprintln!("Error unblocking user: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to unblock user")
+        }
+    }
+}
+
+//List Blocked Users
+//List Blocked Users Input: HttpRequest(JWT Token)
+//List Blocked Users Output: Vec<ListedUser>
+pub async fn list_blocked_users(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let query = "
+        SELECT u.user_id, u.username, u.avatar_url, b.created_at
+        FROM user_blocks b
+        JOIN users u ON b.blocked_id = u.user_id
+        WHERE b.blocker_id = $1
+        ORDER BY b.created_at DESC
+    ";
+    match sqlx::query_as::<_, ListedUser>(query)
+        .bind(claims.id)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(users) => HttpResponse::Ok().json(users),
+        Err(e) => {
+            eprintln!("Error listing blocked users: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to list blocked users")
+        }
+    }
+}
+
+//Mute User
+//Mute User Input: HttpRequest(JWT Token), Path (/mutes/{user_id})
+//Mute User Output: UserMute
+//Muting only hides the user's content from you; they are not told and can still contact you
+pub async fn mute_user(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let muted_id = path.into_inner();
+
+    if let Some(resp) = validate_target(pool.get_ref(), claims.id, muted_id).await {
+        return resp;
+    }
+
+    let insert_query = "
+        INSERT INTO user_mutes (muter_id, muted_id)
+        VALUES ($1, $2)
+        ON CONFLICT (muter_id, muted_id) DO UPDATE SET muter_id = EXCLUDED.muter_id
+        RETURNING muter_id, muted_id, created_at
+    ";
+    match sqlx::query_as::<_, UserMute>(insert_query)
+        .bind(claims.id)
+        .bind(muted_id)
+        .fetch_one(pool.get_ref())
+        .await
+    {
+        Ok(mute) => HttpResponse::Ok().json(mute),
+        Err(e) => {
+            eprintln!("Error muting user: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to mute user")
+        }
+    }
+}
+
+//Unmute User
+//Unmute User Input: HttpRequest(JWT Token), Path (/mutes/{user_id})
+//Unmute User Output: Success message
+pub async fn unmute_user(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    match sqlx::query("DELETE FROM user_mutes WHERE muter_id = $1 AND muted_id = $2")
+        .bind(claims.id)
+        .bind(path.into_inner())
+        .execute(pool.get_ref())
+        .await
+    {
+        Ok(result) if result.rows_affected() > 0 => {
+            HttpResponse::Ok().body("User unmuted successfully")
+        }
+        Ok(_) => HttpResponse::NotFound().body("User is not muted"),
+        Err(e) => {
+            eprintln!("Error unmuting user: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to unmute user")
+        }
+    }
+}
+
+//List Muted Users
+//List Muted Users Input: HttpRequest(JWT Token)
+//List Muted Users Output: Vec<ListedUser>
+pub async fn list_muted_users(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let query = "
+        SELECT u.user_id, u.username, u.avatar_url, m.created_at
+        FROM user_mutes m
+        JOIN users u ON m.muted_id = u.user_id
+        WHERE m.muter_id = $1
+        ORDER BY m.created_at DESC
+    ";
+    match sqlx::query_as::<_, ListedUser>(query)
+        .bind(claims.id)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(users) => HttpResponse::Ok().json(users),
+        Err(e) => {
+            eprintln!("Error listing muted users: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to list muted users")
+        }
+    }
+}
+
+//Config Blocking Routes
+// GET /blocks
+// POST /blocks/{user_id}
+// DELETE /blocks/{user_id}
+// GET /mutes
+// POST /mutes/{user_id}
+// DELETE /mutes/{user_id}
+pub fn config_blocking_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/blocks")
+            .route("", web::get().to(list_blocked_users))
+            .route("/{user_id}", web::post().to(block_user))
+            .route("/{user_id}", web::delete().to(unblock_user)),
+    )
+    .service(
+        web::scope("/mutes")
+            .route("", web::get().to(list_muted_users))
+            .route("/{user_id}", web::post().to(mute_user))
+            .route("/{user_id}", web::delete().to(unmute_user)),
+    );
+}
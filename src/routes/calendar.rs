@@ -0,0 +1,277 @@
+use crate::handlers::auth::Claims;
+use crate::handlers::calendar::{
+    group_feed_events, render_calendar, user_feed_events, CalendarConfig, CalendarEvent,
+};
+use crate::models::all_models::{CalendarFeed, SupportGroupStatus};
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+//Calendar Feed Response
+//Subscribe with `webcal_url`; `url` is the same feed over https for clients that want a plain link
+#[derive(Debug, Serialize)]
+pub struct CalendarFeedResponse {
+    pub url: String,
+    pub webcal_url: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// Helper function: An unguessable feed token (two random UUIDs, 244 random bits)
+fn new_feed_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+// Helper function: The user's active feed, created if they don't have one
+async fn active_feed(conn: &mut PgConnection, user_id: Uuid) -> Result<CalendarFeed, sqlx::Error> {
+    sqlx::query(
+        "INSERT INTO calendar_feeds (user_id, token) VALUES ($1, $2)
+         ON CONFLICT (user_id) WHERE revoked_at IS NULL DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(new_feed_token())
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query_as::<_, CalendarFeed>(
+        "SELECT * FROM calendar_feeds WHERE user_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(user_id)
+    .fetch_one(conn)
+    .await
+}
+
+// Helper function: Revoke the user's active feed, if any
+async fn revoke_feed(conn: &mut PgConnection, user_id: Uuid) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE calendar_feeds SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(conn)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+// Helper function: Public URLs for a feed, on the configured base URL
+fn feed_response(config: &CalendarConfig, feed: &CalendarFeed) -> CalendarFeedResponse {
+    let (url, webcal_url) = config.feed_urls(&feed.token);
+    CalendarFeedResponse {
+        url,
+        webcal_url,
+        created_at: feed.created_at,
+    }
+}
+
+// Helper function: An iCalendar response; with a file name it downloads instead of displaying
+pub fn ics_response(
+    name: &str,
+    events: &[CalendarEvent],
+    download_name: Option<String>,
+) -> HttpResponse {
+    let mut response = HttpResponse::Ok();
+    response.content_type("text/calendar; charset=utf-8");
+    if let Some(file_name) = download_name {
+        response.insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(file_name)],
+        });
+    }
+    response.body(render_calendar(name, events))
+}
+
+//Get Calendar Feed
+//Get Calendar Feed Input: HttpRequest(JWT Token)
+//Get Calendar Feed Output: CalendarFeedResponse
+pub async fn get_calendar_feed(
+    pool: web::Data<PgPool>,
+    config: web::Data<CalendarConfig>,
+    req: HttpRequest,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("Error acquiring connection: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch calendar feed");
+        }
+    };
+    match active_feed(&mut conn, user_id).await {
+        Ok(feed) => HttpResponse::Ok().json(feed_response(&config, &feed)),
+        Err(e) => {
+            eprintln!("Error fetching calendar feed: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch calendar feed")
+        }
+    }
+}
+
+//Reset Calendar Feed
+//Reset Calendar Feed Input: HttpRequest(JWT Token)
+//Reset Calendar Feed Output: CalendarFeedResponse
+//The old URL stops working, so existing subscriptions have to be replaced
+pub async fn reset_calendar_feed(
+    pool: web::Data<PgPool>,
+    config: web::Data<CalendarConfig>,
+    req: HttpRequest,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to reset calendar feed");
+        }
+    };
+    let feed = match revoke_feed(&mut tx, user_id).await {
+        Ok(_) => active_feed(&mut tx, user_id).await,
+        Err(e) => Err(e),
+    };
+    let feed = match feed {
+        Ok(feed) => feed,
+        Err(e) => {
+            eprintln!("Error resetting calendar feed: {:?}", e);
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to reset calendar feed");
+        }
+    };
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing transaction: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to reset calendar feed");
+    }
+
+    HttpResponse::Ok().json(feed_response(&config, &feed))
+}
+
+//Revoke Calendar Feed
+//Revoke Calendar Feed Input: HttpRequest(JWT Token)
+//Revoke Calendar Feed Output: Success message
+pub async fn revoke_calendar_feed(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("Error acquiring connection: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to revoke calendar feed");
+        }
+    };
+    match revoke_feed(&mut conn, user_id).await {
+        Ok(0) => HttpResponse::NotFound().body("You don't have a calendar feed"),
+        Ok(_) => HttpResponse::Ok().body("Calendar feed revoked"),
+        Err(e) => {
+            eprintln!("Error revoking calendar feed: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to revoke calendar feed")
+        }
+    }
+}
+
+//Get User Calendar Feed
+//Get User Calendar Feed Input: Path (/calendar/feeds/{token}.ics)
+//Get User Calendar Feed Output: text/calendar with the meetings the user joined
+//Public: calendar apps can't send a JWT, so the secret token is the only credential
+pub async fn get_user_calendar_feed(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+) -> impl Responder {
+    let token = path.into_inner();
+    let user_id: Option<Uuid> = match sqlx::query_scalar(
+        "SELECT f.user_id FROM calendar_feeds f
+         JOIN users u ON u.user_id = f.user_id
+         WHERE f.token = $1 AND f.revoked_at IS NULL AND u.deleted_at IS NULL",
+    )
+    .bind(&token)
+    .fetch_optional(pool.get_ref())
+    .await
+    {
+        Ok(user_id) => user_id,
+        Err(e) => {
+            eprintln!("Error fetching calendar feed: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch calendar feed");
+        }
+    };
+    let Some(user_id) = user_id else {
+        return HttpResponse::NotFound().body("Calendar feed not found");
+    };
+
+    match user_feed_events(pool.get_ref(), user_id).await {
+        Ok(events) => ics_response("My support group meetings", &events, None),
+        Err(e) => {
+            eprintln!("Error fetching calendar events: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch calendar feed")
+        }
+    }
+}
+
+//Get Group Calendar Feed
+//Get Group Calendar Feed Input: Path (/calendar/groups/{support_group_id}.ics)
+//Get Group Calendar Feed Output: text/calendar with the group's meetings
+//Public, and only for approved groups
+pub async fn get_group_calendar_feed(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let support_group_id = path.into_inner();
+    let title: Option<String> = match sqlx::query_scalar(
+        "SELECT title FROM support_groups WHERE support_group_id = $1 AND status = $2",
+    )
+    .bind(support_group_id)
+    .bind(SupportGroupStatus::Approved)
+    .fetch_optional(pool.get_ref())
+    .await
+    {
+        Ok(title) => title,
+        Err(e) => {
+            eprintln!("Error fetching support group: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch calendar feed");
+        }
+    };
+    let Some(title) = title else {
+        return HttpResponse::NotFound().body("Support group not found or not approved");
+    };
+
+    match group_feed_events(pool.get_ref(), support_group_id).await {
+        Ok(events) => ics_response(&title, &events, None),
+        Err(e) => {
+            eprintln!("Error fetching calendar events: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch calendar feed")
+        }
+    }
+}
+
+//Config Calendar Routes
+// GET /calendar/feed
+// POST /calendar/feed/reset
+// DELETE /calendar/feed
+pub fn config_calendar_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/calendar")
+            .route("/feed", web::get().to(get_calendar_feed))
+            .route("/feed/reset", web::post().to(reset_calendar_feed))
+            .route("/feed", web::delete().to(revoke_calendar_feed)),
+    );
+}
+
+//Config Public Calendar Routes
+// GET /calendar/feeds/{token}.ics
+// GET /calendar/groups/{support_group_id}.ics
+pub fn config_public_calendar_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/calendar")
+            .route("/feeds/{token}.ics", web::get().to(get_user_calendar_feed))
+            .route(
+                "/groups/{support_group_id}.ics",
+                web::get().to(get_group_calendar_feed),
+            ),
+    );
+}
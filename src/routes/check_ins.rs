@@ -0,0 +1,697 @@
+use crate::handlers::auth::Claims;
+use crate::handlers::check_ins::{load_progress, record_new_milestones};
+use crate::handlers::notifications::{create_notifications, push_notifications};
+use crate::handlers::sponsorships::fetch_own_sponsorship;
+use crate::models::all_models::{
+    CheckIn, CheckInMilestone, CheckInSchedule, NotificationKind, SponsorNote, Sponsorship,
+    SponsorshipStatus,
+};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use chrono::{Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const MAX_PROGRESS_LENGTH: usize = 2000;
+const MAX_NOTE_LENGTH: usize = 5000;
+const MAX_FREQUENCY_DAYS: i32 = 90;
+const DEFAULT_CHECK_IN_LIMIT: i64 = 50;
+const MAX_CHECK_IN_LIMIT: i64 = 200;
+
+//Create Check-In Request
+#[derive(Debug, Deserialize)]
+pub struct CreateCheckInRequest {
+    pub mood: i32,
+    pub progress: Option<String>,
+    pub shared_with_sponsor: Option<bool>,
+}
+
+//Update Check-In Request
+#[derive(Debug, Deserialize)]
+pub struct UpdateCheckInRequest {
+    pub mood: Option<i32>,
+    pub progress: Option<String>,
+    pub shared_with_sponsor: Option<bool>,
+}
+
+//Check-In Created Response
+#[derive(Debug, Serialize)]
+pub struct CheckInCreatedResponse {
+    pub check_in: CheckIn,
+    pub new_milestones: Vec<CheckInMilestone>,
+}
+
+//List Check-Ins Params
+#[derive(Debug, Deserialize)]
+pub struct ListCheckInsParams {
+    pub before: Option<NaiveDateTime>,
+    pub limit: Option<i64>,
+}
+
+//Set Check-In Schedule Request
+#[derive(Debug, Deserialize)]
+pub struct SetCheckInScheduleRequest {
+    pub frequency_days: i32,
+    pub next_due_at: Option<NaiveDateTime>,
+}
+
+//Sponsor Note Request
+#[derive(Debug, Deserialize)]
+pub struct SponsorNoteRequest {
+    pub content: String,
+}
+
+// Helper function: The caller and the sponsorship, if they are one of its two parties
+async fn load_party_sponsorship(
+    pool: &PgPool,
+    req: &HttpRequest,
+    sponsorship_id: Uuid,
+) -> Result<(Uuid, Sponsorship), HttpResponse> {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return Err(HttpResponse::Unauthorized().body("Authentication required")),
+    };
+    match fetch_own_sponsorship(pool, user_id, sponsorship_id).await {
+        Ok(Some(sponsorship)) => Ok((user_id, sponsorship)),
+        Ok(None) => Err(HttpResponse::NotFound().body("Sponsorship not found")),
+        Err(e) => {
+            eprintln!("Error fetching sponsorship: {:?}", e);
+            Err(HttpResponse::InternalServerError().body("Failed to fetch sponsorship"))
+        }
+    }
+}
+
+// Helper function: Trim optional progress text, treating blank as none
+#[allow(clippy::result_large_err)]
+fn validate_progress(progress: Option<&str>) -> Result<Option<String>, HttpResponse> {
+    let progress = progress.map(str::trim).filter(|p| !p.is_empty());
+    if progress.is_some_and(|p| p.chars().count() > MAX_PROGRESS_LENGTH) {
+        return Err(HttpResponse::BadRequest().body(format!(
+            "Progress must be at most {} characters",
+            MAX_PROGRESS_LENGTH
+        )));
+    }
+    Ok(progress.map(str::to_string))
+}
+
+// Helper function: Trim a sponsor note and check its length
+#[allow(clippy::result_large_err)]
+fn validate_note(content: &str) -> Result<&str, HttpResponse> {
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(HttpResponse::BadRequest().body("Note cannot be empty"));
+    }
+    if content.chars().count() > MAX_NOTE_LENGTH {
+        return Err(HttpResponse::BadRequest().body(format!(
+            "Note must be at most {} characters",
+            MAX_NOTE_LENGTH
+        )));
+    }
+    Ok(content)
+}
+
+fn invalid_mood(mood: i32) -> bool {
+    !(1..=5).contains(&mood)
+}
+
+//List Check-Ins
+//List Check-Ins Input: HttpRequest(JWT Token), Path (/check-ins/{sponsorship_id}/entries), Query (?before=&limit=)
+//List Check-Ins Output: Vec<CheckIn>
+//The member sees all of their check-ins; the sponsor only sees the shared ones
+pub async fn list_check_ins(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    query: web::Query<ListCheckInsParams>,
+) -> impl Responder {
+    let (user_id, sponsorship) =
+        match load_party_sponsorship(pool.get_ref(), &req, path.into_inner()).await {
+            Ok(party) => party,
+            Err(response) => return response,
+        };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_CHECK_IN_LIMIT)
+        .clamp(1, MAX_CHECK_IN_LIMIT);
+
+    let list_query = "
+        SELECT * FROM check_ins
+        WHERE sponsorship_id = $1 AND ($2 OR shared_with_sponsor)
+          AND ($3::timestamp IS NULL OR created_at < $3)
+        ORDER BY created_at DESC
+        LIMIT $4
+    ";
+    match sqlx::query_as::<_, CheckIn>(list_query)
+        .bind(sponsorship.sponsorship_id)
+        .bind(user_id == sponsorship.member_id)
+        .bind(query.before)
+        .bind(limit)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(check_ins) => HttpResponse::Ok().json(check_ins),
+        Err(e) => {
+            eprintln!("Error fetching check-ins: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch check-ins")
+        }
+    }
+}
+
+//Create Check-In
+//Create Check-In Input: HttpRequest(JWT Token), Path (/check-ins/{sponsorship_id}/entries), CreateCheckInRequest
+//Create Check-In Output: CheckInCreatedResponse
+pub async fn create_check_in(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    payload: web::Json<CreateCheckInRequest>,
+) -> impl Responder {
+    let (user_id, sponsorship) =
+        match load_party_sponsorship(pool.get_ref(), &req, path.into_inner()).await {
+            Ok(party) => party,
+            Err(response) => return response,
+        };
+    if user_id != sponsorship.member_id {
+        return HttpResponse::Forbidden().body("Only the member can check in");
+    }
+    if sponsorship.status != SponsorshipStatus::Active {
+        return HttpResponse::Conflict().body("Check-ins can only be added to active sponsorships");
+    }
+    if invalid_mood(payload.mood) {
+        return HttpResponse::BadRequest().body("Mood must be between 1 and 5");
+    }
+    let progress = match validate_progress(payload.progress.as_deref()) {
+        Ok(progress) => progress,
+        Err(response) => return response,
+    };
+    let shared = payload.shared_with_sponsor.unwrap_or(true);
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            eprintln!("Failed to start transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to save check-in");
+        }
+    };
+
+    let insert_query = "
+        INSERT INTO check_ins (sponsorship_id, member_id, mood, progress, shared_with_sponsor)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *
+    ";
+    let check_in = match sqlx::query_as::<_, CheckIn>(insert_query)
+        .bind(sponsorship.sponsorship_id)
+        .bind(user_id)
+        .bind(payload.mood)
+        .bind(&progress)
+        .bind(shared)
+        .fetch_one(&mut *tx)
+        .await
+    {
+        Ok(check_in) => check_in,
+        Err(e) => {
+            eprintln!("Error saving check-in: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to save check-in");
+        }
+    };
+
+    let new_milestones = match record_new_milestones(&mut tx, &sponsorship).await {
+        Ok(milestones) => milestones,
+        Err(e) => {
+            eprintln!("Error recording milestones: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to save check-in");
+        }
+    };
+
+    // The sponsor hears about shared check-ins; both of them hear about milestones
+    let mut notifications = Vec::new();
+    if shared {
+        let payload = json!({
+            "sponsorship_id": sponsorship.sponsorship_id,
+            "check_in_id": check_in.check_in_id,
+        });
+        match create_notifications(
+            &mut tx,
+            &[sponsorship.sponsor_id],
+            NotificationKind::CheckIn,
+            &payload,
+        )
+        .await
+        {
+            Ok(sent) => notifications.extend(sent),
+            Err(e) => {
+                eprintln!("Error creating notifications: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to save check-in");
+            }
+        }
+    }
+    for milestone in &new_milestones {
+        let payload = json!({
+            "sponsorship_id": sponsorship.sponsorship_id,
+            "milestone": milestone,
+        });
+        match create_notifications(
+            &mut tx,
+            &[sponsorship.member_id, sponsorship.sponsor_id],
+            NotificationKind::CheckIn,
+            &payload,
+        )
+        .await
+        {
+            Ok(sent) => notifications.extend(sent),
+            Err(e) => {
+                eprintln!("Error creating notifications: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to save check-in");
+            }
+        }
+    }
+
+    if let Err(e) = tx.commit().await {
+        eprintln!("Failed to commit check-in: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to save check-in");
+    }
+    push_notifications(&notifications).await;
+
+    HttpResponse::Created().json(CheckInCreatedResponse {
+        check_in,
+        new_milestones,
+    })
+}
+
+//Update Check-In
+//Update Check-In Input: HttpRequest(JWT Token), Path (/check-ins/{sponsorship_id}/entries/{check_in_id}), UpdateCheckInRequest
+//Update Check-In Output: CheckIn
+pub async fn update_check_in(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<UpdateCheckInRequest>,
+) -> impl Responder {
+    let (sponsorship_id, check_in_id) = path.into_inner();
+    let (user_id, sponsorship) =
+        match load_party_sponsorship(pool.get_ref(), &req, sponsorship_id).await {
+            Ok(party) => party,
+            Err(response) => return response,
+        };
+    if user_id != sponsorship.member_id {
+        return HttpResponse::Forbidden().body("Only the member can edit check-ins");
+    }
+    if payload.mood.is_some_and(invalid_mood) {
+        return HttpResponse::BadRequest().body("Mood must be between 1 and 5");
+    }
+    let progress = match validate_progress(payload.progress.as_deref()) {
+        Ok(progress) => progress,
+        Err(response) => return response,
+    };
+
+    // An empty progress string clears it; leaving it out keeps the current text
+    let update_query = "
+        UPDATE check_ins
+        SET mood = COALESCE($3, mood),
+            progress = CASE WHEN $4 THEN $5 ELSE progress END,
+            shared_with_sponsor = COALESCE($6, shared_with_sponsor),
+            updated_at = NOW()
+        WHERE check_in_id = $1 AND sponsorship_id = $2
+        RETURNING *
+    ";
+    match sqlx::query_as::<_, CheckIn>(update_query)
+        .bind(check_in_id)
+        .bind(sponsorship_id)
+        .bind(payload.mood)
+        .bind(payload.progress.is_some())
+        .bind(progress)
+        .bind(payload.shared_with_sponsor)
+        .fetch_optional(pool.get_ref())
+        .await
+    {
+        Ok(Some(check_in)) => HttpResponse::Ok().json(check_in),
+        Ok(None) => HttpResponse::NotFound().body("Check-in not found"),
+        Err(e) => {
+            eprintln!("Error updating check-in: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update check-in")
+        }
+    }
+}
+
+//Delete Check-In
+//Delete Check-In Input: HttpRequest(JWT Token), Path (/check-ins/{sponsorship_id}/entries/{check_in_id})
+//Delete Check-In Output: Success message
+//Milestones already reached are kept
+pub async fn delete_check_in(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+) -> impl Responder {
+    let (sponsorship_id, check_in_id) = path.into_inner();
+    let (user_id, sponsorship) =
+        match load_party_sponsorship(pool.get_ref(), &req, sponsorship_id).await {
+            Ok(party) => party,
+            Err(response) => return response,
+        };
+    if user_id != sponsorship.member_id {
+        return HttpResponse::Forbidden().body("Only the member can delete check-ins");
+    }
+
+    match sqlx::query("DELETE FROM check_ins WHERE check_in_id = $1 AND sponsorship_id = $2")
+        .bind(check_in_id)
+        .bind(sponsorship_id)
+        .execute(pool.get_ref())
+        .await
+    {
+        Ok(result) if result.rows_affected() > 0 => {
+            HttpResponse::Ok().body("Check-in deleted successfully")
+        }
+        Ok(_) => HttpResponse::NotFound().body("Check-in not found"),
+        Err(e) => {
+            eprintln!("Error deleting check-in: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to delete check-in")
+        }
+    }
+}
+
+//Get Check-In Progress
+//Get Check-In Progress Input: HttpRequest(JWT Token), Path (/check-ins/{sponsorship_id}/progress)
+//Get Check-In Progress Output: CheckInProgress
+pub async fn get_check_in_progress(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let (_, sponsorship) =
+        match load_party_sponsorship(pool.get_ref(), &req, path.into_inner()).await {
+            Ok(party) => party,
+            Err(response) => return response,
+        };
+
+    match load_progress(pool.get_ref(), &sponsorship).await {
+        Ok(progress) => HttpResponse::Ok().json(progress),
+        Err(e) => {
+            eprintln!("Error loading check-in progress: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch check-in progress")
+        }
+    }
+}
+
+//Get Check-In Schedule
+//Get Check-In Schedule Input: HttpRequest(JWT Token), Path (/check-ins/{sponsorship_id}/schedule)
+//Get Check-In Schedule Output: CheckInSchedule
+pub async fn get_check_in_schedule(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let (_, sponsorship) =
+        match load_party_sponsorship(pool.get_ref(), &req, path.into_inner()).await {
+            Ok(party) => party,
+            Err(response) => return response,
+        };
+
+    match sqlx::query_as::<_, CheckInSchedule>(
+        "SELECT * FROM check_in_schedules WHERE sponsorship_id = $1",
+    )
+    .bind(sponsorship.sponsorship_id)
+    .fetch_optional(pool.get_ref())
+    .await
+    {
+        Ok(Some(schedule)) => HttpResponse::Ok().json(schedule),
+        Ok(None) => HttpResponse::NotFound().body("No check-in schedule set"),
+        Err(e) => {
+            eprintln!("Error fetching check-in schedule: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch check-in schedule")
+        }
+    }
+}
+
+//Set Check-In Schedule
+//Set Check-In Schedule Input: HttpRequest(JWT Token), Path (/check-ins/{sponsorship_id}/schedule), SetCheckInScheduleRequest
+//Set Check-In Schedule Output: CheckInSchedule
+//Either party can set how often the member is prompted; the first prompt defaults to one period from now
+pub async fn set_check_in_schedule(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    payload: web::Json<SetCheckInScheduleRequest>,
+) -> impl Responder {
+    let (user_id, sponsorship) =
+        match load_party_sponsorship(pool.get_ref(), &req, path.into_inner()).await {
+            Ok(party) => party,
+            Err(response) => return response,
+        };
+    if sponsorship.status == SponsorshipStatus::Ended {
+        return HttpResponse::Conflict().body("This sponsorship has ended");
+    }
+    if !(1..=MAX_FREQUENCY_DAYS).contains(&payload.frequency_days) {
+        return HttpResponse::BadRequest().body(format!(
+            "Frequency must be between 1 and {} days",
+            MAX_FREQUENCY_DAYS
+        ));
+    }
+    let now = Utc::now().naive_utc();
+    let next_due_at = payload
+        .next_due_at
+        .unwrap_or(now + Duration::days(i64::from(payload.frequency_days)));
+    if next_due_at < now {
+        return HttpResponse::BadRequest().body("The next check-in can't be in the past");
+    }
+
+    let upsert_query = "
+        INSERT INTO check_in_schedules (sponsorship_id, frequency_days, next_due_at, updated_by)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (sponsorship_id) DO UPDATE
+        SET frequency_days = EXCLUDED.frequency_days,
+            next_due_at = EXCLUDED.next_due_at,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = NOW()
+        RETURNING *
+    ";
+    match sqlx::query_as::<_, CheckInSchedule>(upsert_query)
+        .bind(sponsorship.sponsorship_id)
+        .bind(payload.frequency_days)
+        .bind(next_due_at)
+        .bind(user_id)
+        .fetch_one(pool.get_ref())
+        .await
+    {
+        Ok(schedule) => HttpResponse::Ok().json(schedule),
+        Err(e) => {
+            eprintln!("Error saving check-in schedule: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to save check-in schedule")
+        }
+    }
+}
+
+//Delete Check-In Schedule
+//Delete Check-In Schedule Input: HttpRequest(JWT Token), Path (/check-ins/{sponsorship_id}/schedule)
+//Delete Check-In Schedule Output: Success message
+pub async fn delete_check_in_schedule(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let (_, sponsorship) =
+        match load_party_sponsorship(pool.get_ref(), &req, path.into_inner()).await {
+            Ok(party) => party,
+            Err(response) => return response,
+        };
+
+    match sqlx::query("DELETE FROM check_in_schedules WHERE sponsorship_id = $1")
+        .bind(sponsorship.sponsorship_id)
+        .execute(pool.get_ref())
+        .await
+    {
+        Ok(result) if result.rows_affected() > 0 => {
+            HttpResponse::Ok().body("Check-in schedule removed")
+        }
+        Ok(_) => HttpResponse::NotFound().body("No check-in schedule set"),
+        Err(e) => {
+            eprintln!("Error deleting check-in schedule: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to remove check-in schedule")
+        }
+    }
+}
+
+//List Sponsor Notes
+//List Sponsor Notes Input: HttpRequest(JWT Token), Path (/check-ins/{sponsorship_id}/notes)
+//List Sponsor Notes Output: Vec<SponsorNote>
+pub async fn list_sponsor_notes(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let (user_id, sponsorship) =
+        match load_party_sponsorship(pool.get_ref(), &req, path.into_inner()).await {
+            Ok(party) => party,
+            Err(response) => return response,
+        };
+    // Notes are private to the sponsor; to the member they don't exist
+    if user_id != sponsorship.sponsor_id {
+        return HttpResponse::NotFound().body("Sponsorship not found");
+    }
+
+    match sqlx::query_as::<_, SponsorNote>(
+        "SELECT * FROM sponsor_notes
+         WHERE sponsorship_id = $1 AND sponsor_id = $2
+         ORDER BY created_at DESC",
+    )
+    .bind(sponsorship.sponsorship_id)
+    .bind(user_id)
+    .fetch_all(pool.get_ref())
+    .await
+    {
+        Ok(notes) => HttpResponse::Ok().json(notes),
+        Err(e) => {
+            eprintln!("Error fetching sponsor notes: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch notes")
+        }
+    }
+}
+
+//Create Sponsor Note
+//Create Sponsor Note Input: HttpRequest(JWT Token), Path (/check-ins/{sponsorship_id}/notes), SponsorNoteRequest
+//Create Sponsor Note Output: SponsorNote
+pub async fn create_sponsor_note(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    payload: web::Json<SponsorNoteRequest>,
+) -> impl Responder {
+    let (user_id, sponsorship) =
+        match load_party_sponsorship(pool.get_ref(), &req, path.into_inner()).await {
+            Ok(party) => party,
+            Err(response) => return response,
+        };
+    if user_id != sponsorship.sponsor_id {
+        return HttpResponse::NotFound().body("Sponsorship not found");
+    }
+    if sponsorship.status == SponsorshipStatus::Ended {
+        return HttpResponse::Conflict().body("This sponsorship has ended");
+    }
+    let content = match validate_note(&payload.content) {
+        Ok(content) => content,
+        Err(response) => return response,
+    };
+
+    match sqlx::query_as::<_, SponsorNote>(
+        "INSERT INTO sponsor_notes (sponsorship_id, sponsor_id, content)
+         VALUES ($1, $2, $3)
+         RETURNING *",
+    )
+    .bind(sponsorship.sponsorship_id)
+    .bind(user_id)
+    .bind(content)
+    .fetch_one(pool.get_ref())
+    .await
+    {
+        Ok(note) => HttpResponse::Created().json(note),
+        Err(e) => {
+            eprintln!("Error saving sponsor note: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to save note")
+        }
+    }
+}
+
+//Update Sponsor Note
+//Update Sponsor Note Input: HttpRequest(JWT Token), Path (/check-ins/{sponsorship_id}/notes/{note_id}), SponsorNoteRequest
+//Update Sponsor Note Output: SponsorNote
+pub async fn update_sponsor_note(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<SponsorNoteRequest>,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let (sponsorship_id, note_id) = path.into_inner();
+    let content = match validate_note(&payload.content) {
+        Ok(content) => content,
+        Err(response) => return response,
+    };
+
+    match sqlx::query_as::<_, SponsorNote>(
+        "UPDATE sponsor_notes SET content = $4, updated_at = NOW()
+         WHERE note_id = $1 AND sponsorship_id = $2 AND sponsor_id = $3
+         RETURNING *",
+    )
+    .bind(note_id)
+    .bind(sponsorship_id)
+    .bind(user_id)
+    .bind(content)
+    .fetch_optional(pool.get_ref())
+    .await
+    {
+        Ok(Some(note)) => HttpResponse::Ok().json(note),
+        Ok(None) => HttpResponse::NotFound().body("Note not found"),
+        Err(e) => {
+            eprintln!("Error updating sponsor note: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update note")
+        }
+    }
+}
+
+//Delete Sponsor Note
+//Delete Sponsor Note Input: HttpRequest(JWT Token), Path (/check-ins/{sponsorship_id}/notes/{note_id})
+//Delete Sponsor Note Output: Success message
+pub async fn delete_sponsor_note(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, Uuid)>,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let (sponsorship_id, note_id) = path.into_inner();
+
+    match sqlx::query(
+        "DELETE FROM sponsor_notes WHERE note_id = $1 AND sponsorship_id = $2 AND sponsor_id = $3",
+    )
+    .bind(note_id)
+    .bind(sponsorship_id)
+    .bind(user_id)
+    .execute(pool.get_ref())
+    .await
+    {
+        Ok(result) if result.rows_affected() > 0 => {
+            HttpResponse::Ok().body("Note deleted successfully")
+        }
+        Ok(_) => HttpResponse::NotFound().body("Note not found"),
+        Err(e) => {
+            eprintln!("Error deleting sponsor note: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to delete note")
+        }
+    }
+}
+
+//Config Check-In Routes
+// GET /check-ins/{sponsorship_id}/entries
+// POST /check-ins/{sponsorship_id}/entries
+// PATCH /check-ins/{sponsorship_id}/entries/{check_in_id}
+// DELETE /check-ins/{sponsorship_id}/entries/{check_in_id}
+// GET /check-ins/{sponsorship_id}/progress
+// GET /check-ins/{sponsorship_id}/schedule
+// PUT /check-ins/{sponsorship_id}/schedule
+// DELETE /check-ins/{sponsorship_id}/schedule
+// GET /check-ins/{sponsorship_id}/notes
+// POST /check-ins/{sponsorship_id}/notes
+// PATCH /check-ins/{sponsorship_id}/notes/{note_id}
+// DELETE /check-ins/{sponsorship_id}/notes/{note_id}
+pub fn config_check_in_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/check-ins/{sponsorship_id}")
+            .route("/entries", web::get().to(list_check_ins))
+            .route("/entries", web::post().to(create_check_in))
+            .route("/entries/{check_in_id}", web::patch().to(update_check_in))
+            .route("/entries/{check_in_id}", web::delete().to(delete_check_in))
+            .route("/progress", web::get().to(get_check_in_progress))
+            .route("/schedule", web::get().to(get_check_in_schedule))
+            .route("/schedule", web::put().to(set_check_in_schedule))
+            .route("/schedule", web::delete().to(delete_check_in_schedule))
+            .route("/notes", web::get().to(list_sponsor_notes))
+            .route("/notes", web::post().to(create_sponsor_note))
+            .route("/notes/{note_id}", web::patch().to(update_sponsor_note))
+            .route("/notes/{note_id}", web::delete().to(delete_sponsor_note)),
+    );
+}
@@ -0,0 +1,309 @@
+use crate::handlers::auth::Claims;
+use crate::handlers::b2_storage::B2Client;
+use crate::handlers::data_export::spawn_data_export;
+use crate::models::all_models::{DataExport, DataExportStatus};
+use actix_web::{http::header, web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use chrono::Utc;
+use log::error;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How long a signed export download link stays valid
+const DOWNLOAD_LINK_TTL: Duration = Duration::from_secs(10 * 60);
+
+const EXPORT_COLUMNS: &str = "export_id, user_id, status, summary, file_name, size_bytes, error, created_at, completed_at, expires_at";
+
+// Helper function: Fetch one of the user's own exports
+async fn find_export(
+    pool: &PgPool,
+    export_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<DataExport>, sqlx::Error> {
+    let query = format!(
+        "SELECT {} FROM data_exports WHERE export_id = $1 AND user_id = $2",
+        EXPORT_COLUMNS
+    );
+    sqlx::query_as::<_, DataExport>(&query)
+        .bind(export_id)
+        .bind(user_id)
+        .fetch_optional(pool)
+        .await
+}
+
+// Why an export can't be downloaded right now
+enum ExportUnavailable {
+    Failed,
+    Preparing,
+    Expired,
+}
+
+impl ExportUnavailable {
+    fn response(&self) -> HttpResponse {
+        match self {
+            ExportUnavailable::Failed => {
+                HttpResponse::Conflict().body("Export failed, please request a new one")
+            }
+            ExportUnavailable::Preparing => {
+                HttpResponse::Accepted().body("Export is still being prepared")
+            }
+            ExportUnavailable::Expired => {
+                HttpResponse::Gone().body("Export has expired, please request a new one")
+            }
+        }
+    }
+}
+
+// Helper function: Reject exports that aren't downloadable yet (or any more)
+fn ensure_downloadable(export: &DataExport) -> Result<(), ExportUnavailable> {
+    match export.status {
+        DataExportStatus::Ready => {}
+        DataExportStatus::Failed => return Err(ExportUnavailable::Failed),
+        _ => return Err(ExportUnavailable::Preparing),
+    }
+
+    match export.expires_at {
+        Some(expires_at) if expires_at <= Utc::now().naive_utc() => Err(ExportUnavailable::Expired),
+        _ => Ok(()),
+    }
+}
+
+//Request Data Export
+//Request Data Export Input: HttpRequest(JWT Token)
+//Request Data Export Output: DataExport (status pending, generated in the background)
+pub async fn request_data_export(
+    pool: web::Data<PgPool>,
+    b2_client: web::Data<B2Client>,
+    req: HttpRequest,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    // Only one export may be in progress per user
+    let in_progress_query = "
+        SELECT EXISTS(
+            SELECT 1 FROM data_exports
+            WHERE user_id = $1 AND status IN ('pending', 'processing')
+        )";
+    match sqlx::query_scalar::<_, bool>(in_progress_query)
+        .bind(claims.id)
+        .fetch_one(pool.get_ref())
+        .await
+    {
+        Ok(false) => {}
+        Ok(true) => {
+            return HttpResponse::Conflict().body("An export is already being prepared");
+        }
+        Err(e) => {
+            eprintln!("Error checking existing exports: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to request export");
+        }
+    }
+
+    let insert_query = format!(
+        "INSERT INTO data_exports (user_id) VALUES ($1) RETURNING {}",
+        EXPORT_COLUMNS
+    );
+    match sqlx::query_as::<_, DataExport>(&insert_query)
+        .bind(claims.id)
+        .fetch_one(pool.get_ref())
+        .await
+    {
+        Ok(export) => {
+            spawn_data_export(
+                pool.get_ref().clone(),
+                b2_client.get_ref().clone(),
+                export.export_id,
+                claims.id,
+            );
+            HttpResponse::Accepted().json(export)
+        }
+        Err(e) => {
+            eprintln!("Error creating export: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to request export")
+        }
+    }
+}
+
+//List Data Exports
+//List Data Exports Input: HttpRequest(JWT Token)
+//List Data Exports Output: Vec<DataExport>
+pub async fn list_data_exports(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let query = format!(
+        "SELECT {} FROM data_exports WHERE user_id = $1 ORDER BY created_at DESC",
+        EXPORT_COLUMNS
+    );
+    match sqlx::query_as::<_, DataExport>(&query)
+        .bind(claims.id)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(exports) => HttpResponse::Ok().json(exports),
+        Err(e) => {
+            eprintln!("Error listing exports: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to list exports")
+        }
+    }
+}
+
+//Get Data Export
+//Get Data Export Input: HttpRequest(JWT Token), Path (/data-export/{export_id})
+//Get Data Export Output: DataExport
+pub async fn get_data_export(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    match find_export(pool.get_ref(), path.into_inner(), claims.id).await {
+        Ok(Some(export)) => HttpResponse::Ok().json(export),
+        Ok(None) => HttpResponse::NotFound().body("Export not found"),
+        Err(e) => {
+            eprintln!("Error fetching export: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch export")
+        }
+    }
+}
+
+//Get Data Export Summary
+//Get Data Export Summary Input: HttpRequest(JWT Token), Path (/data-export/{export_id}/summary)
+//Get Data Export Summary Output: Plain-text summary of the archive
+pub async fn get_data_export_summary(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let export = match find_export(pool.get_ref(), path.into_inner(), claims.id).await {
+        Ok(Some(export)) => export,
+        Ok(None) => return HttpResponse::NotFound().body("Export not found"),
+        Err(e) => {
+            eprintln!("Error fetching export: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch export");
+        }
+    };
+
+    if let Err(reason) = ensure_downloadable(&export) {
+        return reason.response();
+    }
+
+    HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"bth-data-export-{}-summary.txt\"",
+                export.created_at.format("%Y-%m-%d")
+            ),
+        ))
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .body(export.summary.unwrap_or_default())
+}
+
+//Download Data Export
+//Download Data Export Input: HttpRequest(JWT Token), Path (/data-export/{export_id}/download)
+//Download Data Export Output: JSON archive, or a redirect to a short-lived signed URL for large exports
+pub async fn download_data_export(
+    pool: web::Data<PgPool>,
+    b2_client: web::Data<B2Client>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let export = match find_export(pool.get_ref(), path.into_inner(), claims.id).await {
+        Ok(Some(export)) => export,
+        Ok(None) => return HttpResponse::NotFound().body("Export not found"),
+        Err(e) => {
+            eprintln!("Error fetching export: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch export");
+        }
+    };
+
+    if let Err(reason) = ensure_downloadable(&export) {
+        return reason.response();
+    }
+
+    // Large exports live in the private bucket, so hand out a link that expires
+    if let Some(file_name) = &export.file_name {
+        return match b2_client
+            .signed_download_url(file_name, DOWNLOAD_LINK_TTL)
+            .await
+        {
+            Ok(url) => HttpResponse::Found()
+                .insert_header((header::LOCATION, url))
+                .insert_header((header::CACHE_CONTROL, "no-store"))
+                .finish(),
+            Err(e) => {
+                error!("Failed to build export download URL: {:?}", e);
+                HttpResponse::InternalServerError().body("Storage service unavailable")
+            }
+        };
+    }
+
+    let archive: Option<Value> =
+        match sqlx::query_scalar("SELECT archive FROM data_exports WHERE export_id = $1")
+            .bind(export.export_id)
+            .fetch_one(pool.get_ref())
+            .await
+        {
+            Ok(archive) => archive,
+            Err(e) => {
+                eprintln!("Error fetching export archive: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to fetch export");
+            }
+        };
+
+    match archive {
+        Some(archive) => HttpResponse::Ok()
+            .insert_header((
+                header::CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"bth-data-export-{}.json\"",
+                    export.created_at.format("%Y-%m-%d")
+                ),
+            ))
+            .insert_header((header::CACHE_CONTROL, "no-store"))
+            .json(archive),
+        None => HttpResponse::InternalServerError().body("Export archive is missing"),
+    }
+}
+
+//Config Data Export Routes
+// POST /data-export
+// GET /data-export
+// GET /data-export/{export_id}
+// GET /data-export/{export_id}/summary
+// GET /data-export/{export_id}/download
+pub fn config_data_export_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/data-export")
+            .route("", web::post().to(request_data_export))
+            .route("", web::get().to(list_data_exports))
+            .route("/{export_id}", web::get().to(get_data_export))
+            .route(
+                "/{export_id}/summary",
+                web::get().to(get_data_export_summary),
+            )
+            .route("/{export_id}/download", web::get().to(download_data_export)),
+    );
+}
@@ -1,4 +1,5 @@
 use crate::handlers::auth::Claims;
+use crate::handlers::blocking::is_blocked_between;
 use crate::models::all_models::{GroupChat, GroupChatMember, GroupChatMessage};
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
@@ -365,6 +366,18 @@ pub async fn add_group_chat_member(
             .body("Only the creator can add members to this group chat");
     }
 
+    // People who blocked the creator (or were blocked by them) can't be pulled into their chats
+    match is_blocked_between(pool.get_ref(), user_id, payload.member_id).await {
+        Ok(false) => {}
+        Ok(true) => {
+            return HttpResponse::Forbidden().body("This user can't be added to your group chat");
+        }
+        Err(e) => {
+            eprintln!("Error checking blocks: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to verify authorization");
+        }
+    }
+
     // Check if member already exists in the chat.
     let check_query = r#"
         SELECT COUNT(*) FROM group_chat_members
@@ -0,0 +1,614 @@
+use crate::handlers::announcements::push_announcements;
+use crate::handlers::auth::Claims;
+use crate::handlers::meeting_series::{
+    end_series, materialise_occurrences, occurrence_start, reconcile_occurrences, RecurrenceRule,
+};
+use crate::handlers::timezones::{parse_timezone, user_timezone, LocalizedMeeting};
+use crate::models::all_models::{GroupMeeting, MeetingSeries, SupportGroupStatus, UserRole};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use chrono::{Days, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+const MIN_MEETING_TITLE_LENGTH: usize = 5;
+
+//Create Meeting Series Request
+#[derive(Debug, Deserialize)]
+pub struct CreateMeetingSeriesRequest {
+    pub support_group_id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    // RRULE, e.g. FREQ=WEEKLY;BYDAY=TU
+    pub rrule: String,
+    pub starts_on: NaiveDate,
+    // Wall-clock time in `timezone`
+    pub start_time: NaiveTime,
+    // IANA zone; defaults to the host's timezone preference
+    pub timezone: Option<String>,
+}
+
+//Update Meeting Series Request
+//Applies to the occurrences on or after from_date (default today); earlier ones keep the old details
+#[derive(Debug, Deserialize)]
+pub struct UpdateMeetingSeriesRequest {
+    pub from_date: Option<NaiveDate>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub rrule: Option<String>,
+    pub start_time: Option<NaiveTime>,
+    pub timezone: Option<String>,
+}
+
+//Meeting Series Details
+#[derive(Debug, Serialize)]
+pub struct MeetingSeriesDetails {
+    #[serde(flatten)]
+    pub series: MeetingSeries,
+    pub occurrences: Vec<LocalizedMeeting>,
+}
+
+// Helper function: Parse a rule and write it back in canonical form
+fn normalise_rule(raw: &str) -> Result<(RecurrenceRule, String), String> {
+    let rule: RecurrenceRule = raw.parse()?;
+    let text = rule.to_string();
+    Ok((rule, text))
+}
+
+// Helper function: The series with its occurrences from today on, in the viewer's timezone
+async fn series_details(
+    conn: &mut PgConnection,
+    series: MeetingSeries,
+    viewer_timezone: Tz,
+) -> Result<MeetingSeriesDetails, sqlx::Error> {
+    let occurrences = sqlx::query_as::<_, GroupMeeting>(
+        "SELECT * FROM group_meetings
+         WHERE series_id = $1 AND occurrence_date >= CURRENT_DATE
+         ORDER BY scheduled_time ASC",
+    )
+    .bind(series.series_id)
+    .fetch_all(conn)
+    .await?;
+    Ok(MeetingSeriesDetails {
+        series,
+        occurrences: occurrences
+            .into_iter()
+            .map(|meeting| LocalizedMeeting::new(meeting, viewer_timezone))
+            .collect(),
+    })
+}
+
+// Helper function: Lock a series for editing if the caller may manage it
+async fn fetch_managed_series(
+    conn: &mut PgConnection,
+    claims: &Claims,
+    series_id: Uuid,
+) -> Result<Result<MeetingSeries, HttpResponse>, sqlx::Error> {
+    let series = sqlx::query_as::<_, MeetingSeries>(
+        "SELECT * FROM meeting_series WHERE series_id = $1 AND ended_at IS NULL FOR UPDATE",
+    )
+    .bind(series_id)
+    .fetch_optional(conn)
+    .await?;
+    Ok(match series {
+        None => Err(HttpResponse::NotFound().body("Meeting series not found")),
+        Some(series) if series.host_id != claims.id && claims.role != UserRole::Admin => {
+            Err(HttpResponse::Forbidden().body("Only the host can change this meeting series"))
+        }
+        Some(series) => Ok(series),
+    })
+}
+
+//Create Meeting Series
+//Create Meeting Series Input: HttpRequest(JWT Token), CreateMeetingSeriesRequest
+//Create Meeting Series Output: MeetingSeriesDetails
+pub async fn create_meeting_series(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    payload: web::Json<CreateMeetingSeriesRequest>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    if payload.title.trim().chars().count() < MIN_MEETING_TITLE_LENGTH {
+        return HttpResponse::BadRequest().body(format!(
+            "Title must be at least {} characters",
+            MIN_MEETING_TITLE_LENGTH
+        ));
+    }
+    let rrule = match normalise_rule(&payload.rrule) {
+        Ok((_, rrule)) => rrule,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+    let today = Utc::now().date_naive();
+    if payload.starts_on < today {
+        return HttpResponse::BadRequest().body("A meeting series can't start in the past");
+    }
+    let host_timezone = match user_timezone(pool.get_ref(), claims.id).await {
+        Ok(tz) => tz,
+        Err(e) => {
+            eprintln!("Error fetching timezone: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to create meeting series");
+        }
+    };
+    let timezone = match payload.timezone.as_deref().map(parse_timezone) {
+        Some(Ok(tz)) => tz,
+        Some(Err(msg)) => return HttpResponse::BadRequest().body(msg),
+        None => host_timezone,
+    };
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to create meeting series");
+        }
+    };
+
+    let group_query = "
+        SELECT EXISTS (
+            SELECT 1 FROM support_group_members
+            WHERE support_group_id = $1 AND user_id = $2
+        ) FROM support_groups
+        WHERE support_group_id = $1 AND status = $3
+    ";
+    match sqlx::query_scalar::<_, bool>(group_query)
+        .bind(payload.support_group_id)
+        .bind(claims.id)
+        .bind(SupportGroupStatus::Approved)
+        .fetch_optional(&mut *tx)
+        .await
+    {
+        Ok(Some(true)) => {}
+        Ok(Some(false)) if claims.role == UserRole::Admin => {}
+        Ok(Some(false)) => {
+            return HttpResponse::Forbidden()
+                .body("Only members of the support group can host its meetings")
+        }
+        Ok(None) => {
+            return HttpResponse::NotFound().body("Support group not found or not approved")
+        }
+        Err(e) => {
+            eprintln!("Error fetching support group: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to verify support group");
+        }
+    }
+
+    let insert_query = "
+        INSERT INTO meeting_series (support_group_id, host_id, title, description, rrule, starts_on, start_time, timezone)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *
+    ";
+    let series = match sqlx::query_as::<_, MeetingSeries>(insert_query)
+        .bind(payload.support_group_id)
+        .bind(claims.id)
+        .bind(payload.title.trim())
+        .bind(&payload.description)
+        .bind(&rrule)
+        .bind(payload.starts_on)
+        .bind(payload.start_time)
+        .bind(timezone.name())
+        .fetch_one(&mut *tx)
+        .await
+    {
+        Ok(series) => series,
+        Err(e) => {
+            eprintln!("Error creating meeting series: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to create meeting series");
+        }
+    };
+
+    if let Err(e) = materialise_occurrences(&mut tx, &series, today).await {
+        eprintln!("Error creating meeting occurrences: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to create meeting series");
+    }
+    let details = match series_details(&mut tx, series, host_timezone).await {
+        Ok(details) => details,
+        Err(e) => {
+            eprintln!("Error fetching meeting occurrences: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to create meeting series");
+        }
+    };
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing transaction: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to create meeting series");
+    }
+
+    HttpResponse::Created().json(details)
+}
+
+//Get Meeting Series
+//Get Meeting Series Input: HttpRequest(JWT Token), Path (/meetings/series/{series_id})
+//Get Meeting Series Output: MeetingSeriesDetails
+pub async fn get_meeting_series(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let viewer_timezone = match user_timezone(pool.get_ref(), user_id).await {
+        Ok(tz) => tz,
+        Err(e) => {
+            eprintln!("Error fetching timezone: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch meeting series");
+        }
+    };
+
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn,
+        Err(e) => {
+            eprintln!("Error acquiring connection: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch meeting series");
+        }
+    };
+    let series = match sqlx::query_as::<_, MeetingSeries>(
+        "SELECT * FROM meeting_series WHERE series_id = $1",
+    )
+    .bind(path.into_inner())
+    .fetch_optional(&mut *conn)
+    .await
+    {
+        Ok(Some(series)) => series,
+        Ok(None) => return HttpResponse::NotFound().body("Meeting series not found"),
+        Err(e) => {
+            eprintln!("Error fetching meeting series: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch meeting series");
+        }
+    };
+
+    match series_details(&mut conn, series, viewer_timezone).await {
+        Ok(details) => HttpResponse::Ok().json(details),
+        Err(e) => {
+            eprintln!("Error fetching meeting occurrences: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch meeting series")
+        }
+    }
+}
+
+//Update Meeting Series
+//Update Meeting Series Input: HttpRequest(JWT Token), Path (/meetings/series/{series_id}), UpdateMeetingSeriesRequest
+//Update Meeting Series Output: MeetingSeriesDetails (a new series when split at from_date)
+//Editing from the first occurrence changes the whole series; a later from_date ends the series
+//the day before and continues it as a new series ("this and following")
+pub async fn update_meeting_series(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    payload: web::Json<UpdateMeetingSeriesRequest>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let today = Utc::now().date_naive();
+    let from = payload.from_date.unwrap_or(today);
+    if from < today {
+        return HttpResponse::BadRequest().body("Past meetings can't be changed");
+    }
+    if let Some(title) = &payload.title {
+        if title.trim().chars().count() < MIN_MEETING_TITLE_LENGTH {
+            return HttpResponse::BadRequest().body(format!(
+                "Title must be at least {} characters",
+                MIN_MEETING_TITLE_LENGTH
+            ));
+        }
+    }
+    let new_rule = match payload.rrule.as_deref().map(normalise_rule) {
+        Some(Ok((rule, _))) => Some(rule),
+        Some(Err(e)) => return HttpResponse::BadRequest().body(e),
+        None => None,
+    };
+    let new_timezone = match payload.timezone.as_deref().map(parse_timezone) {
+        Some(Ok(tz)) => Some(tz),
+        Some(Err(msg)) => return HttpResponse::BadRequest().body(msg),
+        None => None,
+    };
+    let viewer_timezone = match user_timezone(pool.get_ref(), claims.id).await {
+        Ok(tz) => tz,
+        Err(e) => {
+            eprintln!("Error fetching timezone: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to update meeting series");
+        }
+    };
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to update meeting series");
+        }
+    };
+    let series = match fetch_managed_series(&mut tx, &claims, path.into_inner()).await {
+        Ok(Ok(series)) => series,
+        Ok(Err(response)) => return response,
+        Err(e) => {
+            eprintln!("Error fetching meeting series: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to update meeting series");
+        }
+    };
+    let current_rule: RecurrenceRule = match series.rrule.parse() {
+        Ok(rule) => rule,
+        Err(e) => {
+            eprintln!(
+                "Meeting series {} has an invalid rule: {}",
+                series.series_id, e
+            );
+            return HttpResponse::InternalServerError().body("Failed to update meeting series");
+        }
+    };
+
+    let title = payload
+        .title
+        .as_deref()
+        .map(str::trim)
+        .unwrap_or(&series.title);
+    let description = payload
+        .description
+        .as_deref()
+        .or(series.description.as_deref());
+    let start_time = payload.start_time.unwrap_or(series.start_time);
+    let timezone = new_timezone.map(|tz| tz.name()).unwrap_or(&series.timezone);
+
+    let updated = if from <= series.starts_on {
+        let rule = new_rule.unwrap_or(current_rule);
+        let update_query = "
+            UPDATE meeting_series
+            SET title = $2, description = $3, rrule = $4, start_time = $5, timezone = $6,
+                updated_at = NOW()
+            WHERE series_id = $1
+            RETURNING *
+        ";
+        let updated = sqlx::query_as::<_, MeetingSeries>(update_query)
+            .bind(series.series_id)
+            .bind(title)
+            .bind(description)
+            .bind(rule.to_string())
+            .bind(start_time)
+            .bind(timezone)
+            .fetch_one(&mut *tx)
+            .await;
+        match updated {
+            Ok(updated) => reconcile_occurrences(&mut tx, series.series_id, &updated, today)
+                .await
+                .map(|announcements| (updated, announcements)),
+            Err(e) => Err(e),
+        }
+    } else {
+        let previous_day = from - Days::new(1);
+        // A new rule starts on the chosen date. The old rule carries on from its next
+        // occurrence, so an interval keeps its phase, and a counted series keeps the
+        // occurrences it has left
+        let (rule, starts_on) = match new_rule {
+            Some(rule) => (rule, from),
+            None => {
+                let starts_on = match current_rule.next_occurrence(series.starts_on, from) {
+                    Some(date) => date,
+                    None => {
+                        return HttpResponse::BadRequest()
+                            .body("The meeting series has no meetings left from that date")
+                    }
+                };
+                let rule = match current_rule.count {
+                    Some(count) => {
+                        let used = current_rule
+                            .occurrences(series.starts_on, previous_day)
+                            .len() as u32;
+                        RecurrenceRule {
+                            count: Some(count - used),
+                            ..current_rule.clone()
+                        }
+                    }
+                    None => current_rule.clone(),
+                };
+                (rule, starts_on)
+            }
+        };
+
+        let end_query =
+            "UPDATE meeting_series SET rrule = $2, updated_at = NOW() WHERE series_id = $1";
+        let insert_query = "
+            INSERT INTO meeting_series (support_group_id, host_id, title, description, rrule,
+                                        starts_on, start_time, timezone, parent_series_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+        ";
+        let ended = sqlx::query(end_query)
+            .bind(series.series_id)
+            .bind(current_rule.ending_on(previous_day).to_string())
+            .execute(&mut *tx)
+            .await;
+        let continued = match ended {
+            Ok(_) => {
+                sqlx::query_as::<_, MeetingSeries>(insert_query)
+                    .bind(series.support_group_id)
+                    .bind(series.host_id)
+                    .bind(title)
+                    .bind(description)
+                    .bind(rule.to_string())
+                    .bind(starts_on)
+                    .bind(start_time)
+                    .bind(timezone)
+                    .bind(series.series_id)
+                    .fetch_one(&mut *tx)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        match continued {
+            Ok(continued) => reconcile_occurrences(&mut tx, series.series_id, &continued, from)
+                .await
+                .map(|announcements| (continued, announcements)),
+            Err(e) => Err(e),
+        }
+    };
+
+    let details = match updated {
+        Ok((updated, announcements)) => series_details(&mut tx, updated, viewer_timezone)
+            .await
+            .map(|details| (details, announcements)),
+        Err(e) => Err(e),
+    };
+    let (details, announcements) = match details {
+        Ok(details) => details,
+        Err(e) => {
+            eprintln!("Error updating meeting series: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to update meeting series");
+        }
+    };
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing transaction: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to update meeting series");
+    }
+    push_announcements(&announcements).await;
+
+    HttpResponse::Ok().json(details)
+}
+
+//End Meeting Series
+//End Meeting Series Input: HttpRequest(JWT Token), Path (/meetings/series/{series_id})
+//End Meeting Series Output: Success message
+//Cancels every upcoming occurrence; past meetings are kept
+pub async fn end_meeting_series(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to end meeting series");
+        }
+    };
+    let series = match fetch_managed_series(&mut tx, &claims, path.into_inner()).await {
+        Ok(Ok(series)) => series,
+        Ok(Err(response)) => return response,
+        Err(e) => {
+            eprintln!("Error fetching meeting series: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to end meeting series");
+        }
+    };
+
+    if let Err(e) = end_series(&mut tx, series.series_id).await {
+        eprintln!("Error ending meeting series: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to end meeting series");
+    }
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing transaction: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to end meeting series");
+    }
+
+    HttpResponse::Ok().body("Meeting series ended")
+}
+
+//Cancel Meeting Occurrence
+//Cancel Meeting Occurrence Input: HttpRequest(JWT Token), Path (/meetings/series/{series_id}/occurrences/{date}/cancel)
+//Cancel Meeting Occurrence Output: GroupMeeting
+//Works for occurrences that haven't been created yet too; the cancelled one is kept as an exception
+pub async fn cancel_meeting_occurrence(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<(Uuid, NaiveDate)>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let (series_id, date) = path.into_inner();
+    if date < Utc::now().date_naive() {
+        return HttpResponse::BadRequest().body("Past meetings can't be cancelled");
+    }
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to cancel meeting");
+        }
+    };
+    let series = match fetch_managed_series(&mut tx, &claims, series_id).await {
+        Ok(Ok(series)) => series,
+        Ok(Err(response)) => return response,
+        Err(e) => {
+            eprintln!("Error fetching meeting series: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to cancel meeting");
+        }
+    };
+    let on_rule = series
+        .rrule
+        .parse::<RecurrenceRule>()
+        .map(|rule| rule.occurrences(series.starts_on, date).last() == Some(&date))
+        .unwrap_or(false);
+    if !on_rule {
+        return HttpResponse::NotFound().body("The meeting series has no meeting on that date");
+    }
+
+    // An occurrence beyond the horizon is created already cancelled, so the job skips it later
+    let cancel_query = "
+        INSERT INTO group_meetings (group_chat_id, support_group_id, host_id, title, description,
+                                    scheduled_time, timezone, status, series_id, occurrence_date)
+        SELECT group_chat_id, $2, $3, $4, $5, $6, $8, 'cancelled', $1, $7
+        FROM support_groups WHERE support_group_id = $2
+        ON CONFLICT (series_id, occurrence_date) DO UPDATE SET status = 'cancelled'
+            WHERE group_meetings.status = 'upcoming'
+        RETURNING *
+    ";
+    let meeting = match sqlx::query_as::<_, GroupMeeting>(cancel_query)
+        .bind(series.series_id)
+        .bind(series.support_group_id)
+        .bind(series.host_id)
+        .bind(&series.title)
+        .bind(&series.description)
+        .bind(occurrence_start(&series, date))
+        .bind(date)
+        .bind(&series.timezone)
+        .fetch_optional(&mut *tx)
+        .await
+    {
+        Ok(Some(meeting)) => meeting,
+        Ok(None) => {
+            return HttpResponse::BadRequest().body("Only upcoming meetings can be cancelled")
+        }
+        Err(e) => {
+            eprintln!("Error cancelling meeting: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to cancel meeting");
+        }
+    };
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing transaction: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to cancel meeting");
+    }
+
+    HttpResponse::Ok().json(meeting)
+}
+
+//Config Meeting Series Routes
+//Registered inside the /meetings scope, ahead of the /{meeting_id} routes
+// POST /meetings/series
+// GET /meetings/series/{series_id}
+// PATCH /meetings/series/{series_id}
+// DELETE /meetings/series/{series_id}
+// POST /meetings/series/{series_id}/occurrences/{date}/cancel
+pub fn config_meeting_series_routes(cfg: &mut web::ServiceConfig) {
+    cfg.route("/series", web::post().to(create_meeting_series))
+        .route("/series/{series_id}", web::get().to(get_meeting_series))
+        .route(
+            "/series/{series_id}",
+            web::patch().to(update_meeting_series),
+        )
+        .route("/series/{series_id}", web::delete().to(end_meeting_series))
+        .route(
+            "/series/{series_id}/occurrences/{date}/cancel",
+            web::post().to(cancel_meeting_occurrence),
+        );
+}
@@ -1,12 +1,24 @@
 pub mod admin;
+pub mod announcements;
+pub mod attachments;
+pub mod blocking;
+pub mod calendar;
+pub mod check_ins;
+pub mod data_export;
 pub mod group_chats;
+pub mod meeting_series;
+pub mod notifications;
 pub mod posts;
 pub mod private_messaging;
+pub mod recovery;
 pub mod report;
 pub mod resources;
+pub mod search;
 pub mod sponsor_matching;
 pub mod sponsor_role;
+pub mod sponsorships;
 pub mod support_group_meetings;
 pub mod support_groups;
+pub mod taxonomy;
 pub mod user_auth;
 pub mod user_data;
@@ -0,0 +1,133 @@
+use crate::handlers::auth::Claims;
+use crate::models::all_models::Notification;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const DEFAULT_NOTIFICATION_LIMIT: i64 = 50;
+const MAX_NOTIFICATION_LIMIT: i64 = 200;
+
+//List Notifications Params
+#[derive(Debug, Deserialize)]
+pub struct ListNotificationsParams {
+    pub unread_only: Option<bool>,
+    pub limit: Option<i64>,
+}
+
+//List Notifications
+//List Notifications Input: HttpRequest(JWT Token), Query (?unread_only=&limit=)
+//List Notifications Output: Vec<Notification>
+pub async fn list_notifications(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    query: web::Query<ListNotificationsParams>,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_NOTIFICATION_LIMIT)
+        .clamp(1, MAX_NOTIFICATION_LIMIT);
+
+    let list_query = "
+        SELECT * FROM notifications
+        WHERE user_id = $1 AND (NOT $2 OR read_at IS NULL)
+        ORDER BY created_at DESC
+        LIMIT $3
+    ";
+    match sqlx::query_as::<_, Notification>(list_query)
+        .bind(user_id)
+        .bind(query.unread_only.unwrap_or(false))
+        .bind(limit)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(notifications) => HttpResponse::Ok().json(notifications),
+        Err(e) => {
+            eprintln!("Error fetching notifications: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch notifications")
+        }
+    }
+}
+
+//Mark Notification Read
+//Mark Notification Read Input: HttpRequest(JWT Token), Path (/notifications/{notification_id}/read)
+//Mark Notification Read Output: Notification
+pub async fn mark_notification_read(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let update_query = "
+        UPDATE notifications SET read_at = COALESCE(read_at, NOW())
+        WHERE notification_id = $1 AND user_id = $2
+        RETURNING *
+    ";
+    match sqlx::query_as::<_, Notification>(update_query)
+        .bind(path.into_inner())
+        .bind(user_id)
+        .fetch_optional(pool.get_ref())
+        .await
+    {
+        Ok(Some(notification)) => HttpResponse::Ok().json(notification),
+        Ok(None) => HttpResponse::NotFound().body("Notification not found"),
+        Err(e) => {
+            eprintln!("Error marking notification read: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update notification")
+        }
+    }
+}
+
+//Mark All Notifications Read
+//Mark All Notifications Read Input: HttpRequest(JWT Token)
+//Mark All Notifications Read Output: Success message
+pub async fn mark_all_notifications_read(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    match sqlx::query(
+        "UPDATE notifications SET read_at = NOW() WHERE user_id = $1 AND read_at IS NULL",
+    )
+    .bind(user_id)
+    .execute(pool.get_ref())
+    .await
+    {
+        Ok(result) => HttpResponse::Ok().body(format!(
+            "Marked {} notifications as read",
+            result.rows_affected()
+        )),
+        Err(e) => {
+            eprintln!("Error marking notifications read: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update notifications")
+        }
+    }
+}
+
+//Config Notification Routes
+// GET /notifications
+// PATCH /notifications/read-all
+// PATCH /notifications/{notification_id}/read
+pub fn config_notification_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/notifications")
+            .route("", web::get().to(list_notifications))
+            .route("/read-all", web::patch().to(mark_all_notifications_read))
+            .route(
+                "/{notification_id}/read",
+                web::patch().to(mark_notification_read),
+            ),
+    );
+}
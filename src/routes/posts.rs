@@ -1,9 +1,10 @@
 use crate::handlers::auth::Claims;
-use crate::models::all_models::{Comment, Post, PostLike};
+use crate::handlers::blocking::{hidden_user_ids, is_blocked_between};
+use crate::models::all_models::{Comment, Post, PostLike, UserRole};
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use sqlx::{PgPool, Row};
+use sqlx::{PgConnection, PgPool, Row};
 use uuid::Uuid;
 
 // Create Post Request
@@ -11,6 +12,8 @@ use uuid::Uuid;
 pub struct CreatePostRequest {
     pub content: String,
     pub tags: Option<Vec<String>>,
+    // Posts in a support group are only shown to its members
+    pub support_group_id: Option<Uuid>,
 }
 
 // Post with likes and comments model for API responses
@@ -23,6 +26,76 @@ pub struct PostWithDetails {
     pub like_count: i64,
 }
 
+// Whether the user can read and write posts in a support group: its members, and admins
+pub async fn can_access_group_posts(
+    pool: &PgPool,
+    support_group_id: Uuid,
+    claims: &Claims,
+) -> Result<bool, sqlx::Error> {
+    if claims.role == UserRole::Admin {
+        return Ok(true);
+    }
+    sqlx::query_scalar(
+        "SELECT EXISTS (
+            SELECT 1 FROM support_group_members sgm
+            JOIN support_groups sg ON sg.support_group_id = sgm.support_group_id
+            WHERE sgm.support_group_id = $1 AND sgm.user_id = $2 AND sg.status = 'approved'
+        )",
+    )
+    .bind(support_group_id)
+    .bind(claims.id)
+    .fetch_one(pool)
+    .await
+}
+
+// Whether the user can see a post: it exists, no block stands between them and its author,
+// and a support group post is only seen by the group's members
+pub async fn can_view_post(
+    pool: &PgPool,
+    post_id: Uuid,
+    claims: &Claims,
+) -> Result<bool, sqlx::Error> {
+    let post = sqlx::query_as::<_, (Uuid, Option<Uuid>)>(
+        "SELECT author_id, support_group_id FROM posts WHERE post_id = $1",
+    )
+    .bind(post_id)
+    .fetch_optional(pool)
+    .await?;
+    let Some((author_id, support_group_id)) = post else {
+        return Ok(false);
+    };
+    if is_blocked_between(pool, claims.id, author_id).await? {
+        return Ok(false);
+    }
+    match support_group_id {
+        Some(support_group_id) => can_access_group_posts(pool, support_group_id, claims).await,
+        None => Ok(true),
+    }
+}
+
+// Insert a post, in the public feed or in one support group
+pub async fn insert_post(
+    conn: &mut PgConnection,
+    author_id: Uuid,
+    content: &str,
+    tags: Option<Vec<String>>,
+    support_group_id: Option<Uuid>,
+) -> Result<Post, sqlx::Error> {
+    let query = "
+        INSERT INTO posts (post_id, author_id, content, created_at, tags, support_group_id)
+        VALUES ($1, $2, $3, NOW(), $4, $5)
+        RETURNING post_id, author_id, content, created_at, tags, support_group_id
+    ";
+    sqlx::query_as::<_, Post>(query)
+        .bind(Uuid::new_v4())
+        .bind(author_id)
+        .bind(content)
+        .bind(tags)
+        .bind(support_group_id)
+        .fetch_one(conn)
+        .await
+}
+
 // Create Post Handler
 // Create Post Input: CreatePostRequest
 // Create Post Output: Post
@@ -33,19 +106,34 @@ pub async fn create_post(
 ) -> impl Responder {
     if let Some(claims) = req.extensions().get::<Claims>() {
         let author_id = claims.id;
-        let new_post_id = Uuid::new_v4();
-        let query = "
-            INSERT INTO posts (post_id, author_id, content, created_at, tags)
-            VALUES ($1, $2, $3, NOW(), $4)
-            RETURNING post_id, author_id, content, created_at, tags
-        ";
-        let result = sqlx::query_as::<_, Post>(query)
-            .bind(new_post_id)
-            .bind(author_id)
-            .bind(&payload.content)
-            .bind(payload.tags.clone())
-            .fetch_one(pool.get_ref())
-            .await;
+        if let Some(support_group_id) = payload.support_group_id {
+            match can_access_group_posts(pool.get_ref(), support_group_id, claims).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    return HttpResponse::Forbidden()
+                        .body("Only members of this support group can post in it")
+                }
+                Err(e) => {
+                    eprintln!("Error checking support group membership: {:?}", e);
+                    return HttpResponse::InternalServerError().body("Failed to create post");
+                }
+            }
+        }
+        let mut conn = match pool.acquire().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                eprintln!("Error acquiring connection: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to create post");
+            }
+        };
+        let result = insert_post(
+            &mut conn,
+            author_id,
+            &payload.content,
+            payload.tags.clone(),
+            payload.support_group_id,
+        )
+        .await;
         match result {
             Ok(post) => HttpResponse::Ok().json(post),
             Err(e) => {
@@ -61,12 +149,29 @@ pub async fn create_post(
 // Get Post Handler - Returns a single post with all likes and comments
 // Get Post Input: Post ID
 // Get Post Output: Post with likes and comments
-pub async fn get_post(pool: web::Data<PgPool>, path: web::Path<Uuid>) -> impl Responder {
+pub async fn get_post(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
     let post_id = path.into_inner();
 
+    // Blocks hide a post entirely; mutes only hide comments here since the post was opened directly
+    let hidden = match hidden_user_ids(pool.get_ref(), claims.id).await {
+        Ok(hidden) => hidden,
+        Err(e) => {
+            eprintln!("Error fetching hidden users: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch post");
+        }
+    };
+
     // Get the post
     let post_query = "
-        SELECT post_id, author_id, content, created_at, tags
+        SELECT post_id, author_id, content, created_at, tags, support_group_id
         FROM posts WHERE post_id = $1
     ";
     let post_result = sqlx::query_as::<_, Post>(post_query)
@@ -76,6 +181,25 @@ pub async fn get_post(pool: web::Data<PgPool>, path: web::Path<Uuid>) -> impl Re
 
     match post_result {
         Ok(post) => {
+            match is_blocked_between(pool.get_ref(), claims.id, post.author_id).await {
+                Ok(false) => {}
+                Ok(true) => return HttpResponse::NotFound().body("Post not found"),
+                Err(e) => {
+                    eprintln!("Error checking blocks: {:?}", e);
+                    return HttpResponse::InternalServerError().body("Failed to fetch post");
+                }
+            }
+            if let Some(support_group_id) = post.support_group_id {
+                match can_access_group_posts(pool.get_ref(), support_group_id, &claims).await {
+                    Ok(true) => {}
+                    Ok(false) => return HttpResponse::NotFound().body("Post not found"),
+                    Err(e) => {
+                        eprintln!("Error checking support group membership: {:?}", e);
+                        return HttpResponse::InternalServerError().body("Failed to fetch post");
+                    }
+                }
+            }
+
             // Get likes for this post
             let likes_query = "
                 SELECT post_id, user_id
@@ -109,7 +233,8 @@ pub async fn get_post(pool: web::Data<PgPool>, path: web::Path<Uuid>) -> impl Re
                 .await;
 
             match (likes_result, comments_result, like_count_result) {
-                (Ok(likes), Ok(comments), Ok(like_count)) => {
+                (Ok(likes), Ok(mut comments), Ok(like_count)) => {
+                    comments.retain(|c| !hidden.contains(&c.author_id));
                     let post_with_details = PostWithDetails {
                         post,
                         likes,
@@ -139,6 +264,9 @@ pub struct PostsListParams {
     pub search_tags: Option<String>,
     #[serde(rename = "sort-by")]
     pub sort_by: Option<String>, // "latest" or "most-liked"
+    // Without it the public feed is listed; with it, only that support group's posts
+    #[serde(rename = "support-group")]
+    pub support_group_id: Option<Uuid>,
 }
 
 // List Posts Handler with pagination, tag filtering, sorting, and includes likes and comments
@@ -146,8 +274,38 @@ pub struct PostsListParams {
 // List Posts Output: List of Posts with likes, comments, and like counts (50 per page)
 pub async fn list_posts(
     pool: web::Data<PgPool>,
+    req: HttpRequest,
     params: web::Query<PostsListParams>,
 ) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    // Leave out posts and comments from blocked and muted users
+    let hidden = match hidden_user_ids(pool.get_ref(), claims.id).await {
+        Ok(hidden) => hidden,
+        Err(e) => {
+            eprintln!("Error fetching hidden users: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to list posts");
+        }
+    };
+    let hidden_ids: Vec<Uuid> = hidden.iter().copied().collect();
+
+    if let Some(support_group_id) = params.support_group_id {
+        match can_access_group_posts(pool.get_ref(), support_group_id, &claims).await {
+            Ok(true) => {}
+            Ok(false) => {
+                return HttpResponse::Forbidden()
+                    .body("Only members of this support group can see its posts")
+            }
+            Err(e) => {
+                eprintln!("Error checking support group membership: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to list posts");
+            }
+        }
+    }
+
     // Default to page 1, with 50 posts per page
     let page = params.page.unwrap_or(1);
     let posts_per_page: u32 = 50;
@@ -187,16 +345,19 @@ pub async fn list_posts(
                     GROUP BY post_id
                 )
                 SELECT 
-                    p.post_id, p.author_id, p.content, p.created_at, p.tags,
+                    p.post_id, p.author_id, p.content, p.created_at, p.tags, p.support_group_id,
                     COALESCE(plc.like_count, 0) as like_count
                 FROM posts p
                 LEFT JOIN post_likes_count plc ON p.post_id = plc.post_id
+                WHERE p.author_id <> ALL($1) AND p.support_group_id IS NOT DISTINCT FROM $4
                 {}
-                LIMIT $1 OFFSET $2
+                LIMIT $2 OFFSET $3
             ",
                 order_clause
             ),
-            "SELECT COUNT(*) FROM posts".to_string(),
+            "SELECT COUNT(*) FROM posts
+             WHERE author_id <> ALL($1) AND support_group_id IS NOT DISTINCT FROM $2"
+                .to_string(),
         )
     } else {
         // Filter by tags
@@ -209,17 +370,20 @@ pub async fn list_posts(
                     GROUP BY post_id
                 )
                 SELECT 
-                    p.post_id, p.author_id, p.content, p.created_at, p.tags,
+                    p.post_id, p.author_id, p.content, p.created_at, p.tags, p.support_group_id,
                     COALESCE(plc.like_count, 0) as like_count
                 FROM posts p
                 LEFT JOIN post_likes_count plc ON p.post_id = plc.post_id
-                WHERE COALESCE(p.tags, ARRAY[]::text[]) && $1::text[]
+                WHERE p.author_id <> ALL($1) AND COALESCE(p.tags, ARRAY[]::text[]) && $2::text[]
+                  AND p.support_group_id IS NOT DISTINCT FROM $5
                 {}
-                LIMIT $2 OFFSET $3
+                LIMIT $3 OFFSET $4
             ",
                 order_clause
             ),
-            "SELECT COUNT(*) FROM posts WHERE COALESCE(tags, ARRAY[]::text[]) && $1::text[]"
+            "SELECT COUNT(*) FROM posts
+             WHERE author_id <> ALL($1) AND COALESCE(tags, ARRAY[]::text[]) && $2::text[]
+               AND support_group_id IS NOT DISTINCT FROM $3"
                 .to_string(),
         )
     };
@@ -227,6 +391,8 @@ pub async fn list_posts(
     // Get total count for pagination metadata
     let total_count = if tags.is_empty() {
         match sqlx::query_scalar::<_, i64>(&count_query)
+            .bind(&hidden_ids)
+            .bind(params.support_group_id)
             .fetch_one(pool.get_ref())
             .await
         {
@@ -235,7 +401,9 @@ pub async fn list_posts(
         }
     } else {
         match sqlx::query_scalar::<_, i64>(&count_query)
+            .bind(&hidden_ids)
             .bind(&tags)
+            .bind(params.support_group_id)
             .fetch_one(pool.get_ref())
             .await
         {
@@ -247,15 +415,19 @@ pub async fn list_posts(
     // Execute the main query to get posts
     let posts_result = if tags.is_empty() {
         sqlx::query(&base_query)
+            .bind(&hidden_ids)
             .bind(posts_per_page as i64)
             .bind(offset as i64)
+            .bind(params.support_group_id)
             .fetch_all(pool.get_ref())
             .await
     } else {
         sqlx::query(&base_query)
+            .bind(&hidden_ids)
             .bind(&tags)
             .bind(posts_per_page as i64)
             .bind(offset as i64)
+            .bind(params.support_group_id)
             .fetch_all(pool.get_ref())
             .await
     };
@@ -272,6 +444,7 @@ pub async fn list_posts(
                     content: row.try_get("content").unwrap_or_default(),
                     created_at: row.try_get("created_at").unwrap_or_default(),
                     tags: row.try_get("tags").unwrap_or_default(),
+                    support_group_id: row.try_get("support_group_id").unwrap_or_default(),
                 };
                 let like_count: i64 = row.try_get("like_count").unwrap_or_default();
 
@@ -305,7 +478,10 @@ pub async fn list_posts(
                     .fetch_all(pool.get_ref())
                     .await
                 {
-                    Ok(comments) => comments,
+                    Ok(comments) => comments
+                        .into_iter()
+                        .filter(|c| !hidden.contains(&c.author_id))
+                        .collect(),
                     Err(e) => {
                         eprintln!("Error fetching comments for post {}: {:?}", post_id, e);
                         vec![]
@@ -358,7 +534,7 @@ pub async fn update_post(
             SET content = COALESCE($1, content),
                 tags = COALESCE($2, tags)
             WHERE post_id = $3 AND author_id = $4
-            RETURNING post_id, author_id, content, created_at, tags
+            RETURNING post_id, author_id, content, created_at, tags, support_group_id
         ";
         let result = sqlx::query_as::<_, Post>(query)
             .bind(&payload.content)
@@ -432,6 +608,16 @@ pub async fn toggle_post_like(
     if let Some(claims) = req.extensions().get::<Claims>() {
         let user_id = claims.id;
 
+        // Posts the user can't see can't be liked or unliked either
+        match can_view_post(pool.get_ref(), payload.post_id, claims).await {
+            Ok(true) => {}
+            Ok(false) => return HttpResponse::NotFound().body("Post not found"),
+            Err(e) => {
+                eprintln!("Error checking post access: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to check like status");
+            }
+        }
+
         // First check if the user already liked the post
         let check_query =
             "SELECT EXISTS(SELECT 1 FROM post_likes WHERE post_id = $1 AND user_id = $2)";
@@ -520,6 +706,17 @@ pub async fn create_comment(
 ) -> impl Responder {
     if let Some(claims) = req.extensions().get::<Claims>() {
         let author_id = claims.id;
+
+        // Blocked authors' posts and other groups' posts are invisible to this user
+        match can_view_post(pool.get_ref(), payload.post_id, claims).await {
+            Ok(true) => {}
+            Ok(false) => return HttpResponse::NotFound().body("Post not found"),
+            Err(e) => {
+                eprintln!("Error checking post access: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to create comment");
+            }
+        }
+
         let new_comment_id = Uuid::new_v4();
         let query = "
             INSERT INTO comments (comment_id, post_id, author_id, content, created_at, parent_comment_id)
@@ -627,7 +824,7 @@ pub async fn delete_comment(
 }
 
 // Feed Routes
-// GET /feed/posts - List posts with pagination and optional tag filtering
+// GET /feed/posts - List posts with pagination, optional tag filtering and support group
 // POST /feed/posts/new - Create a new post
 // GET /feed/posts/{id} - Get a specific post
 // PATCH /feed/posts/{id} - Update a post
@@ -1,4 +1,5 @@
 use crate::handlers::auth::Claims;
+use crate::handlers::blocking::is_blocked_between;
 use crate::models::all_models::{Message, Report, ReportStatus, ReportedType};
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use serde::{Deserialize, Serialize};
@@ -38,6 +39,15 @@ pub async fn send_message(
             }
         };
 
+        match is_blocked_between(pool.get_ref(), sender_id, receiver_id).await {
+            Ok(false) => {}
+            Ok(true) => return HttpResponse::Forbidden().body("You can't message this user"),
+            Err(e) => {
+                eprintln!("DB error: {:?}", e);
+                return HttpResponse::InternalServerError().body("Database error");
+            }
+        }
+
         let insert_query = "
             INSERT INTO messages (sender_id, receiver_id, content, timestamp, deleted, edited)
             VALUES ($1, $2, $3, NOW(), false, false)
@@ -0,0 +1,546 @@
+use crate::handlers::auth::Claims;
+use crate::handlers::blocking::has_blocked;
+use crate::handlers::privacy::{privacy_settings_for, viewer_relation, ViewerRelation};
+use crate::handlers::recovery::{record_reached_milestones, RecoveryProgress};
+use crate::handlers::taxonomy::Taxonomy;
+use crate::models::all_models::{
+    RecoveryJourney, RecoveryMilestone, RecoveryReset, TaxonomyKind, UserRole, VisibilityAudience,
+};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const MAX_RESET_NOTE_LENGTH: usize = 2000;
+
+//Create Recovery Journey Request
+#[derive(Debug, Deserialize)]
+pub struct CreateRecoveryJourneyRequest {
+    pub experience: String,
+    pub started_at: NaiveDateTime,
+    pub share_with_groups: Option<bool>,
+}
+
+//Update Recovery Journey Request
+#[derive(Debug, Deserialize)]
+pub struct UpdateRecoveryJourneyRequest {
+    pub share_with_groups: bool,
+}
+
+//Reset Recovery Journey Request
+#[derive(Debug, Deserialize)]
+pub struct ResetRecoveryJourneyRequest {
+    // Defaults to now
+    pub started_at: Option<NaiveDateTime>,
+    pub note: Option<String>,
+}
+
+//Recovery Journey Details
+//The journey with its progress, milestones reached since the current start and every reset
+#[derive(Debug, Serialize)]
+pub struct RecoveryJourneyDetails {
+    #[serde(flatten)]
+    pub progress: RecoveryProgress,
+    pub milestones: Vec<RecoveryMilestone>,
+    pub resets: Vec<RecoveryReset>,
+}
+
+//Shared Recovery Progress
+//What another user can see: no resets or notes, and no area unless their experience is visible
+#[derive(Debug, Serialize)]
+pub struct SharedRecoveryProgress {
+    pub experience: Option<String>,
+    pub started_at: NaiveDateTime,
+    pub days: i32,
+    pub current_milestone: Option<String>,
+}
+
+// Helper function: Group chats and posts show milestones to peers, so sharing needs peers in the audience
+fn peers_can_see(audience: VisibilityAudience) -> bool {
+    ViewerRelation {
+        is_peer: true,
+        ..Default::default()
+    }
+    .can_see(audience)
+}
+
+// Helper function: Fetch one of the caller's journeys
+async fn fetch_own_journey(
+    pool: &PgPool,
+    user_id: Uuid,
+    journey_id: Uuid,
+) -> Result<Option<RecoveryJourney>, sqlx::Error> {
+    sqlx::query_as::<_, RecoveryJourney>(
+        "SELECT * FROM recovery_journeys WHERE journey_id = $1 AND user_id = $2",
+    )
+    .bind(journey_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+//List Recovery Journeys
+//List Recovery Journeys Input: HttpRequest(JWT Token)
+//List Recovery Journeys Output: Vec<RecoveryProgress>
+pub async fn list_recovery_journeys(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    match sqlx::query_as::<_, RecoveryJourney>(
+        "SELECT * FROM recovery_journeys WHERE user_id = $1 ORDER BY created_at",
+    )
+    .bind(user_id)
+    .fetch_all(pool.get_ref())
+    .await
+    {
+        Ok(journeys) => {
+            let now = Utc::now().naive_utc();
+            let progress: Vec<RecoveryProgress> = journeys
+                .into_iter()
+                .map(|journey| RecoveryProgress::new(journey, now))
+                .collect();
+            HttpResponse::Ok().json(progress)
+        }
+        Err(e) => {
+            eprintln!("Error fetching recovery journeys: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch recovery journeys")
+        }
+    }
+}
+
+//Get Recovery Journey
+//Get Recovery Journey Input: HttpRequest(JWT Token), Path (/recovery/journeys/{journey_id})
+//Get Recovery Journey Output: RecoveryJourneyDetails
+pub async fn get_recovery_journey(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let journey = match fetch_own_journey(pool.get_ref(), user_id, path.into_inner()).await {
+        Ok(Some(journey)) => journey,
+        Ok(None) => return HttpResponse::NotFound().body("Recovery journey not found"),
+        Err(e) => {
+            eprintln!("Error fetching recovery journey: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch recovery journey");
+        }
+    };
+
+    let milestones = sqlx::query_as::<_, RecoveryMilestone>(
+        "SELECT * FROM recovery_milestones
+         WHERE journey_id = $1 AND started_at = $2
+         ORDER BY days",
+    )
+    .bind(journey.journey_id)
+    .bind(journey.started_at)
+    .fetch_all(pool.get_ref())
+    .await;
+    let resets = sqlx::query_as::<_, RecoveryReset>(
+        "SELECT * FROM recovery_resets WHERE journey_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(journey.journey_id)
+    .fetch_all(pool.get_ref())
+    .await;
+
+    match (milestones, resets) {
+        (Ok(milestones), Ok(resets)) => HttpResponse::Ok().json(RecoveryJourneyDetails {
+            progress: RecoveryProgress::new(journey, Utc::now().naive_utc()),
+            milestones,
+            resets,
+        }),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Error fetching recovery history: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch recovery journey")
+        }
+    }
+}
+
+//Create Recovery Journey
+//Create Recovery Journey Input: HttpRequest(JWT Token), CreateRecoveryJourneyRequest
+//Create Recovery Journey Output: RecoveryProgress
+//Milestones already behind the start date are recorded without being celebrated
+pub async fn create_recovery_journey(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    payload: web::Json<CreateRecoveryJourneyRequest>,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let now = Utc::now().naive_utc();
+    if payload.started_at > now {
+        return HttpResponse::BadRequest().body("Start date can't be in the future");
+    }
+
+    let taxonomy = match Taxonomy::load(pool.get_ref()).await {
+        Ok(taxonomy) => taxonomy,
+        Err(e) => {
+            eprintln!("Error loading taxonomy: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to save recovery journey");
+        }
+    };
+    let experience = match taxonomy.resolve(TaxonomyKind::Experience, &payload.experience) {
+        Some(code) => code.to_string(),
+        None => {
+            return HttpResponse::BadRequest().body(format!(
+                "Unknown experience area: {}",
+                payload.experience.trim()
+            ))
+        }
+    };
+
+    let share_with_groups = payload.share_with_groups.unwrap_or(false);
+    if share_with_groups {
+        match privacy_settings_for(pool.get_ref(), user_id).await {
+            Ok(settings) if peers_can_see(settings.recovery) => {}
+            Ok(_) => {
+                return HttpResponse::Conflict().body(
+                    "Your recovery privacy setting hides milestones from your support groups",
+                )
+            }
+            Err(e) => {
+                eprintln!("Error fetching privacy settings: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to save recovery journey");
+            }
+        }
+    }
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            eprintln!("Failed to start transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to save recovery journey");
+        }
+    };
+
+    let insert_query = "
+        INSERT INTO recovery_journeys (user_id, experience, started_at, share_with_groups)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id, experience) DO NOTHING
+        RETURNING *
+    ";
+    let journey = match sqlx::query_as::<_, RecoveryJourney>(insert_query)
+        .bind(user_id)
+        .bind(&experience)
+        .bind(payload.started_at)
+        .bind(share_with_groups)
+        .fetch_optional(&mut *tx)
+        .await
+    {
+        Ok(Some(journey)) => journey,
+        Ok(None) => {
+            return HttpResponse::Conflict()
+                .body("You are already tracking this area. Reset it to start the count again.")
+        }
+        Err(e) => {
+            eprintln!("Error saving recovery journey: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to save recovery journey");
+        }
+    };
+
+    if let Err(e) = record_reached_milestones(&mut tx, &journey).await {
+        eprintln!("Error recording milestones: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to save recovery journey");
+    }
+    if let Err(e) = tx.commit().await {
+        eprintln!("Failed to commit recovery journey: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to save recovery journey");
+    }
+
+    HttpResponse::Created().json(RecoveryProgress::new(journey, now))
+}
+
+//Update Recovery Journey
+//Update Recovery Journey Input: HttpRequest(JWT Token), Path (/recovery/journeys/{journey_id}), UpdateRecoveryJourneyRequest
+//Update Recovery Journey Output: RecoveryProgress
+pub async fn update_recovery_journey(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    payload: web::Json<UpdateRecoveryJourneyRequest>,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    if payload.share_with_groups {
+        match privacy_settings_for(pool.get_ref(), user_id).await {
+            Ok(settings) if peers_can_see(settings.recovery) => {}
+            Ok(_) => {
+                return HttpResponse::Conflict().body(
+                    "Your recovery privacy setting hides milestones from your support groups",
+                )
+            }
+            Err(e) => {
+                eprintln!("Error fetching privacy settings: {:?}", e);
+                return HttpResponse::InternalServerError()
+                    .body("Failed to update recovery journey");
+            }
+        }
+    }
+
+    match sqlx::query_as::<_, RecoveryJourney>(
+        "UPDATE recovery_journeys SET share_with_groups = $3, updated_at = NOW()
+         WHERE journey_id = $1 AND user_id = $2
+         RETURNING *",
+    )
+    .bind(path.into_inner())
+    .bind(user_id)
+    .bind(payload.share_with_groups)
+    .fetch_optional(pool.get_ref())
+    .await
+    {
+        Ok(Some(journey)) => {
+            HttpResponse::Ok().json(RecoveryProgress::new(journey, Utc::now().naive_utc()))
+        }
+        Ok(None) => HttpResponse::NotFound().body("Recovery journey not found"),
+        Err(e) => {
+            eprintln!("Error updating recovery journey: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update recovery journey")
+        }
+    }
+}
+
+//Reset Recovery Journey
+//Reset Recovery Journey Input: HttpRequest(JWT Token), Path (/recovery/journeys/{journey_id}/reset), ResetRecoveryJourneyRequest
+//Reset Recovery Journey Output: RecoveryProgress
+//Starts the count again; the note is private to the user
+pub async fn reset_recovery_journey(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    payload: web::Json<ResetRecoveryJourneyRequest>,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let now = Utc::now().naive_utc();
+    let started_at = payload.started_at.unwrap_or(now);
+    if started_at > now {
+        return HttpResponse::BadRequest().body("Start date can't be in the future");
+    }
+    let note = payload
+        .note
+        .as_deref()
+        .map(str::trim)
+        .filter(|note| !note.is_empty());
+    if note.is_some_and(|note| note.chars().count() > MAX_RESET_NOTE_LENGTH) {
+        return HttpResponse::BadRequest().body(format!(
+            "Note must be at most {} characters",
+            MAX_RESET_NOTE_LENGTH
+        ));
+    }
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            eprintln!("Failed to start transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to reset recovery journey");
+        }
+    };
+
+    let previous_started_at: NaiveDateTime = match sqlx::query_scalar(
+        "SELECT started_at FROM recovery_journeys
+         WHERE journey_id = $1 AND user_id = $2
+         FOR UPDATE",
+    )
+    .bind(path.as_ref())
+    .bind(user_id)
+    .fetch_optional(&mut *tx)
+    .await
+    {
+        Ok(Some(previous)) => previous,
+        Ok(None) => return HttpResponse::NotFound().body("Recovery journey not found"),
+        Err(e) => {
+            eprintln!("Error fetching recovery journey: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to reset recovery journey");
+        }
+    };
+    if started_at <= previous_started_at {
+        return HttpResponse::BadRequest().body("The new start must be after the current one");
+    }
+
+    let journey = match sqlx::query_as::<_, RecoveryJourney>(
+        "UPDATE recovery_journeys SET started_at = $2, updated_at = NOW()
+         WHERE journey_id = $1
+         RETURNING *",
+    )
+    .bind(path.as_ref())
+    .bind(started_at)
+    .fetch_one(&mut *tx)
+    .await
+    {
+        Ok(journey) => journey,
+        Err(e) => {
+            eprintln!("Error resetting recovery journey: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to reset recovery journey");
+        }
+    };
+
+    if let Err(e) = sqlx::query(
+        "INSERT INTO recovery_resets (journey_id, previous_started_at, started_at, note)
+         VALUES ($1, $2, $3, $4)",
+    )
+    .bind(journey.journey_id)
+    .bind(previous_started_at)
+    .bind(started_at)
+    .bind(note)
+    .execute(&mut *tx)
+    .await
+    {
+        eprintln!("Error recording reset: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to reset recovery journey");
+    }
+
+    if let Err(e) = record_reached_milestones(&mut tx, &journey).await {
+        eprintln!("Error recording milestones: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to reset recovery journey");
+    }
+    if let Err(e) = tx.commit().await {
+        eprintln!("Failed to commit reset: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to reset recovery journey");
+    }
+
+    HttpResponse::Ok().json(RecoveryProgress::new(journey, now))
+}
+
+//Delete Recovery Journey
+//Delete Recovery Journey Input: HttpRequest(JWT Token), Path (/recovery/journeys/{journey_id})
+//Delete Recovery Journey Output: Success message
+pub async fn delete_recovery_journey(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    match sqlx::query("DELETE FROM recovery_journeys WHERE journey_id = $1 AND user_id = $2")
+        .bind(path.into_inner())
+        .bind(user_id)
+        .execute(pool.get_ref())
+        .await
+    {
+        Ok(result) if result.rows_affected() > 0 => {
+            HttpResponse::Ok().body("Recovery journey deleted successfully")
+        }
+        Ok(_) => HttpResponse::NotFound().body("Recovery journey not found"),
+        Err(e) => {
+            eprintln!("Error deleting recovery journey: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to delete recovery journey")
+        }
+    }
+}
+
+//Get User Recovery
+//Get User Recovery Input: HttpRequest(JWT Token), Path (/recovery/users/{user_id})
+//Get User Recovery Output: Vec<SharedRecoveryProgress>
+//Follows the user's recovery privacy setting; hidden looks the same as not tracking anything
+pub async fn get_user_recovery(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let user_id = path.into_inner();
+
+    let blocked = if claims.role == UserRole::Admin {
+        Ok(false)
+    } else {
+        has_blocked(pool.get_ref(), user_id, claims.id).await
+    };
+    let settings = privacy_settings_for(pool.get_ref(), user_id).await;
+    let relation = viewer_relation(pool.get_ref(), claims.id, claims.role, user_id).await;
+    let (settings, relation) = match (blocked, settings, relation) {
+        (Ok(false), Ok(settings), Ok(relation)) => (settings, relation),
+        (Ok(true), _, _) => return HttpResponse::Ok().json(Vec::<SharedRecoveryProgress>::new()),
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            eprintln!("Error checking recovery visibility: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch recovery");
+        }
+    };
+    if !relation.can_see(settings.recovery) {
+        return HttpResponse::Ok().json(Vec::<SharedRecoveryProgress>::new());
+    }
+    let show_experience = relation.can_see(settings.experience);
+
+    match sqlx::query_as::<_, RecoveryJourney>(
+        "SELECT j.* FROM recovery_journeys j
+         JOIN users u ON u.user_id = j.user_id
+         WHERE j.user_id = $1 AND u.deleted_at IS NULL
+         ORDER BY j.created_at",
+    )
+    .bind(user_id)
+    .fetch_all(pool.get_ref())
+    .await
+    {
+        Ok(journeys) => {
+            let now = Utc::now().naive_utc();
+            let shared: Vec<SharedRecoveryProgress> = journeys
+                .into_iter()
+                .map(|journey| {
+                    let progress = RecoveryProgress::new(journey, now);
+                    SharedRecoveryProgress {
+                        experience: show_experience.then_some(progress.journey.experience),
+                        started_at: progress.journey.started_at,
+                        days: progress.days,
+                        current_milestone: progress.current_milestone,
+                    }
+                })
+                .collect();
+            HttpResponse::Ok().json(shared)
+        }
+        Err(e) => {
+            eprintln!("Error fetching recovery journeys: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch recovery")
+        }
+    }
+}
+
+//Config Recovery Routes
+// GET /recovery/journeys
+// POST /recovery/journeys
+// GET /recovery/journeys/{journey_id}
+// PATCH /recovery/journeys/{journey_id}
+// DELETE /recovery/journeys/{journey_id}
+// POST /recovery/journeys/{journey_id}/reset
+// GET /recovery/users/{user_id}
+pub fn config_recovery_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/recovery")
+            .route("/journeys", web::get().to(list_recovery_journeys))
+            .route("/journeys", web::post().to(create_recovery_journey))
+            .route(
+                "/journeys/{journey_id}",
+                web::get().to(get_recovery_journey),
+            )
+            .route(
+                "/journeys/{journey_id}",
+                web::patch().to(update_recovery_journey),
+            )
+            .route(
+                "/journeys/{journey_id}",
+                web::delete().to(delete_recovery_journey),
+            )
+            .route(
+                "/journeys/{journey_id}/reset",
+                web::post().to(reset_recovery_journey),
+            )
+            .route("/users/{user_id}", web::get().to(get_user_recovery)),
+    );
+}
@@ -0,0 +1,426 @@
+use crate::handlers::auth::Claims;
+use crate::handlers::blocking::{blocked_user_ids, hidden_user_ids};
+use crate::handlers::privacy::{placeholder_avatar_url, AudienceCheck};
+use crate::models::all_models::SupportGroupStatus;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+const DEFAULT_RESULTS_PER_SECTION: i64 = 10;
+const MAX_RESULTS_PER_SECTION: i64 = 50;
+const MAX_QUERY_LENGTH: usize = 200;
+
+// Matched terms are wrapped in <mark> so clients can style them
+const HEADLINE_OPTIONS: &str =
+    "StartSel=<mark>, StopSel=</mark>, MaxWords=35, MinWords=15, MaxFragments=2";
+
+//Search Params
+#[derive(Debug, Deserialize)]
+pub struct SearchParams {
+    pub q: String,
+    // Comma separated list of sections: posts, resources, groups, users (defaults to all)
+    pub types: Option<String>,
+    pub limit: Option<i64>,
+}
+
+//Post Hit
+#[derive(Debug, Serialize, FromRow)]
+pub struct PostHit {
+    pub post_id: Uuid,
+    pub author_id: Uuid,
+    pub author_username: String,
+    pub snippet: String,
+    pub tags: Option<Vec<String>>,
+    pub created_at: NaiveDateTime,
+    pub rank: f32,
+}
+
+//Resource Hit
+#[derive(Debug, Serialize, FromRow)]
+pub struct ResourceHit {
+    pub resource_id: Uuid,
+    pub contributor_id: Uuid,
+    pub title: String,
+    pub snippet: String,
+    pub support_group_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub rank: f32,
+}
+
+//Support Group Hit
+#[derive(Debug, Serialize, FromRow)]
+pub struct SupportGroupHit {
+    pub support_group_id: Uuid,
+    pub title: String,
+    pub snippet: String,
+    pub member_count: i64,
+    pub created_at: NaiveDateTime,
+    pub rank: f32,
+}
+
+//User Hit
+#[derive(Debug, Serialize)]
+pub struct UserHit {
+    pub user_id: Uuid,
+    pub username: String,
+    pub avatar_url: String,
+    // Highlighted bio, only present when the viewer may see the bio
+    pub snippet: Option<String>,
+    pub rank: f32,
+}
+
+#[derive(Debug, FromRow)]
+struct UserSearchRow {
+    user_id: Uuid,
+    username: String,
+    avatar_url: String,
+    bio_snippet: Option<String>,
+    name_match: bool,
+    rank: f32,
+}
+
+//Search Response
+#[derive(Debug, Serialize)]
+pub struct SearchResponse {
+    pub query: String,
+    pub posts: Vec<PostHit>,
+    pub resources: Vec<ResourceHit>,
+    pub support_groups: Vec<SupportGroupHit>,
+    pub users: Vec<UserHit>,
+}
+
+// Which sections a search should fill
+struct SearchSections {
+    posts: bool,
+    resources: bool,
+    support_groups: bool,
+    users: bool,
+}
+
+impl SearchSections {
+    fn parse(types: Option<&str>) -> Result<Self, String> {
+        let types = match types {
+            Some(types) if !types.trim().is_empty() => types,
+            _ => {
+                return Ok(SearchSections {
+                    posts: true,
+                    resources: true,
+                    support_groups: true,
+                    users: true,
+                })
+            }
+        };
+
+        let mut sections = SearchSections {
+            posts: false,
+            resources: false,
+            support_groups: false,
+            users: false,
+        };
+        for section in types.split(',').map(|s| s.trim().to_lowercase()) {
+            match section.as_str() {
+                "posts" => sections.posts = true,
+                "resources" => sections.resources = true,
+                "groups" | "support_groups" => sections.support_groups = true,
+                "users" => sections.users = true,
+                other => return Err(format!("Unknown search type: {}", other)),
+            }
+        }
+        Ok(sections)
+    }
+}
+
+// Posts matching the query, leaving out blocked, muted, banned and deleted authors
+async fn search_posts(
+    pool: &PgPool,
+    query: &str,
+    hidden_ids: &[Uuid],
+    limit: i64,
+) -> Result<Vec<PostHit>, sqlx::Error> {
+    let sql = format!(
+        "
+        WITH q AS (SELECT websearch_to_tsquery('english', $1) AS query)
+        SELECT
+            hits.post_id, hits.author_id, hits.author_username, hits.tags, hits.created_at, hits.rank,
+            ts_headline('english', hits.content, q.query, '{}') AS snippet
+        FROM (
+            SELECT
+                p.post_id, p.author_id, u.username AS author_username, p.content, p.tags, p.created_at,
+                ts_rank(p.search_vector, q.query) AS rank
+            FROM posts p
+            JOIN users u ON p.author_id = u.user_id
+            CROSS JOIN q
+            WHERE p.search_vector @@ q.query
+              AND p.support_group_id IS NULL
+              AND p.author_id <> ALL($2)
+              AND u.deleted_at IS NULL
+              AND (u.banned_until IS NULL OR u.banned_until <= NOW())
+            ORDER BY rank DESC, p.created_at DESC
+            LIMIT $3
+        ) hits
+        CROSS JOIN q
+        ORDER BY hits.rank DESC, hits.created_at DESC
+    ",
+        HEADLINE_OPTIONS
+    );
+    sqlx::query_as::<_, PostHit>(&sql)
+        .bind(query)
+        .bind(hidden_ids)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
+// Approved resources matching the query
+async fn search_resources(
+    pool: &PgPool,
+    query: &str,
+    hidden_ids: &[Uuid],
+    limit: i64,
+) -> Result<Vec<ResourceHit>, sqlx::Error> {
+    let sql = format!(
+        "
+        WITH q AS (SELECT websearch_to_tsquery('english', $1) AS query)
+        SELECT
+            hits.resource_id, hits.contributor_id, hits.title, hits.support_group_id,
+            hits.created_at, hits.rank,
+            ts_headline('english', hits.content, q.query, '{}') AS snippet
+        FROM (
+            SELECT
+                r.resource_id, r.contributor_id, r.title, r.content, r.support_group_id, r.created_at,
+                ts_rank(r.search_vector, q.query) AS rank
+            FROM resources r
+            JOIN users u ON r.contributor_id = u.user_id
+            CROSS JOIN q
+            WHERE r.search_vector @@ q.query
+              AND r.approved = TRUE
+              AND r.contributor_id <> ALL($2)
+              AND (u.banned_until IS NULL OR u.banned_until <= NOW())
+            ORDER BY rank DESC, r.created_at DESC
+            LIMIT $3
+        ) hits
+        CROSS JOIN q
+        ORDER BY hits.rank DESC, hits.created_at DESC
+    ",
+        HEADLINE_OPTIONS
+    );
+    sqlx::query_as::<_, ResourceHit>(&sql)
+        .bind(query)
+        .bind(hidden_ids)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
+// Approved support groups matching the query
+async fn search_support_groups(
+    pool: &PgPool,
+    query: &str,
+    limit: i64,
+) -> Result<Vec<SupportGroupHit>, sqlx::Error> {
+    let sql = format!(
+        "
+        WITH q AS (SELECT websearch_to_tsquery('english', $1) AS query)
+        SELECT
+            hits.support_group_id, hits.title, hits.created_at, hits.rank,
+            (SELECT COUNT(*) FROM support_group_members sgm
+             WHERE sgm.support_group_id = hits.support_group_id) AS member_count,
+            ts_headline('english', hits.description, q.query, '{}') AS snippet
+        FROM (
+            SELECT
+                sg.support_group_id, sg.title, sg.description, sg.created_at,
+                ts_rank(sg.search_vector, q.query) AS rank
+            FROM support_groups sg
+            CROSS JOIN q
+            WHERE sg.search_vector @@ q.query AND sg.status = $2
+            ORDER BY rank DESC, sg.created_at DESC
+            LIMIT $3
+        ) hits
+        CROSS JOIN q
+        ORDER BY hits.rank DESC, hits.created_at DESC
+    ",
+        HEADLINE_OPTIONS
+    );
+    sqlx::query_as::<_, SupportGroupHit>(&sql)
+        .bind(query)
+        .bind(SupportGroupStatus::Approved)
+        .bind(limit)
+        .fetch_all(pool)
+        .await
+}
+
+// Users matching the query by username or bio, with the viewer's privacy rules applied
+async fn search_users(
+    pool: &PgPool,
+    claims: &Claims,
+    query: &str,
+    blocked_ids: &[Uuid],
+    limit: i64,
+) -> Result<Vec<UserHit>, sqlx::Error> {
+    let sql = format!(
+        "
+        WITH q AS (SELECT websearch_to_tsquery('simple', $1) AS query)
+        SELECT
+            hits.user_id, hits.username, hits.avatar_url, hits.rank,
+            to_tsvector('simple', hits.username) @@ q.query AS name_match,
+            CASE WHEN hits.bio IS NULL THEN NULL
+                 ELSE ts_headline('simple', hits.bio, q.query, '{}')
+            END AS bio_snippet
+        FROM (
+            SELECT u.user_id, u.username, u.avatar_url, u.bio, ts_rank(u.search_vector, q.query) AS rank
+            FROM users u
+            CROSS JOIN q
+            WHERE u.search_vector @@ q.query
+              AND u.user_id <> ALL($2)
+              AND u.deleted_at IS NULL
+              AND (u.banned_until IS NULL OR u.banned_until <= NOW())
+            ORDER BY rank DESC, u.username ASC
+            LIMIT $3
+        ) hits
+        CROSS JOIN q
+        ORDER BY hits.rank DESC, hits.username ASC
+    ",
+        HEADLINE_OPTIONS
+    );
+    let rows = sqlx::query_as::<_, UserSearchRow>(&sql)
+        .bind(query)
+        .bind(blocked_ids)
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+    let user_ids: Vec<Uuid> = rows.iter().map(|r| r.user_id).collect();
+    let audience = AudienceCheck::load(pool, claims.id, claims.role, &user_ids).await?;
+
+    // A user who only matched on a bio the viewer can't see must not show up at all,
+    // otherwise the search itself would leak what the bio says
+    Ok(rows
+        .into_iter()
+        .filter_map(|row| {
+            let bio_visible = audience.can_see(row.user_id, |s| s.bio);
+            if !row.name_match && !bio_visible {
+                return None;
+            }
+            let avatar_url = if audience.can_see(row.user_id, |s| s.avatar) {
+                row.avatar_url
+            } else {
+                placeholder_avatar_url(&row.username)
+            };
+            Some(UserHit {
+                user_id: row.user_id,
+                username: row.username,
+                avatar_url,
+                snippet: if bio_visible { row.bio_snippet } else { None },
+                rank: row.rank,
+            })
+        })
+        .collect())
+}
+
+//Search
+//Search Input: HttpRequest(JWT Token), SearchParams (?q=&types=&limit=)
+//Search Output: SearchResponse
+pub async fn search(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    params: web::Query<SearchParams>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let query = params.q.trim().to_string();
+    if query.is_empty() {
+        return HttpResponse::BadRequest().body("Search query cannot be empty");
+    }
+    if query.chars().count() > MAX_QUERY_LENGTH {
+        return HttpResponse::BadRequest().body(format!(
+            "Search query cannot be longer than {} characters",
+            MAX_QUERY_LENGTH
+        ));
+    }
+
+    let sections = match SearchSections::parse(params.types.as_deref()) {
+        Ok(sections) => sections,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_RESULTS_PER_SECTION)
+        .clamp(1, MAX_RESULTS_PER_SECTION);
+
+    // Content from blocked and muted users is left out; blocked users are left out of people results
+    let hidden_ids: Vec<Uuid> = match hidden_user_ids(pool.get_ref(), claims.id).await {
+        Ok(hidden) => hidden.into_iter().collect(),
+        Err(e) => {
+            eprintln!("Error fetching hidden users: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to search");
+        }
+    };
+    let blocked_ids: Vec<Uuid> = match blocked_user_ids(pool.get_ref(), claims.id).await {
+        Ok(blocked) => blocked.into_iter().collect(),
+        Err(e) => {
+            eprintln!("Error fetching blocked users: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to search");
+        }
+    };
+
+    let mut response = SearchResponse {
+        query: query.clone(),
+        posts: Vec::new(),
+        resources: Vec::new(),
+        support_groups: Vec::new(),
+        users: Vec::new(),
+    };
+
+    if sections.posts {
+        match search_posts(pool.get_ref(), &query, &hidden_ids, limit).await {
+            Ok(posts) => response.posts = posts,
+            Err(e) => {
+                eprintln!("Error searching posts: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to search posts");
+            }
+        }
+    }
+
+    if sections.resources {
+        match search_resources(pool.get_ref(), &query, &hidden_ids, limit).await {
+            Ok(resources) => response.resources = resources,
+            Err(e) => {
+                eprintln!("Error searching resources: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to search resources");
+            }
+        }
+    }
+
+    if sections.support_groups {
+        match search_support_groups(pool.get_ref(), &query, limit).await {
+            Ok(groups) => response.support_groups = groups,
+            Err(e) => {
+                eprintln!("Error searching support groups: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to search support groups");
+            }
+        }
+    }
+
+    if sections.users {
+        match search_users(pool.get_ref(), &claims, &query, &blocked_ids, limit).await {
+            Ok(users) => response.users = users,
+            Err(e) => {
+                eprintln!("Error searching users: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to search users");
+            }
+        }
+    }
+
+    HttpResponse::Ok().json(response)
+}
+
+//Config Search Routes
+// GET /search
+pub fn config_search_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/search").route("", web::get().to(search)));
+}
@@ -1,85 +1,189 @@
 use crate::handlers::auth::Claims;
-use crate::handlers::matching_algo::calculate_match_score;
+use crate::handlers::blocking::{blocked_user_ids, is_blocked_between};
+use crate::handlers::matching_algo::{
+    calculate_match_score, load_matching_config, FactorScore, MatchFactor,
+};
+use crate::handlers::matching_requests::{cancel_sibling_requests, notify_request_change};
+use crate::handlers::notifications::push_notifications;
+use crate::handlers::privacy::{location_city, AudienceCheck};
+use crate::handlers::sponsor_candidates::{load_match_users, ranked_sponsors_for, RankedSponsor};
+use crate::handlers::sponsor_capacity::{lock_sponsor_capacity, sponsor_capacity};
+use crate::handlers::sponsorships::{in_rematch_cooldown, open_sponsorship_of, start_sponsorship};
+use crate::handlers::taxonomy::profile_completeness;
 use crate::models::all_models::{MatchUser, MatchingRequest, MatchingStatus, UserRole};
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use sqlx::PgPool;
 use uuid::Uuid;
 
+// Helper function: Remove fields a sponsor doesn't share with the viewer.
+// Location is reduced to the city so coordinates never leave the server.
+fn redact_match_user(sponsor: &mut MatchUser, audience: &AudienceCheck) {
+    let id = sponsor.id;
+    sponsor.location = if audience.can_see(id, |s| s.location_city) {
+        let location: Option<Value> = sponsor
+            .location
+            .as_deref()
+            .and_then(|loc| serde_json::from_str(loc).ok());
+        location_city(location.as_ref()).map(|city| json!({ "city": city }).to_string())
+    } else {
+        None
+    };
+    if !audience.can_see(id, |s| s.interests) {
+        sponsor.interests = None;
+    }
+    if !audience.can_see(id, |s| s.experience) {
+        sponsor.experience = None;
+    }
+    if !audience.can_see(id, |s| s.languages) {
+        sponsor.languages = None;
+    }
+}
+
+// Helper function: Drop factor details that would reveal fields hidden from the viewer
+fn redact_factor_details(sponsor_id: Uuid, factors: &mut [FactorScore], audience: &AudienceCheck) {
+    for factor in factors.iter_mut() {
+        let visible = match factor.factor {
+            MatchFactor::Location => audience.can_see(sponsor_id, |s| s.location_city),
+            MatchFactor::Interests => audience.can_see(sponsor_id, |s| s.interests),
+            MatchFactor::Experience => audience.can_see(sponsor_id, |s| s.experience),
+            MatchFactor::Language => audience.can_see(sponsor_id, |s| s.languages),
+            MatchFactor::Age | MatchFactor::Availability => true,
+        };
+        if !visible {
+            factor.detail = None;
+        }
+    }
+}
+
+//Sponsor Recommendation
+#[derive(Debug, Serialize)]
+pub struct SponsorRecommendation {
+    pub sponsor: MatchUser,
+    pub match_score: f32,
+    pub factors: Vec<FactorScore>,
+    pub load_penalty: f32,
+}
+
+//Recommend Sponsors Params
+#[derive(Debug, Deserialize)]
+pub struct RecommendSponsorsParams {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+//Sponsor Recommendations Response
+#[derive(Debug, Serialize)]
+pub struct SponsorRecommendationsResponse {
+    pub sponsors: Vec<SponsorRecommendation>,
+    pub page: u32,
+    pub per_page: u32,
+    pub total_count: usize,
+}
+
 //Recommend Sponsors
-//Recommend Sponsors Input: HttpRequest(JWT Token)
-//Recommend Sponsors Output: Vec<MatchUser>
-pub async fn recommend_sponsors(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+//Recommend Sponsors Input: HttpRequest(JWT Token), RecommendSponsorsParams (?page=&per_page=)
+//Recommend Sponsors Output: SponsorRecommendationsResponse, best match first (20 per page by default)
+pub async fn recommend_sponsors(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    params: web::Query<RecommendSponsorsParams>,
+) -> impl Responder {
     if let Some(claims) = req.extensions().get::<Claims>() {
         let user_id = claims.id;
 
-        let user_query = "
-            SELECT 
-                user_id as id, 
-                dob, 
-                location::text as location, 
-                interests, 
-                experience, 
-                available_days, 
-                languages
-            FROM users WHERE user_id = $1";
-
-        let user_result = sqlx::query_as::<_, MatchUser>(user_query)
-            .bind(user_id)
-            .fetch_one(pool.get_ref())
-            .await;
-
-        if let Ok(member) = user_result {
-            if member.location.is_none()
-                || member.interests.is_none()
-                || member.experience.is_none()
-                || member.available_days.is_none()
-                || member.languages.is_none()
-            {
-                return HttpResponse::BadRequest()
-                    .body("Complete your profile before requesting a sponsor.");
+        match profile_completeness(pool.get_ref(), user_id).await {
+            Ok(completeness) if !completeness.ready_for_matching => {
+                return HttpResponse::BadRequest().body(format!(
+                    "Complete your profile before requesting a sponsor. Missing: {}",
+                    completeness.missing_for_matching().join(", ")
+                ));
             }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Failed to check profile completeness: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to fetch sponsors.");
+            }
+        }
 
-            let sponsor_query = "
-                SELECT 
-                    user_id as id, 
-                    dob, 
-                    location::text as location, 
-                    interests, 
-                    experience, 
-                    available_days, 
-                    languages
-                FROM users WHERE role = $1";
-
-            let sponsors_result = sqlx::query_as::<_, MatchUser>(sponsor_query)
-                .bind(UserRole::Sponsor)
-                .fetch_all(pool.get_ref())
-                .await;
+        // Sponsors with a block either way are never recommended
+        let blocked: Vec<Uuid> = match blocked_user_ids(pool.get_ref(), user_id).await {
+            Ok(blocked) => blocked.into_iter().collect(),
+            Err(e) => {
+                eprintln!("Failed to fetch blocked users: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to fetch sponsors.");
+            }
+        };
 
-            match sponsors_result {
-                Ok(sponsors) => {
-                    let mut sponsor_scores: Vec<(MatchUser, f32)> = sponsors
-                        .into_iter()
-                        .map(|sponsor| {
-                            let score = calculate_match_score(&member, &sponsor);
-                            (sponsor, score)
-                        })
-                        .collect();
+        let config = match load_matching_config(pool.get_ref()).await {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Failed to load matching config: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to fetch sponsors.");
+            }
+        };
 
-                    sponsor_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        // Sponsors ruled out by a hard filter, paused or full are left out entirely
+        let ranked = match ranked_sponsors_for(pool.get_ref(), user_id, &config, &blocked).await {
+            Ok(ranked) => ranked,
+            Err(e) => {
+                eprintln!("Failed to rank sponsors: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to fetch sponsors.");
+            }
+        };
 
-                    HttpResponse::Ok().json(sponsor_scores)
-                }
+        let page = params.page.unwrap_or(1).max(1);
+        let per_page = params.per_page.unwrap_or(20).clamp(1, 50);
+        let total_count = ranked.len();
+        let page_of_ranked: Vec<RankedSponsor> = ranked
+            .into_iter()
+            .skip((page as usize - 1) * per_page as usize)
+            .take(per_page as usize)
+            .collect();
+
+        // Only the sponsors on this page are loaded and checked for privacy
+        let sponsor_ids: Vec<Uuid> = page_of_ranked.iter().map(|r| r.sponsor_id).collect();
+        let mut sponsors = match load_match_users(pool.get_ref(), &sponsor_ids).await {
+            Ok(sponsors) => sponsors,
+            Err(e) => {
+                eprintln!("Failed to fetch sponsors: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to fetch sponsors.");
+            }
+        };
+        let audience =
+            match AudienceCheck::load(pool.get_ref(), user_id, claims.role, &sponsor_ids).await {
+                Ok(audience) => audience,
                 Err(e) => {
-                    eprintln!("Failed to fetch sponsors: {:?}", e);
-                    HttpResponse::InternalServerError().body("Failed to fetch sponsors.")
+                    eprintln!("Failed to load sponsor privacy settings: {:?}", e);
+                    return HttpResponse::InternalServerError().body("Failed to fetch sponsors.");
                 }
-            }
-        } else {
-            eprintln!("Failed to fetch user data: {:?}", user_result.err());
-            HttpResponse::InternalServerError().body("Failed to fetch user data.")
-        }
+            };
+
+        // Scores use the full profiles, but only visible fields are returned
+        let recommendations: Vec<SponsorRecommendation> = page_of_ranked
+            .into_iter()
+            .filter_map(|ranked| {
+                let mut sponsor = sponsors.remove(&ranked.sponsor_id)?;
+                let mut factors = ranked.result.factors;
+                redact_factor_details(sponsor.id, &mut factors, &audience);
+                redact_match_user(&mut sponsor, &audience);
+                Some(SponsorRecommendation {
+                    sponsor,
+                    match_score: ranked.result.score,
+                    factors,
+                    load_penalty: ranked.result.load_penalty,
+                })
+            })
+            .collect();
+
+        HttpResponse::Ok().json(SponsorRecommendationsResponse {
+            sponsors: recommendations,
+            page,
+            per_page,
+            total_count,
+        })
     } else {
         HttpResponse::Unauthorized().body("Authentication required")
     }
@@ -119,38 +223,93 @@ pub async fn request_sponsor(
             return HttpResponse::Conflict().body("You have already requested this sponsor.");
         }
 
-        // Ensure user has filled required fields before requesting
-        let user_query = "
-            SELECT 
-                location IS NOT NULL as has_location, 
-                interests IS NOT NULL as has_interests, 
-                experience IS NOT NULL as has_experience, 
-                available_days IS NOT NULL as has_available_days, 
-                languages IS NOT NULL as has_languages
-            FROM users WHERE user_id = $1";
-
-        let user_result: Result<(bool, bool, bool, bool, bool), sqlx::Error> =
-            sqlx::query_as(user_query)
-                .bind(user_id)
-                .fetch_one(pool.get_ref())
-                .await;
+        match is_blocked_between(pool.get_ref(), user_id, payload.sponsor_id).await {
+            Ok(false) => {}
+            Ok(true) => {
+                return HttpResponse::Forbidden().body("You can't send a request to this sponsor.")
+            }
+            Err(e) => {
+                eprintln!("Failed to check blocks: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to request sponsor.");
+            }
+        }
 
-        match user_result {
-            Ok((
-                has_location,
-                has_interests,
-                has_experience,
-                has_available_days,
-                has_languages,
-            )) => {
-                if !has_location
-                    || !has_interests
-                    || !has_experience
-                    || !has_available_days
-                    || !has_languages
-                {
-                    return HttpResponse::BadRequest()
-                        .body("Complete your profile before requesting a sponsor.");
+        // Only current sponsors can be requested; revoked sponsors drop back to members
+        match sqlx::query_scalar::<_, UserRole>("SELECT role FROM users WHERE user_id = $1")
+            .bind(payload.sponsor_id)
+            .fetch_optional(pool.get_ref())
+            .await
+        {
+            Ok(Some(UserRole::Sponsor)) => {}
+            Ok(_) => return HttpResponse::NotFound().body("Sponsor not found."),
+            Err(e) => {
+                eprintln!("Failed to check sponsor role: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to request sponsor.");
+            }
+        }
+
+        match sponsor_capacity(pool.get_ref(), payload.sponsor_id).await {
+            Ok(capacity) if capacity.is_open() => {}
+            Ok(_) => {
+                return HttpResponse::Conflict()
+                    .body("This sponsor isn't taking on new members right now.")
+            }
+            Err(e) => {
+                eprintln!("Failed to check sponsor capacity: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to request sponsor.");
+            }
+        }
+
+        // Members have one sponsor at a time
+        match open_sponsorship_of(pool.get_ref(), user_id).await {
+            Ok(None) => {}
+            Ok(Some(_)) => {
+                return HttpResponse::Conflict().body(
+                    "You already have a sponsor. End that sponsorship before requesting another.",
+                )
+            }
+            Err(e) => {
+                eprintln!("Failed to check sponsorships: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to request sponsor.");
+            }
+        }
+
+        let config = match load_matching_config(pool.get_ref()).await {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Failed to load matching config: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to request sponsor.");
+            }
+        };
+
+        match in_rematch_cooldown(
+            pool.get_ref(),
+            user_id,
+            payload.sponsor_id,
+            config.rematch_cooldown_days,
+        )
+        .await
+        {
+            Ok(false) => {}
+            Ok(true) => {
+                return HttpResponse::Conflict().body(
+                    "You recently ended a sponsorship with this sponsor. Please try again later.",
+                )
+            }
+            Err(e) => {
+                eprintln!("Failed to check rematch cooldown: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to request sponsor.");
+            }
+        }
+
+        // Ensure user has filled required fields before requesting
+        match profile_completeness(pool.get_ref(), user_id).await {
+            Ok(completeness) => {
+                if !completeness.ready_for_matching {
+                    return HttpResponse::BadRequest().body(format!(
+                        "Complete your profile before requesting a sponsor. Missing: {}",
+                        completeness.missing_for_matching().join(", ")
+                    ));
                 }
 
                 // Calculate match score before inserting the request
@@ -188,7 +347,7 @@ pub async fn request_sponsor(
 
                 // If we can get both users' data, calculate match score
                 if let (Ok(member), Ok(sponsor)) = (member_result, sponsor_result) {
-                    let match_score = calculate_match_score(&member, &sponsor);
+                    let match_score = calculate_match_score(&member, &sponsor, &config);
 
                     // Insert the matching request with match score
                     let insert_query = "
@@ -196,16 +355,84 @@ pub async fn request_sponsor(
                         VALUES ($1, $2, $3, NOW(), $4)
                         RETURNING matching_request_id, member_id, sponsor_id, status, created_at, match_score";
 
+                    let mut tx = match pool.begin().await {
+                        Ok(tx) => tx,
+                        Err(e) => {
+                            eprintln!("Failed to start transaction: {:?}", e);
+                            return HttpResponse::InternalServerError()
+                                .body("Failed to request sponsor.");
+                        }
+                    };
+
+                    // Lock the member so concurrent requests can't both slip under the limit
+                    if let Err(e) = sqlx::query("SELECT 1 FROM users WHERE user_id = $1 FOR UPDATE")
+                        .bind(user_id)
+                        .execute(&mut *tx)
+                        .await
+                    {
+                        eprintln!("Failed to lock member: {:?}", e);
+                        let _ = tx.rollback().await;
+                        return HttpResponse::InternalServerError()
+                            .body("Failed to request sponsor.");
+                    }
+
+                    // Members can only have a few requests waiting at once
+                    let pending_query = "
+                        SELECT COUNT(*) FROM matching_requests
+                        WHERE member_id = $1 AND status = $2";
+                    match sqlx::query_scalar::<_, i64>(pending_query)
+                        .bind(user_id)
+                        .bind(MatchingStatus::Pending)
+                        .fetch_one(&mut *tx)
+                        .await
+                    {
+                        Ok(pending) if pending < config.max_pending_requests as i64 => {}
+                        Ok(_) => {
+                            let _ = tx.rollback().await;
+                            return HttpResponse::Conflict().body(format!(
+                                "You can have at most {} pending requests. Withdraw one or wait for a reply.",
+                                config.max_pending_requests
+                            ));
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to count pending requests: {:?}", e);
+                            let _ = tx.rollback().await;
+                            return HttpResponse::InternalServerError()
+                                .body("Failed to request sponsor.");
+                        }
+                    }
+
                     let request_result = sqlx::query_as::<_, MatchingRequest>(insert_query)
                         .bind(user_id)
                         .bind(payload.sponsor_id)
                         .bind(MatchingStatus::Pending)
                         .bind(match_score)
-                        .fetch_one(pool.get_ref())
+                        .fetch_one(&mut *tx)
                         .await;
 
-                    match request_result {
-                        Ok(request) => HttpResponse::Ok().json(request),
+                    let request = match request_result {
+                        Ok(request) => request,
+                        Err(e) => {
+                            eprintln!("Failed to request sponsor: {:?}", e);
+                            return HttpResponse::InternalServerError()
+                                .body("Failed to request sponsor.");
+                        }
+                    };
+
+                    let notifications = match notify_request_change(&mut tx, &request).await {
+                        Ok(notifications) => notifications,
+                        Err(e) => {
+                            eprintln!("Failed to create notifications: {:?}", e);
+                            return HttpResponse::InternalServerError()
+                                .body("Failed to request sponsor.");
+                        }
+                    };
+
+                    match tx.commit().await {
+                        Ok(_) => {
+                            push_notifications(&notifications).await;
+                            HttpResponse::Ok().json(request)
+                        }
                         Err(e) => {
                             eprintln!("Failed to request sponsor: {:?}", e);
                             HttpResponse::InternalServerError().body("Failed to request sponsor.")
@@ -323,11 +550,51 @@ pub async fn respond_to_matching_request(
             .await
             .unwrap_or(None);
 
-        if let Some((_member_id, _member_username)) = member_info {
+        if let Some((member_id, _member_username)) = member_info {
+            if payload.accept {
+                match open_sponsorship_of(pool.get_ref(), member_id).await {
+                    Ok(None) => {}
+                    Ok(Some(_)) => {
+                        return HttpResponse::Conflict().body("This member already has a sponsor.")
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to check sponsorships: {:?}", e);
+                        return HttpResponse::InternalServerError()
+                            .body("Failed to update request.");
+                    }
+                }
+            }
+
+            let mut tx = match pool.begin().await {
+                Ok(tx) => tx,
+                Err(e) => {
+                    eprintln!("Failed to start transaction: {:?}", e);
+                    return HttpResponse::InternalServerError().body("Failed to update request.");
+                }
+            };
+
+            // Sponsors can't go over the number of mentees they've said they can take.
+            // Checked under a lock so two accepts at once can't both take the last place
+            if payload.accept {
+                match lock_sponsor_capacity(&mut tx, sponsor_id).await {
+                    Ok(capacity) if capacity.has_room() => {}
+                    Ok(_) => {
+                        return HttpResponse::Conflict()
+                            .body("You've reached your maximum number of active mentees.")
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to check sponsor capacity: {:?}", e);
+                        return HttpResponse::InternalServerError()
+                            .body("Failed to update request.");
+                    }
+                }
+            }
+
+            // Only pending requests can be answered, so a request is never accepted twice
             let update_query = "
                 UPDATE matching_requests 
                 SET status = $1, updated_at = NOW() 
-                WHERE matching_request_id = $2 AND sponsor_id = $3
+                WHERE matching_request_id = $2 AND sponsor_id = $3 AND status = $4
                 RETURNING matching_request_id, member_id, sponsor_id, status, created_at, updated_at, match_score";
 
             let new_status = if payload.accept {
@@ -338,13 +605,72 @@ pub async fn respond_to_matching_request(
 
             let result = sqlx::query_as::<_, MatchingRequest>(update_query)
                 .bind(new_status)
-                .bind(&payload.matching_request_id)
-                .bind(&sponsor_id)
-                .fetch_one(pool.get_ref())
+                .bind(payload.matching_request_id)
+                .bind(sponsor_id)
+                .bind(MatchingStatus::Pending)
+                .fetch_optional(&mut *tx)
                 .await;
 
-            match result {
-                Ok(updated_request) => HttpResponse::Ok().json(updated_request),
+            let updated_request = match result {
+                Ok(Some(updated_request)) => updated_request,
+                Ok(None) => {
+                    return HttpResponse::Conflict().body("This request has already been answered.")
+                }
+                Err(e) => {
+                    eprintln!("Failed to update matching request: {:?}", e);
+                    return HttpResponse::InternalServerError().body("Failed to update request.");
+                }
+            };
+
+            // Accepting starts the sponsorship and its private chat,
+            // and the member's requests to other sponsors are no longer needed
+            let mut cancelled = Vec::new();
+            if payload.accept {
+                if let Err(e) = start_sponsorship(
+                    &mut tx,
+                    updated_request.matching_request_id,
+                    member_id,
+                    sponsor_id,
+                )
+                .await
+                {
+                    eprintln!("Failed to start sponsorship: {:?}", e);
+                    return HttpResponse::InternalServerError().body("Failed to update request.");
+                }
+
+                match cancel_sibling_requests(
+                    &mut tx,
+                    member_id,
+                    updated_request.matching_request_id,
+                )
+                .await
+                {
+                    Ok(requests) => cancelled = requests,
+                    Err(e) => {
+                        eprintln!("Failed to cancel other requests: {:?}", e);
+                        return HttpResponse::InternalServerError()
+                            .body("Failed to update request.");
+                    }
+                }
+            }
+
+            let mut notifications = Vec::new();
+            for request in std::iter::once(&updated_request).chain(&cancelled) {
+                match notify_request_change(&mut tx, request).await {
+                    Ok(created) => notifications.extend(created),
+                    Err(e) => {
+                        eprintln!("Failed to create notifications: {:?}", e);
+                        return HttpResponse::InternalServerError()
+                            .body("Failed to update request.");
+                    }
+                }
+            }
+
+            match tx.commit().await {
+                Ok(_) => {
+                    push_notifications(&notifications).await;
+                    HttpResponse::Ok().json(updated_request)
+                }
                 Err(e) => {
                     eprintln!("Failed to update matching request: {:?}", e);
                     HttpResponse::InternalServerError().body("Failed to update request.")
@@ -358,17 +684,106 @@ pub async fn respond_to_matching_request(
     }
 }
 
+//Withdraw Matching Request
+//Withdraw Matching Request Input: HttpRequest(JWT Token), Path (/matching/requests/{matching_request_id}/withdraw)
+//Withdraw Matching Request Output: MatchingRequest
+pub async fn withdraw_matching_request(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let matching_request_id = path.into_inner();
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            eprintln!("Failed to start transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to withdraw request.");
+        }
+    };
+
+    let update_query = "
+        UPDATE matching_requests SET status = $1, updated_at = NOW()
+        WHERE matching_request_id = $2 AND member_id = $3 AND status = $4
+        RETURNING *";
+    let request = match sqlx::query_as::<_, MatchingRequest>(update_query)
+        .bind(MatchingStatus::Withdrawn)
+        .bind(matching_request_id)
+        .bind(user_id)
+        .bind(MatchingStatus::Pending)
+        .fetch_optional(&mut *tx)
+        .await
+    {
+        Ok(Some(request)) => request,
+        Ok(None) => {
+            // Tell apart requests that don't exist from ones that were already answered
+            let exists_query = "
+                SELECT EXISTS(
+                    SELECT 1 FROM matching_requests
+                    WHERE matching_request_id = $1 AND member_id = $2
+                )";
+            return match sqlx::query_scalar::<_, bool>(exists_query)
+                .bind(matching_request_id)
+                .bind(user_id)
+                .fetch_one(&mut *tx)
+                .await
+            {
+                Ok(true) => {
+                    HttpResponse::Conflict().body("Only pending requests can be withdrawn.")
+                }
+                Ok(false) => HttpResponse::NotFound().body("Request not found."),
+                Err(e) => {
+                    eprintln!("Failed to fetch matching request: {:?}", e);
+                    HttpResponse::InternalServerError().body("Failed to withdraw request.")
+                }
+            };
+        }
+        Err(e) => {
+            eprintln!("Failed to withdraw matching request: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to withdraw request.");
+        }
+    };
+
+    let notifications = match notify_request_change(&mut tx, &request).await {
+        Ok(notifications) => notifications,
+        Err(e) => {
+            eprintln!("Failed to create notifications: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to withdraw request.");
+        }
+    };
+
+    match tx.commit().await {
+        Ok(_) => {
+            push_notifications(&notifications).await;
+            HttpResponse::Ok().json(request)
+        }
+        Err(e) => {
+            eprintln!("Failed to withdraw matching request: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to withdraw request.")
+        }
+    }
+}
+
 //Config Matching Routes
 // GET /matching/recommend-sponsors
 // POST /matching/request-sponsor
 // GET /matching/status
 // PATCH /matching/respond
+// PATCH /matching/requests/{matching_request_id}/withdraw
 pub fn config_matching_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/matching")
             .route("/recommend-sponsors", web::get().to(recommend_sponsors))
             .route("/request-sponsor", web::post().to(request_sponsor))
             .route("/status", web::get().to(check_matching_status))
-            .route("/respond", web::patch().to(respond_to_matching_request)),
+            .route("/respond", web::patch().to(respond_to_matching_request))
+            .route(
+                "/requests/{matching_request_id}/withdraw",
+                web::patch().to(withdraw_matching_request),
+            ),
     );
 }
@@ -1,209 +1,475 @@
 use crate::handlers::auth::Claims;
-use crate::models::all_models::ApplicationStatus;
+use crate::handlers::sponsor_applications::{
+    application_details, reapply_available_at, record_revision,
+};
+use crate::handlers::sponsor_capacity::sponsor_capacity;
+use crate::handlers::taxonomy::Taxonomy;
+use crate::models::all_models::{
+    ApplicationStatus, SponsorApplication, SponsorReferee, TaxonomyKind, UserRole,
+};
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+const MAX_APPLICATION_INFO_LENGTH: usize = 5000;
+const MAX_REFEREES: usize = 3;
+const MAX_REFEREE_FIELD_LENGTH: usize = 200;
+
 //Sponsor Application Request
+//application_info is the applicant's own account of why they want to sponsor
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SponsorApplicationRequest {
     pub application_info: String,
+    pub recovery_since: NaiveDate,
+    pub experience_areas: Vec<String>,
+    pub availability: Vec<String>,
+    pub referees: Vec<SponsorReferee>,
+    pub agreed_to_code_of_conduct: bool,
 }
 
-//Sponsor Application
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
-pub struct SponsorApplication {
-    pub application_id: Uuid,
-    pub user_id: Uuid,
-    pub status: ApplicationStatus,
-    pub application_info: String,
-    pub reviewed_by: Option<Uuid>,
-    pub admin_comments: Option<String>,
-    pub created_at: NaiveDateTime,
+//Validated Application
+struct ValidatedApplication {
+    application_info: String,
+    recovery_since: NaiveDate,
+    experience_areas: Vec<String>,
+    availability: Vec<String>,
+    referees: Value,
 }
 
+// Helper function: Check the form and resolve its vocabulary fields to term codes
+async fn validate_application(
+    pool: &PgPool,
+    payload: &SponsorApplicationRequest,
+) -> Result<ValidatedApplication, HttpResponse> {
+    let application_info = payload.application_info.trim().to_string();
+    let info_length = application_info.chars().count();
+    if info_length <= 20 || info_length > MAX_APPLICATION_INFO_LENGTH {
+        return Err(HttpResponse::BadRequest().body(format!(
+            "Tell us why you want to sponsor in 21 to {} characters.",
+            MAX_APPLICATION_INFO_LENGTH
+        )));
+    }
+    if payload.recovery_since > Utc::now().date_naive() {
+        return Err(HttpResponse::BadRequest().body("Recovery date can't be in the future."));
+    }
+    if !payload.agreed_to_code_of_conduct {
+        return Err(
+            HttpResponse::BadRequest().body("You must agree to the sponsor code of conduct.")
+        );
+    }
+
+    if payload.referees.is_empty() || payload.referees.len() > MAX_REFEREES {
+        return Err(HttpResponse::BadRequest()
+            .body(format!("Give between 1 and {} referees.", MAX_REFEREES)));
+    }
+    let referees: Vec<SponsorReferee> = payload
+        .referees
+        .iter()
+        .map(|r| SponsorReferee {
+            name: r.name.trim().to_string(),
+            relationship: r.relationship.trim().to_string(),
+            contact: r.contact.trim().to_string(),
+        })
+        .collect();
+    let referee_fields_valid = referees.iter().all(|r| {
+        [&r.name, &r.relationship, &r.contact]
+            .iter()
+            .all(|field| !field.is_empty() && field.chars().count() <= MAX_REFEREE_FIELD_LENGTH)
+    });
+    if !referee_fields_valid {
+        return Err(HttpResponse::BadRequest().body(format!(
+            "Each referee needs a name, relationship and contact of up to {} characters.",
+            MAX_REFEREE_FIELD_LENGTH
+        )));
+    }
+
+    // Experience and availability use the same vocabularies as profiles
+    let taxonomy = Taxonomy::load(pool).await.map_err(|e| {
+        eprintln!("Error loading taxonomy: {:?}", e);
+        HttpResponse::InternalServerError().body("Failed to save application.")
+    })?;
+    let mut errors: Vec<String> = Vec::new();
+    let mut normalise = |field: &str, kind: TaxonomyKind, values: &[String]| match taxonomy
+        .normalise(kind, values)
+    {
+        Ok(codes) if codes.is_empty() => {
+            errors.push(format!("{}: at least one is required", field));
+            Vec::new()
+        }
+        Ok(codes) => codes,
+        Err(unknown) => {
+            errors.push(format!("{}: {}", field, unknown.join(", ")));
+            Vec::new()
+        }
+    };
+    let experience_areas = normalise(
+        "experience_areas",
+        TaxonomyKind::Experience,
+        &payload.experience_areas,
+    );
+    let availability = normalise("availability", TaxonomyKind::Day, &payload.availability);
+    if !errors.is_empty() {
+        return Err(
+            HttpResponse::BadRequest().body(format!("Invalid values ({})", errors.join("; ")))
+        );
+    }
+
+    Ok(ValidatedApplication {
+        application_info,
+        recovery_since: payload.recovery_since,
+        experience_areas,
+        availability,
+        referees: serde_json::to_value(referees).unwrap_or_else(|_| Value::Array(Vec::new())),
+    })
+}
+
+// Helper function: Fetch the user's most recent application
+async fn latest_application(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Option<SponsorApplication>, sqlx::Error> {
+    sqlx::query_as::<_, SponsorApplication>(
+        "SELECT * FROM sponsor_applications WHERE user_id = $1 ORDER BY created_at DESC LIMIT 1",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await
+}
+
+// Helper function: Save a new revision of the form, create the application if there isn't one yet,
+// and put it back in the review queue
+async fn save_application(
+    pool: &PgPool,
+    user_id: Uuid,
+    existing: Option<Uuid>,
+    form: ValidatedApplication,
+) -> Result<SponsorApplication, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let application = match existing {
+        Some(application_id) => {
+            let update_query = "
+                UPDATE sponsor_applications
+                SET application_info = $2, recovery_since = $3, experience_areas = $4,
+                    availability = $5, referees = $6, code_of_conduct_agreed_at = NOW(),
+                    status = $7, revision = revision + 1, submitted_at = NOW(),
+                    reviewed_by = NULL, admin_comments = NULL, reviewed_at = NULL
+                WHERE application_id = $1
+                RETURNING *";
+            sqlx::query_as::<_, SponsorApplication>(update_query)
+                .bind(application_id)
+                .bind(&form.application_info)
+                .bind(form.recovery_since)
+                .bind(&form.experience_areas)
+                .bind(&form.availability)
+                .bind(&form.referees)
+                .bind(ApplicationStatus::Pending)
+                .fetch_one(&mut *tx)
+                .await?
+        }
+        None => {
+            let insert_query = "
+                INSERT INTO sponsor_applications (
+                    user_id, status, application_info, recovery_since, experience_areas,
+                    availability, referees, code_of_conduct_agreed_at, created_at, submitted_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, NOW(), NOW(), NOW())
+                RETURNING *";
+            sqlx::query_as::<_, SponsorApplication>(insert_query)
+                .bind(user_id)
+                .bind(ApplicationStatus::Pending)
+                .bind(&form.application_info)
+                .bind(form.recovery_since)
+                .bind(&form.experience_areas)
+                .bind(&form.availability)
+                .bind(&form.referees)
+                .fetch_one(&mut *tx)
+                .await?
+        }
+    };
+
+    record_revision(&mut tx, &application).await?;
+    tx.commit().await?;
+    Ok(application)
+}
 //Submit Sponsor Application
 //Submit Sponsor Application Input: HttpRequest(JWT Token), SponsorApplicationRequest
 //Submit Sponsor Application Output: SponsorApplication
+//A rejected applicant can apply again once the reapply cooldown has passed
 pub async fn submit_sponsor_application(
     pool: web::Data<PgPool>,
     req: HttpRequest,
     payload: web::Json<SponsorApplicationRequest>,
 ) -> impl Responder {
-    if let Some(claims) = req.extensions().get::<Claims>() {
-        // Check if the user has already submitted an application
-        let check_query = "SELECT status FROM sponsor_applications WHERE user_id = $1";
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
 
-        let existing_status = sqlx::query_scalar::<_, Option<ApplicationStatus>>(check_query)
-            .bind(&claims.id)
-            .fetch_one(pool.get_ref())
-            .await;
+    let existing = match latest_application(pool.get_ref(), user_id).await {
+        Ok(existing) => existing,
+        Err(e) => {
+            eprintln!("Failed to fetch sponsor application: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to submit application");
+        }
+    };
 
-        match existing_status {
-            Ok(Some(status)) => {
-                // If application exists and is approved, user cannot reapply
-                if status == ApplicationStatus::Approved {
-                    return HttpResponse::Forbidden()
-                        .body("You already have an approved sponsor application.");
-                }
-                // If application exists but is pending or rejected, user should update instead
+    let reapply_to = match &existing {
+        None => None,
+        Some(application) => match application.status {
+            ApplicationStatus::Approved => {
+                return HttpResponse::Forbidden()
+                    .body("You already have an approved sponsor application.")
+            }
+            ApplicationStatus::Pending | ApplicationStatus::InfoRequested => {
                 return HttpResponse::Conflict().body(
                     "You already have an application. Please use the update endpoint instead.",
-                );
+                )
             }
-            Ok(None) | Err(_) => {
-                // No existing application, proceed with creating a new one
-                let insert_query = "
-                    INSERT INTO sponsor_applications (user_id, status, application_info, created_at)
-                    VALUES ($1, $2, $3, NOW())
-                    RETURNING application_id, user_id, status, application_info, reviewed_by, admin_comments, created_at";
-
-                let application_result = sqlx::query_as::<_, SponsorApplication>(insert_query)
-                    .bind(&claims.id)
-                    .bind(ApplicationStatus::Pending)
-                    .bind(&payload.application_info)
-                    .fetch_one(pool.get_ref())
-                    .await;
-
-                match application_result {
-                    Ok(application) => HttpResponse::Ok().json(application),
-                    Err(_) => {
-                        HttpResponse::InternalServerError().body("Failed to submit application")
+            ApplicationStatus::Rejected | ApplicationStatus::Revoked => {
+                match reapply_available_at(application) {
+                    Some(available_at) if available_at > Utc::now().naive_utc() => {
+                        return HttpResponse::Conflict().body(format!(
+                            "You can apply to be a sponsor again from {}.",
+                            available_at.format("%Y-%m-%d")
+                        ))
                     }
+                    _ => Some(application.application_id),
                 }
             }
+        },
+    };
+
+    let form = match validate_application(pool.get_ref(), &payload).await {
+        Ok(form) => form,
+        Err(resp) => return resp,
+    };
+
+    match save_application(pool.get_ref(), user_id, reapply_to, form).await {
+        Ok(application) => HttpResponse::Ok().json(application),
+        Err(e) => {
+            eprintln!("Failed to submit sponsor application: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to submit application")
         }
-    } else {
-        HttpResponse::Unauthorized().body("Authentication required")
     }
 }
 
 //Check Sponsor Application Status
 //Check Sponsor Application Status Input: HttpRequest(JWT Token)
-//Check Sponsor Application Status Output: SponsorApplication
+//Check Sponsor Application Status Output: SponsorApplicationDetails
 pub async fn check_sponsor_application_status(
     pool: web::Data<PgPool>,
     req: HttpRequest,
 ) -> impl Responder {
-    if let Some(claims) = req.extensions().get::<Claims>() {
-        let query = "SELECT * FROM sponsor_applications WHERE user_id = $1";
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
 
-        let result = sqlx::query_as::<_, SponsorApplication>(query)
-            .bind(&claims.id)
-            .fetch_one(pool.get_ref())
-            .await;
+    let application = match latest_application(pool.get_ref(), user_id).await {
+        Ok(Some(application)) => application,
+        Ok(None) => return HttpResponse::NotFound().body("No sponsor application found."),
+        Err(e) => {
+            eprintln!("Failed to fetch sponsor application: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch application.");
+        }
+    };
 
-        match result {
-            Ok(application) => HttpResponse::Ok().json(application),
-            Err(_) => HttpResponse::NotFound().body("No sponsor application found."),
+    match application_details(pool.get_ref(), application).await {
+        Ok(details) => HttpResponse::Ok().json(details),
+        Err(e) => {
+            eprintln!("Failed to fetch sponsor application history: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch application.")
         }
-    } else {
-        HttpResponse::Unauthorized().body("Authentication required")
     }
 }
 
-//Update Sponsor Application Request
-#[derive(Debug, Deserialize, Serialize)]
-pub struct UpdateSponsorApplicationRequest {
-    pub application_info: String,
-}
-
 //Update Sponsor Application
-//Update Sponsor Application Input: HttpRequest(JWT Token), UpdateSponsorApplicationRequest
+//Update Sponsor Application Input: HttpRequest(JWT Token), SponsorApplicationRequest
 //Update Sponsor Application Output: SponsorApplication
+//Each update is kept as a new revision, and answers a request for more information
 pub async fn update_sponsor_application(
     pool: web::Data<PgPool>,
     req: HttpRequest,
-    payload: web::Json<UpdateSponsorApplicationRequest>,
+    payload: web::Json<SponsorApplicationRequest>,
 ) -> impl Responder {
-    if let Some(claims) = req.extensions().get::<Claims>() {
-        // Check if application exists and get its status
-        let check_query = "SELECT status FROM sponsor_applications WHERE user_id = $1";
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
 
-        let result: Result<ApplicationStatus, sqlx::Error> = sqlx::query_scalar(check_query)
-            .bind(&claims.id)
-            .fetch_one(pool.get_ref())
-            .await;
+    let application = match latest_application(pool.get_ref(), user_id).await {
+        Ok(Some(application)) => application,
+        Ok(None) => {
+            return HttpResponse::NotFound()
+                .body("No sponsor application found. Please submit an application first.")
+        }
+        Err(e) => {
+            eprintln!("Failed to fetch sponsor application: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to update application.");
+        }
+    };
 
-        match result {
-            Ok(status) => {
-                // If application is approved, user cannot update it
-                if status == ApplicationStatus::Approved {
-                    return HttpResponse::Forbidden()
-                        .body("You cannot update an approved application.");
-                }
+    match application.status {
+        ApplicationStatus::Pending | ApplicationStatus::InfoRequested => {}
+        ApplicationStatus::Approved => {
+            return HttpResponse::Forbidden().body("You cannot update an approved application.")
+        }
+        ApplicationStatus::Rejected | ApplicationStatus::Revoked => {
+            return HttpResponse::Conflict()
+                .body("This application is closed. Please submit a new application.")
+        }
+    }
 
-                // Update application - if rejected, set back to pending
-                let update_query = "
-                    UPDATE sponsor_applications 
-                    SET application_info = $1, status = CASE WHEN status = $2 THEN $3 ELSE status END 
-                    WHERE user_id = $4
-                    RETURNING application_id, user_id, status, application_info, reviewed_by, admin_comments, created_at";
-
-                let updated_result = sqlx::query_as::<_, SponsorApplication>(update_query)
-                    .bind(&payload.application_info)
-                    .bind(ApplicationStatus::Rejected)
-                    .bind(ApplicationStatus::Pending)
-                    .bind(&claims.id)
-                    .fetch_one(pool.get_ref())
-                    .await;
-
-                match updated_result {
-                    Ok(application) => HttpResponse::Ok().json(application),
-                    Err(_) => {
-                        HttpResponse::InternalServerError().body("Failed to update application.")
-                    }
-                }
-            }
-            Err(_) => HttpResponse::NotFound()
-                .body("No sponsor application found. Please submit an application first."),
+    let form = match validate_application(pool.get_ref(), &payload).await {
+        Ok(form) => form,
+        Err(resp) => return resp,
+    };
+
+    match save_application(
+        pool.get_ref(),
+        user_id,
+        Some(application.application_id),
+        form,
+    )
+    .await
+    {
+        Ok(application) => HttpResponse::Ok().json(application),
+        Err(e) => {
+            eprintln!("Failed to update sponsor application: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update application.")
         }
-    } else {
-        HttpResponse::Unauthorized().body("Authentication required")
     }
 }
 
 //Delete Sponsor Application
 //Delete Sponsor Application Input: HttpRequest(JWT Token)
 //Delete Sponsor Application Output: Success message
+//Only applications still under review can be withdrawn; decided ones are kept for the history
 pub async fn delete_sponsor_application(
     pool: web::Data<PgPool>,
     req: HttpRequest,
 ) -> impl Responder {
+    let user_id = match req.extensions().get::<Claims>() {
+        Some(claims) => claims.id,
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let application = match latest_application(pool.get_ref(), user_id).await {
+        Ok(Some(application)) => application,
+        Ok(None) => return HttpResponse::NotFound().body("No sponsor application found."),
+        Err(e) => {
+            eprintln!("Failed to fetch sponsor application: {:?}", e);
+            return HttpResponse::InternalServerError()
+                .body("Failed to delete sponsor application.");
+        }
+    };
+
+    match application.status {
+        ApplicationStatus::Pending | ApplicationStatus::InfoRequested => {}
+        ApplicationStatus::Approved => {
+            return HttpResponse::Forbidden().body("You cannot delete an approved application.")
+        }
+        ApplicationStatus::Rejected | ApplicationStatus::Revoked => {
+            return HttpResponse::Conflict()
+                .body("You cannot delete an application that has been reviewed.")
+        }
+    }
+
+    // Its documents are left for storage GC to clean up
+    let result = sqlx::query("DELETE FROM sponsor_applications WHERE application_id = $1")
+        .bind(application.application_id)
+        .execute(pool.get_ref())
+        .await;
+
+    match result {
+        Ok(_) => HttpResponse::Ok().body("Sponsor application deleted successfully."),
+        Err(e) => {
+            eprintln!("Failed to delete sponsor application: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to delete sponsor application.")
+        }
+    }
+}
+
+//Get Sponsor Settings
+//Get Sponsor Settings Input: HttpRequest(JWT Token)
+//Get Sponsor Settings Output: SponsorCapacity
+pub async fn get_sponsor_settings(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
     if let Some(claims) = req.extensions().get::<Claims>() {
-        // Check if application exists and get its status
-        let check_query = "SELECT status FROM sponsor_applications WHERE user_id = $1";
+        if claims.role != UserRole::Sponsor {
+            return HttpResponse::Forbidden().body("Only sponsors have sponsor settings.");
+        }
 
-        let result: Result<ApplicationStatus, sqlx::Error> = sqlx::query_scalar(check_query)
-            .bind(&claims.id)
-            .fetch_one(pool.get_ref())
-            .await;
+        match sponsor_capacity(pool.get_ref(), claims.id).await {
+            Ok(capacity) => HttpResponse::Ok().json(capacity),
+            Err(e) => {
+                eprintln!("Failed to fetch sponsor settings: {:?}", e);
+                HttpResponse::InternalServerError().body("Failed to fetch sponsor settings.")
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().body("Authentication required")
+    }
+}
 
-        match result {
-            Ok(status) => {
-                // If application is approved, user cannot delete it
-                if status == ApplicationStatus::Approved {
-                    return HttpResponse::Forbidden()
-                        .body("You cannot delete an approved application.");
-                }
+//Update Sponsor Settings Request
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpdateSponsorSettingsRequest {
+    pub max_active_mentees: Option<i32>,
+    pub accepting_new_members: Option<bool>,
+}
+
+//Update Sponsor Settings
+//Update Sponsor Settings Input: HttpRequest(JWT Token), UpdateSponsorSettingsRequest
+//Update Sponsor Settings Output: SponsorCapacity
+//Lowering the limit below the current number of mentees keeps them, but stops new requests
+pub async fn update_sponsor_settings(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    payload: web::Json<UpdateSponsorSettingsRequest>,
+) -> impl Responder {
+    if let Some(claims) = req.extensions().get::<Claims>() {
+        if claims.role != UserRole::Sponsor {
+            return HttpResponse::Forbidden().body("Only sponsors have sponsor settings.");
+        }
 
-                // Delete the application
-                let delete_query = "DELETE FROM sponsor_applications WHERE user_id = $1";
+        if payload
+            .max_active_mentees
+            .is_some_and(|max| !(1..=20).contains(&max))
+        {
+            return HttpResponse::BadRequest()
+                .body("Maximum active mentees must be between 1 and 20.");
+        }
 
-                let result = sqlx::query(delete_query)
-                    .bind(&claims.id)
-                    .execute(pool.get_ref())
-                    .await;
+        let upsert_query = "
+            INSERT INTO sponsor_settings (user_id, max_active_mentees, accepting_new_members, updated_at)
+            VALUES ($1, COALESCE($2, 3), COALESCE($3, TRUE), NOW())
+            ON CONFLICT (user_id) DO UPDATE SET
+                max_active_mentees = COALESCE($2, sponsor_settings.max_active_mentees),
+                accepting_new_members = COALESCE($3, sponsor_settings.accepting_new_members),
+                updated_at = NOW()";
 
-                match result {
-                    Ok(_) => HttpResponse::Ok().body("Sponsor application deleted successfully."),
-                    Err(_) => HttpResponse::InternalServerError()
-                        .body("Failed to delete sponsor application."),
-                }
+        let result = sqlx::query(upsert_query)
+            .bind(claims.id)
+            .bind(payload.max_active_mentees)
+            .bind(payload.accepting_new_members)
+            .execute(pool.get_ref())
+            .await;
+
+        if let Err(e) = result {
+            eprintln!("Failed to update sponsor settings: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to update sponsor settings.");
+        }
+
+        match sponsor_capacity(pool.get_ref(), claims.id).await {
+            Ok(capacity) => HttpResponse::Ok().json(capacity),
+            Err(e) => {
+                eprintln!("Failed to fetch sponsor settings: {:?}", e);
+                HttpResponse::InternalServerError().body("Failed to fetch sponsor settings.")
             }
-            Err(_) => HttpResponse::NotFound().body("No sponsor application found."),
         }
     } else {
         HttpResponse::Unauthorized().body("Authentication required")
@@ -215,12 +481,16 @@ pub async fn delete_sponsor_application(
 // GET /sponsor/application-status
 // PUT /sponsor/update-application
 // DELETE /sponsor/delete-application
+// GET /sponsor/settings
+// PATCH /sponsor/settings
 pub fn config_sponsor_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/sponsor")
             .route("/apply", web::post().to(submit_sponsor_application))
             .route("/check", web::get().to(check_sponsor_application_status))
             .route("/update", web::patch().to(update_sponsor_application))
-            .route("/delete", web::delete().to(delete_sponsor_application)),
+            .route("/delete", web::delete().to(delete_sponsor_application))
+            .route("/settings", web::get().to(get_sponsor_settings))
+            .route("/settings", web::patch().to(update_sponsor_settings)),
     );
 }
@@ -0,0 +1,329 @@
+use crate::handlers::auth::Claims;
+use crate::handlers::notifications::push_notifications;
+use crate::handlers::privacy::{placeholder_avatar_url, AudienceCheck};
+use crate::handlers::sponsorships::{
+    close_sponsorship_chats, fetch_own_sponsorship, notify_sponsorship_ended,
+};
+use crate::models::all_models::{Sponsorship, SponsorshipEndReason, SponsorshipStatus};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+//Sponsorship With User Info
+#[derive(Debug, Serialize, FromRow)]
+pub struct SponsorshipWithUserInfo {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub sponsorship: Sponsorship,
+    pub other_user_id: Uuid,
+    pub other_username: String,
+    pub other_avatar_url: String,
+}
+
+//End Sponsorship Request
+#[derive(Debug, Deserialize)]
+pub struct EndSponsorshipRequest {
+    pub reason: SponsorshipEndReason,
+    pub note: Option<String>,
+}
+
+const MAX_END_NOTE_LENGTH: usize = 1000;
+
+// Sponsorships joined with whoever is on the other side from the caller ($1)
+const SPONSORSHIP_WITH_USER_QUERY: &str = "
+    SELECT s.*, u.user_id AS other_user_id, u.username AS other_username,
+           u.avatar_url AS other_avatar_url
+    FROM sponsorships s
+    JOIN users u ON u.user_id = CASE WHEN s.member_id = $1 THEN s.sponsor_id ELSE s.member_id END
+    WHERE (s.member_id = $1 OR s.sponsor_id = $1)
+";
+
+// Helper function: Swap in the placeholder avatar where the other party hides theirs
+async fn hide_restricted_avatars(
+    pool: &PgPool,
+    claims: &Claims,
+    sponsorships: &mut [SponsorshipWithUserInfo],
+) -> Result<(), sqlx::Error> {
+    let user_ids: Vec<Uuid> = sponsorships.iter().map(|s| s.other_user_id).collect();
+    let audience = AudienceCheck::load(pool, claims.id, claims.role, &user_ids).await?;
+    for sponsorship in sponsorships.iter_mut() {
+        if !audience.can_see(sponsorship.other_user_id, |s| s.avatar) {
+            sponsorship.other_avatar_url = placeholder_avatar_url(&sponsorship.other_username);
+        }
+    }
+    Ok(())
+}
+
+//List Sponsorships
+//List Sponsorships Input: HttpRequest(JWT Token)
+//List Sponsorships Output: Vec<SponsorshipWithUserInfo>
+pub async fn list_sponsorships(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let query = format!(
+        "{} ORDER BY (s.status = 'ended'), s.started_at DESC",
+        SPONSORSHIP_WITH_USER_QUERY
+    );
+    let mut sponsorships = match sqlx::query_as::<_, SponsorshipWithUserInfo>(&query)
+        .bind(claims.id)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(sponsorships) => sponsorships,
+        Err(e) => {
+            eprintln!("Error fetching sponsorships: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch sponsorships");
+        }
+    };
+
+    if let Err(e) = hide_restricted_avatars(pool.get_ref(), &claims, &mut sponsorships).await {
+        eprintln!("Error loading privacy settings: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to fetch sponsorships");
+    }
+
+    HttpResponse::Ok().json(sponsorships)
+}
+
+//Get Sponsorship
+//Get Sponsorship Input: HttpRequest(JWT Token), Path (/sponsorships/{sponsorship_id})
+//Get Sponsorship Output: SponsorshipWithUserInfo
+pub async fn get_sponsorship(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let query = format!("{} AND s.sponsorship_id = $2", SPONSORSHIP_WITH_USER_QUERY);
+    let sponsorship = match sqlx::query_as::<_, SponsorshipWithUserInfo>(&query)
+        .bind(claims.id)
+        .bind(path.into_inner())
+        .fetch_optional(pool.get_ref())
+        .await
+    {
+        Ok(Some(sponsorship)) => sponsorship,
+        Ok(None) => return HttpResponse::NotFound().body("Sponsorship not found"),
+        Err(e) => {
+            eprintln!("Error fetching sponsorship: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to fetch sponsorship");
+        }
+    };
+
+    let mut sponsorships = [sponsorship];
+    if let Err(e) = hide_restricted_avatars(pool.get_ref(), &claims, &mut sponsorships).await {
+        eprintln!("Error loading privacy settings: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to fetch sponsorship");
+    }
+    let [sponsorship] = sponsorships;
+
+    HttpResponse::Ok().json(sponsorship)
+}
+
+// Helper function: Move a sponsorship between active and paused
+async fn set_paused(
+    pool: &PgPool,
+    req: &HttpRequest,
+    sponsorship_id: Uuid,
+    pause: bool,
+) -> HttpResponse {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    let (from, to) = if pause {
+        (SponsorshipStatus::Active, SponsorshipStatus::Paused)
+    } else {
+        (SponsorshipStatus::Paused, SponsorshipStatus::Active)
+    };
+
+    let update_query = "
+        UPDATE sponsorships
+        SET status = $1, paused_at = CASE WHEN $1 = 'paused'::sponsorship_status THEN NOW() END
+        WHERE sponsorship_id = $2 AND (member_id = $3 OR sponsor_id = $3) AND status = $4
+        RETURNING *
+    ";
+    match sqlx::query_as::<_, Sponsorship>(update_query)
+        .bind(to)
+        .bind(sponsorship_id)
+        .bind(claims.id)
+        .bind(from)
+        .fetch_optional(pool)
+        .await
+    {
+        Ok(Some(sponsorship)) => HttpResponse::Ok().json(sponsorship),
+        Ok(None) => match fetch_own_sponsorship(pool, claims.id, sponsorship_id).await {
+            Ok(Some(_)) if pause => {
+                HttpResponse::Conflict().body("Only active sponsorships can be paused")
+            }
+            Ok(Some(_)) => HttpResponse::Conflict().body("Only paused sponsorships can be resumed"),
+            Ok(None) => HttpResponse::NotFound().body("Sponsorship not found"),
+            Err(e) => {
+                eprintln!("Error fetching sponsorship: {:?}", e);
+                HttpResponse::InternalServerError().body("Failed to update sponsorship")
+            }
+        },
+        Err(e) => {
+            eprintln!("Error updating sponsorship: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update sponsorship")
+        }
+    }
+}
+
+//Pause Sponsorship
+//Pause Sponsorship Input: HttpRequest(JWT Token), Path (/sponsorships/{sponsorship_id}/pause)
+//Pause Sponsorship Output: Sponsorship
+pub async fn pause_sponsorship(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    set_paused(pool.get_ref(), &req, path.into_inner(), true).await
+}
+
+//Resume Sponsorship
+//Resume Sponsorship Input: HttpRequest(JWT Token), Path (/sponsorships/{sponsorship_id}/resume)
+//Resume Sponsorship Output: Sponsorship
+pub async fn resume_sponsorship(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    set_paused(pool.get_ref(), &req, path.into_inner(), false).await
+}
+
+//End Sponsorship
+//End Sponsorship Input: HttpRequest(JWT Token), Path (/sponsorships/{sponsorship_id}/end), EndSponsorshipRequest
+//End Sponsorship Output: Sponsorship
+pub async fn end_sponsorship(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    payload: web::Json<EndSponsorshipRequest>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let sponsorship_id = path.into_inner();
+
+    // Account deletion and sponsor revocation end sponsorships on their own; users can't pick them
+    if matches!(
+        payload.reason,
+        SponsorshipEndReason::AccountDeleted | SponsorshipEndReason::SponsorRevoked
+    ) {
+        return HttpResponse::BadRequest().body("Invalid end reason");
+    }
+    let note = payload
+        .note
+        .as_deref()
+        .map(str::trim)
+        .filter(|note| !note.is_empty());
+    if note.is_some_and(|note| note.chars().count() > MAX_END_NOTE_LENGTH) {
+        return HttpResponse::BadRequest().body(format!(
+            "Note must be at most {} characters",
+            MAX_END_NOTE_LENGTH
+        ));
+    }
+
+    let mut tx = match pool.begin().await {
+        Ok(tx) => tx,
+        Err(e) => {
+            eprintln!("Error starting transaction: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to end sponsorship");
+        }
+    };
+
+    let update_query = "
+        UPDATE sponsorships
+        SET status = 'ended', paused_at = NULL, ended_at = NOW(), ended_by = $2,
+            end_reason = $3, end_note = $4
+        WHERE sponsorship_id = $1 AND (member_id = $2 OR sponsor_id = $2) AND status <> 'ended'
+        RETURNING *
+    ";
+    let sponsorship = match sqlx::query_as::<_, Sponsorship>(update_query)
+        .bind(sponsorship_id)
+        .bind(claims.id)
+        .bind(payload.reason)
+        .bind(note)
+        .fetch_optional(&mut *tx)
+        .await
+    {
+        Ok(Some(sponsorship)) => sponsorship,
+        Ok(None) => {
+            let _ = tx.rollback().await;
+            return match fetch_own_sponsorship(pool.get_ref(), claims.id, sponsorship_id).await {
+                Ok(Some(_)) => HttpResponse::Conflict().body("This sponsorship has already ended"),
+                Ok(None) => HttpResponse::NotFound().body("Sponsorship not found"),
+                Err(e) => {
+                    eprintln!("Error fetching sponsorship: {:?}", e);
+                    HttpResponse::InternalServerError().body("Failed to end sponsorship")
+                }
+            };
+        }
+        Err(e) => {
+            eprintln!("Error ending sponsorship: {:?}", e);
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to end sponsorship");
+        }
+    };
+
+    if let Err(e) = close_sponsorship_chats(&mut tx, std::slice::from_ref(&sponsorship)).await {
+        eprintln!("Error closing sponsorship chat: {:?}", e);
+        let _ = tx.rollback().await;
+        return HttpResponse::InternalServerError().body("Failed to end sponsorship");
+    }
+
+    // Let whoever didn't end it know
+    let other_id = if sponsorship.member_id == claims.id {
+        sponsorship.sponsor_id
+    } else {
+        sponsorship.member_id
+    };
+    let notifications = match notify_sponsorship_ended(&mut tx, &sponsorship, other_id).await {
+        Ok(notifications) => notifications,
+        Err(e) => {
+            eprintln!("Error creating notification: {:?}", e);
+            let _ = tx.rollback().await;
+            return HttpResponse::InternalServerError().body("Failed to end sponsorship");
+        }
+    };
+
+    if let Err(e) = tx.commit().await {
+        eprintln!("Error committing transaction: {:?}", e);
+        return HttpResponse::InternalServerError().body("Failed to end sponsorship");
+    }
+    push_notifications(&notifications).await;
+
+    HttpResponse::Ok().json(sponsorship)
+}
+
+//Config Sponsorship Routes
+// GET /sponsorships
+// GET /sponsorships/{sponsorship_id}
+// PATCH /sponsorships/{sponsorship_id}/pause
+// PATCH /sponsorships/{sponsorship_id}/resume
+// PATCH /sponsorships/{sponsorship_id}/end
+pub fn config_sponsorship_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/sponsorships")
+            .route("", web::get().to(list_sponsorships))
+            .route("/{sponsorship_id}", web::get().to(get_sponsorship))
+            .route(
+                "/{sponsorship_id}/pause",
+                web::patch().to(pause_sponsorship),
+            )
+            .route(
+                "/{sponsorship_id}/resume",
+                web::patch().to(resume_sponsorship),
+            )
+            .route("/{sponsorship_id}/end", web::patch().to(end_sponsorship)),
+    );
+}
@@ -1,10 +1,16 @@
 use crate::handlers::auth::Claims;
+use crate::handlers::calendar::meeting_event;
+use crate::handlers::timezones::{
+    local_to_utc, localize, parse_timezone, timezone_or_default, user_timezone, LocalizedMeeting,
+};
+use crate::routes::calendar::ics_response;
+use crate::routes::meeting_series::config_meeting_series_routes;
 
 use crate::models::all_models::{
     GroupChat, GroupMeeting, MeetingParticipant, MeetingStatus, SupportGroupStatus,
 };
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::{PgPool, Row};
@@ -16,7 +22,10 @@ pub struct CreateSupportGroupMeetingRequest {
     pub support_group_id: Uuid,
     pub title: String,
     pub description: Option<String>,
+    // Wall-clock time in `timezone`
     pub scheduled_time: NaiveDateTime,
+    // IANA zone; defaults to the host's timezone preference
+    pub timezone: Option<String>,
 }
 
 //Create Support Group Meeting
@@ -28,6 +37,21 @@ pub async fn create_support_group_meeting(
     payload: web::Json<CreateSupportGroupMeetingRequest>,
 ) -> impl Responder {
     if let Some(claims) = req.extensions().get::<Claims>() {
+        let host_timezone = match user_timezone(pool.get_ref(), claims.id).await {
+            Ok(tz) => tz,
+            Err(e) => {
+                eprintln!("Error fetching timezone: {:?}", e);
+                return HttpResponse::InternalServerError()
+                    .body("Failed to process meeting creation");
+            }
+        };
+        let timezone = match payload.timezone.as_deref().map(parse_timezone) {
+            Some(Ok(tz)) => tz,
+            Some(Err(msg)) => return HttpResponse::BadRequest().body(msg),
+            None => host_timezone,
+        };
+        let scheduled_time = local_to_utc(timezone, payload.scheduled_time);
+
         // Use a transaction to ensure data consistency
         let mut tx = match pool.begin().await {
             Ok(tx) => tx,
@@ -63,9 +87,9 @@ pub async fn create_support_group_meeting(
         }
 
         let query = "
-            INSERT INTO group_meetings (meeting_id, group_chat_id, host_id, title, description, scheduled_time, support_group_id, status)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            RETURNING meeting_id, group_chat_id, support_group_id, host_id, title, description, scheduled_time, status, meeting_chat_id
+            INSERT INTO group_meetings (meeting_id, group_chat_id, host_id, title, description, scheduled_time, support_group_id, status, timezone)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING meeting_id, group_chat_id, support_group_id, host_id, title, description, scheduled_time, status, meeting_chat_id, series_id, occurrence_date, series_exception, timezone, started_at, ended_at, ended_automatically
         ";
 
         let meeting_id = Uuid::new_v4();
@@ -75,9 +99,10 @@ pub async fn create_support_group_meeting(
             .bind(claims.id)
             .bind(&payload.title)
             .bind(&payload.description)
-            .bind(&payload.scheduled_time)
+            .bind(scheduled_time)
             .bind(payload.support_group_id)
             .bind(MeetingStatus::Upcoming)
+            .bind(timezone.name())
             .fetch_one(&mut *tx)
             .await
         {
@@ -111,7 +136,7 @@ pub async fn create_support_group_meeting(
             return HttpResponse::InternalServerError().body("Failed to complete meeting creation");
         }
 
-        HttpResponse::Ok().json(meeting)
+        HttpResponse::Ok().json(LocalizedMeeting::new(meeting, host_timezone))
     } else {
         HttpResponse::Unauthorized().body("Authentication required")
     }
@@ -162,6 +187,14 @@ pub async fn join_meeting(
             }
         };
 
+        if meeting_status == MeetingStatus::Cancelled {
+            let _ = tx.rollback().await;
+            return HttpResponse::BadRequest().body("This meeting has been cancelled");
+        }
+        if meeting_status == MeetingStatus::Missed {
+            let _ = tx.rollback().await;
+            return HttpResponse::BadRequest().body("This meeting didn't take place");
+        }
         if meeting_status != MeetingStatus::Upcoming && meeting_status != MeetingStatus::Ongoing {
             let _ = tx.rollback().await;
             return HttpResponse::BadRequest().body("Cannot join a meeting that has ended");
@@ -424,9 +457,9 @@ pub async fn start_meeting(
         // Update the meeting status to 'ongoing' and set the meeting chat.
         let update_query = "
             UPDATE group_meetings 
-            SET status = $1, meeting_chat_id = $2
+            SET status = $1, meeting_chat_id = $2, started_at = NOW()
             WHERE meeting_id = $3
-            RETURNING meeting_id, group_chat_id, support_group_id, host_id, title, description, scheduled_time, status, meeting_chat_id
+            RETURNING meeting_id, group_chat_id, support_group_id, host_id, title, description, scheduled_time, status, meeting_chat_id, series_id, occurrence_date, series_exception, timezone, started_at, ended_at, ended_automatically
         ";
         let updated_meeting = match sqlx::query_as::<_, GroupMeeting>(update_query)
             .bind(MeetingStatus::Ongoing)
@@ -571,9 +604,9 @@ pub async fn end_meeting(
         // Update the meeting status to 'ended'
         let update_query = "
             UPDATE group_meetings 
-            SET status = $1
+            SET status = $1, ended_at = NOW()
             WHERE meeting_id = $2
-            RETURNING meeting_id, group_chat_id, support_group_id, host_id, title, description, scheduled_time, status, meeting_chat_id
+            RETURNING meeting_id, group_chat_id, support_group_id, host_id, title, description, scheduled_time, status, meeting_chat_id, series_id, occurrence_date, series_exception, timezone, started_at, ended_at, ended_automatically
         ";
 
         let updated_meeting = match sqlx::query_as::<_, GroupMeeting>(update_query)
@@ -603,18 +636,55 @@ pub async fn end_meeting(
     }
 }
 
+//Get Meeting Query
+#[derive(Debug, Deserialize)]
+pub struct GetMeetingQuery {
+    // "ics" downloads the meeting as an iCalendar file
+    pub format: Option<String>,
+}
+
 //Get Meeting
-//Get Meeting Input: Path (/meetings/{meeting_id})
-//Get Meeting Output: GroupMeeting
+//Get Meeting Input: Path (/meetings/{meeting_id}), Query (?format=ics)
+//Get Meeting Output: GroupMeeting, or a .ics file
 pub async fn get_meeting(
     pool: web::Data<PgPool>,
     req: HttpRequest,
     path: web::Path<Uuid>, // meeting_id passed in URL
+    query: web::Query<GetMeetingQuery>,
 ) -> impl Responder {
     if let Some(claims) = req.extensions().get::<Claims>() {
         let meeting_id = path.into_inner();
         let user_id = claims.id;
 
+        if query.format.as_deref() == Some("ics") {
+            let mut conn = match pool.acquire().await {
+                Ok(conn) => conn,
+                Err(e) => {
+                    eprintln!("Error acquiring connection: {:?}", e);
+                    return HttpResponse::InternalServerError()
+                        .body("Failed to fetch meeting details");
+                }
+            };
+            return match meeting_event(&mut conn, meeting_id).await {
+                Ok(Some(event)) => {
+                    let name = event.title.clone();
+                    ics_response(&name, &[event], Some(format!("meeting-{}.ics", meeting_id)))
+                }
+                Ok(None) => HttpResponse::NotFound().body("Meeting not found"),
+                Err(e) => {
+                    eprintln!("Error fetching meeting: {:?}", e);
+                    HttpResponse::InternalServerError().body("Failed to fetch meeting details")
+                }
+            };
+        }
+        let viewer_timezone = match user_timezone(pool.get_ref(), user_id).await {
+            Ok(tz) => tz,
+            Err(e) => {
+                eprintln!("Error fetching timezone: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to fetch meeting details");
+            }
+        };
+
         // Start a transaction
         let mut tx = match pool.begin().await {
             Ok(tx) => tx,
@@ -676,6 +746,7 @@ pub async fn get_meeting(
         }
 
         // Create a response with additional fields
+        let host_timezone = timezone_or_default(Some(&meeting.timezone));
         let response = json!({
             "data": {
                 "meeting_id": meeting.meeting_id,
@@ -686,7 +757,16 @@ pub async fn get_meeting(
                 "title": meeting.title,
                 "description": meeting.description,
                 "scheduled_time": meeting.scheduled_time,
+                "timezone": meeting.timezone,
+                "host_local_time": localize(meeting.scheduled_time, host_timezone),
+                "local_time": localize(meeting.scheduled_time, viewer_timezone),
+                "local_timezone": viewer_timezone.name(),
                 "status": meeting.status,
+                "started_at": meeting.started_at,
+                "ended_at": meeting.ended_at,
+                "ended_automatically": meeting.ended_automatically,
+                "series_id": meeting.series_id,
+                "occurrence_date": meeting.occurrence_date,
                 "participant_count": participant_count,
                 "is_participant": is_participant > 0
             }
@@ -704,6 +784,13 @@ pub async fn get_meeting(
 pub async fn get_user_meetings(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
     if let Some(claims) = req.extensions().get::<Claims>() {
         let user_id = claims.id;
+        let viewer_timezone = match user_timezone(pool.get_ref(), user_id).await {
+            Ok(tz) => tz,
+            Err(e) => {
+                eprintln!("Error fetching timezone: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to fetch meetings");
+            }
+        };
 
         // Fetch all meetings the user is a participant in
         let query = "
@@ -745,10 +832,13 @@ pub async fn get_user_meetings(pool: web::Data<PgPool>, req: HttpRequest) -> imp
                         let title: String = row.try_get("title").unwrap_or_default();
                         let description: Option<String> =
                             row.try_get("description").unwrap_or(None);
-                        let scheduled_time: NaiveDateTime =
+                        let scheduled_time: DateTime<Utc> =
                             row.try_get("scheduled_time").unwrap_or_default();
+                        let timezone: String = row.try_get("timezone").unwrap_or_default();
+                        let host_timezone = timezone_or_default(Some(&timezone));
                         let status: MeetingStatus =
                             row.try_get("status").unwrap_or(MeetingStatus::Upcoming);
+                        let series_id: Option<Uuid> = row.try_get("series_id").unwrap_or(None);
                         let group_title: String = row.try_get("group_title").unwrap_or_default();
                         let participant_count: i64 = row.try_get("participant_count").unwrap_or(0);
                         let is_participant: bool = row.try_get("is_participant").unwrap_or(false);
@@ -763,7 +853,12 @@ pub async fn get_user_meetings(pool: web::Data<PgPool>, req: HttpRequest) -> imp
                             "title": title,
                             "description": description,
                             "scheduled_time": scheduled_time,
+                            "timezone": timezone,
+                            "host_local_time": localize(scheduled_time, host_timezone),
+                            "local_time": localize(scheduled_time, viewer_timezone),
+                            "local_timezone": viewer_timezone.name(),
                             "status": status,
+                            "series_id": series_id,
                             "group_title": group_title,
                             "participant_count": participant_count,
                             "is_participant": is_participant,
@@ -784,6 +879,144 @@ pub async fn get_user_meetings(pool: web::Data<PgPool>, req: HttpRequest) -> imp
     }
 }
 
+//Update Meeting Request
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpdateMeetingRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    // Wall-clock time in `timezone`, or in the meeting's current zone if that's not given
+    pub scheduled_time: Option<NaiveDateTime>,
+    pub timezone: Option<String>,
+}
+
+//Update Meeting
+//Update Meeting Input: HttpRequest(JWT Token), Path (/meetings/{meeting_id}), UpdateMeetingRequest
+//Update Meeting Output: GroupMeeting
+//For a recurring meeting this changes just this occurrence, which then keeps its changes when the series is edited
+pub async fn update_meeting(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    payload: web::Json<UpdateMeetingRequest>,
+) -> impl Responder {
+    if let Some(claims) = req.extensions().get::<Claims>() {
+        let meeting_id = path.into_inner();
+        let new_timezone = match payload.timezone.as_deref().map(parse_timezone) {
+            Some(Ok(tz)) => Some(tz),
+            Some(Err(msg)) => return HttpResponse::BadRequest().body(msg),
+            None => None,
+        };
+        let current_timezone: Option<String> = match sqlx::query_scalar(
+            "SELECT timezone FROM group_meetings WHERE meeting_id = $1 AND host_id = $2",
+        )
+        .bind(meeting_id)
+        .bind(claims.id)
+        .fetch_optional(pool.get_ref())
+        .await
+        {
+            Ok(Some(timezone)) => Some(timezone),
+            Ok(None) => {
+                return HttpResponse::NotFound()
+                    .body("No upcoming meeting you host was found with that ID")
+            }
+            Err(e) => {
+                eprintln!("Error fetching meeting: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to update meeting");
+            }
+        };
+        let timezone =
+            new_timezone.unwrap_or_else(|| timezone_or_default(current_timezone.as_deref()));
+        let scheduled_time = payload
+            .scheduled_time
+            .map(|time| local_to_utc(timezone, time));
+        if scheduled_time.is_some_and(|time| time < Utc::now()) {
+            return HttpResponse::BadRequest().body("A meeting can't be moved into the past");
+        }
+        let viewer_timezone = match user_timezone(pool.get_ref(), claims.id).await {
+            Ok(tz) => tz,
+            Err(e) => {
+                eprintln!("Error fetching timezone: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to update meeting");
+            }
+        };
+
+        let update_query = "
+            UPDATE group_meetings
+            SET title = COALESCE($3, title),
+                description = COALESCE($4, description),
+                scheduled_time = COALESCE($5, scheduled_time),
+                timezone = $7,
+                series_exception = series_id IS NOT NULL
+            WHERE meeting_id = $1 AND host_id = $2 AND status = $6
+            RETURNING meeting_id, group_chat_id, support_group_id, host_id, title, description, scheduled_time, status, meeting_chat_id, series_id, occurrence_date, series_exception, timezone, started_at, ended_at, ended_automatically
+        ";
+        match sqlx::query_as::<_, GroupMeeting>(update_query)
+            .bind(meeting_id)
+            .bind(claims.id)
+            .bind(&payload.title)
+            .bind(&payload.description)
+            .bind(scheduled_time)
+            .bind(MeetingStatus::Upcoming)
+            .bind(timezone.name())
+            .fetch_optional(pool.get_ref())
+            .await
+        {
+            Ok(Some(meeting)) => {
+                HttpResponse::Ok().json(LocalizedMeeting::new(meeting, viewer_timezone))
+            }
+            Ok(None) => {
+                HttpResponse::NotFound().body("No upcoming meeting you host was found with that ID")
+            }
+            Err(e) => {
+                eprintln!("Error updating meeting: {:?}", e);
+                HttpResponse::InternalServerError().body("Failed to update meeting")
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().body("Authentication required")
+    }
+}
+
+//Cancel Meeting
+//Cancel Meeting Input: HttpRequest(JWT Token), Path (/meetings/{meeting_id}/cancel)
+//Cancel Meeting Output: GroupMeeting
+//For a recurring meeting only this occurrence is cancelled
+pub async fn cancel_meeting(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    if let Some(claims) = req.extensions().get::<Claims>() {
+        let meeting_id = path.into_inner();
+
+        let cancel_query = "
+            UPDATE group_meetings
+            SET status = $3
+            WHERE meeting_id = $1 AND host_id = $2 AND status = $4
+            RETURNING meeting_id, group_chat_id, support_group_id, host_id, title, description, scheduled_time, status, meeting_chat_id, series_id, occurrence_date, series_exception, timezone, started_at, ended_at, ended_automatically
+        ";
+        match sqlx::query_as::<_, GroupMeeting>(cancel_query)
+            .bind(meeting_id)
+            .bind(claims.id)
+            .bind(MeetingStatus::Cancelled)
+            .bind(MeetingStatus::Upcoming)
+            .fetch_optional(pool.get_ref())
+            .await
+        {
+            Ok(Some(meeting)) => HttpResponse::Ok().json(meeting),
+            Ok(None) => {
+                HttpResponse::NotFound().body("No upcoming meeting you host was found with that ID")
+            }
+            Err(e) => {
+                eprintln!("Error cancelling meeting: {:?}", e);
+                HttpResponse::InternalServerError().body("Failed to cancel meeting")
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().body("Authentication required")
+    }
+}
+
 //Config Meeting Routes
 // POST /meetings/create
 // POST /meetings/join
@@ -792,7 +1025,10 @@ pub async fn get_user_meetings(pool: web::Data<PgPool>, req: HttpRequest) -> imp
 // POST /meetings/{meeting_id}/start
 // POST /meetings/{meeting_id}/end
 // GET /meetings/{meeting_id}
+// PATCH /meetings/{meeting_id}
+// POST /meetings/{meeting_id}/cancel
 // GET /meetings/user
+// /meetings/series/... (see config_meeting_series_routes)
 pub fn config_meeting_routes(cfg: &mut web::ServiceConfig) {
     // For operations on individual meetings.
     cfg.service(
@@ -800,6 +1036,7 @@ pub fn config_meeting_routes(cfg: &mut web::ServiceConfig) {
             .route("/new", web::post().to(create_support_group_meeting))
             .route("/join", web::post().to(join_meeting))
             .route("/user", web::get().to(get_user_meetings))
+            .configure(config_meeting_series_routes)
             .route("/{meeting_id}/leave", web::delete().to(leave_meeting))
             .route(
                 "/{meeting_id}/participants",
@@ -807,6 +1044,8 @@ pub fn config_meeting_routes(cfg: &mut web::ServiceConfig) {
             )
             .route("/{meeting_id}/start", web::post().to(start_meeting))
             .route("/{meeting_id}/end", web::post().to(end_meeting))
-            .route("/{meeting_id}", web::get().to(get_meeting)),
+            .route("/{meeting_id}/cancel", web::post().to(cancel_meeting))
+            .route("/{meeting_id}", web::get().to(get_meeting))
+            .route("/{meeting_id}", web::patch().to(update_meeting)),
     );
 }
@@ -1,7 +1,8 @@
 use crate::handlers::auth::Claims;
-
+use crate::handlers::privacy::{placeholder_avatar_url, AudienceCheck};
 use crate::models::all_models::{
-    GroupChat, GroupMeeting, SupportGroup, SupportGroupMember, SupportGroupStatus, UserRole,
+    GroupChat, GroupMeeting, MeetingSeries, SupportGroup, SupportGroupMember, SupportGroupStatus,
+    UserRole,
 };
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use chrono::NaiveDateTime;
@@ -241,6 +242,7 @@ pub struct SupportGroupDetails {
     pub sponsors: Vec<SponsorInfo>,
     pub main_group_chat: Option<GroupChat>,
     pub meetings: Vec<GroupMeeting>,
+    pub meeting_series: Vec<MeetingSeries>,
     pub meeting_group_chats: Vec<GroupChat>,
 }
 
@@ -253,9 +255,10 @@ pub async fn get_support_group_details(
     path: web::Path<Uuid>,
 ) -> impl Responder {
     // Check authentication.
-    if req.extensions().get::<Claims>().is_none() {
-        return HttpResponse::Unauthorized().body("Authentication required");
-    }
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
 
     let support_group_id = path.into_inner();
 
@@ -308,6 +311,32 @@ pub async fn get_support_group_details(
         }
     };
 
+    // Hide members who don't share their support groups with this viewer,
+    // and sponsors' avatars where those are restricted
+    let user_ids: Vec<Uuid> = members.iter().map(|m| m.user_id).collect();
+    let audience =
+        match AudienceCheck::load(pool.get_ref(), claims.id, claims.role, &user_ids).await {
+            Ok(audience) => audience,
+            Err(e) => {
+                eprintln!("Error loading member privacy settings: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to load support group");
+            }
+        };
+    let members: Vec<SupportGroupMember> = members
+        .into_iter()
+        .filter(|m| audience.can_see(m.user_id, |s| s.support_groups))
+        .collect();
+    let sponsors: Vec<SponsorInfo> = sponsors
+        .into_iter()
+        .filter(|sponsor| audience.can_see(sponsor.user_id, |s| s.support_groups))
+        .map(|mut sponsor| {
+            if !audience.can_see(sponsor.user_id, |s| s.avatar) {
+                sponsor.avatar_url = placeholder_avatar_url(&sponsor.username);
+            }
+            sponsor
+        })
+        .collect();
+
     // Retrieve the main group chat for the support group.
     let main_group_chat = if let Some(gc_id) = group.group_chat_id {
         let chat_query = "SELECT * FROM group_chats WHERE group_chat_id = $1";
@@ -341,6 +370,20 @@ pub async fn get_support_group_details(
         }
     };
 
+    // Retrieve the group's active recurring meetings.
+    let series_query = "SELECT * FROM meeting_series WHERE support_group_id = $1 AND ended_at IS NULL ORDER BY starts_on ASC";
+    let meeting_series: Vec<MeetingSeries> = match sqlx::query_as::<_, MeetingSeries>(series_query)
+        .bind(support_group_id)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(series) => series,
+        Err(e) => {
+            eprintln!("Error fetching meeting series: {:?}", e);
+            Vec::new()
+        }
+    };
+
     // Retrieve all distinct group chats associated with these meetings.
     let meeting_group_chats: Vec<GroupChat> = match sqlx::query_as::<_, GroupChat>(
         "SELECT DISTINCT gc.* FROM group_meetings gm \
@@ -364,6 +407,7 @@ pub async fn get_support_group_details(
         sponsors,
         main_group_chat,
         meetings,
+        meeting_series,
         meeting_group_chats,
     };
 
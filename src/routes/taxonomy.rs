@@ -0,0 +1,80 @@
+use crate::handlers::auth::Claims;
+use crate::models::all_models::{TaxonomyKind, TaxonomyTerm};
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+//Taxonomy Option
+#[derive(Debug, Serialize)]
+pub struct TaxonomyOption {
+    pub code: String,
+    pub label: String,
+}
+
+//Taxonomy Listing
+#[derive(Debug, Serialize, Default)]
+pub struct TaxonomyListing {
+    pub interests: Vec<TaxonomyOption>,
+    pub experience: Vec<TaxonomyOption>,
+    pub available_days: Vec<TaxonomyOption>,
+    pub languages: Vec<TaxonomyOption>,
+}
+
+//Taxonomy Params
+#[derive(Debug, Deserialize)]
+pub struct TaxonomyParams {
+    pub kind: Option<TaxonomyKind>,
+}
+
+//List Taxonomy
+//List Taxonomy Input: HttpRequest(JWT Token), TaxonomyParams (?kind=)
+//List Taxonomy Output: TaxonomyListing
+//Profiles store the codes; clients display the labels
+pub async fn list_taxonomy(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    params: web::Query<TaxonomyParams>,
+) -> impl Responder {
+    if req.extensions().get::<Claims>().is_none() {
+        return HttpResponse::Unauthorized().body("Authentication required");
+    }
+
+    let query = "
+        SELECT * FROM taxonomy_terms
+        WHERE active = TRUE AND ($1::taxonomy_kind IS NULL OR kind = $1)
+        ORDER BY kind, position, label
+    ";
+    let terms = match sqlx::query_as::<_, TaxonomyTerm>(query)
+        .bind(params.kind)
+        .fetch_all(pool.get_ref())
+        .await
+    {
+        Ok(terms) => terms,
+        Err(e) => {
+            eprintln!("Error listing taxonomy: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to list taxonomy");
+        }
+    };
+
+    let mut listing = TaxonomyListing::default();
+    for term in terms {
+        let section = match term.kind {
+            TaxonomyKind::Interest => &mut listing.interests,
+            TaxonomyKind::Experience => &mut listing.experience,
+            TaxonomyKind::Day => &mut listing.available_days,
+            TaxonomyKind::Language => &mut listing.languages,
+        };
+        section.push(TaxonomyOption {
+            code: term.code,
+            label: term.label,
+        });
+    }
+
+    HttpResponse::Ok().json(listing)
+}
+
+//Config Taxonomy Routes
+// GET /taxonomy
+pub fn config_taxonomy_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::scope("/taxonomy").route("", web::get().to(list_taxonomy)));
+}
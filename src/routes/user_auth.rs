@@ -36,6 +36,11 @@ pub async fn create_user(
     pool: web::Data<PgPool>,
     payload: web::Json<CreateUserRequest>,
 ) -> impl Responder {
+    // Reserved for anonymised accounts
+    if payload.username.to_lowercase().starts_with("deleted-user-") {
+        return HttpResponse::BadRequest().body("This username is not available");
+    }
+
     let avatar_url = format!(
         "https://ui-avatars.com/api/?name={}&background=random",
         payload.username
@@ -87,6 +92,7 @@ struct UserAuth {
     pub avatar_url: String,
     pub role: UserRole,
     pub banned_until: Option<NaiveDateTime>,
+    pub deleted_at: Option<NaiveDateTime>,
 }
 
 //Login Response
@@ -108,7 +114,7 @@ pub async fn login(
 ) -> impl Responder {
     // Query the user by username and fetch necessary fields
     let query = "
-        SELECT user_id, username, password_hash, avatar_url, role, banned_until 
+        SELECT user_id, username, password_hash, avatar_url, role, banned_until, deleted_at
         FROM users WHERE username = $1";
 
     let user = sqlx::query_as::<_, UserAuth>(query)
@@ -118,6 +124,11 @@ pub async fn login(
 
     match user {
         Ok(user) => {
+            // Anonymised accounts can never sign in again
+            if user.deleted_at.is_some() {
+                return HttpResponse::Unauthorized().body("Invalid credentials");
+            }
+
             // Check if the user is banned
             if let Some(banned_until) = user.banned_until {
                 if banned_until > chrono::Utc::now().naive_utc() {
@@ -1,17 +1,28 @@
+use crate::handlers::account_deletion::ACCOUNT_DELETION_GRACE_DAYS;
 use crate::handlers::auth::Claims;
 use crate::handlers::b2_storage::B2Client;
-use crate::models::all_models::UserRole;
+use crate::handlers::blocking::has_blocked;
+use crate::handlers::geocoding::{coarse_location, GeocodeError, Geocoder, LocationInput};
+use crate::handlers::image_processing::{
+    avatar_file_name, process_avatar, sniff_image_format, stored_avatar_file_names,
+    PRIMARY_AVATAR_SIZE,
+};
+use crate::handlers::privacy::{
+    apply_privacy_preset, location_city, placeholder_avatar_url, privacy_settings_for,
+    viewer_relation,
+};
+use crate::handlers::taxonomy::{profile_completeness, Taxonomy};
+use crate::handlers::timezones::parse_timezone;
+use crate::handlers::upload_stream::{read_field_limited, UploadError};
+use crate::models::all_models::{PrivacySettings, TaxonomyKind, UserRole, VisibilityAudience};
 use actix_multipart::Multipart;
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, Responder};
 use chrono::{NaiveDate, NaiveDateTime};
-use futures::{StreamExt, TryStreamExt};
+use futures::TryStreamExt;
 use log::{error, info};
-use mime_guess::from_path;
-use sanitize_filename::sanitize;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sqlx::{PgPool, Row};
-use std::io::Write;
 use uuid::Uuid;
 
 //User Info
@@ -33,6 +44,8 @@ struct UserInfo {
     pub available_days: Option<Vec<String>>,
     pub languages: Option<Vec<String>>,
     pub privacy: bool,
+    pub deletion_scheduled_for: Option<NaiveDateTime>,
+    pub timezone: Option<String>,
 }
 //Get Logged In User Info
 //Get Logged In User Info Input: HttpRequest(JWT Token)
@@ -43,7 +56,8 @@ pub async fn get_logged_in_user_info(pool: web::Data<PgPool>, req: HttpRequest)
 
         let query = sqlx::query_as::<_, UserInfo>(
             "SELECT user_id, username, role, avatar_url, created_at, dob, user_profile, bio, 
-            email_verified, banned_until, location, interests, experience, available_days, languages, privacy
+            email_verified, banned_until, location, interests, experience, available_days, languages, privacy,
+            deletion_scheduled_for, timezone
             FROM users WHERE user_id = $1"
         )
         .bind(user_id)
@@ -59,9 +73,10 @@ pub async fn get_logged_in_user_info(pool: web::Data<PgPool>, req: HttpRequest)
     }
 }
 
-//Public User Info
-#[derive(Serialize, Deserialize, sqlx::FromRow)]
-struct PublicUserInfo {
+//Profile Row
+#[derive(sqlx::FromRow)]
+struct ProfileRow {
+    user_id: Uuid,
     username: String,
     role: String,
     avatar_url: String,
@@ -70,134 +85,259 @@ struct PublicUserInfo {
     interests: Option<Vec<String>>,
     experience: Option<Vec<String>>,
     languages: Option<Vec<String>>,
+    location: Option<Value>,
 }
 
-//Private User Info
+const PROFILE_COLUMNS: &str = "user_id, username, role::text as role, avatar_url, user_profile, bio, interests, experience, languages, location";
+
+//Profile Support Group
 #[derive(Serialize, Deserialize, sqlx::FromRow)]
-struct PrivateUserInfo {
+struct ProfileSupportGroup {
+    support_group_id: Uuid,
+    title: String,
+}
+
+//Profile View
+//Fields the viewer isn't allowed to see are null
+#[derive(Serialize, Deserialize)]
+struct ProfileView {
     username: String,
     role: String,
     avatar_url: String,
+    user_profile: Option<String>,
+    bio: Option<String>,
+    interests: Option<Vec<String>>,
+    experience: Option<Vec<String>>,
+    languages: Option<Vec<String>>,
+    location_city: Option<String>,
+    support_groups: Option<Vec<ProfileSupportGroup>>,
 }
 
-//User Privacy Check
-#[derive(sqlx::FromRow, Serialize, Deserialize)]
-struct UserPrivacyCheck {
-    privacy: bool,
-}
-
-//Get User By Name
-//Get User By Name Input: Path (/users/{username})
-//Get User By Name Output: PublicUserInfo or PrivateUserInfo
-pub async fn get_user_by_name(pool: web::Data<PgPool>, path: web::Path<String>) -> impl Responder {
-    let username = path.into_inner();
+// Helper function: Apply the user's privacy settings for this viewer.
+// Returns None when the user has blocked the viewer.
+async fn build_profile_view(
+    pool: &PgPool,
+    viewer: &Claims,
+    row: ProfileRow,
+) -> Result<Option<ProfileView>, sqlx::Error> {
+    if viewer.role != UserRole::Admin && has_blocked(pool, row.user_id, viewer.id).await? {
+        return Ok(None);
+    }
 
-    // Debug log to check the username being queried
-    eprintln!("Looking up user with username: {}", username);
+    let settings = privacy_settings_for(pool, row.user_id).await?;
+    let relation = viewer_relation(pool, viewer.id, viewer.role, row.user_id).await?;
 
-    let privacy_result =
-        sqlx::query_as::<_, UserPrivacyCheck>("SELECT privacy FROM users WHERE username = $1")
-            .bind(&username)
-            .fetch_one(pool.get_ref())
-            .await;
+    let support_groups = if relation.can_see(settings.support_groups) {
+        let groups = sqlx::query_as::<_, ProfileSupportGroup>(
+            "SELECT sg.support_group_id, sg.title
+            FROM support_groups sg
+            JOIN support_group_members sgm ON sg.support_group_id = sgm.support_group_id
+            WHERE sgm.user_id = $1 AND sg.status = 'approved'
+            ORDER BY sgm.joined_at DESC",
+        )
+        .bind(row.user_id)
+        .fetch_all(pool)
+        .await?;
+        Some(groups)
+    } else {
+        None
+    };
 
-    match privacy_result {
-        Ok(privacy_data) => {
-            eprintln!("Found user, privacy setting: {}", privacy_data.privacy);
-            if privacy_data.privacy {
-                let private_user_result = sqlx::query_as::<_, PrivateUserInfo>(
-                    "SELECT username, role::text as role, avatar_url FROM users WHERE username = $1",
-                )
-                .bind(&username)
-                .fetch_one(pool.get_ref())
-                .await;
-
-                return match private_user_result {
-                    Ok(private_user) => HttpResponse::Ok().json(private_user),
-                    Err(e) => {
-                        eprintln!("Error fetching private user data: {:?}", e);
-                        HttpResponse::InternalServerError().body("Failed to retrieve user data")
-                    }
-                };
-            }
+    let show_bio = relation.can_see(settings.bio);
+    Ok(Some(ProfileView {
+        avatar_url: if relation.can_see(settings.avatar) {
+            row.avatar_url
+        } else {
+            placeholder_avatar_url(&row.username)
+        },
+        location_city: if relation.can_see(settings.location_city) {
+            location_city(row.location.as_ref())
+        } else {
+            None
+        },
+        user_profile: show_bio.then_some(row.user_profile),
+        bio: row.bio.filter(|_| show_bio),
+        interests: row
+            .interests
+            .filter(|_| relation.can_see(settings.interests)),
+        experience: row
+            .experience
+            .filter(|_| relation.can_see(settings.experience)),
+        languages: row
+            .languages
+            .filter(|_| relation.can_see(settings.languages)),
+        support_groups,
+        username: row.username,
+        role: row.role,
+    }))
+}
 
-            let user_result = sqlx::query_as::<_, PublicUserInfo>(
-                "SELECT username, role::text as role, avatar_url, user_profile, bio, interests, experience, languages
-                FROM users WHERE username = $1"
-            )
-            .bind(&username)
-            .fetch_one(pool.get_ref())
-            .await;
+//Get User By Name
+//Get User By Name Input: HttpRequest(JWT Token), Path (/users/{username})
+//Get User By Name Output: ProfileView
+pub async fn get_user_by_name(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<String>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+    let username = path.into_inner();
 
-            match user_result {
-                Ok(user) => HttpResponse::Ok().json(user),
-                Err(e) => {
-                    eprintln!("Error fetching public user data: {:?}", e);
-                    HttpResponse::InternalServerError().body("Failed to retrieve user data")
-                }
-            }
+    let query = format!("SELECT {} FROM users WHERE username = $1", PROFILE_COLUMNS);
+    let row = match sqlx::query_as::<_, ProfileRow>(&query)
+        .bind(&username)
+        .fetch_optional(pool.get_ref())
+        .await
+    {
+        Ok(Some(row)) => row,
+        Ok(None) => return HttpResponse::NotFound().body("User not found"),
+        Err(e) => {
+            eprintln!("Error fetching user data: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to retrieve user data");
         }
+    };
+
+    match build_profile_view(pool.get_ref(), &claims, row).await {
+        Ok(Some(profile)) => HttpResponse::Ok().json(profile),
+        // Same response as a missing user so a block can't be detected
+        Ok(None) => HttpResponse::NotFound().body("User not found"),
         Err(e) => {
-            eprintln!("Error checking user privacy: {:?}", e);
-            HttpResponse::NotFound().body("User not found")
+            eprintln!("Error applying privacy settings: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve user data")
         }
     }
 }
 
 //Get User By ID
-//Get User By ID Input: Path (/users/id/{user_id})
-//Get User By ID Output: PublicUserInfo or PrivateUserInfo
-pub async fn get_user_by_id(pool: web::Data<PgPool>, path: web::Path<Uuid>) -> impl Responder {
+//Get User By ID Input: HttpRequest(JWT Token), Path (/users/id/{user_id})
+//Get User By ID Output: ProfileView
+pub async fn get_user_by_id(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
     let user_id = path.into_inner();
 
-    // Debug log to check the user ID being queried
-    eprintln!("Looking up user with ID: {}", user_id);
+    let query = format!("SELECT {} FROM users WHERE user_id = $1", PROFILE_COLUMNS);
+    let row = match sqlx::query_as::<_, ProfileRow>(&query)
+        .bind(user_id)
+        .fetch_optional(pool.get_ref())
+        .await
+    {
+        Ok(Some(row)) => row,
+        Ok(None) => return HttpResponse::NotFound().body("User not found"),
+        Err(e) => {
+            eprintln!("Error fetching user data: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to retrieve user data");
+        }
+    };
+
+    match build_profile_view(pool.get_ref(), &claims, row).await {
+        Ok(Some(profile)) => HttpResponse::Ok().json(profile),
+        // Same response as a missing user so a block can't be detected
+        Ok(None) => HttpResponse::NotFound().body("User not found"),
+        Err(e) => {
+            eprintln!("Error applying privacy settings: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to retrieve user data")
+        }
+    }
+}
 
-    let privacy_result =
-        sqlx::query_as::<_, UserPrivacyCheck>("SELECT privacy FROM users WHERE user_id = $1")
-            .bind(user_id)
-            .fetch_one(pool.get_ref())
-            .await;
+//Update Privacy Settings Request
+//Omitted fields keep their current audience
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UpdatePrivacySettingsRequest {
+    pub bio: Option<VisibilityAudience>,
+    pub interests: Option<VisibilityAudience>,
+    pub experience: Option<VisibilityAudience>,
+    pub languages: Option<VisibilityAudience>,
+    pub location_city: Option<VisibilityAudience>,
+    pub avatar: Option<VisibilityAudience>,
+    pub support_groups: Option<VisibilityAudience>,
+    pub recovery: Option<VisibilityAudience>,
+}
 
-    match privacy_result {
-        Ok(privacy_data) => {
-            eprintln!("Found user, privacy setting: {}", privacy_data.privacy);
-            if privacy_data.privacy {
-                let private_user_result = sqlx::query_as::<_, PrivateUserInfo>(
-                    "SELECT username, role::text as role, avatar_url FROM users WHERE user_id = $1",
-                )
-                .bind(user_id)
-                .fetch_one(pool.get_ref())
-                .await;
-
-                return match private_user_result {
-                    Ok(private_user) => HttpResponse::Ok().json(private_user),
-                    Err(e) => {
-                        eprintln!("Error fetching private user data: {:?}", e);
-                        HttpResponse::InternalServerError().body("Failed to retrieve user data")
-                    }
-                };
-            }
+//Get Privacy Settings
+//Get Privacy Settings Input: HttpRequest(JWT Token)
+//Get Privacy Settings Output: PrivacySettings
+pub async fn get_privacy_settings(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
 
-            let user_result = sqlx::query_as::<_, PublicUserInfo>(
-                "SELECT username, role::text as role, avatar_url, user_profile, bio, interests, experience, languages
-                FROM users WHERE user_id = $1"
-            )
-            .bind(user_id)
-            .fetch_one(pool.get_ref())
-            .await;
+    match privacy_settings_for(pool.get_ref(), claims.id).await {
+        Ok(settings) => HttpResponse::Ok().json(settings),
+        Err(e) => {
+            eprintln!("Error fetching privacy settings: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to fetch privacy settings")
+        }
+    }
+}
 
-            match user_result {
-                Ok(user) => HttpResponse::Ok().json(user),
-                Err(e) => {
-                    eprintln!("Error fetching public user data: {:?}", e);
-                    HttpResponse::InternalServerError().body("Failed to retrieve user data")
-                }
-            }
+//Update Privacy Settings
+//Update Privacy Settings Input: HttpRequest(JWT Token), UpdatePrivacySettingsRequest
+//Update Privacy Settings Output: PrivacySettings
+pub async fn update_privacy_settings(
+    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    payload: web::Json<UpdatePrivacySettingsRequest>,
+) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    // Start from the current settings so a first save keeps the defaults for omitted fields
+    let current = match privacy_settings_for(pool.get_ref(), claims.id).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Error fetching privacy settings: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to update privacy settings");
         }
+    };
+
+    let query = "
+        INSERT INTO user_privacy_settings
+            (user_id, bio, interests, experience, languages, location_city, avatar, support_groups,
+             recovery, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW())
+        ON CONFLICT (user_id) DO UPDATE SET
+            bio = EXCLUDED.bio,
+            interests = EXCLUDED.interests,
+            experience = EXCLUDED.experience,
+            languages = EXCLUDED.languages,
+            location_city = EXCLUDED.location_city,
+            avatar = EXCLUDED.avatar,
+            support_groups = EXCLUDED.support_groups,
+            recovery = EXCLUDED.recovery,
+            updated_at = NOW()
+        RETURNING *
+    ";
+    match sqlx::query_as::<_, PrivacySettings>(query)
+        .bind(claims.id)
+        .bind(payload.bio.unwrap_or(current.bio))
+        .bind(payload.interests.unwrap_or(current.interests))
+        .bind(payload.experience.unwrap_or(current.experience))
+        .bind(payload.languages.unwrap_or(current.languages))
+        .bind(payload.location_city.unwrap_or(current.location_city))
+        .bind(payload.avatar.unwrap_or(current.avatar))
+        .bind(payload.support_groups.unwrap_or(current.support_groups))
+        .bind(payload.recovery.unwrap_or(current.recovery))
+        .fetch_one(pool.get_ref())
+        .await
+    {
+        Ok(settings) => HttpResponse::Ok().json(settings),
         Err(e) => {
-            eprintln!("Error checking user privacy: {:?}", e);
-            HttpResponse::NotFound().body("User not found")
+            eprintln!("Error updating privacy settings: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to update privacy settings")
         }
     }
 }
@@ -207,12 +347,15 @@ pub async fn get_user_by_id(pool: web::Data<PgPool>, path: web::Path<Uuid>) -> i
 pub struct UpdateUserRequest {
     pub user_profile: Option<String>,
     pub bio: Option<String>,
-    pub location: Option<Value>,
+    // Geocoded on save; only a coarse grid cell is stored
+    pub location: Option<LocationInput>,
     pub interests: Option<Vec<String>>,
     pub experience: Option<Vec<String>>,
     pub available_days: Option<Vec<String>>,
     pub languages: Option<Vec<String>>,
     pub privacy: Option<bool>,
+    // IANA zone meeting times are shown in, e.g. Europe/London
+    pub timezone: Option<String>,
 }
 //Updated User Profile
 #[derive(Serialize, sqlx::FromRow)]
@@ -225,19 +368,112 @@ pub struct UpdatedUserProfile {
     pub available_days: Option<Vec<String>>,
     pub languages: Option<Vec<String>>,
     pub privacy: bool,
+    pub timezone: Option<String>,
 }
 //Update User Profile
 //Update User Profile Input: HttpRequest(JWT Token), UpdateUserRequest
 //Update User Profile Output: UpdatedUserProfile
 pub async fn update_user_profile(
     pool: web::Data<PgPool>,
+    geocoder: web::Data<dyn Geocoder>,
     req: HttpRequest,
     payload: web::Json<UpdateUserRequest>,
 ) -> impl Responder {
     if let Some(claims) = req.extensions().get::<Claims>() {
         let user_id = claims.id;
 
-        let result = sqlx::query_as::<_, UpdatedUserProfile>(
+        // Matching fields must use the controlled vocabularies, stored as term codes
+        let taxonomy = match Taxonomy::load(pool.get_ref()).await {
+            Ok(taxonomy) => taxonomy,
+            Err(e) => {
+                eprintln!("Error loading taxonomy: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to update profile");
+            }
+        };
+        let mut errors: Vec<String> = Vec::new();
+        let mut normalise = |field: &str, kind: TaxonomyKind, values: &Option<Vec<String>>| {
+            values
+                .as_ref()
+                .and_then(|values| match taxonomy.normalise(kind, values) {
+                    Ok(codes) => Some(codes),
+                    Err(unknown) => {
+                        errors.push(format!("{}: {}", field, unknown.join(", ")));
+                        None
+                    }
+                })
+        };
+        let interests = normalise("interests", TaxonomyKind::Interest, &payload.interests);
+        let experience = normalise("experience", TaxonomyKind::Experience, &payload.experience);
+        let available_days =
+            normalise("available_days", TaxonomyKind::Day, &payload.available_days);
+        let languages = normalise("languages", TaxonomyKind::Language, &payload.languages);
+        if !errors.is_empty() {
+            return HttpResponse::BadRequest()
+                .body(format!("Unrecognised values ({})", errors.join("; ")));
+        }
+
+        let timezone = match payload.timezone.as_deref().map(parse_timezone) {
+            Some(Ok(tz)) => Some(tz.name().to_string()),
+            Some(Err(msg)) => return HttpResponse::BadRequest().body(msg),
+            None => None,
+        };
+
+        let place = match &payload.location {
+            Some(input) => match geocoder.geocode(input) {
+                Ok(Some(place)) => Some(place),
+                Ok(None) => {
+                    return HttpResponse::BadRequest().body(
+                        "We couldn't find that location, check the spelling or add a country",
+                    )
+                }
+                Err(GeocodeError::InvalidInput(msg)) | Err(GeocodeError::Unsupported(msg)) => {
+                    return HttpResponse::BadRequest().body(msg)
+                }
+            },
+            None => None,
+        };
+        let location = match place
+            .as_ref()
+            .map(|p| serde_json::to_value(coarse_location(p)))
+        {
+            Some(Ok(location)) => Some(location),
+            Some(Err(e)) => {
+                eprintln!("Error serialising location: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to update profile");
+            }
+            None => None,
+        };
+        // A new location's timezone fills in the profile timezone if none was ever set
+        let place_timezone = place
+            .as_ref()
+            .and_then(|p| p.timezone.as_deref())
+            .and_then(|tz| parse_timezone(tz).ok())
+            .map(|tz| tz.name().to_string());
+
+        let mut tx = match pool.begin().await {
+            Ok(tx) => tx,
+            Err(e) => {
+                eprintln!("Error starting transaction: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to update profile");
+            }
+        };
+
+        // Lock the row so the preset below is judged against the flag we replace
+        let was_private = match sqlx::query_scalar::<_, bool>(
+            "SELECT privacy FROM users WHERE user_id = $1 FOR UPDATE",
+        )
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await
+        {
+            Ok(privacy) => privacy,
+            Err(e) => {
+                eprintln!("Error fetching user privacy: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to update profile");
+            }
+        };
+
+        let updated_user = match sqlx::query_as::<_, UpdatedUserProfile>(
             "UPDATE users 
             SET user_profile = COALESCE($1, user_profile),
                 bio = COALESCE($2, bio),
@@ -246,59 +482,167 @@ pub async fn update_user_profile(
                 experience = COALESCE($5, experience),
                 available_days = COALESCE($6, available_days),
                 languages = COALESCE($7, languages),
-                privacy = COALESCE($8, privacy)
-            WHERE user_id = $9
-            RETURNING user_profile, bio, location, interests, experience, available_days, languages, privacy"
+                privacy = COALESCE($8, privacy),
+                timezone = COALESCE($9, timezone, $11)
+            WHERE user_id = $10
+            RETURNING user_profile, bio, location, interests, experience, available_days, languages, privacy, timezone"
         )
         .bind(payload.user_profile.as_ref())
         .bind(payload.bio.as_ref())
-        .bind(payload.location.as_ref())
-        .bind(payload.interests.as_ref())
-        .bind(payload.experience.as_ref())
-        .bind(payload.available_days.as_ref())
-        .bind(payload.languages.as_ref())
+        .bind(location)
+        .bind(interests)
+        .bind(experience)
+        .bind(available_days)
+        .bind(languages)
         .bind(payload.privacy)
+        .bind(timezone)
         .bind(user_id)
-        .fetch_one(pool.get_ref())
-        .await;
-
-        match result {
-            Ok(updated_user) => HttpResponse::Ok().json(updated_user),
+        .bind(place_timezone)
+        .fetch_one(&mut *tx)
+        .await
+        {
+            Ok(updated_user) => updated_user,
             Err(e) => {
                 eprintln!("Error updating user profile: {:?}", e);
-                HttpResponse::InternalServerError().body("Failed to update profile")
+                return HttpResponse::InternalServerError().body("Failed to update profile");
+            }
+        };
+
+        // The old single flag maps onto a preset of per-field settings, but only when it
+        // actually flips, so resending it doesn't wipe settings tuned since
+        if let Some(private) = payload.privacy.filter(|private| *private != was_private) {
+            if let Err(e) = apply_privacy_preset(&mut tx, user_id, private).await {
+                eprintln!("Error applying privacy preset: {:?}", e);
+                return HttpResponse::InternalServerError().body("Failed to update privacy");
             }
         }
+
+        if let Err(e) = tx.commit().await {
+            eprintln!("Error committing profile update: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to update profile");
+        }
+
+        HttpResponse::Ok().json(updated_user)
     } else {
         HttpResponse::Unauthorized().body("Authentication required")
     }
 }
 
+//Get Profile Completeness
+//Get Profile Completeness Input: HttpRequest(JWT Token)
+//Get Profile Completeness Output: ProfileCompleteness
+pub async fn get_profile_completeness(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    let claims: Claims = match req.extensions().get::<Claims>() {
+        Some(c) => c.clone(),
+        None => return HttpResponse::Unauthorized().body("Authentication required"),
+    };
+
+    match profile_completeness(pool.get_ref(), claims.id).await {
+        Ok(completeness) => HttpResponse::Ok().json(completeness),
+        Err(e) => {
+            eprintln!("Error checking profile completeness: {:?}", e);
+            HttpResponse::InternalServerError().body("Failed to check profile completeness")
+        }
+    }
+}
+
+//Account Deletion Response
+#[derive(Serialize)]
+pub struct AccountDeletionResponse {
+    pub message: String,
+    pub deletion_scheduled_for: Option<NaiveDateTime>,
+}
+
+//Delete User Account
+//Delete User Account Input: HttpRequest(JWT Token)
+//Delete User Account Output: AccountDeletionResponse
+//The account is anonymised once the grace period ends; until then it can be restored
 pub async fn delete_user_account(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
     if let Some(claims) = req.extensions().get::<Claims>() {
         let user_id = claims.id;
 
-        let result = sqlx::query("DELETE FROM users WHERE user_id = $1")
-            .bind(user_id)
-            .execute(pool.get_ref())
-            .await;
+        // Keep the original date if deletion was already requested
+        let result = sqlx::query_scalar::<_, Option<NaiveDateTime>>(
+            "UPDATE users
+            SET deletion_requested_at = COALESCE(deletion_requested_at, NOW()),
+                deletion_scheduled_for = COALESCE(deletion_scheduled_for, NOW() + make_interval(days => $2))
+            WHERE user_id = $1 AND deleted_at IS NULL
+            RETURNING deletion_scheduled_for",
+        )
+        .bind(user_id)
+        .bind(ACCOUNT_DELETION_GRACE_DAYS as i32)
+        .fetch_optional(pool.get_ref())
+        .await;
 
         match result {
-            Ok(_) => HttpResponse::Ok().body("Account deleted successfully"),
-            Err(_) => HttpResponse::InternalServerError().body("Failed to delete account"),
+            Ok(Some(scheduled_for)) => HttpResponse::Ok().json(AccountDeletionResponse {
+                message: format!(
+                    "Account scheduled for deletion. You have {} days to restore it.",
+                    ACCOUNT_DELETION_GRACE_DAYS
+                ),
+                deletion_scheduled_for: scheduled_for,
+            }),
+            Ok(None) => HttpResponse::NotFound().body("User not found"),
+            Err(e) => {
+                eprintln!("Error scheduling account deletion: {:?}", e);
+                HttpResponse::InternalServerError().body("Failed to delete account")
+            }
         }
     } else {
         HttpResponse::Unauthorized().body("Authentication required")
     }
 }
 
+//Restore User Account
+//Restore User Account Input: HttpRequest(JWT Token)
+//Restore User Account Output: AccountDeletionResponse
+pub async fn restore_user_account(pool: web::Data<PgPool>, req: HttpRequest) -> impl Responder {
+    if let Some(claims) = req.extensions().get::<Claims>() {
+        let result = sqlx::query(
+            "UPDATE users
+            SET deletion_requested_at = NULL, deletion_scheduled_for = NULL
+            WHERE user_id = $1 AND deleted_at IS NULL AND deletion_scheduled_for IS NOT NULL",
+        )
+        .bind(claims.id)
+        .execute(pool.get_ref())
+        .await;
+
+        match result {
+            Ok(r) if r.rows_affected() > 0 => HttpResponse::Ok().json(AccountDeletionResponse {
+                message: "Account deletion cancelled".to_string(),
+                deletion_scheduled_for: None,
+            }),
+            Ok(_) => HttpResponse::BadRequest().body("Account is not scheduled for deletion"),
+            Err(e) => {
+                eprintln!("Error cancelling account deletion: {:?}", e);
+                HttpResponse::InternalServerError().body("Failed to restore account")
+            }
+        }
+    } else {
+        HttpResponse::Unauthorized().body("Authentication required")
+    }
+}
+
+// Maximum accepted avatar upload size
+const MAX_AVATAR_UPLOAD_BYTES: usize = 5 * 1024 * 1024;
+
 // Avatar upload response
 #[derive(Serialize, Deserialize)]
 pub struct AvatarUploadResponse {
     pub avatar_url: String,
+    pub variants: Vec<AvatarVariantUrl>,
+}
+
+// Uploaded avatar variant
+#[derive(Serialize, Deserialize)]
+pub struct AvatarVariantUrl {
+    pub size: u32,
+    pub url: String,
 }
 
 // Upload avatar handler
+// Accepts JPEG/PNG/WebP (sniffed from magic bytes), strips metadata,
+// centre-crops and stores WebP variants for every size in AVATAR_SIZES
 pub async fn upload_avatar(
     pool: web::Data<PgPool>,
     req: HttpRequest,
@@ -334,24 +678,8 @@ pub async fn upload_avatar(
         }
     };
 
-    // If the current avatar is from B2 (not the default UI Avatars), delete it
-    if current_avatar.contains("/file/") && !current_avatar.contains("ui-avatars.com") {
-        // Extract filename from URL
-        let filename = current_avatar.split('/').last().unwrap_or_default();
-
-        // Delete file from B2
-        if let Err(e) = b2_client.delete_file(filename).await {
-            error!("Failed to delete old avatar from B2: {:?}", e);
-            // Continue anyway to upload the new avatar
-        } else {
-            info!("Successfully deleted old avatar from B2");
-        }
-    }
-
     // Process the multipart form data
     let mut file_bytes: Option<Vec<u8>> = None;
-    let mut file_name: Option<String> = None;
-    let mut content_type: Option<String> = None;
 
     // Improved multipart handling
     info!("Starting to process multipart form data");
@@ -377,39 +705,15 @@ pub async fn upload_avatar(
         info!("Processing field: {}", field_name);
 
         if field_name == "avatar" {
-            // Get filename
-            let original_filename = content_disposition
-                .get_filename()
-                .map(|f| sanitize(f))
-                .unwrap_or_else(|| format!("avatar_{}.jpg", Uuid::new_v4()));
-
-            info!("Original filename: {}", original_filename);
-
-            // Create a unique filename with user ID
-            let extension = original_filename.split('.').last().unwrap_or("jpg");
-            let unique_filename = format!("avatar_{}.{}", claims.id, extension);
-            file_name = Some(unique_filename.clone());
-            info!("Generated unique filename: {}", unique_filename);
-
-            // Guess content type from filename
-            let mime_type = from_path(&original_filename)
-                .first_or_octet_stream()
-                .to_string();
-            content_type = Some(mime_type.clone());
-            info!("Detected content type: {}", mime_type);
-
-            // Read file data using a more reliable approach
-            match read_field_body(field).await {
-                Ok(data) => {
-                    info!("Successfully read file data: {} bytes", data.len());
-
-                    // Check file size (limit to 5MB)
-                    if data.len() > 5 * 1024 * 1024 {
-                        error!("File too large: {} bytes", data.len());
-                        return HttpResponse::BadRequest().body("File too large (max 5MB)");
-                    }
-
-                    file_bytes = Some(data);
+            // Stream the field, aborting as soon as it passes the size limit
+            match read_field_limited(field, MAX_AVATAR_UPLOAD_BYTES).await {
+                Ok(upload) => {
+                    info!("Successfully read file data: {} bytes", upload.data.len());
+                    file_bytes = Some(upload.data);
+                }
+                Err(UploadError::TooLarge(limit)) => {
+                    error!("Avatar upload exceeded {} bytes", limit);
+                    return HttpResponse::PayloadTooLarge().body("File too large (max 5MB)");
                 }
                 Err(e) => {
                     error!("Failed to read field body: {:?}", e);
@@ -420,35 +724,71 @@ pub async fn upload_avatar(
         }
     }
 
-    // Check if we have a file
-    let (file_data, filename, mime_type) = match (file_bytes, file_name, content_type) {
-        (Some(data), Some(name), Some(mime)) => {
-            info!(
-                "File data ready for upload: {} bytes, name: {}, type: {}",
-                data.len(),
-                name,
-                mime
-            );
-            (data, name, mime)
-        }
-        _ => {
+    let file_data = match file_bytes {
+        Some(data) => data,
+        None => {
             error!("No avatar file provided or incomplete data");
             return HttpResponse::BadRequest().body("No avatar file provided or incomplete data");
         }
     };
 
-    // Upload to B2
-    info!("Uploading file to B2 storage");
-    let avatar_url = match b2_client
-        .upload_file(&file_data, &filename, &mime_type)
+    // The filename and client content type are not trusted, only the bytes are
+    if sniff_image_format(&file_data).is_none() {
+        error!("Rejected avatar upload with unrecognised magic bytes");
+        return HttpResponse::UnsupportedMediaType()
+            .body("Avatar must be a JPEG, PNG or WebP image");
+    }
+
+    // Decode, strip metadata, crop and resize off the async executor
+    let variants = match web::block(move || process_avatar(&file_data).map_err(|e| e.to_string()))
         .await
     {
-        Ok(url) => {
-            info!("Successfully uploaded file to B2: {}", url);
-            url
+        Ok(Ok(variants)) => variants,
+        Ok(Err(e)) => {
+            error!("Failed to process avatar image: {}", e);
+            return HttpResponse::BadRequest().body(e);
         }
         Err(e) => {
-            error!("Failed to upload avatar to B2: {:?}", e);
+            error!("Avatar processing task failed: {:?}", e);
+            return HttpResponse::InternalServerError().body("Failed to process avatar");
+        }
+    };
+
+    // Upload every variant to B2
+    info!("Uploading {} avatar variants to B2 storage", variants.len());
+    let upload_id = Uuid::new_v4();
+    let mut uploaded: Vec<AvatarVariantUrl> = Vec::with_capacity(variants.len());
+    for variant in &variants {
+        let filename = avatar_file_name(&claims.id, &upload_id, variant.size);
+        match b2_client
+            .upload_file(&variant.data, &filename, "image/webp")
+            .await
+        {
+            Ok(url) => {
+                info!("Successfully uploaded avatar variant to B2: {}", url);
+                uploaded.push(AvatarVariantUrl {
+                    size: variant.size,
+                    url,
+                });
+            }
+            Err(e) => {
+                error!("Failed to upload avatar variant to B2: {:?}", e);
+                // Don't leave a partial set of variants behind
+                for done in &uploaded {
+                    let name = avatar_file_name(&claims.id, &upload_id, done.size);
+                    if let Err(e) = b2_client.delete_file(&name).await {
+                        error!("Failed to clean up avatar variant {}: {:?}", name, e);
+                    }
+                }
+                return HttpResponse::InternalServerError().body("Failed to upload avatar");
+            }
+        }
+    }
+
+    let primary_url = match uploaded.iter().find(|v| v.size == PRIMARY_AVATAR_SIZE) {
+        Some(variant) => variant.url.clone(),
+        None => {
+            error!("Primary avatar variant missing after upload");
             return HttpResponse::InternalServerError().body("Failed to upload avatar");
         }
     };
@@ -457,43 +797,35 @@ pub async fn upload_avatar(
     info!("Updating avatar URL in database");
     let result =
         sqlx::query("UPDATE users SET avatar_url = $1 WHERE user_id = $2 RETURNING avatar_url")
-            .bind(&avatar_url)
+            .bind(&primary_url)
             .bind(claims.id)
             .fetch_one(pool.get_ref())
             .await;
 
-    match result {
-        Ok(record) => {
-            let avatar_url: String = record.get("avatar_url");
-            info!("Avatar URL updated successfully: {}", avatar_url);
-            HttpResponse::Ok().json(AvatarUploadResponse { avatar_url })
-        }
+    let avatar_url: String = match result {
+        Ok(record) => record.get("avatar_url"),
         Err(e) => {
             error!("Error updating avatar URL in database: {:?}", e);
-            HttpResponse::InternalServerError().body("Failed to update avatar URL in database")
+            return HttpResponse::InternalServerError()
+                .body("Failed to update avatar URL in database");
         }
-    }
-}
-
-// Helper function to read field body more reliably
-async fn read_field_body(
-    mut field: actix_multipart::Field,
-) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let mut data = Vec::new();
+    };
+    info!("Avatar URL updated successfully: {}", avatar_url);
 
-    while let Some(chunk) = field.next().await {
-        match chunk {
-            Ok(bytes) => {
-                data.write_all(&bytes)?;
-            }
-            Err(e) => {
-                error!("Error reading chunk: {:?}", e);
-                return Err(Box::new(e));
+    // The new avatar is live, now remove the previous one from B2 (not the default UI Avatars)
+    if current_avatar.contains("/file/") && !current_avatar.contains("ui-avatars.com") {
+        let old_filename = current_avatar.rsplit('/').next().unwrap_or_default();
+        for name in stored_avatar_file_names(old_filename) {
+            if let Err(e) = b2_client.delete_file(&name).await {
+                error!("Failed to delete old avatar {} from B2: {:?}", name, e);
             }
         }
     }
 
-    Ok(data)
+    HttpResponse::Ok().json(AvatarUploadResponse {
+        avatar_url,
+        variants: uploaded,
+    })
 }
 
 // Reset avatar handler
@@ -533,10 +865,12 @@ pub async fn reset_avatar(pool: web::Data<PgPool>, req: HttpRequest) -> impl Res
         // Extract filename from URL
         let filename = current_avatar.split('/').last().unwrap_or_default();
 
-        // Delete file from B2
-        if let Err(e) = b2_client.delete_file(filename).await {
-            error!("Failed to delete avatar from B2: {:?}", e);
-            // Continue anyway to update the database
+        // Delete file (and any size variants) from B2
+        for name in stored_avatar_file_names(filename) {
+            if let Err(e) = b2_client.delete_file(&name).await {
+                error!("Failed to delete avatar {} from B2: {:?}", name, e);
+                // Continue anyway to update the database
+            }
         }
     }
 
@@ -558,7 +892,10 @@ pub async fn reset_avatar(pool: web::Data<PgPool>, req: HttpRequest) -> impl Res
     match result {
         Ok(record) => {
             let avatar_url: String = record.get("avatar_url");
-            HttpResponse::Ok().json(AvatarUploadResponse { avatar_url })
+            HttpResponse::Ok().json(AvatarUploadResponse {
+                avatar_url,
+                variants: Vec::new(),
+            })
         }
         Err(e) => {
             error!("Error resetting avatar URL in database: {:?}", e);
@@ -587,15 +924,26 @@ pub async fn get_current_user(req: HttpRequest) -> impl Responder {
 // GET /users/{username}
 // GET /users/id/{user_id}
 // PATCH /users/update-info
+// GET /users/privacy
+// PATCH /users/privacy
+// GET /users/profile-completeness
 // DELETE /users/delete-user
+// POST /users/restore-account
 pub fn config_user_data_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/users")
             .route("/info", web::get().to(get_logged_in_user_info))
+            .route("/privacy", web::get().to(get_privacy_settings))
+            .route("/privacy", web::patch().to(update_privacy_settings))
+            .route(
+                "/profile-completeness",
+                web::get().to(get_profile_completeness),
+            )
             .route("/{username}", web::get().to(get_user_by_name))
             .route("/id/{user_id}", web::get().to(get_user_by_id))
             .route("/update-info", web::patch().to(update_user_profile))
             .route("/delete-user", web::delete().to(delete_user_account))
+            .route("/restore-account", web::post().to(restore_user_account))
             .route("/avatar/upload", web::post().to(upload_avatar))
             .route("/avatar/reset", web::post().to(reset_avatar))
             .route("/current-user", web::get().to(get_current_user)),